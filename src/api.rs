@@ -1,7 +1,22 @@
+use crate::digest::Checksum;
 use serde::{Deserialize, Serialize};
 
-pub(crate) const FORMULA_API_URL: &str = "https://formulae.brew.sh/api/formula.json";
-pub(crate) const CASK_API_URL: &str = "https://formulae.brew.sh/api/cask.json";
+/// Base of the Homebrew JSON API; `WAX_API_URL` points wax at a mirror of it.
+pub const DEFAULT_API_URL: &str = "https://formulae.brew.sh/api";
+pub const API_URL_ENV: &str = "WAX_API_URL";
+
+/// `path` (`formula.json`, `cask/<token>.json`, …) under the configured API base.
+pub(crate) fn api_url(path: &str) -> String {
+    join_api_url(std::env::var(API_URL_ENV).ok().as_deref(), path)
+}
+
+fn join_api_url(base: Option<&str>, path: &str) -> String {
+    let base = base
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+        .unwrap_or(DEFAULT_API_URL);
+    format!("{}/{}", base.trim_end_matches('/'), path)
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Formula {
@@ -26,11 +41,50 @@ pub struct Formula {
     pub keg_only_reason: Option<serde_json::Value>,
     #[serde(default)]
     pub post_install_defined: bool,
+    /// Installs over the last 30 days, when the index carries analytics: a bare count
+    /// (`popularity` / `install_30d`) or Homebrew's `analytics.install.30d` map.
+    #[serde(
+        default,
+        alias = "install_30d",
+        alias = "analytics",
+        deserialize_with = "deserialize_popularity",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub popularity: Option<u64>,
+    /// Other names that install this formula (`pkg-config` → `pkgconf`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Names this formula was renamed from; dependencies may still use them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub oldnames: Vec<String>,
     /// Path to the local .rb file (set for tap formulae; not serialized).
     #[serde(skip, default)]
     pub rb_path: Option<std::path::PathBuf>,
 }
 
+fn deserialize_popularity<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value.as_ref().and_then(popularity_from_value))
+}
+
+fn popularity_from_value(value: &serde_json::Value) -> Option<u64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::Object(map) => {
+            if let Some(count) = map.get("install_30d").and_then(|v| v.as_u64()) {
+                return Some(count);
+            }
+            // {"install": {"30d": {"<name>": 1234, "<name> --HEAD": 5}}}
+            let per_variant = value.pointer("/install/30d")?.as_object()?;
+            Some(per_variant.values().filter_map(|v| v.as_u64()).sum())
+        }
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BottleInfo {
     pub stable: Option<BottleStable>,
@@ -99,10 +153,22 @@ pub struct CaskDetails {
     pub homepage: String,
     pub version: String,
     pub url: String,
-    pub sha256: String,
+    #[serde(default)]
+    pub sha256: Checksum,
     pub artifacts: Option<Vec<CaskArtifact>>,
 }
 
+impl CaskDetails {
+    /// The checksum to verify the download against. A `version :latest` cask that
+    /// declares no digest is treated as `:no_check`: its URL serves whatever is newest.
+    pub fn checksum(&self) -> Checksum {
+        match &self.sha256 {
+            Checksum::Missing if self.version == "latest" => Checksum::NoCheck,
+            other => other.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum CaskArtifact {
@@ -213,6 +279,27 @@ impl Formula {
             .map(|s| s.rebuild)
             .unwrap_or(0)
     }
+
+    /// Tap formulae carry a `user/repo/name` full name; core formulae do not.
+    pub fn is_from_tap(&self) -> bool {
+        self.full_name != self.name
+    }
+}
+
+/// Look up formulae by bare name (as the Cellar records them) and by full name. When a
+/// tap ships a formula with the same name as a core one, the bare name resolves to core.
+pub fn index_formulae(formulae: &[Formula]) -> std::collections::HashMap<&str, &Formula> {
+    let mut index = std::collections::HashMap::with_capacity(formulae.len());
+    for formula in formulae {
+        index.insert(formula.full_name.as_str(), formula);
+        let shadows_core = index
+            .get(formula.name.as_str())
+            .is_some_and(|existing: &&Formula| !existing.is_from_tap());
+        if !(formula.is_from_tap() && shadows_core) {
+            index.insert(formula.name.as_str(), formula);
+        }
+    }
+    index
 }
 
 #[derive(Debug)]
@@ -314,6 +401,9 @@ mod formula_tests {
             keg_only: None,
             keg_only_reason: None,
             post_install_defined: false,
+            popularity: None,
+            aliases: Vec::new(),
+            oldnames: Vec::new(),
             rb_path: None,
         }
     }
@@ -330,6 +420,38 @@ mod formula_tests {
         assert_eq!(f.full_version(), "1.2.3_2");
     }
 
+    #[test]
+    fn popularity_parses_bare_counts_and_homebrew_analytics() {
+        let parse = |extra: serde_json::Value| -> Option<u64> {
+            let mut json = serde_json::json!({
+                "name": "wget",
+                "full_name": "wget",
+                "homepage": "",
+                "versions": { "stable": "1.0", "bottle": true },
+            });
+            json.as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            serde_json::from_value::<Formula>(json).unwrap().popularity
+        };
+
+        assert_eq!(parse(serde_json::json!({})), None);
+        assert_eq!(parse(serde_json::json!({ "install_30d": 42 })), Some(42));
+        assert_eq!(
+            parse(serde_json::json!({
+                "analytics": { "install": { "30d": { "wget": 100, "wget --HEAD": 2 } } }
+            })),
+            Some(102)
+        );
+        assert_eq!(parse(serde_json::json!({ "analytics": null })), None);
+
+        let mut f = create_mock_formula("1.0", 0);
+        f.popularity = Some(7);
+        let round_trip: Formula =
+            serde_json::from_str(&serde_json::to_string(&f).unwrap()).unwrap();
+        assert_eq!(round_trip.popularity, Some(7));
+    }
+
     fn dummy_formula(bottle: Option<BottleInfo>) -> Formula {
         Formula {
             name: "test-formula".into(),
@@ -352,10 +474,32 @@ mod formula_tests {
             keg_only: None,
             keg_only_reason: None,
             post_install_defined: false,
+            popularity: None,
+            aliases: Vec::new(),
+            oldnames: Vec::new(),
             rb_path: None,
         }
     }
 
+    #[test]
+    fn index_formulae_prefers_core_over_same_named_tap_formula() {
+        let mut core = create_mock_formula("3.6.1", 0);
+        core.name = "openssl@3".into();
+        core.full_name = "openssl@3".into();
+        let mut tapped = create_mock_formula("3.6.0", 0);
+        tapped.name = "openssl@3".into();
+        tapped.full_name = "someone/tap/openssl@3".into();
+
+        for order in [
+            vec![core.clone(), tapped.clone()],
+            vec![tapped.clone(), core.clone()],
+        ] {
+            let index = index_formulae(&order);
+            assert_eq!(index["openssl@3"].versions.stable, "3.6.1");
+            assert_eq!(index["someone/tap/openssl@3"].versions.stable, "3.6.0");
+        }
+    }
+
     #[test]
     fn test_bottle_rebuild_none() {
         let f = dummy_formula(None);
@@ -378,4 +522,58 @@ mod formula_tests {
         }));
         assert_eq!(f.bottle_rebuild(), 42);
     }
+
+    #[test]
+    fn latest_cask_without_digest_skips_verification() {
+        let details = |version: &str, sha256: serde_json::Value| -> CaskDetails {
+            serde_json::from_value(serde_json::json!({
+                "token": "nightly-app",
+                "name": ["Nightly App"],
+                "desc": null,
+                "homepage": "",
+                "version": version,
+                "url": "https://example.com/app.dmg",
+                "sha256": sha256,
+                "artifacts": null,
+            }))
+            .unwrap()
+        };
+        assert_eq!(
+            details("latest", serde_json::Value::Null).checksum(),
+            Checksum::NoCheck
+        );
+        assert_eq!(
+            details("1.0", serde_json::json!("no_check")).checksum(),
+            Checksum::NoCheck
+        );
+        assert_eq!(
+            details("1.0", serde_json::Value::Null).checksum(),
+            Checksum::Missing
+        );
+        assert_eq!(
+            details("1.0", serde_json::json!("abc")).checksum(),
+            Checksum::Digest("abc".to_string())
+        );
+    }
+}
+
+#[cfg(test)]
+mod api_url_tests {
+    use super::join_api_url;
+
+    #[test]
+    fn mirror_base_replaces_default() {
+        assert_eq!(
+            join_api_url(None, "formula.json"),
+            "https://formulae.brew.sh/api/formula.json"
+        );
+        assert_eq!(
+            join_api_url(Some("  "), "cask.json"),
+            "https://formulae.brew.sh/api/cask.json"
+        );
+        assert_eq!(
+            join_api_url(Some("https://brew.corp.example/api/"), "cask/firefox.json"),
+            "https://brew.corp.example/api/cask/firefox.json"
+        );
+    }
 }
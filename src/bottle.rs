@@ -10,7 +10,7 @@ use std::thread;
 use std::time::{Duration, Instant};
 use tar::Archive;
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
-use tracing::{debug, instrument};
+use tracing::{debug, info, instrument};
 
 /// Tracks aggregate downloaded / expected bytes across concurrent downloads (e.g. multiple casks).
 #[derive(Clone, Default)]
@@ -23,6 +23,131 @@ pub struct DownloadTotals {
 const MAX_EXTRACT_BYTES: u64 = 5 * 1024 * 1024 * 1024;
 const PROBE_SIZE_CACHE_TTL: Duration = Duration::from_secs(3600);
 
+/// Archive container/compression handled by [`BottleDownloader::extract`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    TarGz,
+    TarXz,
+    TarBz2,
+    Tar,
+    Zip,
+    /// Extracted with an external `7zz`/`7z`/`7za`; there is no in-process decoder.
+    SevenZip,
+}
+
+/// Executables tried, in order, for `.7z` archives.
+const SEVEN_ZIP_PROGRAMS: &[&str] = &["7zz", "7z", "7za"];
+
+impl ArchiveFormat {
+    pub fn detect(path: &Path) -> Result<Self> {
+        use std::io::Read;
+
+        let mut header = [0u8; 512];
+        let mut read = 0;
+        let mut file = std::fs::File::open(path)?;
+        while read < header.len() {
+            let n = file.read(&mut header[read..])?;
+            if n == 0 {
+                break;
+            }
+            read += n;
+        }
+
+        Self::from_magic(&header[..read])
+            .or_else(|| Self::from_extension(path))
+            .ok_or_else(|| {
+                let found = Self::unsupported_name(&header[..read], path)
+                    .map(|name| format!(" {}", name))
+                    .unwrap_or_default();
+                WaxError::InstallError(format!(
+                    "Unsupported archive format{}: {} (expected .tar.gz, .tar.xz, .tar.bz2, .tar, .zip or .7z)",
+                    found,
+                    path.display()
+                ))
+            })
+    }
+
+    /// A name for a recognisable format wax cannot unpack, for the error message.
+    fn unsupported_name(header: &[u8], path: &Path) -> Option<String> {
+        if header.starts_with(b"Rar!\x1a\x07") {
+            return Some("RAR".to_string());
+        }
+        if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            return Some("zstd".to_string());
+        }
+        if header.starts_with(b"xar!") {
+            return Some("xar".to_string());
+        }
+        let ext = path.extension()?.to_str()?;
+        Some(format!(".{}", ext.to_ascii_lowercase()))
+    }
+
+    fn from_magic(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::TarGz)
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Self::TarXz)
+        } else if header.starts_with(b"BZh") {
+            Some(Self::TarBz2)
+        } else if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Some(Self::Zip)
+        } else if header.starts_with(&[b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c]) {
+            Some(Self::SevenZip)
+        } else if header.get(257..262) == Some(b"ustar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+
+    /// Format named by the file name at the end of a download URL, ignoring any query
+    /// string or fragment.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        Self::from_extension(Path::new(path.rsplit('/').next()?))
+    }
+
+    /// Canonical file extension, leading dot included.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::TarGz => ".tar.gz",
+            Self::TarXz => ".tar.xz",
+            Self::TarBz2 => ".tar.bz2",
+            Self::Tar => ".tar",
+            Self::Zip => ".zip",
+            Self::SevenZip => ".7z",
+        }
+    }
+
+    fn from_extension(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            Some(Self::TarXz)
+        } else if name.ends_with(".tar.bz2") || name.ends_with(".tbz") || name.ends_with(".tbz2") {
+            Some(Self::TarBz2)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".7z") {
+            Some(Self::SevenZip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Temp file name for a download of `url`: `stem` plus the extension the URL names
+/// (`.tbz` becomes `.tar.bz2`), so [`BottleDownloader::extract`] can fall back on it when
+/// the contents carry no magic bytes. URLs without one (GHCR blobs end in a digest) get
+/// `.tar.gz`, which is what Homebrew bottles are.
+pub fn archive_file_name(stem: &str, url: &str) -> String {
+    let format = ArchiveFormat::from_url(url).unwrap_or(ArchiveFormat::TarGz);
+    format!("{}{}", stem, format.extension())
+}
+
 fn probe_size_cache() -> &'static Mutex<HashMap<String, (u64, Instant)>> {
     static CACHE: OnceLock<Mutex<HashMap<String, (u64, Instant)>>> = OnceLock::new();
     CACHE.get_or_init(|| Mutex::new(HashMap::new()))
@@ -46,8 +171,24 @@ fn store_probe_size(url: &str, size: u64) {
     }
 }
 
-pub struct BottleDownloader {
-    client: reqwest::Client,
+pub struct BottleDownloader;
+
+/// Top-level directory of an extracted bottle holding the keg for `name`. Usually that
+/// is `name` itself; a bottle shared by several formulae (same URL and checksum) is laid
+/// out under the one name it was built for, so fall back to its only directory.
+pub fn bottle_dir_name(extract_dir: &Path, name: &str) -> String {
+    if extract_dir.join(name).is_dir() {
+        return name.to_string();
+    }
+    let mut dirs = std::fs::read_dir(extract_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false));
+    match (dirs.next(), dirs.next()) {
+        (Some(only), None) => only.file_name().to_string_lossy().into_owned(),
+        _ => name.to_string(),
+    }
 }
 
 pub fn copy_extracted_bottle_to_cellar(
@@ -55,16 +196,17 @@ pub fn copy_extracted_bottle_to_cellar(
     name: &str,
     cellar_version: &str,
     formula_cellar: &Path,
+    progress: Option<&ProgressBar>,
 ) -> Result<()> {
-    use crate::ui::copy_dir_all;
+    use crate::ui::copy_dir_all_with_progress;
     let name_dir = extract_dir.join(name);
     let actual_content_dir = name_dir.join(cellar_version);
     if actual_content_dir.exists() {
-        copy_dir_all(&actual_content_dir, formula_cellar)
+        copy_dir_all_with_progress(&actual_content_dir, formula_cellar, progress)
     } else if name_dir.exists() {
-        copy_dir_all(&name_dir, formula_cellar)
+        copy_dir_all_with_progress(&name_dir, formula_cellar, progress)
     } else {
-        copy_dir_all(extract_dir, formula_cellar)
+        copy_dir_all_with_progress(extract_dir, formula_cellar, progress)
     }
 }
 
@@ -72,9 +214,13 @@ impl BottleDownloader {
     const TRANSIENT_RETRY_ATTEMPTS: usize = 3;
 
     pub fn new() -> Self {
-        Self {
-            client: crate::http_client::download().clone(),
-        }
+        Self
+    }
+
+    /// Chosen per URL so a loopback mirror may serve plain HTTP while everything else
+    /// stays HTTPS-only.
+    fn client(url: &str) -> &'static reqwest::Client {
+        crate::http_client::download(url)
     }
 
     // Minimum file size to bother splitting across multiple connections.
@@ -132,7 +278,7 @@ impl BottleDownloader {
         max_connections: usize,
         totals: Option<&DownloadTotals>,
     ) -> Result<()> {
-        debug!("Downloading from {}", url);
+        info!("Downloading {}", url);
 
         // Fetch auth token once (GHCR only — needed for the first redirect).
         let auth_token: Option<String> = if url.contains("ghcr.io") {
@@ -196,7 +342,7 @@ impl BottleDownloader {
         auth_token: &Option<String>,
     ) -> Result<(String, u64, bool)> {
         // Try HEAD first — cheap and avoids downloading any body.
-        let mut head_req = self.client.head(url);
+        let mut head_req = Self::client(url).head(url);
         if let Some(ref tok) = auth_token {
             head_req = head_req.header("Authorization", format!("Bearer {}", tok));
         }
@@ -205,7 +351,7 @@ impl BottleDownloader {
             Ok(r) if r.status().is_success() || r.status().as_u16() == 206 => r,
             _ => {
                 // HEAD rejected or failed — fall back to a tiny range GET.
-                let mut get_req = self.client.get(url).header("Range", "bytes=0-0");
+                let mut get_req = Self::client(url).get(url).header("Range", "bytes=0-0");
                 if let Some(ref tok) = auth_token {
                     get_req = get_req.header("Authorization", format!("Bearer {}", tok));
                 }
@@ -284,7 +430,7 @@ impl BottleDownloader {
         }
 
         let downloaded_so_far = Arc::new(std::sync::atomic::AtomicU64::new(0));
-        let client = self.client.clone();
+        let client = Self::client(url).clone();
         let url = url.to_string();
         let dest_path_buf = dest_path.to_path_buf();
 
@@ -406,7 +552,7 @@ impl BottleDownloader {
         progress: Option<&ProgressBar>,
         totals: Option<&DownloadTotals>,
     ) -> Result<()> {
-        let mut request = self.client.get(url);
+        let mut request = Self::client(url).get(url);
         if let Some(ref tok) = auth_token {
             request = request.header("Authorization", format!("Bearer {}", tok));
         }
@@ -522,7 +668,8 @@ impl BottleDownloader {
             token: String,
         }
 
-        let response = self.client.get(&token_url).send().await?;
+        info!("Fetching ghcr token: {}", token_url);
+        let response = Self::client(&token_url).get(&token_url).send().await?;
         let token_resp: TokenResponse = response.json().await?;
         Ok(token_resp.token)
     }
@@ -650,14 +797,108 @@ impl BottleDownloader {
         Ok(())
     }
 
+    /// Extract a `.tar.gz`, `.tar.xz`, `.tar.bz2`, plain `.tar` or `.zip` archive into
+    /// `dest_dir`. The format comes from the file's magic bytes, falling back to its
+    /// extension.
     pub fn extract(tarball_path: &Path, dest_dir: &Path) -> Result<()> {
         debug!("Extracting {:?} to {:?}", tarball_path, dest_dir);
 
         std::fs::create_dir_all(dest_dir)?;
 
+        let format = ArchiveFormat::detect(tarball_path)?;
+        debug!("Detected archive format {:?}", format);
         let file = std::fs::File::open(tarball_path)?;
-        let decoder = GzDecoder::new(file);
-        let mut archive = Archive::new(decoder);
+        match format {
+            ArchiveFormat::TarGz => Self::extract_tar(GzDecoder::new(file), dest_dir),
+            ArchiveFormat::TarXz => Self::extract_tar(xz2::read::XzDecoder::new(file), dest_dir),
+            ArchiveFormat::TarBz2 => Self::extract_tar(bzip2::read::BzDecoder::new(file), dest_dir),
+            ArchiveFormat::Tar => Self::extract_tar(file, dest_dir),
+            ArchiveFormat::Zip => Self::extract_zip(file, dest_dir),
+            ArchiveFormat::SevenZip => Self::extract_7z(tarball_path, dest_dir),
+        }
+    }
+
+    fn extract_7z(archive: &Path, dest_dir: &Path) -> Result<()> {
+        let program = SEVEN_ZIP_PROGRAMS
+            .iter()
+            .find_map(|p| crate::ui::find_in_path(p))
+            .ok_or_else(|| {
+                WaxError::InstallError(format!(
+                    "{} is a 7z archive; install 7-Zip (`7zz`) or p7zip (`7z`) to extract it",
+                    archive.display()
+                ))
+            })?;
+        let output = Command::new(&program)
+            .arg("x")
+            .arg("-y")
+            .arg(format!("-o{}", dest_dir.display()))
+            .arg(archive)
+            .output()?;
+        if !output.status.success() {
+            return Err(WaxError::InstallError(format!(
+                "Failed to extract 7z archive {}: {}",
+                archive.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        debug!("Extraction complete");
+        Ok(())
+    }
+
+    fn extract_zip(file: std::fs::File, dest_dir: &Path) -> Result<()> {
+        let mut archive = zip::ZipArchive::new(file)
+            .map_err(|e| WaxError::InstallError(format!("Invalid zip archive: {}", e)))?;
+        let canonical_dest = dunce::canonicalize(dest_dir)?;
+        let mut extracted_bytes: u64 = 0;
+
+        for i in 0..archive.len() {
+            let mut entry = archive
+                .by_index(i)
+                .map_err(|e| WaxError::InstallError(format!("Invalid zip entry: {}", e)))?;
+            let Some(path) = entry.enclosed_name() else {
+                return Err(WaxError::InstallError(format!(
+                    "Zip entry contains unsafe path: {}",
+                    entry.name()
+                )));
+            };
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+
+            extracted_bytes = extracted_bytes.saturating_add(entry.size());
+            if extracted_bytes > MAX_EXTRACT_BYTES {
+                return Err(WaxError::InstallError(format!(
+                    "Archive extraction exceeds size limit ({} bytes)",
+                    MAX_EXTRACT_BYTES
+                )));
+            }
+
+            let full_path = canonical_dest.join(&path);
+            if entry.is_dir() {
+                std::fs::create_dir_all(&full_path)?;
+                continue;
+            }
+            if let Some(parent) = full_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut out = std::fs::File::create(&full_path)?;
+            std::io::copy(&mut entry, &mut out)?;
+            #[cfg(unix)]
+            if let Some(mode) = entry.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(
+                    &full_path,
+                    std::fs::Permissions::from_mode(mode & 0o777),
+                )?;
+            }
+        }
+
+        debug!("Extraction complete");
+        Ok(())
+    }
+
+    fn extract_tar<R: std::io::Read>(reader: R, dest_dir: &Path) -> Result<()> {
+        let mut archive = Archive::new(reader);
 
         let canonical_dest = dunce::canonicalize(dest_dir)?;
         let mut extracted_bytes: u64 = 0;
@@ -1183,6 +1424,15 @@ pub fn run_command_with_timeout(
     }
 }
 
+/// Whether `tag` names a bottle platform: `x86_64_linux`, `arm64_linux` or a macOS
+/// release with an optional `arm64_` prefix, as `detect_platform()` reports them.
+pub fn is_platform_tag(tag: &str) -> bool {
+    match tag {
+        "x86_64_linux" | "arm64_linux" | "aarch64_linux" => true,
+        _ => crate::formula_parser::MACOS_RELEASES.contains(&tag.trim_start_matches("arm64_")),
+    }
+}
+
 pub fn detect_platform() -> String {
     let os = std::env::consts::OS;
     let arch = std::env::consts::ARCH;
@@ -1467,6 +1717,17 @@ mod tests {
     use std::io::Write;
     use tempfile::NamedTempFile;
 
+    #[test]
+    fn bottle_dir_name_falls_back_to_the_only_keg_directory() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(temp.path().join("libfoo/1.0")).unwrap();
+        assert_eq!(bottle_dir_name(temp.path(), "libfoo"), "libfoo");
+        assert_eq!(bottle_dir_name(temp.path(), "foo-compat"), "libfoo");
+
+        std::fs::create_dir_all(temp.path().join("other/1.0")).unwrap();
+        assert_eq!(bottle_dir_name(temp.path(), "foo-compat"), "foo-compat");
+    }
+
     #[cfg(unix)]
     fn archive_with_symlink(link_path: &str, target: &str) -> (tempfile::TempDir, PathBuf) {
         let temp = tempfile::tempdir().unwrap();
@@ -1548,7 +1809,7 @@ mod tests {
         let mut f = NamedTempFile::new().unwrap();
         f.write_all(b"hello world").unwrap();
         let hash = format!("{:x}", Sha256::digest(b"hello world"));
-        let result = crate::digest::verify_sha256_file(f.path(), &hash);
+        let result = crate::digest::verify_digest_file(f.path(), &hash);
         assert!(result.is_ok(), "{:?}", result);
     }
 
@@ -1557,7 +1818,7 @@ mod tests {
         let mut f = NamedTempFile::new().unwrap();
         f.write_all(b"hello world").unwrap();
         let wrong = "0000000000000000000000000000000000000000000000000000000000000000";
-        let result = crate::digest::verify_sha256_file(f.path(), wrong);
+        let result = crate::digest::verify_digest_file(f.path(), wrong);
         assert!(result.is_err(), "expected checksum mismatch error");
         let msg = format!("{:?}", result.unwrap_err());
         assert!(
@@ -1569,7 +1830,7 @@ mod tests {
     #[test]
     fn verify_checksum_missing_file_returns_error() {
         let path = std::path::Path::new("/tmp/wax-test-nonexistent-file-xyz-123.tar.gz");
-        let result = crate::digest::verify_sha256_file(path, "abc123");
+        let result = crate::digest::verify_digest_file(path, "abc123");
         assert!(result.is_err());
     }
 
@@ -1612,6 +1873,135 @@ mod tests {
         assert!(format!("{:?}", result.unwrap_err()).contains("absolute"));
     }
 
+    fn tar_with_file(path: &str, contents: &[u8]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append_data(&mut header, path, contents).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    fn assert_extracts_hello(archive_name: &str, bytes: &[u8]) {
+        let temp = tempfile::tempdir().unwrap();
+        let archive = temp.path().join(archive_name);
+        std::fs::write(&archive, bytes).unwrap();
+        let dest = temp.path().join("out");
+
+        BottleDownloader::extract(&archive, &dest).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dest.join("pkg/bin/hello")).unwrap(),
+            "hi\n",
+            "{archive_name}"
+        );
+    }
+
+    #[test]
+    fn extract_dispatches_on_archive_format() {
+        use std::io::Write;
+
+        let tar = tar_with_file("pkg/bin/hello", b"hi\n");
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&tar).unwrap();
+        assert_extracts_hello("a.tar.gz", &gz.finish().unwrap());
+
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+        xz.write_all(&tar).unwrap();
+        assert_extracts_hello("a.tar.xz", &xz.finish().unwrap());
+
+        let mut bz = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bz.write_all(&tar).unwrap();
+        assert_extracts_hello("a.tar.bz2", &bz.finish().unwrap());
+
+        assert_extracts_hello("a.tar", &tar);
+
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip.start_file("pkg/bin/hello", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"hi\n").unwrap();
+        assert_extracts_hello("a.zip", &zip.finish().unwrap().into_inner());
+
+        // Magic bytes win over a misleading extension.
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+        xz.write_all(&tar).unwrap();
+        assert_extracts_hello("mislabeled.tar.gz", &xz.finish().unwrap());
+    }
+
+    #[test]
+    fn archive_file_name_follows_the_url() {
+        let ghcr = "https://ghcr.io/v2/homebrew/core/jq/blobs/sha256:abc";
+        assert_eq!(archive_file_name("jq-1.7.1", ghcr), "jq-1.7.1.tar.gz");
+        assert_eq!(
+            archive_file_name("foo-1.0", "https://example.com/foo-1.0.tbz?raw=1"),
+            "foo-1.0.tar.bz2"
+        );
+        assert_eq!(
+            archive_file_name("foo-1.0", "https://example.com/foo-1.0.txz#frag"),
+            "foo-1.0.tar.xz"
+        );
+        assert_eq!(
+            archive_file_name("foo-1.0", "https://example.com/v1.0/foo.zip"),
+            "foo-1.0.zip"
+        );
+
+        // An old-style tar has no `ustar` magic, so only the name says what it is.
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_old();
+        header.set_size(3);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "pkg/bin/hello", &b"hi\n"[..])
+            .unwrap();
+        let tar = builder.into_inner().unwrap();
+        assert_extracts_hello(
+            &archive_file_name("pkg", "https://example.com/pkg.tar"),
+            &tar,
+        );
+    }
+
+    #[test]
+    fn extract_rejects_unknown_archive_format() {
+        let temp = tempfile::tempdir().unwrap();
+        let archive = temp.path().join("archive.rar");
+        std::fs::write(&archive, b"Rar!\x1a\x07\x00 not supported").unwrap();
+
+        let err = BottleDownloader::extract(&archive, &temp.path().join("out")).unwrap_err();
+        assert!(
+            err.to_string().contains("Unsupported archive format RAR"),
+            "{err}"
+        );
+
+        let archive = temp.path().join("source.lzma");
+        std::fs::write(&archive, b"not an archive").unwrap();
+        let err = BottleDownloader::extract(&archive, &temp.path().join("out")).unwrap_err();
+        assert!(
+            err.to_string().contains("Unsupported archive format .lzma"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn seven_zip_is_detected_by_magic_and_extension() {
+        let temp = tempfile::tempdir().unwrap();
+        let magic = temp.path().join("source.tar.gz");
+        std::fs::write(&magic, [b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c, 0, 4]).unwrap();
+        assert_eq!(
+            ArchiveFormat::detect(&magic).unwrap(),
+            ArchiveFormat::SevenZip
+        );
+
+        let named = temp.path().join("Source.7Z");
+        std::fs::write(&named, b"").unwrap();
+        assert_eq!(
+            ArchiveFormat::detect(&named).unwrap(),
+            ArchiveFormat::SevenZip
+        );
+    }
+
     #[test]
     fn extract_rejects_hardlink_parent_traversal() {
         let (_archive_dir, tarball) = archive_with_hardlink("bin/tool", "../outside");
@@ -1712,6 +2102,7 @@ mod tests {
             name,
             cellar_version,
             formula_cellar.path(),
+            None,
         )
         .unwrap();
 
@@ -1736,6 +2127,7 @@ mod tests {
             name,
             cellar_version,
             formula_cellar.path(),
+            None,
         )
         .unwrap();
 
@@ -1758,6 +2150,7 @@ mod tests {
             name,
             cellar_version,
             formula_cellar.path(),
+            None,
         )
         .unwrap();
 
@@ -1,5 +1,6 @@
+use crate::bottle::BottleDownloader;
 use crate::error::{Result, WaxError};
-use crate::formula_parser::{BuildSystem, ParsedFormula};
+use crate::formula_parser::{BuildEnvironment, BuildSystem, FormulaSource, ParsedFormula};
 use crate::ui::find_in_path;
 use indicatif::ProgressBar;
 use std::path::{Path, PathBuf};
@@ -9,6 +10,8 @@ use tracing::{debug, info, instrument};
 pub struct Builder {
     num_cores: usize,
     use_ccache: bool,
+    /// The `env`/compiler hints of the formula being built.
+    build_env: BuildEnvironment,
 }
 
 impl Builder {
@@ -24,6 +27,22 @@ impl Builder {
         Self {
             num_cores,
             use_ccache,
+            build_env: BuildEnvironment::default(),
+        }
+    }
+
+    /// This builder set up for `formula`'s `env :std` and compiler requirements.
+    fn for_formula(&self, formula: &ParsedFormula) -> Self {
+        if formula.build_env != BuildEnvironment::default() {
+            info!(
+                "Build environment for {}: {:?}",
+                formula.name, formula.build_env
+            );
+        }
+        Self {
+            num_cores: self.num_cores,
+            use_ccache: self.use_ccache && !formula.build_env.std_env,
+            build_env: formula.build_env.clone(),
         }
     }
 
@@ -65,21 +84,25 @@ impl Builder {
             pb.set_message("Configuring build...");
         }
 
+        let builder = self.for_formula(formula);
         match formula.build_system {
             BuildSystem::Autotools => {
-                self.build_autotools(&source_dir, install_prefix, &formula.configure_args)
+                builder
+                    .build_autotools(&source_dir, install_prefix, &formula.configure_args)
                     .await?
             }
             BuildSystem::CMake => {
-                self.build_cmake(&source_dir, install_prefix, &formula.configure_args)
+                builder
+                    .build_cmake(&source_dir, install_prefix, &formula.configure_args)
                     .await?
             }
             BuildSystem::Meson => {
-                self.build_meson(&source_dir, install_prefix, &formula.configure_args)
+                builder
+                    .build_meson(&source_dir, install_prefix, &formula.configure_args)
                     .await?
             }
-            BuildSystem::Make => self.build_make(&source_dir, install_prefix).await?,
-            BuildSystem::Cargo => self.build_cargo(&source_dir, install_prefix).await?,
+            BuildSystem::Make => builder.build_make(&source_dir, install_prefix).await?,
+            BuildSystem::Cargo => builder.build_cargo(&source_dir, install_prefix).await?,
             BuildSystem::Unknown => {
                 return Err(WaxError::BuildError(
                     "Unknown build system - cannot build from source".to_string(),
@@ -105,21 +128,25 @@ impl Builder {
     ) -> Result<()> {
         info!("Building {} from directory {:?}", formula.name, source_dir);
 
+        let builder = self.for_formula(formula);
         match formula.build_system {
             BuildSystem::Autotools => {
-                self.build_autotools(source_dir, install_prefix, &formula.configure_args)
+                builder
+                    .build_autotools(source_dir, install_prefix, &formula.configure_args)
                     .await?
             }
             BuildSystem::CMake => {
-                self.build_cmake(source_dir, install_prefix, &formula.configure_args)
+                builder
+                    .build_cmake(source_dir, install_prefix, &formula.configure_args)
                     .await?
             }
             BuildSystem::Meson => {
-                self.build_meson(source_dir, install_prefix, &formula.configure_args)
+                builder
+                    .build_meson(source_dir, install_prefix, &formula.configure_args)
                     .await?
             }
-            BuildSystem::Make => self.build_make(source_dir, install_prefix).await?,
-            BuildSystem::Cargo => self.build_cargo(source_dir, install_prefix).await?,
+            BuildSystem::Make => builder.build_make(source_dir, install_prefix).await?,
+            BuildSystem::Cargo => builder.build_cargo(source_dir, install_prefix).await?,
             BuildSystem::Unknown => {
                 return Err(WaxError::BuildError(
                     "Unknown build system - cannot build from source".to_string(),
@@ -134,27 +161,88 @@ impl Builder {
         Ok(())
     }
 
-    async fn extract_source(&self, tarball: &Path, dest: &Path) -> Result<()> {
-        debug!("Extracting {:?} to {:?}", tarball, dest);
+    /// Shallow-clone a git stable source at its `tag` or `revision`.
+    ///
+    /// Git sources carry no tarball checksum; when both a tag and a revision are
+    /// given, the checked-out commit must match the revision instead.
+    #[instrument(skip(self, source))]
+    pub async fn fetch_git_source(&self, source: &FormulaSource, dest: &Path) -> Result<()> {
+        debug!(
+            "Cloning {} (tag: {:?}, revision: {:?}) into {:?}",
+            source.url, source.tag, source.revision, dest
+        );
 
-        tokio::fs::create_dir_all(dest).await?;
+        match (&source.tag, &source.revision) {
+            (None, Some(revision)) => {
+                tokio::fs::create_dir_all(dest).await?;
+                Self::git(dest, &["init", "--quiet"]).await?;
+                Self::git(dest, &["remote", "add", "origin", &source.url]).await?;
+                Self::git(dest, &["fetch", "--quiet", "--depth=1", "origin", revision]).await?;
+                Self::git(dest, &["checkout", "--quiet", "FETCH_HEAD"]).await?;
+            }
+            (tag, _) => {
+                let parent = dest.parent().unwrap_or(dest);
+                tokio::fs::create_dir_all(parent).await?;
+                let mut args = vec!["clone", "--quiet", "--depth=1"];
+                if let Some(tag) = tag {
+                    args.extend(["--branch", tag.as_str()]);
+                }
+                args.push(source.url.as_str());
+                let dest_str = dest.to_string_lossy();
+                args.push(&dest_str);
+                Self::git(parent, &args).await?;
+            }
+        }
 
-        let output = Command::new("tar")
-            .arg("xzf")
-            .arg(tarball)
-            .arg("-C")
-            .arg(dest)
-            .output()?;
+        if let Some(expected) = &source.revision {
+            let head = Self::git(dest, &["rev-parse", "HEAD"]).await?;
+            if !head.starts_with(expected.as_str()) {
+                return Err(WaxError::BuildError(format!(
+                    "Git source {} at {} resolved to {}, expected revision {}",
+                    source.url,
+                    source.tag.as_deref().unwrap_or("HEAD"),
+                    head,
+                    expected
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn git(cwd: &Path, args: &[&str]) -> Result<String> {
+        let output = tokio::process::Command::new("git")
+            .args(args)
+            .current_dir(cwd)
+            .output()
+            .await?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
             return Err(WaxError::BuildError(format!(
-                "Failed to extract source: {}",
-                stderr
+                "git {} failed: {}",
+                args.first().copied().unwrap_or_default(),
+                stderr.trim()
             )));
         }
 
-        Ok(())
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    async fn extract_source(&self, tarball: &Path, dest: &Path) -> Result<()> {
+        debug!("Extracting {:?} to {:?}", tarball, dest);
+
+        tokio::fs::create_dir_all(dest).await?;
+
+        // Source releases come as .tar.gz, .tar.xz, .tar.bz2, .zip or .7z; `extract` picks the
+        // decoder from the file contents, falling back to the temp name's extension, which
+        // follows the download URL.
+        let tarball = tarball.to_path_buf();
+        let dest = dest.to_path_buf();
+        tokio::task::spawn_blocking(move || BottleDownloader::extract(&tarball, &dest))
+            .await
+            .map_err(|e| WaxError::BuildError(format!("Extraction task failed: {}", e)))?
+            .map_err(|e| WaxError::BuildError(format!("Failed to extract source: {}", e)))
     }
 
     fn find_source_directory(&self, build_dir: &Path) -> Result<PathBuf> {
@@ -196,7 +284,7 @@ impl Builder {
         }
 
         let mut args = vec![format!("--prefix={}", prefix.display())];
-        args.extend(configure_args.iter().cloned());
+        args.extend(interpolate_prefix(configure_args, prefix));
 
         self.run_command(source_dir, "./configure", &args, "Configuring")
             .await?;
@@ -242,7 +330,7 @@ impl Builder {
             format!("-DCMAKE_INSTALL_RPATH={}/lib", homebrew_prefix.display()),
             "-DCMAKE_BUILD_WITH_INSTALL_RPATH=ON".to_string(),
         ];
-        args.extend(configure_args.iter().cloned());
+        args.extend(interpolate_prefix(configure_args, prefix));
 
         self.run_command(source_dir, "cmake", &args, "Configuring CMake")
             .await?;
@@ -278,7 +366,7 @@ impl Builder {
             build_dir.display().to_string(),
             format!("--prefix={}", prefix.display()),
         ];
-        args.extend(configure_args.iter().cloned());
+        args.extend(interpolate_prefix(configure_args, prefix));
 
         self.run_command(source_dir, "meson", &args, "Configuring Meson")
             .await?;
@@ -354,6 +442,7 @@ impl Builder {
         let args = args.to_vec();
         let use_ccache = self.use_ccache;
         let num_cores = self.num_cores;
+        let build_env = self.build_env.clone();
         let phase = phase.to_string();
 
         tokio::task::spawn_blocking(move || {
@@ -380,6 +469,21 @@ impl Builder {
                 cmd.env("CXX", format!("{} {}", ccache_path, program));
             }
 
+            if let Some(cc) = &build_env.compiler {
+                cmd.env("CC", cc);
+                if let Some(cxx) = build_env.cxx_compiler() {
+                    cmd.env("CXX", cxx);
+                }
+            }
+
+            if build_env.std_env {
+                for var in ["CFLAGS", "CXXFLAGS"] {
+                    if let Ok(flags) = std::env::var(var) {
+                        cmd.env(var, strip_optimization_flags(&flags));
+                    }
+                }
+            }
+
             cmd.env("MAKEFLAGS", format!("-j{}", num_cores));
 
             let output = cmd.output()?;
@@ -405,6 +509,33 @@ impl Builder {
     }
 }
 
+/// `flags` without optimization and CPU-tuning switches (`-O2`, `-march=native`,
+/// `-mtune=…`, `-flto`), for builds that ask for the standard environment.
+fn strip_optimization_flags(flags: &str) -> String {
+    flags
+        .split_whitespace()
+        .filter(|flag| {
+            !(flag.starts_with("-O")
+                || flag.starts_with("-march=")
+                || flag.starts_with("-mtune=")
+                || flag.starts_with("-mcpu=")
+                || flag.starts_with("-flto"))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Formula configure args with `#{prefix}` replaced by the keg being built into.
+fn interpolate_prefix<'a>(
+    configure_args: &'a [String],
+    prefix: &'a Path,
+) -> impl Iterator<Item = String> + 'a {
+    let prefix = prefix.display().to_string();
+    configure_args
+        .iter()
+        .map(move |arg| arg.replace("#{prefix}", &prefix))
+}
+
 impl Default for Builder {
     fn default() -> Self {
         Self::new()
@@ -449,6 +580,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn configure_args_get_the_keg_prefix() {
+        let args = vec![
+            "--sysconfdir=#{prefix}/etc".to_string(),
+            "--disable-silent-rules".to_string(),
+        ];
+        let prefix = Path::new("/opt/homebrew/Cellar/libfoo/2.4.1");
+        assert_eq!(
+            interpolate_prefix(&args, prefix).collect::<Vec<_>>(),
+            vec![
+                "--sysconfdir=/opt/homebrew/Cellar/libfoo/2.4.1/etc",
+                "--disable-silent-rules"
+            ]
+        );
+    }
+
+    #[test]
+    fn std_env_strips_optimization_flags() {
+        assert_eq!(
+            strip_optimization_flags("-O3 -march=native -I/opt/include -flto=auto -g -pipe"),
+            "-I/opt/include -g -pipe"
+        );
+        assert_eq!(strip_optimization_flags(""), "");
+    }
+
     #[test]
     fn test_detect_cpu_cores_sanity() {
         let cores = Builder::detect_cpu_cores();
@@ -1,12 +1,16 @@
-use crate::api::{Cask, CaskDetails, FetchResult, Formula, CASK_API_URL, FORMULA_API_URL};
-use crate::error::Result;
-use crate::tap::TapManager;
+use crate::api::{api_url, Cask, CaskDetails, FetchResult, Formula};
+use crate::error::{Result, WaxError};
+use crate::state_file::{parse_or_quarantine, temp_path_for, write_atomic};
+use crate::tap::{local_core_tap, TapManager};
 use crate::ui::{create_spinner, dirs};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use tokio::fs;
 use tracing::{debug, info, instrument};
 
@@ -17,6 +21,24 @@ struct FormulaeIndexCache {
 
 static FORMULAE_INDEX_CACHE: Mutex<Option<FormulaeIndexCache>> = Mutex::new(None);
 
+/// Formulae parsed from the `WAX_CORE_TAP` checkout, once per process.
+static LOCAL_CORE_FORMULAE: Mutex<Option<(PathBuf, Arc<Vec<Formula>>)>> = Mutex::new(None);
+
+async fn load_local_core_formulae(core: &Path) -> Result<Vec<Formula>> {
+    if let Ok(guard) = LOCAL_CORE_FORMULAE.lock() {
+        if let Some((path, formulae)) = guard.as_ref() {
+            if path == core {
+                return Ok((**formulae).clone());
+            }
+        }
+    }
+    let formulae = TapManager::load_core_formulae(core).await?;
+    if let Ok(mut guard) = LOCAL_CORE_FORMULAE.lock() {
+        *guard = Some((core.to_path_buf(), Arc::new(formulae.clone())));
+    }
+    Ok(formulae)
+}
+
 fn clear_formulae_index_cache() {
     if let Ok(mut guard) = FORMULAE_INDEX_CACHE.lock() {
         *guard = None;
@@ -25,6 +47,7 @@ fn clear_formulae_index_cache() {
 
 async fn formulae_index_signature(cache: &Cache, tap_names: &[String]) -> Result<u64> {
     let mut hasher = DefaultHasher::new();
+    local_core_tap().hash(&mut hasher);
     if let Ok(meta) = fs::metadata(cache.formulae_path()).await {
         if let Ok(mtime) = meta.modified() {
             mtime.hash(&mut hasher);
@@ -53,27 +76,164 @@ pub struct CacheMetadata {
     pub casks_last_modified: Option<String>,
 }
 
+/// Days after which a cached index that could not be refreshed is called out.
+/// Override with `WAX_INDEX_MAX_AGE_DAYS` (`0` disables the warning).
+const DEFAULT_INDEX_MAX_AGE_DAYS: i64 = 7;
+const INDEX_MAX_AGE_ENV: &str = "WAX_INDEX_MAX_AGE_DAYS";
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+fn index_max_age_days() -> i64 {
+    std::env::var(INDEX_MAX_AGE_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_INDEX_MAX_AGE_DAYS)
+}
+
+/// Warning text for an index `age_secs` old, if it exceeds the configured maximum.
+pub fn index_age_warning(age_secs: i64) -> Option<String> {
+    index_age_warning_with(age_secs, index_max_age_days())
+}
+
+fn index_age_warning_with(age_secs: i64, max_age_days: i64) -> Option<String> {
+    if max_age_days == 0 || age_secs <= max_age_days * 86_400 {
+        return None;
+    }
+    let days = age_secs / 86_400;
+    Some(format!(
+        "package index is {} {} old — run `wax update`",
+        days,
+        if days == 1 { "day" } else { "days" }
+    ))
+}
+
+/// Whether a failed index refresh means the API could not be reached at all (offline,
+/// DNS failure, timeout), as opposed to a real error.
+fn is_unreachable(error: &crate::error::WaxError) -> bool {
+    matches!(error, crate::error::WaxError::HttpError(e) if e.is_connect() || e.is_timeout())
+}
+
+/// Parallel requests when prefetching cask details.
+const CASK_DETAILS_CONCURRENCY: usize = 8;
+
+/// Scoop and winget catalogues `wax search` keeps in the cache directory.
+pub(crate) const SCOOP_INDEX_CACHE: &str = "scoop_main_index.json";
+pub(crate) const WINGET_INDEX_CACHE: &str = "winget_pkgs_index.json";
+pub(crate) const SCOOP_REPO_CACHE: &str = "scoop-main.git";
+pub(crate) const WINGET_REPO_CACHE: &str = "winget-pkgs.git";
+
+/// Top-level cache entries wax writes itself and can fetch again on demand: the
+/// Scoop/winget catalogues, files quarantined as corrupt, and temp files left behind by
+/// an interrupted write.
+fn is_refetchable_artifact(name: &str) -> bool {
+    [
+        SCOOP_INDEX_CACHE,
+        WINGET_INDEX_CACHE,
+        SCOOP_REPO_CACHE,
+        WINGET_REPO_CACHE,
+    ]
+    .contains(&name)
+        || name.ends_with(".corrupt")
+        || (name.starts_with('.') && name.ends_with(".tmp"))
+}
+
 #[derive(Clone)]
 pub struct Cache {
     cache_dir: PathBuf,
+    /// Set by `--refresh`: the next `ensure_fresh` revalidates the index regardless of age.
+    refresh_pending: Arc<AtomicBool>,
+    /// Cask details a caller already resolved this run (e.g. `upgrade` after computing the
+    /// outdated set), served by `fetch_cask_details` without touching disk or network.
+    resolved_casks: Arc<Mutex<HashMap<String, CaskDetails>>>,
 }
 
 impl Cache {
     pub fn new() -> Result<Self> {
         let cache_dir = dirs::wax_cache_dir()?;
-        Ok(Self { cache_dir })
+        Ok(Self {
+            cache_dir,
+            refresh_pending: Arc::default(),
+            resolved_casks: Arc::default(),
+        })
+    }
+
+    /// Hand details resolved earlier in this run to later `fetch_cask_details` calls.
+    pub fn remember_cask_details(&self, token: &str, details: CaskDetails) {
+        if let Ok(mut resolved) = self.resolved_casks.lock() {
+            resolved.insert(token.to_string(), details);
+        }
     }
 
+    /// Make the next `ensure_fresh` revalidate the index even if it is not stale.
+    pub fn force_refresh(self) -> Self {
+        self.refresh_pending.store(true, Ordering::SeqCst);
+        self
+    }
+
+    /// Create the cache directory and check that it can be written to, so a read-only
+    /// mount fails up front with a hint rather than with a bare IO error mid-write.
+    /// The write probe runs once per process for a given directory.
     pub async fn ensure_cache_dir(&self) -> Result<()> {
-        fs::create_dir_all(&self.cache_dir).await?;
+        static PROBED: OnceLock<PathBuf> = OnceLock::new();
+        let probe = async {
+            fs::create_dir_all(&self.cache_dir).await?;
+            if PROBED.get() == Some(&self.cache_dir) {
+                return Ok(());
+            }
+            let probe_path = temp_path_for(&self.cache_dir.join("write-probe"));
+            fs::write(&probe_path, b"").await?;
+            fs::remove_file(&probe_path).await
+        };
+        probe
+            .await
+            .map_err(|e| unwritable_cache_error(&self.cache_dir, &e))?;
+        let _ = PROBED.set(self.cache_dir.clone());
         Ok(())
     }
 
-    #[cfg_attr(not(windows), allow(dead_code))]
     pub fn cache_dir_path(&self) -> &Path {
         &self.cache_dir
     }
 
+    /// The cache's contents grouped for `wax cache info`: the package index, tap caches,
+    /// cached cask details, the other files wax can fetch again (see
+    /// `is_refetchable_artifact`), and everything else, which wax did not write and
+    /// `wax cache clean` leaves alone.
+    pub fn sections(&self) -> Vec<(&'static str, Vec<PathBuf>)> {
+        let index = vec![
+            self.formulae_path(),
+            self.casks_path(),
+            self.metadata_path(),
+        ];
+        let taps = self.taps_cache_dir();
+        let cask_details = self.cask_details_dir();
+        let (catalogues, other): (Vec<PathBuf>, Vec<PathBuf>) = std::fs::read_dir(&self.cache_dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| !index.contains(p) && *p != taps && *p != cask_details)
+                    .partition(|p| {
+                        p.file_name()
+                            .is_some_and(|n| is_refetchable_artifact(&n.to_string_lossy()))
+                    })
+            })
+            .unwrap_or_default();
+        vec![
+            ("index", index),
+            ("taps", vec![taps]),
+            ("cask details", vec![cask_details]),
+            ("catalogues", catalogues),
+            ("other", other),
+        ]
+    }
+
     fn formulae_path(&self) -> PathBuf {
         self.cache_dir.join("formulae.json")
     }
@@ -90,6 +250,26 @@ impl Cache {
         self.cache_dir.join("taps")
     }
 
+    fn cask_details_dir(&self) -> PathBuf {
+        self.cache_dir.join("cask-details")
+    }
+
+    fn cask_details_path(&self, token: &str) -> PathBuf {
+        self.cask_details_dir()
+            .join(format!("{}.json", token.replace('/', "-")))
+    }
+
+    /// Cached details are reused until the cask index is refreshed: a file older than
+    /// `casks.json` may describe a version the index has since moved past.
+    fn cask_details_is_current(&self, path: &Path) -> bool {
+        let mtime = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+        match (mtime(path), mtime(&self.casks_path())) {
+            (Some(details), Some(index)) => details >= index,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
     fn tap_cache_path(&self, tap_name: &str) -> PathBuf {
         self.taps_cache_dir()
             .join(format!("{}.json", tap_name.replace('/', "-")))
@@ -102,99 +282,122 @@ impl Cache {
     }
 
     pub async fn ensure_fresh(&self) -> Result<()> {
+        // Formulae come from the local core tap; the cask index is fetched when first read.
+        if !self.is_initialized() && local_core_tap().is_some() {
+            return Ok(());
+        }
         if !self.is_initialized() {
             self.auto_init().await?;
             return Ok(());
         }
 
         let metadata = self.load_metadata().await?;
+        if self.refresh_pending.swap(false, Ordering::SeqCst) {
+            // Conditional fetch: a 304 keeps the files, new data overwrites them. Either
+            // way the in-process index is dropped so this run re-reads what is on disk.
+            let spinner = create_spinner("Refreshing index…");
+            let refreshed = self.refresh_stale(metadata.as_ref()).await;
+            spinner.finish_and_clear();
+            clear_formulae_index_cache();
+            return refreshed;
+        }
+
         let is_stale = match &metadata {
-            Some(m) => {
-                let now = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs() as i64;
-                (now - m.last_updated) > Self::STALE_THRESHOLD_SECS
-            }
+            Some(m) => (unix_now() - m.last_updated) > Self::STALE_THRESHOLD_SECS,
             None => true,
         };
 
         if is_stale {
             let spinner = create_spinner("Refreshing index…");
+            let refreshed = self.refresh_stale(metadata.as_ref()).await;
+            spinner.finish_and_clear();
 
-            let (formulae_etag, formulae_last_modified) = metadata
-                .as_ref()
-                .map(|m| {
-                    (
-                        m.formulae_etag.as_deref(),
-                        m.formulae_last_modified.as_deref(),
-                    )
-                })
-                .unwrap_or((None, None));
-
-            let (casks_etag, casks_last_modified) = metadata
-                .as_ref()
-                .map(|m| (m.casks_etag.as_deref(), m.casks_last_modified.as_deref()))
-                .unwrap_or((None, None));
-
-            let (formulae_result, casks_result) = tokio::join!(
-                self.fetch_formulae_conditional(formulae_etag, formulae_last_modified),
-                self.fetch_casks_conditional(casks_etag, casks_last_modified)
-            );
-
-            let formulae_fetch = formulae_result?;
-            let casks_fetch = casks_result?;
-
-            let formula_count = if let Some(data) = &formulae_fetch.data {
-                self.save_formulae(data).await?;
-                data.len()
-            } else {
-                metadata.as_ref().map(|m| m.formula_count).unwrap_or(0)
-            };
+            // Being offline is not fatal: the cached index still works, and the commands
+            // that read it note when it has grown old (`warn_if_index_old`). Any other
+            // failure (an error response, a bad index, an unwritable cache) is reported.
+            match refreshed {
+                Err(e) if is_unreachable(&e) => {
+                    debug!("Index refresh failed, using cached index: {}", e);
+                }
+                refreshed => refreshed?,
+            }
+        }
+        Ok(())
+    }
 
-            let cask_count = if let Some(data) = &casks_fetch.data {
-                self.save_casks(data).await?;
-                data.len()
-            } else {
-                metadata.as_ref().map(|m| m.cask_count).unwrap_or(0)
-            };
+    /// For the commands that read the index (`search`, `install`, `info`, `outdated`):
+    /// note when it is older than `WAX_INDEX_MAX_AGE_DAYS`, which happens when refreshes
+    /// keep failing because the API is unreachable.
+    pub async fn warn_if_index_old(&self) {
+        let Ok(Some(metadata)) = self.load_metadata().await else {
+            return;
+        };
+        if let Some(warning) = index_age_warning(unix_now() - metadata.last_updated) {
+            eprintln!("{} {}", console::style("note:").yellow(), warning);
+        }
+    }
 
-            let new_metadata = CacheMetadata {
-                last_updated: std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs() as i64,
-                formula_count,
-                cask_count,
-                formulae_etag: formulae_fetch
-                    .etag
-                    .or_else(|| metadata.as_ref().and_then(|m| m.formulae_etag.clone())),
-                formulae_last_modified: formulae_fetch.last_modified.or_else(|| {
-                    metadata
-                        .as_ref()
-                        .and_then(|m| m.formulae_last_modified.clone())
-                }),
-                casks_etag: casks_fetch
-                    .etag
-                    .or_else(|| metadata.as_ref().and_then(|m| m.casks_etag.clone())),
-                casks_last_modified: casks_fetch.last_modified.or_else(|| {
-                    metadata
-                        .as_ref()
-                        .and_then(|m| m.casks_last_modified.clone())
-                }),
-            };
-            self.save_metadata(&new_metadata).await?;
+    async fn refresh_stale(&self, metadata: Option<&CacheMetadata>) -> Result<()> {
+        let (formulae_etag, formulae_last_modified) = metadata
+            .map(|m| {
+                (
+                    m.formulae_etag.as_deref(),
+                    m.formulae_last_modified.as_deref(),
+                )
+            })
+            .unwrap_or((None, None));
 
-            spinner.finish_and_clear();
-        }
-        Ok(())
+        let (casks_etag, casks_last_modified) = metadata
+            .map(|m| (m.casks_etag.as_deref(), m.casks_last_modified.as_deref()))
+            .unwrap_or((None, None));
+
+        let (formulae_result, casks_result) = tokio::join!(
+            self.fetch_formulae_conditional(formulae_etag, formulae_last_modified),
+            self.fetch_casks_conditional(casks_etag, casks_last_modified)
+        );
+
+        let formulae_fetch = formulae_result?;
+        let casks_fetch = casks_result?;
+
+        let formula_count = if let Some(data) = &formulae_fetch.data {
+            self.save_formulae(data).await?;
+            data.len()
+        } else {
+            metadata.map(|m| m.formula_count).unwrap_or(0)
+        };
+
+        let cask_count = if let Some(data) = &casks_fetch.data {
+            self.save_casks(data).await?;
+            data.len()
+        } else {
+            metadata.map(|m| m.cask_count).unwrap_or(0)
+        };
+
+        let new_metadata = CacheMetadata {
+            last_updated: unix_now(),
+            formula_count,
+            cask_count,
+            formulae_etag: formulae_fetch
+                .etag
+                .or_else(|| metadata.and_then(|m| m.formulae_etag.clone())),
+            formulae_last_modified: formulae_fetch
+                .last_modified
+                .or_else(|| metadata.and_then(|m| m.formulae_last_modified.clone())),
+            casks_etag: casks_fetch
+                .etag
+                .or_else(|| metadata.and_then(|m| m.casks_etag.clone())),
+            casks_last_modified: casks_fetch
+                .last_modified
+                .or_else(|| metadata.and_then(|m| m.casks_last_modified.clone())),
+        };
+        self.save_metadata(&new_metadata).await
     }
 
     #[instrument(skip(self, formulae))]
     pub async fn save_formulae(&self, formulae: &[Formula]) -> Result<()> {
         self.ensure_cache_dir().await?;
         let json = serde_json::to_string(formulae)?;
-        fs::write(self.formulae_path(), json).await?;
+        write_atomic(&self.formulae_path(), json).await?;
         clear_formulae_index_cache();
         info!("Saved {} formulae to cache", formulae.len());
         Ok(())
@@ -204,7 +407,7 @@ impl Cache {
     pub async fn save_casks(&self, casks: &[Cask]) -> Result<()> {
         self.ensure_cache_dir().await?;
         let json = serde_json::to_string(casks)?;
-        fs::write(self.casks_path(), json).await?;
+        write_atomic(&self.casks_path(), json).await?;
         info!("Saved {} casks to cache", casks.len());
         Ok(())
     }
@@ -212,31 +415,37 @@ impl Cache {
     pub async fn save_metadata(&self, metadata: &CacheMetadata) -> Result<()> {
         self.ensure_cache_dir().await?;
         let json = serde_json::to_string_pretty(metadata)?;
-        fs::write(self.metadata_path(), json).await?;
-        Ok(())
+        write_atomic(&self.metadata_path(), json).await
     }
 
+    /// The core formulae: the API index, or with `WAX_CORE_TAP` set, the formulae of
+    /// that local homebrew-core checkout.
     pub async fn load_formulae(&self) -> Result<Vec<Formula>> {
-        let path = self.formulae_path();
-        if !path.exists() {
-            self.auto_init().await?;
+        if let Some(core) = local_core_tap() {
+            return load_local_core_formulae(&core).await;
         }
-        let json = fs::read_to_string(path).await?;
-        let formulae = serde_json::from_str(&json)?;
-        Ok(formulae)
+        self.load_index(self.formulae_path()).await
     }
 
     pub async fn load_casks(&self) -> Result<Vec<Cask>> {
-        let path = self.casks_path();
-        if !path.exists() {
-            self.auto_init().await?;
+        self.load_index(self.casks_path()).await
+    }
+
+    /// Read an index file, fetching it when it is missing or had to be quarantined as corrupt.
+    async fn load_index<T: DeserializeOwned>(&self, path: PathBuf) -> Result<T> {
+        if path.exists() {
+            let json = fs::read_to_string(&path).await?;
+            if let Some(index) = parse_or_quarantine(&path, &json) {
+                return Ok(index);
+            }
         }
-        let json = fs::read_to_string(path).await?;
-        let casks = serde_json::from_str(&json)?;
-        Ok(casks)
+        self.auto_init().await?;
+        let json = fs::read_to_string(&path).await?;
+        Ok(serde_json::from_str(&json)?)
     }
 
     async fn auto_init(&self) -> Result<()> {
+        self.ensure_cache_dir().await?;
         let spinner = create_spinner("Fetching package index…");
 
         let (formulae_result, casks_result) = tokio::join!(
@@ -278,8 +487,8 @@ impl Cache {
             return Ok(None);
         }
         let json = fs::read_to_string(self.metadata_path()).await?;
-        let metadata = serde_json::from_str(&json)?;
-        Ok(Some(metadata))
+        // Without metadata the index is treated as never fetched and refreshed.
+        Ok(parse_or_quarantine(&self.metadata_path(), &json))
     }
 
     pub async fn invalidate_tap_cache(&self, tap_name: &str) -> Result<()> {
@@ -292,6 +501,63 @@ impl Cache {
         Ok(())
     }
 
+    /// Cache entries `wax cleanup --prune-cache` may delete: tap JSON for taps that are
+    /// no longer registered, and cask details and other files wax wrote itself (see
+    /// `is_refetchable_artifact`) untouched for `max_age`. Anything else in the cache
+    /// directory is left alone. With `include_index`, the formula/cask index and every
+    /// tap cache go too.
+    pub fn stale_entries(
+        &self,
+        live_taps: &[String],
+        max_age: std::time::Duration,
+        include_index: bool,
+    ) -> Vec<PathBuf> {
+        let index_files = [
+            self.formulae_path(),
+            self.casks_path(),
+            self.metadata_path(),
+        ];
+        let taps_dir = self.taps_cache_dir();
+        let live_tap_files: Vec<PathBuf> =
+            live_taps.iter().map(|t| self.tap_cache_path(t)).collect();
+        let now = std::time::SystemTime::now();
+        let is_old = |path: &Path| {
+            std::fs::symlink_metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|mtime| now.duration_since(mtime).ok())
+                .is_some_and(|age| age >= max_age)
+        };
+
+        let mut stale = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(&taps_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if include_index || !live_tap_files.contains(&path) {
+                    stale.push(path);
+                }
+            }
+        }
+        if let Ok(entries) = std::fs::read_dir(self.cask_details_dir()) {
+            stale.extend(entries.flatten().map(|e| e.path()).filter(|p| is_old(p)));
+        }
+        if let Ok(entries) = std::fs::read_dir(&self.cache_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let stale_entry = if index_files.contains(&path) {
+                    include_index
+                } else {
+                    is_refetchable_artifact(&entry.file_name().to_string_lossy()) && is_old(&path)
+                };
+                if stale_entry {
+                    stale.push(path);
+                }
+            }
+        }
+        stale.sort();
+        stale
+    }
+
     pub async fn invalidate_all_tap_caches(&self) -> Result<()> {
         let taps_dir = self.taps_cache_dir();
         if taps_dir.exists() {
@@ -308,9 +574,10 @@ impl Cache {
         etag: Option<&str>,
         last_modified: Option<&str>,
     ) -> Result<FetchResult<Vec<Formula>>> {
-        info!("Fetching formulae from API with conditional headers");
-        let client = crate::http_client::api();
-        let mut request = client.get(FORMULA_API_URL);
+        let url = api_url("formula.json");
+        info!("Fetching formulae index: {}", url);
+        let client = crate::http_client::api(&url);
+        let mut request = client.get(&url);
 
         if let Some(etag) = etag {
             request = request.header("If-None-Match", etag);
@@ -361,9 +628,10 @@ impl Cache {
         etag: Option<&str>,
         last_modified: Option<&str>,
     ) -> Result<FetchResult<Vec<Cask>>> {
-        info!("Fetching casks from API with conditional headers");
-        let client = crate::http_client::api();
-        let mut request = client.get(CASK_API_URL);
+        let url = api_url("cask.json");
+        info!("Fetching casks index: {}", url);
+        let client = crate::http_client::api(&url);
+        let mut request = client.get(&url);
 
         if let Some(etag) = etag {
             request = request.header("If-None-Match", etag);
@@ -411,15 +679,112 @@ impl Cache {
     #[instrument(skip(self))]
     pub async fn fetch_cask_details(&self, cask_name: &str) -> Result<CaskDetails> {
         crate::error::validate_package_name(cask_name)?;
-        info!("Fetching details for cask: {}", cask_name);
-        let client = crate::http_client::api();
-        let url = format!("https://formulae.brew.sh/api/cask/{}.json", cask_name);
+        if let Some(details) = self
+            .resolved_casks
+            .lock()
+            .ok()
+            .and_then(|resolved| resolved.get(cask_name).cloned())
+        {
+            return Ok(details);
+        }
+        let cached_path = self.cask_details_path(cask_name);
+        if self.cask_details_is_current(&cached_path) {
+            let json = fs::read_to_string(&cached_path).await?;
+            if let Some(cask) = parse_or_quarantine::<CaskDetails>(&cached_path, &json) {
+                debug!("Using cached details for cask: {}", cask_name);
+                return Ok(cask);
+            }
+        }
+
+        // Versioned tokens (`firefox@esr`) have their own endpoint under the same name.
+        let url = api_url(&format!("cask/{}.json", cask_name));
+        let client = crate::http_client::api(&url);
+        info!("Fetching details for cask {}: {}", cask_name, url);
         let response = client.get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(crate::error::WaxError::CaskNotFound(format!(
+                "{} (HTTP {} from {})",
+                cask_name,
+                response.status(),
+                url
+            )));
+        }
         let cask: CaskDetails = response.json().await?;
         info!("Fetched details for cask: {}", cask_name);
+
+        // Best effort: a read-only cache must not turn a successful fetch into an error.
+        if fs::create_dir_all(self.cask_details_dir()).await.is_ok() {
+            if let Ok(json) = serde_json::to_string(&cask) {
+                let _ = write_atomic(&cached_path, json).await;
+            }
+        }
         Ok(cask)
     }
 
+    /// Fetch one formula from the per-formula API endpoint, for callers that want a
+    /// single known formula without downloading and parsing the full index.
+    #[instrument(skip(self))]
+    pub async fn fetch_formula(&self, name: &str) -> Result<Formula> {
+        crate::error::validate_package_name(name)?;
+        let url = api_url(&format!("formula/{}.json", name));
+        let client = crate::http_client::api(&url);
+        info!("Fetching formula: {}", url);
+        let response = client.get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(crate::error::WaxError::FormulaNotFound(format!(
+                "{} (HTTP {} from {})",
+                name,
+                response.status(),
+                url
+            )));
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Fetch details for `tokens` concurrently, in completion order.
+    pub async fn fetch_cask_details_many(
+        &self,
+        tokens: &[String],
+    ) -> Vec<(String, std::result::Result<CaskDetails, String>)> {
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(CASK_DETAILS_CONCURRENCY));
+        let mut tasks = tokio::task::JoinSet::new();
+        for token in tokens {
+            let cache = self.clone();
+            let semaphore = Arc::clone(&semaphore);
+            let token = token.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let result = cache.fetch_cask_details(&token).await;
+                (token, result.map_err(|e| e.to_string()))
+            });
+        }
+
+        let mut results = Vec::with_capacity(tokens.len());
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(result) => results.push(result),
+                Err(e) => results.push(("<task>".to_string(), Err(e.to_string()))),
+            }
+        }
+        results
+    }
+
+    /// Fetch details for `tokens` concurrently into the cask-details cache so later
+    /// commands (`outdated`, `upgrade`, `info --cask`) read them from disk.
+    /// Returns the number cached and the tokens that failed, with their errors.
+    pub async fn warm_cask_details(&self, tokens: &[String]) -> (usize, Vec<(String, String)>) {
+        let mut cached = 0;
+        let mut failed = Vec::new();
+        for (token, result) in self.fetch_cask_details_many(tokens).await {
+            match result {
+                Ok(_) => cached += 1,
+                Err(e) => failed.push((token, e)),
+            }
+        }
+        failed.sort();
+        (cached, failed)
+    }
+
     pub async fn load_all_formulae(&self) -> Result<Vec<Formula>> {
         let mut tap_manager = TapManager::new()?;
         tap_manager.load().await?;
@@ -444,13 +809,18 @@ impl Cache {
         for tap in tap_manager.trusted_taps() {
             let tap_cache_path = self.tap_cache_path(&tap.full_name);
 
-            let tap_formulae = if tap_cache_path.exists() {
+            let cached: Option<Vec<Formula>> = if tap_cache_path.exists() {
                 debug!(
                     "Loading tap formulae from cache: {}",
                     tap_cache_path.display()
                 );
                 let json = fs::read_to_string(&tap_cache_path).await?;
-                let mut formulae: Vec<Formula> = serde_json::from_str(&json)?;
+                parse_or_quarantine(&tap_cache_path, &json)
+            } else {
+                None
+            };
+
+            let tap_formulae = if let Some(mut formulae) = cached {
                 // rb_path is skipped during serialisation — restore it from the filesystem.
                 let formula_dir = tap.formula_dir();
                 for f in &mut formulae {
@@ -464,9 +834,11 @@ impl Cache {
                 debug!("Loading tap formulae from filesystem: {}", tap.full_name);
                 let formulae = tap_manager.load_formulae_from_tap(tap).await?;
 
-                fs::create_dir_all(self.taps_cache_dir()).await?;
-                let json = serde_json::to_string_pretty(&formulae)?;
-                fs::write(&tap_cache_path, json).await?;
+                // Best effort: a read-only cache only costs re-parsing the tap next time.
+                if fs::create_dir_all(self.taps_cache_dir()).await.is_ok() {
+                    let json = serde_json::to_string_pretty(&formulae)?;
+                    let _ = write_atomic(&tap_cache_path, json).await;
+                }
 
                 formulae
             };
@@ -485,6 +857,15 @@ impl Cache {
     }
 }
 
+fn unwritable_cache_error(cache_dir: &Path, err: &std::io::Error) -> WaxError {
+    WaxError::CacheError(format!(
+        "cache directory {} is not writable ({}); point WAX_CACHE_DIR at a writable \
+         directory, e.g. WAX_CACHE_DIR=/tmp/wax-cache",
+        cache_dir.display(),
+        err
+    ))
+}
+
 impl Default for Cache {
     fn default() -> Self {
         Self::new().expect("Failed to initialize cache")
@@ -495,6 +876,90 @@ impl Default for Cache {
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn unwritable_cache_dir_is_reported_with_relocation_hint() {
+        let tmp = tempfile::tempdir().unwrap();
+        let blocker = tmp.path().join("not-a-dir");
+        std::fs::write(&blocker, "").unwrap();
+        let cache = Cache {
+            cache_dir: blocker.join("cache"),
+            refresh_pending: Arc::default(),
+            resolved_casks: Arc::default(),
+        };
+
+        let err = cache.save_metadata(&CacheMetadata {
+            last_updated: 0,
+            formula_count: 0,
+            cask_count: 0,
+            formulae_etag: None,
+            formulae_last_modified: None,
+            casks_etag: None,
+            casks_last_modified: None,
+        });
+        let message = err.await.unwrap_err().to_string();
+        assert!(message.contains("is not writable"), "{message}");
+        assert!(message.contains("WAX_CACHE_DIR"), "{message}");
+
+        let writable = Cache {
+            cache_dir: tmp.path().join("cache"),
+            refresh_pending: Arc::default(),
+            resolved_casks: Arc::default(),
+        };
+        writable.ensure_cache_dir().await.unwrap();
+        assert_eq!(
+            std::fs::read_dir(tmp.path().join("cache")).unwrap().count(),
+            0
+        );
+    }
+
+    #[test]
+    fn stale_entries_keeps_index_live_taps_and_foreign_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = Cache {
+            cache_dir: tmp.path().to_path_buf(),
+            refresh_pending: Arc::default(),
+            resolved_casks: Arc::default(),
+        };
+        let taps = cache.taps_cache_dir();
+        std::fs::create_dir_all(&taps).unwrap();
+        for file in [
+            "formulae.json",
+            "casks.json",
+            "metadata.json",
+            "scoop_main_index.json",
+            "installed.json.corrupt",
+            "bottle.tar.gz",
+        ] {
+            std::fs::write(tmp.path().join(file), "{}").unwrap();
+        }
+        std::fs::create_dir_all(cache.cask_details_dir()).unwrap();
+        std::fs::write(cache.cask_details_path("firefox"), "{}").unwrap();
+        std::fs::write(cache.tap_cache_path("user/live"), "[]").unwrap();
+        std::fs::write(cache.tap_cache_path("user/gone"), "[]").unwrap();
+        let live = vec!["user/live".to_string()];
+
+        let day = std::time::Duration::from_secs(86_400);
+        assert_eq!(
+            cache.stale_entries(&live, day, false),
+            vec![cache.tap_cache_path("user/gone")]
+        );
+
+        let stale = cache.stale_entries(&live, std::time::Duration::ZERO, false);
+        assert!(stale.contains(&tmp.path().join("scoop_main_index.json")));
+        assert!(stale.contains(&tmp.path().join("installed.json.corrupt")));
+        assert!(stale.contains(&cache.cask_details_path("firefox")));
+        // Files wax did not write (another tool's, or the user's) are never pruned.
+        assert!(!stale.contains(&tmp.path().join("bottle.tar.gz")));
+        assert!(!stale.contains(&cache.cask_details_dir()));
+        assert!(!stale.contains(&cache.formulae_path()));
+        assert!(!stale.contains(&cache.tap_cache_path("user/live")));
+
+        let all = cache.stale_entries(&live, day, true);
+        assert!(all.contains(&cache.formulae_path()));
+        assert!(all.contains(&cache.tap_cache_path("user/live")));
+        assert!(!all.contains(&tmp.path().join("bottle.tar.gz")));
+    }
+
     #[test]
     fn cache_metadata_serializes_roundtrip() {
         let meta = CacheMetadata {
@@ -525,6 +990,78 @@ mod tests {
         assert!(ts > 1_577_836_800, "timestamp looks wrong: {ts}");
     }
 
+    #[test]
+    fn index_age_warning_respects_threshold() {
+        assert_eq!(index_age_warning_with(6 * 86_400, 7), None);
+        assert_eq!(
+            index_age_warning_with(10 * 86_400 + 5, 7).as_deref(),
+            Some("package index is 10 days old — run `wax update`")
+        );
+        assert_eq!(index_age_warning_with(400 * 86_400, 0), None);
+    }
+
+    #[tokio::test]
+    async fn cask_details_cache_is_reused_until_index_refresh() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = Cache {
+            cache_dir: tmp.path().to_path_buf(),
+            refresh_pending: Arc::default(),
+            resolved_casks: Arc::default(),
+        };
+        std::fs::write(cache.casks_path(), "[]").unwrap();
+        std::fs::create_dir_all(cache.cask_details_dir()).unwrap();
+        let details = serde_json::json!({
+            "token": "firefox",
+            "name": ["Firefox"],
+            "homepage": "https://www.mozilla.org/firefox/",
+            "version": "130.0",
+            "url": "https://example.com/firefox.dmg",
+            "sha256": "abc",
+        });
+        let path = cache.cask_details_path("firefox");
+        std::fs::write(&path, details.to_string()).unwrap();
+
+        let cached = cache.fetch_cask_details("firefox").await.unwrap();
+        assert_eq!(cached.version, "130.0");
+
+        let older = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(older)
+            .unwrap();
+        assert!(!cache.cask_details_is_current(&path));
+    }
+
+    #[tokio::test]
+    async fn remembered_cask_details_skip_disk_and_network() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = Cache {
+            cache_dir: tmp.path().to_path_buf(),
+            refresh_pending: Arc::default(),
+            resolved_casks: Arc::default(),
+        };
+        let details: CaskDetails = serde_json::from_value(serde_json::json!({
+            "token": "firefox",
+            "name": ["Firefox"],
+            "homepage": "https://www.mozilla.org/firefox/",
+            "version": "131.0",
+            "url": "https://example.com/firefox.dmg",
+            "sha256": "abc",
+        }))
+        .unwrap();
+        cache.remember_cask_details("firefox", details);
+
+        let clone = cache.clone();
+        let fetched = clone
+            .fetch_cask_details_many(&["firefox".to_string()])
+            .await;
+        assert_eq!(fetched.len(), 1);
+        assert_eq!(fetched[0].1.as_ref().unwrap().version, "131.0");
+        assert!(!cache.cask_details_path("firefox").exists());
+    }
+
     #[test]
     fn stale_threshold_constant_is_one_hour() {
         assert_eq!(Cache::STALE_THRESHOLD_SECS, 3600);
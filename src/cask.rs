@@ -1,6 +1,8 @@
 use crate::api::{Cask, CaskDetails};
 use crate::bottle::{homebrew_prefix, BottleDownloader, DownloadTotals};
 use crate::error::{Result, WaxError};
+use crate::install::InstallMode;
+use crate::state_file::{parse_or_quarantine, to_sorted_json, write_atomic};
 use crate::ui::dirs;
 use crate::version::sort_versions;
 use indicatif::ProgressBar;
@@ -9,7 +11,7 @@ use serde_json::json;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::UNIX_EPOCH;
 use tokio::fs;
 use tracing::{debug, info, instrument};
 
@@ -24,6 +26,18 @@ pub struct InstalledCask {
     pub binary_paths: Option<Vec<String>>,
     #[serde(default)]
     pub app_name: Option<String>,
+    /// Further `.app` bundles when a cask ships more than one; `app_name` is the first.
+    #[serde(default)]
+    pub additional_apps: Option<Vec<String>>,
+}
+
+impl InstalledCask {
+    /// Every recorded `.app` bundle, primary first.
+    pub fn app_names(&self) -> impl Iterator<Item = &String> {
+        self.app_name
+            .iter()
+            .chain(self.additional_apps.iter().flatten())
+    }
 }
 
 static CASK_STATE_WRITE_LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
@@ -37,19 +51,6 @@ pub struct CaskState {
     legacy_state_path: PathBuf,
 }
 
-fn temp_path_for(path: &Path) -> PathBuf {
-    let pid = std::process::id();
-    let nanos = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_nanos())
-        .unwrap_or_default();
-    let file_name = path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("installed_casks.json");
-    path.with_file_name(format!(".{}.{}.{}.tmp", file_name, pid, nanos))
-}
-
 fn normalize_existing_prefix(path: &Path) -> PathBuf {
     if let Ok(normalized) = dunce::canonicalize(path) {
         return normalized;
@@ -174,7 +175,8 @@ fn merge_caskroom_entry(
                 .as_ref()
                 .and_then(|cask| cask.artifact_type.clone()),
             binary_paths: existing.as_ref().and_then(|cask| cask.binary_paths.clone()),
-            app_name: existing.and_then(|cask| cask.app_name),
+            app_name: existing.as_ref().and_then(|cask| cask.app_name.clone()),
+            additional_apps: existing.and_then(|cask| cask.additional_apps),
         },
     );
 }
@@ -235,6 +237,21 @@ fn homebrew_metadata_timestamp(install_date: i64) -> String {
     format!("{year:04}{month:02}{day:02}{hour:02}{minute:02}{second:02}.000")
 }
 
+/// `firefox@esr` → `firefox`: the base token of a versioned cask token, if it is one.
+pub fn versioned_token_base(token: &str) -> Option<&str> {
+    let (base, variant) = token.rsplit_once('@')?;
+    (!base.is_empty() && !variant.is_empty()).then_some(base)
+}
+
+/// `YYYY-MM-DD` (UTC) for a recorded install date; `None` when it was never recorded.
+pub fn format_install_date(install_date: i64) -> Option<String> {
+    if install_date <= 0 {
+        return None;
+    }
+    let (year, month, day) = civil_from_days(install_date.div_euclid(86_400));
+    Some(format!("{year:04}-{month:02}-{day:02}"))
+}
+
 fn civil_from_days(days_since_unix_epoch: i64) -> (i32, u32, u32) {
     let z = days_since_unix_epoch + 719_468;
     let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
@@ -273,7 +290,7 @@ fn fallback_cask_display_name(token: &str) -> String {
 fn cask_artifacts_from_installed(cask: &InstalledCask) -> Vec<serde_json::Value> {
     let mut artifacts = Vec::new();
 
-    if let Some(app_name) = &cask.app_name {
+    for app_name in cask.app_names() {
         artifacts.push(json!({ "app": [app_name] }));
     }
 
@@ -369,9 +386,11 @@ impl CaskState {
         // This ensures we only show casks that were explicitly tracked
         if self.legacy_state_path.exists() {
             if let Ok(json) = fs::read_to_string(&self.legacy_state_path).await {
-                if let Ok(legacy_casks) =
-                    serde_json::from_str::<HashMap<String, InstalledCask>>(&json)
-                {
+                // A corrupt file is moved aside; saving over it would silently drop every cask.
+                if let Some(legacy_casks) = parse_or_quarantine::<HashMap<String, InstalledCask>>(
+                    &self.legacy_state_path,
+                    &json,
+                ) {
                     casks.extend(legacy_casks);
                 }
             }
@@ -387,6 +406,16 @@ impl CaskState {
 
     pub async fn sync_from_caskrooms(&self) -> Result<HashSet<String>> {
         let _guard = cask_state_write_lock().lock().await;
+        let (casks, synced_names) = self.load_with_caskrooms().await?;
+        self.save(&casks).await?;
+        Ok(synced_names)
+    }
+
+    /// Recorded casks updated from the Caskrooms without saving, with the names found
+    /// there.
+    pub async fn load_with_caskrooms(
+        &self,
+    ) -> Result<(HashMap<String, InstalledCask>, HashSet<String>)> {
         let mut casks = self.load().await?;
         let mut synced_names = HashSet::new();
         let mut roots = vec![Self::caskroom_dir()];
@@ -423,8 +452,7 @@ impl CaskState {
             }
         }
 
-        self.save(&casks).await?;
-        Ok(synced_names)
+        Ok((casks, synced_names))
     }
 
     pub async fn save(&self, casks: &HashMap<String, InstalledCask>) -> Result<()> {
@@ -434,15 +462,8 @@ impl CaskState {
             .ok_or_else(|| WaxError::CacheError("Cannot determine parent directory".into()))?;
         fs::create_dir_all(parent).await?;
 
-        let json = serde_json::to_string_pretty(casks)?;
-        let temp_path = temp_path_for(&self.legacy_state_path);
-        fs::write(&temp_path, json).await?;
-        fs::rename(&temp_path, &self.legacy_state_path)
-            .await
-            .inspect_err(|_| {
-                let _ = std::fs::remove_file(&temp_path);
-            })?;
-        Ok(())
+        let json = to_sorted_json(casks)?;
+        write_atomic(&self.legacy_state_path, json).await
     }
 
     #[allow(dead_code)]
@@ -512,7 +533,7 @@ impl CaskState {
         fs::create_dir_all(&metadata_dir).await?;
 
         let json = serde_json::to_string_pretty(source)?;
-        fs::write(metadata_file, json).await?;
+        write_atomic(&metadata_file, json).await?;
         Ok(true)
     }
 
@@ -605,7 +626,7 @@ pub async fn relink_installed_cask(cask: &InstalledCask) -> Result<Vec<PathBuf>>
         return Ok(links);
     };
 
-    if let Some(app_name) = &cask.app_name {
+    for app_name in cask.app_names() {
         #[cfg(target_os = "macos")]
         {
             let app_path = PathBuf::from("/Applications").join(app_name);
@@ -645,7 +666,7 @@ pub async fn unlink_installed_cask(cask: &InstalledCask) -> Result<Vec<PathBuf>>
     let mut removed = Vec::new();
     let version_dir = installed_cask_version_dir(cask).await?;
 
-    if let Some(app_name) = &cask.app_name {
+    for app_name in cask.app_names() {
         if let Some(version_dir) = &version_dir {
             let link_path = version_dir.join(app_name);
             if link_path.exists() {
@@ -866,6 +887,15 @@ impl StagingContext {
                     )));
                 }
             }
+            "7z" => {
+                let archive = download_path.to_path_buf();
+                let dest = staging_root.clone();
+                tokio::task::spawn_blocking(move || {
+                    crate::bottle::BottleDownloader::extract(&archive, &dest)
+                })
+                .await
+                .map_err(|e| WaxError::InstallError(format!("Extraction task failed: {}", e)))??;
+            }
             "tar.gz" | "tar" | "tgz" | "tar.bz2" | "tbz" | "tar.xz" | "txz" => {
                 let tar_output = tokio::process::Command::new("tar")
                     .arg("-xf")
@@ -980,24 +1010,20 @@ impl CaskInstaller {
         }
     }
 
-    pub async fn detect_writable_bin_dir() -> Result<PathBuf> {
-        let candidates = vec![
-            crate::bottle::homebrew_prefix().join("bin"),
-            PathBuf::from("/usr/local/bin"),
-            PathBuf::from("/opt/homebrew/bin"),
-        ];
-
-        for candidate in candidates {
-            if candidate.exists() && Self::is_dir_writable(&candidate).await {
-                debug!("Using writable bin directory: {:?}", candidate);
-                return Ok(candidate);
-            }
-        }
-
-        let local_bin = Self::user_bin_dir()?;
-        tokio::fs::create_dir_all(&local_bin).await?;
-        debug!("Using fallback bin directory: {:?}", local_bin);
-        Ok(local_bin)
+    /// Directory `binary` artifacts are linked into: the `bin` of the prefix
+    /// chosen by [`InstallMode::detect`], the same one formulae link into.
+    pub async fn binary_link_dir() -> Result<PathBuf> {
+        let global_bin = crate::bottle::homebrew_prefix().join("bin");
+        let bin_dir = if InstallMode::detect() == InstallMode::Global
+            && (!global_bin.exists() || Self::is_dir_writable(&global_bin).await)
+        {
+            global_bin
+        } else {
+            Self::user_bin_dir()?
+        };
+        tokio::fs::create_dir_all(&bin_dir).await?;
+        debug!("Linking cask binaries into {:?}", bin_dir);
+        Ok(bin_dir)
     }
 
     fn user_bin_dir() -> Result<PathBuf> {
@@ -1138,7 +1164,7 @@ impl CaskInstaller {
     /// Falls back to a ranged GET if HEAD is not supported (e.g. 405).
     /// Returns None if type cannot be determined.
     pub async fn probe_artifact_type(&self, url: &str) -> Option<&'static str> {
-        let client = crate::http_client::default_client();
+        let client = crate::http_client::default_client(url);
 
         let response = match client.head(url).send().await {
             Ok(r) if r.status().is_success() => r,
@@ -1457,7 +1483,21 @@ impl CaskInstaller {
             return Ok(None);
         }
 
-        let bin_dest_dir = Self::detect_writable_bin_dir().await?;
+        let bin_dest_dir = Self::binary_link_dir().await?;
+        Self::link_binary(staging, rollback, &source, name, &bin_dest_dir)
+            .await
+            .map(Some)
+    }
+
+    /// Symlink an extracted executable into `bin_dest_dir` as `name`, replacing
+    /// whatever is there, and return the link path for uninstall tracking.
+    async fn link_binary(
+        staging: &StagingContext,
+        rollback: &mut RollbackContext,
+        source: &Path,
+        name: &str,
+        bin_dest_dir: &Path,
+    ) -> Result<PathBuf> {
         let binary_dest_path = bin_dest_dir.join(name);
 
         if let Ok(metadata) = tokio::fs::symlink_metadata(&binary_dest_path).await {
@@ -1482,10 +1522,10 @@ impl CaskInstaller {
                 let perm_dir = staging.permanent_dir();
                 tokio::fs::create_dir_all(&perm_dir).await?;
                 let dest = perm_dir.join(name);
-                tokio::fs::copy(&source, &dest).await?;
+                tokio::fs::copy(source, &dest).await?;
                 dest
             } else {
-                source
+                source.to_path_buf()
             };
 
             tokio::fs::symlink(&link_target, &binary_dest_path).await?;
@@ -1496,7 +1536,8 @@ impl CaskInstaller {
         }
         #[cfg(not(unix))]
         {
-            tokio::fs::copy(&source, &binary_dest_path).await?;
+            let _ = staging;
+            tokio::fs::copy(source, &binary_dest_path).await?;
         }
 
         info!(
@@ -1505,7 +1546,7 @@ impl CaskInstaller {
             bin_dest_dir.display()
         );
 
-        Ok(Some(binary_dest_path))
+        Ok(binary_dest_path)
     }
 
     #[instrument(skip(self, staging, rollback))]
@@ -1727,6 +1768,8 @@ pub fn detect_artifact_type(url: &str) -> Option<&'static str> {
         Some("pkg")
     } else if path.ends_with(".zip") {
         Some("zip")
+    } else if path.ends_with(".7z") {
+        Some("7z")
     } else if path.ends_with(".tar.gz")
         || path.ends_with(".tgz")
         || path.ends_with(".tar.bz2")
@@ -1750,6 +1793,7 @@ pub fn detect_artifact_type_from_content_type(content_type: &str) -> Option<&'st
         "application/x-apple-diskimage" => Some("dmg"),
         "application/octet-stream" => Some("binary"),
         "application/zip" | "application/x-zip-compressed" => Some("zip"),
+        "application/x-7z-compressed" => Some("7z"),
         "application/x-tar" | "application/gzip" | "application/x-gzip" => Some("tar.gz"),
         "application/x-pkg" | "application/vnd.apple.installer+xml" => Some("pkg"),
         _ => None,
@@ -1916,6 +1960,42 @@ mod tests {
         assert_eq!(resolved, target);
     }
 
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn binary_artifact_is_linked_into_bin_dir() {
+        let temp = tempdir().unwrap();
+        let staging_root = temp.path().join("staging");
+        std::fs::create_dir_all(staging_root.join("foo-1.0")).unwrap();
+        std::fs::write(staging_root.join("foo-1.0/foo"), "#!/bin/sh\necho foo\n").unwrap();
+        let bin_dir = temp.path().join("prefix/bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        // A stale file at the destination is replaced.
+        std::fs::write(bin_dir.join("foo"), "old").unwrap();
+
+        let installer = CaskInstaller::new();
+        let staging = StagingContext {
+            staging_root: staging_root.clone(),
+            mount_point: None,
+            _temp_dir: None,
+        };
+        let mut rollback = RollbackContext::new();
+        let source = installer.resolve_source_path(&staging, "foo-1.0/foo");
+
+        let link = CaskInstaller::link_binary(&staging, &mut rollback, &source, "foo", &bin_dir)
+            .await
+            .unwrap();
+        rollback.commit();
+
+        assert_eq!(link, bin_dir.join("foo"));
+        assert_eq!(
+            std::fs::read_link(&link).unwrap(),
+            staging_root.join("foo-1.0/foo")
+        );
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&link).unwrap().permissions().mode();
+        assert_eq!(mode & 0o111, 0o111);
+    }
+
     #[test]
     fn user_bin_dir_matches_documented_path() {
         let user_bin_dir = CaskInstaller::user_bin_dir().unwrap();
@@ -1930,6 +2010,24 @@ mod tests {
         assert_eq!(homebrew_metadata_timestamp(0), "19700101000000.000");
     }
 
+    #[test]
+    fn format_install_date_is_utc_day_or_unknown() {
+        assert_eq!(
+            format_install_date(1_700_000_000).as_deref(),
+            Some("2023-11-14")
+        );
+        assert_eq!(format_install_date(0), None);
+    }
+
+    #[test]
+    fn versioned_token_base_splits_on_last_at() {
+        assert_eq!(versioned_token_base("firefox@esr"), Some("firefox"));
+        assert_eq!(versioned_token_base("font-foo@2"), Some("font-foo"));
+        assert_eq!(versioned_token_base("firefox"), None);
+        assert_eq!(versioned_token_base("@esr"), None);
+        assert_eq!(versioned_token_base("firefox@"), None);
+    }
+
     #[test]
     fn detects_homebrew_cask_metadata_file() {
         let temp = tempdir().unwrap();
@@ -1957,6 +2055,7 @@ mod tests {
             artifact_type: Some("dmg".to_string()),
             binary_paths: Some(vec!["/opt/homebrew/bin/example".to_string()]),
             app_name: Some("Example.app".to_string()),
+            additional_apps: None,
         };
 
         let source = cask_metadata_from_installed(&cask, None);
@@ -1969,6 +2068,29 @@ mod tests {
         assert_eq!(source["artifacts"].as_array().unwrap().len(), 2);
     }
 
+    #[test]
+    fn fallback_cask_metadata_lists_every_app_and_binary() {
+        let cask = InstalledCask {
+            name: "example-suite".to_string(),
+            version: "2.0.0".to_string(),
+            install_date: 0,
+            artifact_type: Some("dmg".to_string()),
+            binary_paths: Some(vec!["/opt/homebrew/bin/example".to_string()]),
+            app_name: Some("Example.app".to_string()),
+            additional_apps: Some(vec!["Example Helper.app".to_string()]),
+        };
+
+        assert_eq!(
+            cask.app_names().collect::<Vec<_>>(),
+            vec!["Example.app", "Example Helper.app"]
+        );
+
+        let source = cask_metadata_from_installed(&cask, None);
+        let artifacts = source["artifacts"].as_array().unwrap();
+        assert_eq!(artifacts.len(), 3);
+        assert_eq!(artifacts[1]["app"][0], "Example Helper.app");
+    }
+
     #[test]
     fn latest_caskroom_version_prefers_homebrew_metadata() {
         let temp = tempdir().unwrap();
@@ -2021,6 +2143,10 @@ mod tests {
             detect_artifact_type("https://example.com/app.zip"),
             Some("zip")
         );
+        assert_eq!(
+            detect_artifact_type("https://example.com/app-1.0.7z"),
+            Some("7z")
+        );
 
         // Tarball variants all map to "tar.gz"
         assert_eq!(
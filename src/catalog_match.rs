@@ -1,11 +1,18 @@
 //! Shared catalogue search scoring (Homebrew names, Scoop/winget/choco ids).
 
+use crate::version::BrewVersion;
+use std::cmp::Ordering;
+
 pub fn catalog_match_score(name: &str, query: &str) -> Option<i32> {
     let q = query.to_lowercase();
     let n = name.to_lowercase();
     if n == q {
         return Some(1000);
     }
+    // `python` → `python@3.12`: a versioned release of exactly what was asked for.
+    if n.strip_prefix(&q).is_some_and(|rest| rest.starts_with('@')) {
+        return Some(950);
+    }
     if n.starts_with(&q) {
         return Some(900);
     }
@@ -26,6 +33,23 @@ pub fn catalog_match_score(name: &str, query: &str) -> Option<i32> {
     None
 }
 
+/// `python@3.12` → `("python", "3.12")`.
+fn versioned_family(name: &str) -> Option<(&str, &str)> {
+    name.split_once('@').filter(|(base, _)| !base.is_empty())
+}
+
+/// Result order: higher score first; within one versioned family (`python@3.12`,
+/// `python@3.9`) the newest version first; otherwise by name.
+pub fn compare_ranked(a: (&str, i32), b: (&str, i32)) -> Ordering {
+    b.1.cmp(&a.1)
+        .then_with(|| match (versioned_family(a.0), versioned_family(b.0)) {
+            (Some((base_a, ver_a)), Some((base_b, ver_b))) if base_a == base_b => {
+                BrewVersion::parse(ver_b).cmp(&BrewVersion::parse(ver_a))
+            }
+            _ => a.0.cmp(b.0),
+        })
+}
+
 #[cfg(not(target_os = "windows"))]
 pub fn match_score(name: &str, desc: Option<&str>, query: &str) -> Option<i32> {
     let mut best = catalog_match_score(name, query);
@@ -57,6 +81,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn versioned_family_ranks_below_exact_match_newest_first() {
+        let names = [
+            "ipython",
+            "python@3.9",
+            "python-tk",
+            "python",
+            "python@3.12",
+            "python@3.11",
+        ];
+        let mut ranked: Vec<(&str, i32)> = names
+            .iter()
+            .filter_map(|n| catalog_match_score(n, "python").map(|s| (*n, s)))
+            .collect();
+        ranked.sort_by(|a, b| compare_ranked(*a, *b));
+        let order: Vec<&str> = ranked.iter().map(|(n, _)| *n).collect();
+        assert_eq!(
+            order,
+            [
+                "python",
+                "python@3.12",
+                "python@3.11",
+                "python@3.9",
+                "python-tk",
+                "ipython"
+            ]
+        );
+    }
+
     #[test]
     #[cfg(not(target_os = "windows"))]
     fn desc_boosts_score() {
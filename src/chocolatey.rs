@@ -26,8 +26,8 @@ pub async fn search_package_ids(query: &str, limit: usize) -> Result<Vec<String>
         &[("q", query)],
     )
     .map_err(|e| WaxError::InvalidInput(format!("invalid chocolatey search URL: {e}")))?;
-    let html = crate::http_client::default_client()
-        .get(url)
+    let html = crate::http_client::default_client(url.as_str())
+        .get(url.clone())
         .send()
         .await?
         .text()
@@ -53,7 +53,11 @@ pub async fn search_package_ids(query: &str, limit: usize) -> Result<Vec<String>
 pub async fn package_exists(id: &str) -> bool {
     let url = format!("https://community.chocolatey.org/api/v2/package/{}", id);
     // Chocolatey v2 feed returns 501 for HEAD; GET redirects to the .nupkg on success.
-    match crate::http_client::default_client().get(&url).send().await {
+    match crate::http_client::default_client(&url)
+        .get(&url)
+        .send()
+        .await
+    {
         Ok(r) => r.status().is_success() || r.status().is_redirection(),
         Err(_) => false,
     }
@@ -0,0 +1,135 @@
+use crate::cache::Cache;
+use crate::commands::cleanup::{prune_cache, CachePrune};
+use crate::error::Result;
+use crate::install::InstallMode;
+use crate::ui::{dir_size, format_bytes};
+use console::style;
+use std::path::Path;
+
+fn path_size(path: &Path) -> u64 {
+    if path.is_dir() {
+        dir_size(path)
+    } else {
+        std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    }
+}
+
+/// `3 hours ago`, for the index age.
+fn format_age(secs: i64) -> String {
+    let (count, unit) = match secs.max(0) {
+        s if s < 60 => return "just now".to_string(),
+        s if s < 3_600 => (s / 60, "minute"),
+        s if s < 86_400 => (s / 3_600, "hour"),
+        s => (s / 86_400, "day"),
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// `wax cache info`: how much disk the cache and each Cellar use, and how old the index is.
+pub async fn info(cache: &Cache) -> Result<()> {
+    let cache_dir = cache.cache_dir_path();
+    println!("{} {}", style("cache:").bold(), cache_dir.display());
+
+    let mut total = 0;
+    for (section, paths) in cache.sections() {
+        let size: u64 = paths.iter().map(|p| path_size(p)).sum();
+        total += size;
+        let mut line = format!("  {:<14}{:>10}", section, format_bytes(size));
+        if section == "index" {
+            match cache.load_metadata().await.ok().flatten() {
+                Some(meta) => {
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs() as i64;
+                    line.push_str(&format!(
+                        "  {}",
+                        style(format!(
+                            "updated {} ({} formulae, {} {})",
+                            format_age(now - meta.last_updated),
+                            meta.formula_count,
+                            meta.cask_count,
+                            if meta.cask_count == 1 {
+                                "cask"
+                            } else {
+                                "casks"
+                            }
+                        ))
+                        .dim()
+                    ));
+                }
+                None => line.push_str(&format!(
+                    "  {}",
+                    style("not downloaded yet (run `wax update`)").dim()
+                )),
+            }
+        }
+        println!("{}", line);
+    }
+    println!("  {:<14}{:>10}", "total", format_bytes(total));
+
+    for (label, mode) in [("user", InstallMode::User), ("global", InstallMode::Global)] {
+        let Ok(cellar) = mode.cellar_path() else {
+            continue;
+        };
+        println!();
+        println!(
+            "{} {}",
+            style(format!("{} cellar:", label)).bold(),
+            cellar.display()
+        );
+        if !cellar.is_dir() {
+            println!("  {}", style("not present").dim());
+            continue;
+        }
+        let kegs = std::fs::read_dir(&cellar)
+            .map(|entries| entries.flatten().filter(|e| e.path().is_dir()).count())
+            .unwrap_or(0);
+        println!(
+            "  {} {}, {}",
+            kegs,
+            if kegs == 1 { "formula" } else { "formulae" },
+            format_bytes(dir_size(&cellar))
+        );
+    }
+    Ok(())
+}
+
+/// `wax cache clean`: drop what wax can fetch again on demand (the "cask details" and
+/// "catalogues" sections of [`Cache::sections`], caches of removed taps), keeping the
+/// package index, caches of current taps, and files wax did not write.
+pub async fn clean(cache: &Cache, dry_run: bool) -> Result<()> {
+    let prune = CachePrune {
+        older_than_days: 0,
+        all: false,
+    };
+    let (count, freed) = prune_cache(cache, &prune, dry_run).await?;
+    if count == 0 {
+        println!("nothing to clean up");
+    } else if dry_run {
+        println!("\nwould free {}", format_bytes(freed));
+        println!("run without --dry-run to remove");
+    } else {
+        println!("\nfreed {}", format_bytes(freed));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_age_picks_the_largest_whole_unit() {
+        assert_eq!(format_age(5), "just now");
+        assert_eq!(format_age(60), "1 minute ago");
+        assert_eq!(format_age(3 * 3_600 + 59), "3 hours ago");
+        assert_eq!(format_age(86_400), "1 day ago");
+        assert_eq!(format_age(-10), "just now");
+    }
+}
@@ -0,0 +1,33 @@
+use crate::cache::Cache;
+use crate::error::{Result, WaxError};
+use crate::formula_parser::FormulaParser;
+use tracing::instrument;
+
+/// `wax cat`: the Ruby source wax would parse for a source build of `name`.
+#[instrument(skip(cache))]
+pub async fn cat(cache: &Cache, name: &str) -> Result<()> {
+    cache.ensure_fresh().await?;
+
+    let formulae = cache.load_all_formulae().await?;
+    let formula = formulae
+        .iter()
+        .find(|f| f.name == name || f.full_name == name)
+        .ok_or_else(|| WaxError::FormulaNotFound(name.to_string()))?;
+
+    let ruby = match &formula.rb_path {
+        Some(rb_path) => tokio::fs::read_to_string(rb_path).await.map_err(|e| {
+            WaxError::InvalidInput(format!(
+                "Failed to read formula file {}: {}",
+                rb_path.display(),
+                e
+            ))
+        })?,
+        None => FormulaParser::fetch_formula_rb(&formula.name).await?,
+    };
+
+    print!("{}", ruby);
+    if !ruby.ends_with('\n') {
+        println!();
+    }
+    Ok(())
+}
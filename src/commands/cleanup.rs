@@ -1,9 +1,20 @@
+use crate::cache::Cache;
 use crate::error::Result;
 use crate::install::InstallState;
+use crate::tap::TapManager;
+use crate::ui::{dir_size, format_bytes};
 use crate::version::sort_versions;
 use console::style;
+use std::time::Duration;
 
-pub async fn cleanup(dry_run: bool) -> Result<()> {
+/// What `--prune-cache` removes besides old Cellar versions.
+pub struct CachePrune {
+    pub older_than_days: u64,
+    /// Also drop the formula/cask index and every tap cache.
+    pub all: bool,
+}
+
+pub async fn cleanup(cache: &Cache, dry_run: bool, prune: Option<CachePrune>) -> Result<()> {
     let state = InstallState::new()?;
     state.sync_from_cellar().await.ok();
     let installed = state.load().await?;
@@ -70,51 +81,109 @@ pub async fn cleanup(dry_run: bool) -> Result<()> {
         }
     }
 
-    if removed_count == 0 {
+    let (cache_count, cache_freed) = match prune {
+        Some(prune) => prune_cache(cache, &prune, dry_run).await?,
+        None => (0, 0),
+    };
+    total_freed += cache_freed;
+
+    if removed_count == 0 && cache_count == 0 {
         println!("nothing to clean up");
-    } else if dry_run {
-        println!(
-            "\nwould free {} across {} old version{}",
-            format_bytes(total_freed),
+        return Ok(());
+    }
+
+    let mut what = Vec::new();
+    if removed_count > 0 {
+        what.push(format!(
+            "{} old version{}",
             removed_count,
             if removed_count == 1 { "" } else { "s" }
+        ));
+    }
+    if cache_count > 0 {
+        what.push(format!(
+            "{} cache entr{}",
+            cache_count,
+            if cache_count == 1 { "y" } else { "ies" }
+        ));
+    }
+    if dry_run {
+        println!(
+            "\nwould free {} across {}",
+            format_bytes(total_freed),
+            what.join(" and ")
         );
         println!("run without --dry-run to remove");
     } else {
         println!(
-            "\nfreed {} ({} old version{} removed)",
+            "\nfreed {} ({} removed)",
             format_bytes(total_freed),
-            removed_count,
-            if removed_count == 1 { "" } else { "s" }
+            what.join(" and ")
         );
     }
 
     Ok(())
 }
 
-fn dir_size(path: &std::path::Path) -> u64 {
-    let mut total = 0u64;
-    if let Ok(entries) = std::fs::read_dir(path) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            let p = entry.path();
-            if p.is_dir() {
-                total += dir_size(&p);
-            } else if let Ok(meta) = std::fs::metadata(&p) {
-                total += meta.len();
+/// Remove the cache entries `prune` selects, printing each; returns `(count, bytes)`.
+pub(crate) async fn prune_cache(
+    cache: &Cache,
+    prune: &CachePrune,
+    dry_run: bool,
+) -> Result<(usize, u64)> {
+    let mut tap_manager = TapManager::new()?;
+    tap_manager.load().await?;
+    let live_taps: Vec<String> = tap_manager
+        .list_taps()
+        .iter()
+        .map(|t| t.full_name.clone())
+        .collect();
+    let max_age = Duration::from_secs(prune.older_than_days.saturating_mul(86_400));
+
+    let mut count = 0;
+    let mut freed = 0;
+    for path in cache.stale_entries(&live_taps, max_age, prune.all) {
+        let size = if path.is_dir() {
+            dir_size(&path)
+        } else {
+            std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+        };
+        let label = path
+            .strip_prefix(cache.cache_dir_path())
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+
+        if dry_run {
+            println!(
+                "  would remove cache {} ({})",
+                style(&label).dim(),
+                format_bytes(size)
+            );
+        } else {
+            let removed = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            if let Err(e) = removed {
+                eprintln!(
+                    "  {} failed to remove cache {}: {}",
+                    style("✗").red(),
+                    label,
+                    e
+                );
+                continue;
             }
+            println!(
+                "  {} cache {} ({})",
+                style("removed").green(),
+                style(&label).dim(),
+                format_bytes(size)
+            );
         }
+        count += 1;
+        freed += size;
     }
-    total
-}
-
-fn format_bytes(bytes: u64) -> String {
-    if bytes >= 1_073_741_824 {
-        format!("{:.1} GB", bytes as f64 / 1_073_741_824.0)
-    } else if bytes >= 1_048_576 {
-        format!("{:.1} MB", bytes as f64 / 1_048_576.0)
-    } else if bytes >= 1_024 {
-        format!("{:.1} KB", bytes as f64 / 1_024.0)
-    } else {
-        format!("{} B", bytes)
-    }
+    Ok((count, freed))
 }
@@ -1,22 +1,20 @@
 use crate::error::{Result, WaxError};
-use clap::CommandFactory;
+use clap::Command;
 use clap_complete::{generate, Shell};
 use std::io;
 use std::path::PathBuf;
 
-use crate::Cli;
-
-pub fn completions(shell: Option<Shell>, print: bool) -> Result<()> {
+/// `cmd` is the CLI definition to complete, i.e. `Cli::command()` from the binary.
+pub fn completions(mut cmd: Command, shell: Option<Shell>, print: bool, yes: bool) -> Result<()> {
     let shell = shell.unwrap_or_else(detect_shell);
 
     if print {
         // --print: dump to stdout for manual piping
-        let mut cmd = Cli::command();
         generate(shell, &mut cmd, "wax", &mut io::stdout());
         Ok(())
     } else {
         // Default: auto-detect shell and install completions
-        install_completions(shell)
+        install_completions(&mut cmd, shell, yes)
     }
 }
 
@@ -34,11 +32,11 @@ fn detect_shell() -> Shell {
     Shell::Zsh
 }
 
-fn install_completions(shell: Shell) -> Result<()> {
+fn install_completions(cmd: &mut Command, shell: Shell, yes: bool) -> Result<()> {
     let home =
         std::env::var("HOME").map_err(|_| WaxError::InstallError("$HOME not set".to_string()))?;
 
-    let (dest, content) = generate_completions(shell, &home)?;
+    let (dest, content) = generate_completions(cmd, shell, &home)?;
 
     std::fs::write(&dest, &content)?;
 
@@ -51,7 +49,7 @@ fn install_completions(shell: Shell) -> Result<()> {
 
     match shell {
         Shell::Zsh => {
-            configure_zsh(&home)?;
+            configure_zsh(&home, yes)?;
         }
         Shell::Bash => {
             println!("completions will load automatically in new shells.");
@@ -65,15 +63,14 @@ fn install_completions(shell: Shell) -> Result<()> {
     Ok(())
 }
 
-fn generate_completions(shell: Shell, home: &str) -> Result<(PathBuf, Vec<u8>)> {
+fn generate_completions(cmd: &mut Command, shell: Shell, home: &str) -> Result<(PathBuf, Vec<u8>)> {
     match shell {
         Shell::Zsh => {
             let dir = PathBuf::from(home).join(".zsh/completions");
             std::fs::create_dir_all(&dir)?;
             let path = dir.join("_wax");
             let mut buf = Vec::new();
-            let mut cmd = Cli::command();
-            generate(Shell::Zsh, &mut cmd, "wax", &mut buf);
+            generate(Shell::Zsh, cmd, "wax", &mut buf);
             Ok((path, buf))
         }
         Shell::Bash => {
@@ -81,8 +78,7 @@ fn generate_completions(shell: Shell, home: &str) -> Result<(PathBuf, Vec<u8>)>
             std::fs::create_dir_all(&dir)?;
             let path = dir.join("wax");
             let mut buf = Vec::new();
-            let mut cmd = Cli::command();
-            generate(Shell::Bash, &mut cmd, "wax", &mut buf);
+            generate(Shell::Bash, cmd, "wax", &mut buf);
             Ok((path, buf))
         }
         Shell::Fish => {
@@ -90,8 +86,7 @@ fn generate_completions(shell: Shell, home: &str) -> Result<(PathBuf, Vec<u8>)>
             std::fs::create_dir_all(&dir)?;
             let path = dir.join("wax.fish");
             let mut buf = Vec::new();
-            let mut cmd = Cli::command();
-            generate(Shell::Fish, &mut cmd, "wax", &mut buf);
+            generate(Shell::Fish, cmd, "wax", &mut buf);
             Ok((path, buf))
         }
         _ => Err(WaxError::InstallError(format!(
@@ -101,7 +96,7 @@ fn generate_completions(shell: Shell, home: &str) -> Result<(PathBuf, Vec<u8>)>
     }
 }
 
-fn configure_zsh(home: &str) -> Result<()> {
+fn configure_zsh(home: &str, yes: bool) -> Result<()> {
     use console::style;
 
     let zshrc = PathBuf::from(home).join(".zshrc");
@@ -117,9 +112,7 @@ fn configure_zsh(home: &str) -> Result<()> {
             style("exec zsh").cyan()
         );
     } else {
-        let prompt = inquire::Confirm::new("Add fpath to ~/.zshrc?")
-            .with_default(true)
-            .prompt();
+        let prompt = crate::ui::confirm_prompt(yes, "Add fpath to ~/.zshrc?", true);
 
         match prompt {
             Ok(true) => {
@@ -1,9 +1,10 @@
 use crate::bottle::{detect_platform, homebrew_prefix};
 use crate::cache::Cache;
 use crate::error::Result;
-use crate::install::{is_writable, InstallMode};
+use crate::install::{is_writable, relink_dangling_symlinks, InstallMode, InstallState};
 use crate::ui::dirs;
 use console::style;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
@@ -225,6 +226,56 @@ fn check_path(s: &mut Summary) {
     }
 }
 
+async fn check_links(s: &mut Summary, fix: bool) {
+    let Ok(state) = InstallState::new() else {
+        return;
+    };
+    let Ok(installed) = state.load().await else {
+        return;
+    };
+    // One sweep of each prefix covers every formula installed into it.
+    let mut found = BTreeMap::new();
+    for mode in [InstallMode::Global, InstallMode::User] {
+        let kegs: Vec<(&str, &str)> = installed
+            .iter()
+            .filter(|(_, pkg)| pkg.install_mode == mode)
+            .map(|(name, pkg)| (name.as_str(), pkg.version.as_str()))
+            .collect();
+        if kegs.is_empty() {
+            continue;
+        }
+        let Ok(cellar) = mode.cellar_path() else {
+            continue;
+        };
+        match relink_dangling_symlinks(&kegs, &cellar, !fix, mode).await {
+            Ok(repaired) => found.extend(repaired),
+            Err(e) => s.warn(&format!("could not check links: {e}")),
+        }
+    }
+
+    let broken = found.len();
+    for (name, found) in found {
+        let detail = format!(
+            "{} dangling {} into old kegs ({} relinkable)",
+            found.relinked.len() + found.removed.len(),
+            if found.relinked.len() + found.removed.len() == 1 {
+                "link"
+            } else {
+                "links"
+            },
+            found.relinked.len()
+        );
+        if fix {
+            s.fixed(&format!("{name}: repaired {detail}"));
+        } else {
+            s.warn(&format!("{name}: {detail}"));
+        }
+    }
+    if broken == 0 {
+        s.pass("no dangling symlinks into the Cellar");
+    }
+}
+
 fn check_writable_prefix(fix: bool) -> Result<()> {
     if !fix {
         return Ok(());
@@ -301,6 +352,8 @@ pub async fn doctor(cache: &Cache, fix: bool, _full: bool) -> Result<()> {
     check_wax_update(&mut s).await;
     section("path");
     check_path(&mut s);
+    section("links");
+    check_links(&mut s, fix).await;
 
     print_summary(&s, start, fix);
     Ok(())
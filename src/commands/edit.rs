@@ -0,0 +1,123 @@
+use crate::error::{Result, WaxError};
+use crate::tap::{core_formula_path, local_core_tap, TapManager, CORE_TAP_ENV};
+use console::style;
+use std::path::PathBuf;
+use tracing::instrument;
+
+/// Split `user/repo/name` into its tap and formula; bare names and `homebrew/core/…`
+/// have no tap.
+fn split_qualified(formula: &str) -> (Option<&str>, &str) {
+    match formula.rsplit_once('/') {
+        Some((tap, name)) if tap.contains('/') && tap != "homebrew/core" => (Some(tap), name),
+        Some((_, name)) => (None, name),
+        None => (None, formula),
+    }
+}
+
+/// `$VISUAL`, then `$EDITOR`, then `vi`, split into program and arguments
+/// (`code --wait` is a common setting).
+fn editor_command() -> Vec<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .map(|value| {
+            value
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .find(|parts| !parts.is_empty())
+        .unwrap_or_else(|| vec!["vi".to_string()])
+}
+
+async fn locate(formula: &str) -> Result<PathBuf> {
+    let (tap_name, name) = split_qualified(formula);
+
+    let mut manager = TapManager::new()?;
+    manager.load().await?;
+
+    if let Some(tap_name) = tap_name {
+        let tap = manager.get_tap(tap_name).ok_or_else(|| {
+            WaxError::TapError(format!(
+                "Tap {} not found; add it with: wax tap add {}",
+                tap_name, tap_name
+            ))
+        })?;
+        return tap
+            .formula_path(name)
+            .ok_or_else(|| WaxError::FormulaNotFound(formula.to_string()));
+    }
+
+    if let Some(core) = local_core_tap() {
+        return core_formula_path(&core, name)
+            .ok_or_else(|| WaxError::FormulaNotFound(formula.to_string()));
+    }
+
+    let mut in_taps: Vec<(String, PathBuf)> = manager
+        .list_taps()
+        .into_iter()
+        .filter_map(|tap| Some((tap.full_name.clone(), tap.formula_path(name)?)))
+        .collect();
+    match in_taps.len() {
+        0 => {
+            eprintln!(
+                "{} no added tap has {}; core formulae come from the API and have no local \
+                 file unless {} points at a homebrew-core clone",
+                style("note:").yellow(),
+                name,
+                CORE_TAP_ENV
+            );
+            Err(WaxError::FormulaNotFound(name.to_string()))
+        }
+        1 => Ok(in_taps.remove(0).1),
+        _ => {
+            let candidates: Vec<String> = in_taps
+                .iter()
+                .map(|(tap, _)| format!("{}/{}", tap, name))
+                .collect();
+            Err(WaxError::InvalidInput(format!(
+                "{} is in more than one tap; name the one to edit: {}",
+                name,
+                candidates.join(", ")
+            )))
+        }
+    }
+}
+
+/// `wax edit`: open a tap formula's `.rb` in the user's editor.
+#[instrument]
+pub async fn edit(formula: &str) -> Result<()> {
+    let path = locate(formula).await?;
+    let editor = editor_command();
+    eprintln!(
+        "{} {} {}",
+        style("editing").dim(),
+        style(formula).magenta(),
+        style(path.display()).dim()
+    );
+
+    let status = std::process::Command::new(&editor[0])
+        .args(&editor[1..])
+        .arg(&path)
+        .status()
+        .map_err(|e| WaxError::InvalidInput(format!("Failed to launch {}: {}", editor[0], e)))?;
+    if !status.success() {
+        return Err(WaxError::InvalidInput(format!(
+            "{} exited with {}",
+            editor[0], status
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qualified_names_split_into_tap_and_formula() {
+        assert_eq!(split_qualified("user/repo/foo"), (Some("user/repo"), "foo"));
+        assert_eq!(split_qualified("foo"), (None, "foo"));
+        assert_eq!(split_qualified("homebrew/core/jq"), (None, "jq"));
+    }
+}
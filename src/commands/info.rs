@@ -1,11 +1,13 @@
 use crate::api::Formula;
 use crate::cache::Cache;
-use crate::cask::CaskState;
+use crate::cask::{format_install_date, CaskState};
 use crate::error::{Result, WaxError};
-use crate::install::InstallState;
+use crate::install::{InstallMode, InstallState, InstalledPackage};
+use crate::ui::{dir_size, format_bytes};
 
 use console::style;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use tracing::instrument;
 
 fn tap_slug_from_qualified_name(qualified: &str) -> Option<String> {
@@ -19,9 +21,46 @@ fn tap_slug_from_qualified_name(qualified: &str) -> Option<String> {
     Some(format!("{}/{}", parts[0], parts[1]))
 }
 
+/// Installed version of dependency `dep`, which may be tap-qualified (`user/tap/foo`).
+fn installed_dependency_version<'a>(
+    dep: &str,
+    installed: &'a HashMap<String, InstalledPackage>,
+) -> Option<&'a str> {
+    installed
+        .get(dep)
+        .or_else(|| installed.get(dep.rsplit('/').next().unwrap_or(dep)))
+        .map(|pkg| pkg.version.as_str())
+}
+
+/// `1234567` → `1,234,567`.
+fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// `size:` line for an installed keg; walking the tree only happens here, once the
+/// package is known to be installed.
+async fn print_keg_size(keg: &Path) {
+    if !keg.is_dir() {
+        return;
+    }
+    let walk = keg.to_path_buf();
+    if let Ok(bytes) = tokio::task::spawn_blocking(move || dir_size(&walk)).await {
+        println!("{} {}", style("size:").dim(), format_bytes(bytes));
+    }
+}
+
 #[instrument(skip(cache))]
 pub async fn info(cache: &Cache, name: &str, cask: bool) -> Result<()> {
     cache.ensure_fresh().await?;
+    cache.warn_if_index_old().await;
 
     if cask {
         return info_cask(cache, name).await;
@@ -42,10 +81,7 @@ pub async fn info(cache: &Cache, name: &str, cask: bool) -> Result<()> {
             return info_cask(cache, name).await;
         }
 
-        return Err(WaxError::FormulaNotFound(format!(
-            "{} (not found as formula or cask)",
-            name
-        )));
+        return Err(WaxError::FormulaNotFound(name.to_string()));
     }
 
     let formula = formulae
@@ -56,6 +92,90 @@ pub async fn info(cache: &Cache, name: &str, cask: bool) -> Result<()> {
     info_formula(formula, name, &formulae).await
 }
 
+/// A formula or cask looked up the way `wax info` does: formulae first, then casks.
+enum Package {
+    Formula(Box<Formula>),
+    Cask(Box<crate::api::CaskDetails>),
+}
+
+async fn resolve_package(cache: &Cache, name: &str, cask: bool) -> Result<Package> {
+    cache.ensure_fresh().await?;
+    if !cask {
+        let formulae = cache.load_all_formulae().await?;
+        if let Some(formula) = formulae
+            .into_iter()
+            .find(|f| f.name == name || f.full_name == name)
+        {
+            return Ok(Package::Formula(Box::new(formula)));
+        }
+    }
+    let casks = cache.load_casks().await?;
+    if !casks
+        .iter()
+        .any(|c| c.token == name || c.full_token == name)
+    {
+        return Err(if cask {
+            WaxError::CaskNotFound(name.to_string())
+        } else {
+            WaxError::FormulaNotFound(name.to_string())
+        });
+    }
+    Ok(Package::Cask(Box::new(
+        cache.fetch_cask_details(name).await?,
+    )))
+}
+
+/// `wax info --open`: open the homepage in the default browser, like `brew home`. Without
+/// a browser to hand it to (a headless server), the printed link is all there is.
+pub async fn info_open(cache: &Cache, name: &str, cask: bool) -> Result<()> {
+    let homepage = match resolve_package(cache, name, cask).await? {
+        Package::Formula(formula) => formula.homepage,
+        Package::Cask(details) => details.homepage,
+    };
+    println!(
+        "{} → {}",
+        style(name).magenta(),
+        style(&homepage).cyan().underlined()
+    );
+    if !crate::commands::source::open_in_browser(&homepage)? {
+        eprintln!(
+            "{} no browser available to open it; copy the link above",
+            style("note:").yellow()
+        );
+    }
+    Ok(())
+}
+
+/// `wax info --url`: what an install would download on this platform. That is the
+/// bottle when there is one, otherwise the formula's source archive; for a cask, its
+/// download.
+pub async fn info_url(cache: &Cache, name: &str, cask: bool) -> Result<()> {
+    let formula = match resolve_package(cache, name, cask).await? {
+        Package::Cask(details) => {
+            println!("{}", details.url);
+            return Ok(());
+        }
+        Package::Formula(formula) => formula,
+    };
+    let platform = crate::bottle::detect_platform();
+    if let Some(file) = formula
+        .bottle
+        .as_ref()
+        .and_then(|b| b.stable.as_ref())
+        .and_then(|s| s.file_for_platform(&platform))
+    {
+        println!("{}", file.url);
+        return Ok(());
+    }
+    let ruby = match &formula.rb_path {
+        Some(rb_path) => tokio::fs::read_to_string(rb_path).await?,
+        None => crate::formula_parser::FormulaParser::fetch_formula_rb(&formula.name).await?,
+    };
+    let parsed = crate::formula_parser::FormulaParser::parse_ruby_formula(&formula.name, &ruby)?;
+    println!("{}", parsed.source.url);
+    Ok(())
+}
+
 async fn info_formula(formula: &Formula, name: &str, formulae: &[Formula]) -> Result<()> {
     let installed_suffix = if let Some(installed) = &formula.installed {
         if !installed.is_empty() {
@@ -88,6 +208,15 @@ async fn info_formula(formula: &Formula, name: &str, formulae: &[Formula]) -> Re
         println!("{} {}", style("tap:").dim(), style(tap).cyan());
     }
 
+    if let Some(status) = crate::commands::install::lifecycle_status(formula) {
+        let status = if formula.disabled {
+            style(status).red()
+        } else {
+            style(status).yellow()
+        };
+        println!("{} {}", style("status:").dim(), status);
+    }
+
     if let Some(desc) = &formula.desc {
         println!("{}", desc);
     }
@@ -95,22 +224,34 @@ async fn info_formula(formula: &Formula, name: &str, formulae: &[Formula]) -> Re
     println!();
     println!("{}", &formula.homepage);
 
-    if let Some(deps) = &formula.dependencies {
-        if !deps.is_empty() {
-            println!();
-            println!("{}", style("dependencies:").dim());
-            for dep in deps {
-                println!("  {}", dep);
-            }
-        }
+    if let Some(installs) = formula.popularity {
+        println!(
+            "{} {} installs (30 days)",
+            style("popularity:").dim(),
+            group_thousands(installs)
+        );
     }
 
-    if let Some(build_deps) = &formula.build_dependencies {
-        if !build_deps.is_empty() {
-            println!();
-            println!("{}", style("build dependencies:").dim());
-            for dep in build_deps {
-                println!("  {}", dep);
+    let state = InstallState::new()?;
+    let installed_packages = state.load().await?;
+
+    for (label, deps) in [
+        ("dependencies:", &formula.dependencies),
+        ("build dependencies:", &formula.build_dependencies),
+    ] {
+        let Some(deps) = deps.as_ref().filter(|d| !d.is_empty()) else {
+            continue;
+        };
+        println!();
+        println!("{}", style(label).dim());
+        for dep in deps {
+            match installed_dependency_version(dep, &installed_packages) {
+                Some(version) => println!(
+                    "  {} {}",
+                    dep,
+                    style(format!("(installed {})", version)).green()
+                ),
+                None => println!("  {} {}", dep, style("(missing)").yellow()),
             }
         }
     }
@@ -121,8 +262,6 @@ async fn info_formula(formula: &Formula, name: &str, formulae: &[Formula]) -> Re
     }
 
     // Show "why installed" section if the package is installed locally
-    let state = InstallState::new()?;
-    let installed_packages = state.load().await?;
     let installed_pkg = installed_packages
         .get(name)
         .or_else(|| installed_packages.get(formula.full_name.as_str()))
@@ -143,10 +282,15 @@ async fn info_formula(formula: &Formula, name: &str, formulae: &[Formula]) -> Re
             .map(|f| f.name.as_str())
             .collect();
 
+        let reason = if pkg.installed_as_dependency {
+            "as a dependency"
+        } else {
+            "on request"
+        };
         if dependents.is_empty() {
-            println!("{} installed explicitly", style("installed:").dim());
+            println!("{} {}", style("installed:").dim(), reason);
         } else {
-            println!("{} required by:", style("installed:").dim());
+            println!("{} {}, required by:", style("installed:").dim(), reason);
             for dep in &dependents {
                 println!("  {} {}", style("←").dim(), style(dep).cyan());
             }
@@ -163,11 +307,96 @@ async fn info_formula(formula: &Formula, name: &str, formulae: &[Formula]) -> Re
         let package_path = cellar_path.join(&pkg.name).join(&pkg.version);
         println!();
         println!("{} {}", style("path:").dim(), package_path.display());
+        print_keg_size(&package_path).await;
     }
 
     Ok(())
 }
 
+/// `wax info --installed`: only what wax recorded at install time. Skips the package
+/// index entirely, so it is fast and works offline.
+pub async fn info_installed(name: &str, cask: bool) -> Result<()> {
+    let short_name = name.rsplit('/').next().unwrap_or(name);
+
+    if !cask {
+        let packages = InstallState::new()?.load().await?;
+        if let Some(pkg) = packages.get(name).or_else(|| packages.get(short_name)) {
+            println!();
+            println!(
+                "{} · {}",
+                style(&pkg.name).magenta(),
+                style(&pkg.version).dim()
+            );
+            println!();
+            let mode = match pkg.install_mode {
+                InstallMode::User => "user",
+                InstallMode::Global => "global",
+            };
+            println!("{} {}", style("mode:").dim(), mode);
+            println!(
+                "{} {}",
+                style("installed:").dim(),
+                format_install_date(pkg.install_date).unwrap_or_else(|| "unknown".to_string())
+            );
+            println!(
+                "{} {}",
+                style("built:").dim(),
+                if pkg.from_source {
+                    "from source"
+                } else {
+                    "bottle"
+                }
+            );
+            println!(
+                "{} {}",
+                style("reason:").dim(),
+                if pkg.installed_as_dependency {
+                    "dependency"
+                } else {
+                    "on request"
+                }
+            );
+            if !pkg.options.is_empty() {
+                println!("{} {}", style("options:").dim(), pkg.options.join(", "));
+            }
+            if pkg.pinned {
+                println!("{}", style("pinned").yellow());
+            }
+            let package_path = pkg
+                .install_mode
+                .cellar_path()?
+                .join(&pkg.name)
+                .join(&pkg.version);
+            println!("{} {}", style("path:").dim(), package_path.display());
+            print_keg_size(&package_path).await;
+            return Ok(());
+        }
+    }
+
+    let casks = CaskState::new()?.load().await?;
+    if let Some(installed) = casks.get(name).or_else(|| casks.get(short_name)) {
+        println!();
+        println!(
+            "{} · {} {}",
+            style(&installed.name).magenta(),
+            style(&installed.version).dim(),
+            style("(cask)").yellow()
+        );
+        println!();
+        println!(
+            "{} {}",
+            style("installed:").dim(),
+            format_install_date(installed.install_date).unwrap_or_else(|| "unknown".to_string())
+        );
+        for app in installed.app_names() {
+            println!("{} {}", style("app:").dim(), app);
+        }
+        return Ok(());
+    }
+
+    Err(WaxError::NotInstalled(name.to_string()))
+}
+
 #[instrument(skip(cache))]
 async fn info_cask(cache: &Cache, name: &str) -> Result<()> {
     cache.ensure_fresh().await?;
@@ -253,7 +482,37 @@ async fn info_cask(cache: &Cache, name: &str) -> Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use super::tap_slug_from_qualified_name;
+    use super::{group_thousands, installed_dependency_version, tap_slug_from_qualified_name};
+    use crate::install::InstalledPackage;
+    use std::collections::HashMap;
+
+    #[test]
+    fn dependency_version_matches_bare_and_tap_qualified_names() {
+        let installed: HashMap<String, InstalledPackage> = [(
+            "openssl@3".to_string(),
+            crate::test_support::installed_package("openssl@3", "3.6.1"),
+        )]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            installed_dependency_version("openssl@3", &installed),
+            Some("3.6.1")
+        );
+        assert_eq!(
+            installed_dependency_version("user/tap/openssl@3", &installed),
+            Some("3.6.1")
+        );
+        assert_eq!(installed_dependency_version("pcre2", &installed), None);
+    }
+
+    #[test]
+    fn group_thousands_inserts_separators() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(1000), "1,000");
+        assert_eq!(group_thousands(1234567), "1,234,567");
+    }
 
     #[test]
     fn tap_slug_from_user_tap_formula() {
@@ -1,41 +1,131 @@
 use crate::api::{CaskArtifact, Formula};
-use crate::bottle::{detect_platform, BottleDownloader, DownloadTotals};
+use crate::bottle::{
+    archive_file_name, detect_platform, is_platform_tag, BottleDownloader, DownloadTotals,
+};
 use crate::builder::Builder;
 use crate::cache::Cache;
 use crate::cask::{
-    detect_artifact_type, CaskInstaller, CaskState, InstalledCask, RollbackContext, StagingContext,
+    detect_artifact_type, versioned_token_base, CaskInstaller, CaskState, InstalledCask,
+    RollbackContext, StagingContext,
 };
 use crate::commands::version_install;
-use crate::deps::resolve_dependencies;
-use crate::discovery::discover_manually_installed_casks;
+use crate::deps::{add_alias_names, resolve_dependencies_for_roots};
+use crate::digest::Checksum;
+use crate::discovery::{discover_manually_installed_casks, resolve_installed_kind, InstalledKind};
 use crate::error::{Result, WaxError};
 use crate::formula_parser::{BuildSystem, FormulaParser};
 use crate::install::{create_symlinks, InstallMode, InstallState, InstalledPackage};
 use crate::signal::{check_cancelled, set_active_multi, CriticalSection};
 use crate::system_pm::SystemPm;
-use crate::tap::TapManager;
+use crate::tap::{local_core_tap, TapManager};
 use crate::ui::{
-    confirm_prompt, copy_dir_all, dirs, find_in_path, PROGRESS_BAR_CHARS,
+    confirm_prompt, copy_dir_all, dirs, find_in_path, format_bytes, PROGRESS_BAR_CHARS,
     PROGRESS_BAR_PREFIX_TEMPLATE, PROGRESS_BAR_TEMPLATE,
 };
 use console::style;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use sha2::Digest;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use tempfile::TempDir;
 use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
 use tracing::{debug, info, instrument};
 
+/// Compare a downloaded source archive against the formula's declared sha256. Tap formulae
+/// sometimes omit it; that is refused unless the user opted out with `--no-verify`, so an
+/// empty string is never silently treated as "matches nothing, fail" or "matches anything".
+/// An explicit `sha256 :no_check` is the formula opting out, so it only warns.
+fn check_source_checksum(
+    name: &str,
+    url: &str,
+    expected: &Checksum,
+    actual: &str,
+    no_verify: bool,
+) -> Result<()> {
+    let expected = match expected {
+        Checksum::Digest(hex) => crate::digest::Algorithm::detect(hex).1,
+        Checksum::NoCheck => {
+            eprintln!(
+                "{} {} declares sha256 :no_check; building from an unverified source download",
+                style("warning:").yellow(),
+                name
+            );
+            return Ok(());
+        }
+        Checksum::Missing => "",
+    };
+    if expected.is_empty() {
+        if !no_verify {
+            return Err(WaxError::InstallError(format!(
+                "{} declares no sha256 for its source, so the download cannot be verified; \
+                 re-run with --no-verify to build it anyway",
+                name
+            )));
+        }
+        eprintln!(
+            "{} {} declares no sha256; building from an unverified source download",
+            style("warning:").yellow(),
+            name
+        );
+        return Ok(());
+    }
+    if !expected.eq_ignore_ascii_case(actual) {
+        return Err(WaxError::ChecksumMismatch {
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+            name: name.to_string(),
+            url: url.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Download a source tarball, falling back to the formula's `mirror` URLs in order when
+/// a host fails or answers with an error status (redirects are followed first). Returns
+/// the URL that served the archive, after redirects, along with its bytes.
+async fn download_source(
+    source: &crate::formula_parser::FormulaSource,
+) -> Result<(String, Vec<u8>)> {
+    let client = reqwest::Client::new();
+    let mut failures = Vec::new();
+    for url in source.download_urls() {
+        let attempt = async {
+            info!("Downloading source: {}", url);
+            let response = client.get(url).send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(WaxError::BuildError(format!("HTTP {}", status)));
+            }
+            let served_by = response.url().to_string();
+            Ok((served_by, response.bytes().await?.to_vec()))
+        }
+        .await;
+        match attempt {
+            Ok(download) => return Ok(download),
+            Err(e) => {
+                debug!("Source download from {} failed: {}", url, e);
+                failures.push(format!("{}: {}", url, e));
+            }
+        }
+    }
+    Err(WaxError::BuildError(format!(
+        "Failed to download source:\n  {}",
+        failures.join("\n  ")
+    )))
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn install_from_source_task(
     formula: Formula,
     cellar: &Path,
     install_mode: InstallMode,
     state: &InstallState,
     platform: &str,
+    options: &[String],
+    no_verify: bool,
+    overwrite: bool,
 ) -> Result<()> {
     info!("Installing {} from source", formula.name);
 
@@ -63,7 +153,18 @@ async fn install_from_source_task(
     };
 
     spinner.set_message("Parsing formula...");
-    let parsed_formula = FormulaParser::parse_ruby_formula(&formula.name, &ruby_content)?;
+    let mut parsed_formula = FormulaParser::parse_ruby_formula(&formula.name, &ruby_content)?;
+    if let Err(e) = parsed_formula.apply_options(options) {
+        spinner.finish_and_clear();
+        return Err(e);
+    }
+    if let Some(reason) = parsed_formula.requirements.unmet(platform) {
+        spinner.finish_and_clear();
+        return Err(WaxError::PlatformNotSupported(format!(
+            "{} {}",
+            formula.name, reason
+        )));
+    }
 
     // Binary-release formula: `bin.install` entries with no build system.
     // Download the platform-appropriate pre-built tarball and copy the named files.
@@ -90,11 +191,14 @@ async fn install_from_source_task(
             )));
         }
         let bytes = response.bytes().await?;
-        let actual_sha = format!("{:x}", sha2::Sha256::digest(&bytes));
-        if actual_sha != dl_sha {
+        let (algorithm, expected_sha) = crate::digest::Algorithm::detect(&dl_sha);
+        let actual_sha = algorithm.hex_digest(&bytes);
+        if !actual_sha.eq_ignore_ascii_case(expected_sha) {
             return Err(WaxError::ChecksumMismatch {
-                expected: dl_sha,
+                expected: expected_sha.to_string(),
                 actual: actual_sha,
+                name: formula.name.clone(),
+                url: dl_url,
             });
         }
 
@@ -174,8 +278,16 @@ async fn install_from_source_task(
         let version = &parsed_formula.source.version;
         let formula_cellar = cellar.join(&formula.name).join(version);
         tokio::fs::create_dir_all(&formula_cellar).await?;
-        copy_dir_all(&install_prefix, &formula_cellar)?;
-        create_symlinks(&formula.name, version, cellar, false, install_mode).await?;
+        copy_dir_all_blocking(&install_prefix, &formula_cellar).await?;
+        create_symlinks(
+            &formula.name,
+            version,
+            cellar,
+            false,
+            overwrite,
+            install_mode,
+        )
+        .await?;
 
         let package = InstalledPackage {
             name: formula.name.clone(),
@@ -190,6 +302,9 @@ async fn install_from_source_task(
             bottle_rebuild: 0,
             bottle_sha256: None,
             pinned: false,
+            options: Vec::new(),
+            installed_as_dependency: false,
+            unverified_source: false,
         };
         state.add(package).await?;
 
@@ -214,45 +329,53 @@ async fn install_from_source_task(
     }
 
     let temp_dir = TempDir::new()?;
-    let source_tarball = temp_dir.path().join(format!(
-        "{}-{}.tar.gz",
-        formula.name, parsed_formula.source.version
-    ));
-
-    let client = reqwest::Client::new();
-    let response = client.get(&parsed_formula.source.url).send().await?;
+    let install_prefix = temp_dir.path().join("install");
+    tokio::fs::create_dir_all(&install_prefix).await?;
+    let builder = Builder::new();
 
-    if !response.status().is_success() {
-        return Err(WaxError::BuildError(format!(
-            "Failed to download source: HTTP {}",
-            response.status()
-        )));
-    }
+    if parsed_formula.source.is_git() {
+        spinner.set_message(format!("Cloning {}...", parsed_formula.source.url));
+        let clone_dir = temp_dir.path().join("git-src");
+        builder
+            .fetch_git_source(&parsed_formula.source, &clone_dir)
+            .await?;
 
-    let content = response.bytes().await?;
-    let sha256 = format!("{:x}", sha2::Sha256::digest(&content));
-    tokio::fs::write(&source_tarball, &content).await?;
-    if sha256 != parsed_formula.source.sha256 {
-        return Err(WaxError::ChecksumMismatch {
-            expected: parsed_formula.source.sha256.clone(),
-            actual: sha256,
-        });
-    }
+        spinner.set_message("Building from source (this may take several minutes)...");
+        builder
+            .build_from_directory(&parsed_formula, &clone_dir, &install_prefix, Some(&spinner))
+            .await?;
+    } else {
+        let source_tarball = temp_dir.path().join(crate::bottle::archive_file_name(
+            &format!("{}-{}", formula.name, parsed_formula.source.version),
+            &parsed_formula.source.url,
+        ));
 
-    let build_dir = temp_dir.path().join("build");
-    let install_prefix = temp_dir.path().join("install");
-    tokio::fs::create_dir_all(&install_prefix).await?;
+        let (fetched_url, content) = download_source(&parsed_formula.source).await?;
+        let actual = parsed_formula
+            .source
+            .sha256
+            .algorithm()
+            .hex_digest(&content);
+        tokio::fs::write(&source_tarball, &content).await?;
+        check_source_checksum(
+            &formula.name,
+            &fetched_url,
+            &parsed_formula.source.sha256,
+            &actual,
+            no_verify,
+        )?;
 
-    let builder = Builder::new();
-    builder
-        .build_from_source(
-            &parsed_formula,
-            &source_tarball,
-            &build_dir,
-            &install_prefix,
-            Some(&spinner),
-        )
-        .await?;
+        let build_dir = temp_dir.path().join("build");
+        builder
+            .build_from_source(
+                &parsed_formula,
+                &source_tarball,
+                &build_dir,
+                &install_prefix,
+                Some(&spinner),
+            )
+            .await?;
+    }
 
     spinner.set_message("Installing to Cellar...");
 
@@ -260,13 +383,14 @@ async fn install_from_source_task(
     let formula_cellar = cellar.join(&formula.name).join(version);
     tokio::fs::create_dir_all(&formula_cellar).await?;
 
-    copy_dir_all(&install_prefix, &formula_cellar)?;
+    copy_dir_all_blocking(&install_prefix, &formula_cellar).await?;
 
     create_symlinks(
         &formula.name,
         version,
         cellar,
         false, /* dry_run */
+        overwrite,
         install_mode,
     )
     .await?;
@@ -284,6 +408,9 @@ async fn install_from_source_task(
         bottle_rebuild: 0,
         bottle_sha256: None,
         pinned: false,
+        options: options.to_vec(),
+        installed_as_dependency: false,
+        unverified_source: no_verify && matches!(parsed_formula.source.sha256, Checksum::Missing),
     };
     state.add(package).await?;
 
@@ -401,12 +528,16 @@ fn binary_release_download_filename(url: &str, formula_name: &str) -> String {
 }
 
 /// Clone and build from a formula's HEAD git URL.
+#[allow(clippy::too_many_arguments)]
 async fn install_from_head_task(
     formula: Formula,
     cellar: &Path,
     install_mode: InstallMode,
     state: &InstallState,
     platform: &str,
+    options: &[String],
+    no_verify: bool,
+    overwrite: bool,
 ) -> Result<()> {
     info!("Installing {} from HEAD", formula.name);
 
@@ -433,7 +564,18 @@ async fn install_from_head_task(
     };
 
     spinner.set_message("Parsing formula...");
-    let parsed_formula = FormulaParser::parse_ruby_formula(&formula.name, &ruby_content)?;
+    let mut parsed_formula = FormulaParser::parse_ruby_formula(&formula.name, &ruby_content)?;
+    if let Err(e) = parsed_formula.apply_options(options) {
+        spinner.finish_and_clear();
+        return Err(e);
+    }
+    if let Some(reason) = parsed_formula.requirements.unmet(platform) {
+        spinner.finish_and_clear();
+        return Err(WaxError::PlatformNotSupported(format!(
+            "{} {}",
+            formula.name, reason
+        )));
+    }
 
     let Some(head_url) = parsed_formula.head_url.as_deref() else {
         spinner.finish_and_clear();
@@ -442,7 +584,17 @@ async fn install_from_head_task(
             console::style("note:").yellow(),
             formula.name
         );
-        return install_from_source_task(formula, cellar, install_mode, state, platform).await;
+        return install_from_source_task(
+            formula,
+            cellar,
+            install_mode,
+            state,
+            platform,
+            options,
+            no_verify,
+            overwrite,
+        )
+        .await;
     };
 
     let temp_dir = TempDir::new()?;
@@ -496,13 +648,14 @@ async fn install_from_head_task(
     let formula_cellar = cellar.join(&formula.name).join(&version);
     tokio::fs::create_dir_all(&formula_cellar).await?;
 
-    copy_dir_all(&install_prefix, &formula_cellar)?;
+    copy_dir_all_blocking(&install_prefix, &formula_cellar).await?;
 
     create_symlinks(
         &formula.name,
         &version,
         cellar,
         false, /* dry_run */
+        overwrite,
         install_mode,
     )
     .await?;
@@ -520,6 +673,9 @@ async fn install_from_head_task(
         bottle_rebuild: 0,
         bottle_sha256: None,
         pinned: false,
+        options: options.to_vec(),
+        installed_as_dependency: false,
+        unverified_source: false,
     };
     state.add(package).await?;
 
@@ -534,52 +690,265 @@ async fn install_from_head_task(
     Ok(())
 }
 
-pub(crate) struct InstallArgs<'a> {
-    pub(crate) dry_run: bool,
-    pub(crate) ask: bool,
-    pub(crate) cask: bool,
-    pub(crate) user: bool,
-    pub(crate) global: bool,
-    pub(crate) build_from_source: bool,
-    pub(crate) head: bool,
-    pub(crate) run_scripts: bool,
-    pub(crate) quiet: bool,
-    pub(crate) force_reinstall: bool,
-    pub(crate) external_pb: Option<&'a ProgressBar>,
+/// What `wax install` was asked to do; the flags of `wax install`, plus how callers
+/// that install on behalf of another command want progress reported.
+#[derive(Default)]
+pub struct InstallArgs<'a> {
+    pub dry_run: bool,
+    pub ask: bool,
+    pub cask: bool,
+    pub user: bool,
+    pub global: bool,
+    pub build_from_source: bool,
+    pub head: bool,
+    pub run_scripts: bool,
+    /// Suppress per-package output, for callers that report progress themselves.
+    pub quiet: bool,
+    /// Reinstall already-installed casks, and install formulae into the user prefix even
+    /// when Homebrew already has a keg for them.
+    pub force_reinstall: bool,
+    /// Build options for the requested formulae; forces a source build for them.
+    pub options: &'a [String],
+    /// Install only the requested formulae, skipping dependency resolution.
+    pub ignore_dependencies: bool,
+    /// Install the requested formulae's dependencies but not the formulae themselves.
+    pub only_dependencies: bool,
+    /// Allow source builds whose formula declares no sha256 to proceed unverified.
+    pub no_verify: bool,
+    /// Leave Homebrew prefix placeholders in bottles instead of relocating them.
+    pub no_relocate: bool,
+    /// Bottle platform tag to install instead of this machine's (`--platform`).
+    pub platform: Option<&'a str>,
+    /// Replace files and other kegs' links that are in the way when linking (`--overwrite`).
+    pub overwrite: bool,
+    /// Spinner of the calling command to report progress on instead of a new one.
+    pub external_pb: Option<&'a ProgressBar>,
 }
 
-#[instrument(skip(cache))]
-#[allow(clippy::too_many_arguments)]
-pub async fn install(
+#[instrument(skip(cache, args))]
+pub async fn install(cache: &Cache, package_names: &[String], args: InstallArgs<'_>) -> Result<()> {
+    if args.cask && !args.options.is_empty() {
+        return Err(WaxError::InvalidInput(
+            "--with/--without only apply to formulae, not casks".to_string(),
+        ));
+    }
+    install_impl(cache, package_names, args).await
+}
+
+/// Validate a `--platform` tag and warn when it is not this machine's platform.
+fn foreign_platform(tag: &str, quiet: bool) -> Result<String> {
+    if !is_platform_tag(tag) {
+        return Err(WaxError::InvalidInput(format!(
+            "unknown platform '{}' (expected a bottle tag such as arm64_sonoma, sonoma, x86_64_linux or arm64_linux)",
+            tag
+        )));
+    }
+    let local = detect_platform();
+    if tag != local && !quiet {
+        eprintln!(
+            "{} installing {} bottles on {}; their binaries may not run on this machine",
+            style("warning:").yellow(),
+            style(tag).cyan(),
+            local
+        );
+    }
+    Ok(tag.to_string())
+}
+
+/// On a machine with no index yet, a lone bare formula name that installs from a bottle
+/// with nothing left to resolve only needs its own JSON. Anything else (a tap name, a
+/// missing dependency, no bottle for this platform, a failed request) returns `None`,
+/// and the caller falls back to the full index.
+async fn fetch_single_formula_without_index(
     cache: &Cache,
     package_names: &[String],
-    dry_run: bool,
-    ask: bool,
-    cask: bool,
-    user: bool,
-    global: bool,
+    installed: &HashSet<String>,
+    ignore_dependencies: bool,
+    platform: &str,
+) -> Option<Formula> {
+    let [name] = package_names else {
+        return None;
+    };
+    if name.contains('/') || installed.contains(name.as_str()) {
+        return None;
+    }
+    let formula = match cache.fetch_formula(name).await {
+        Ok(formula) => formula,
+        Err(e) => {
+            debug!("Per-formula lookup for {} failed, using index: {}", name, e);
+            return None;
+        }
+    };
+    if !bottle_is_self_contained(&formula, platform, installed, ignore_dependencies) {
+        debug!("{} needs dependency resolution; using index", name);
+        return None;
+    }
+    debug!("Installing {} without loading the full index", name);
+    Some(formula)
+}
+
+/// How `install` will obtain a formula. The install loop and the `--dry-run` plan both
+/// decide through `planned_build`, so the plan cannot drift from what install does.
+#[derive(Debug, Clone, PartialEq)]
+enum PlannedBuild {
+    Bottle { url: String, sha256: String },
+    Source,
+    Head,
+}
+
+/// `foreign` is set for `--platform` installs of another machine's bottles; those never
+/// fall back to a source build, which would produce binaries for this machine instead.
+fn planned_build(
+    formula: &Formula,
+    platform: &str,
+    head: bool,
     build_from_source: bool,
+    has_options: bool,
+    foreign: bool,
+) -> Result<PlannedBuild> {
+    if head {
+        return Ok(PlannedBuild::Head);
+    }
+    let bottle = formula
+        .bottle
+        .as_ref()
+        .and_then(|b| b.stable.as_ref())
+        .and_then(|s| s.file_for_platform(platform));
+    match bottle {
+        Some(file) if !build_from_source && !has_options => Ok(PlannedBuild::Bottle {
+            url: file.url.clone(),
+            sha256: file.sha256.clone(),
+        }),
+        _ if foreign => Err(WaxError::BottleNotAvailable(format!(
+            "{} for platform {}",
+            formula.name, platform
+        ))),
+        _ => Ok(PlannedBuild::Source),
+    }
+}
+
+/// Why a formula cannot be poured from a bottle on `platform`, or `None` if it can.
+/// Tells a formula that ships no bottles at all apart from one whose bottles simply
+/// do not cover this platform.
+fn missing_bottle_reason(formula: &Formula, platform: &str) -> Option<String> {
+    match formula.bottle.as_ref().and_then(|b| b.stable.as_ref()) {
+        Some(stable) if stable.file_for_platform(platform).is_some() => None,
+        Some(stable) if !stable.files.is_empty() => Some(format!("no bottle for {}", platform)),
+        _ => Some("no bottle".to_string()),
+    }
+}
+
+/// `(name, "bottle, 12.0 MB" | "source build" | "HEAD build")` for each planned formula.
+/// Bottle sizes are probed concurrently; the probe result is reused by the real download.
+/// Fails like the install would when a formula cannot be installed as asked.
+#[allow(clippy::too_many_arguments)]
+async fn install_plan(
+    names: &[String],
+    by_name: &HashMap<&str, &Formula>,
+    requested: &HashSet<String>,
+    platform: &str,
     head: bool,
-    run_scripts: bool,
-) -> Result<()> {
-    install_impl(
-        cache,
-        package_names,
-        InstallArgs {
-            dry_run,
-            ask,
-            cask,
-            user,
-            global,
-            build_from_source,
-            head,
-            run_scripts,
-            quiet: false,
-            force_reinstall: false,
-            external_pb: None,
-        },
-    )
-    .await
+    build_from_source: bool,
+    has_options: bool,
+    foreign: bool,
+) -> Result<Vec<(String, String)>> {
+    let downloader = Arc::new(BottleDownloader::new());
+    let mut probes = JoinSet::new();
+    let mut plan = Vec::with_capacity(names.len());
+    for (index, name) in names.iter().enumerate() {
+        let Some(formula) = by_name.get(name.as_str()) else {
+            plan.push((name.clone(), "not in index".to_string()));
+            continue;
+        };
+        // Options only apply to the formulae the user asked for, not their dependencies.
+        let options = has_options && requested.contains(&formula.name);
+        let how = match planned_build(formula, platform, head, build_from_source, options, foreign)?
+        {
+            PlannedBuild::Bottle { url, .. } => {
+                let downloader = Arc::clone(&downloader);
+                probes.spawn(async move { (index, downloader.probe_size(&url).await) });
+                "bottle".to_string()
+            }
+            PlannedBuild::Source if !build_from_source && !options => {
+                match missing_bottle_reason(formula, platform) {
+                    Some(reason) => format!("source build, {}", reason),
+                    None => "source build".to_string(),
+                }
+            }
+            PlannedBuild::Source => "source build".to_string(),
+            PlannedBuild::Head => "HEAD build".to_string(),
+        };
+        plan.push((name.clone(), how));
+    }
+    while let Some(Ok((index, size))) = probes.join_next().await {
+        if size > 0 {
+            plan[index].1 = format!("bottle, {}", crate::ui::format_bytes(size));
+        }
+    }
+    Ok(plan)
+}
+
+/// The formula has a bottle for `platform` and every runtime dependency is already
+/// installed (or is being skipped), so no other formula has to be looked up.
+fn bottle_is_self_contained(
+    formula: &Formula,
+    platform: &str,
+    installed: &HashSet<String>,
+    ignore_dependencies: bool,
+) -> bool {
+    let has_bottle = formula
+        .bottle
+        .as_ref()
+        .and_then(|b| b.stable.as_ref())
+        .and_then(|s| s.file_for_platform(platform))
+        .is_some();
+    has_bottle
+        && (ignore_dependencies
+            || formula
+                .dependencies
+                .iter()
+                .flatten()
+                .all(|dep| installed.contains(dep)))
+}
+
+/// `--ignore-dependencies` installs just the roots; name the runtime dependencies that are
+/// not already installed so a broken install is not a surprise.
+fn warn_skipped_dependencies(roots: &[&crate::api::Formula], installed: &HashSet<String>) {
+    for formula in roots {
+        let missing: Vec<&str> = formula
+            .dependencies
+            .iter()
+            .flatten()
+            .filter(|dep| !installed.contains(dep.as_str()))
+            .map(String::as_str)
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+        eprintln!(
+            "{} installing {} without its dependencies ({}); it may not work until they are installed",
+            style("warning:").yellow(),
+            style(&formula.name).magenta(),
+            missing.join(", ")
+        );
+    }
+}
+
+/// Turn `--with x11` / `--without docs` into formula option names (`with-x11`,
+/// `without-docs`). Values already carrying the prefix are accepted as-is.
+pub fn build_options(with: &[String], without: &[String]) -> Vec<String> {
+    let normalize = |prefix: &str, value: &String| {
+        let value = value.trim_start_matches("--");
+        if value.starts_with(&format!("{}-", prefix)) {
+            value.to_string()
+        } else {
+            format!("{}-{}", prefix, value)
+        }
+    };
+    with.iter()
+        .map(|v| normalize("with", v))
+        .chain(without.iter().map(|v| normalize("without", v)))
+        .collect()
 }
 
 #[cfg(target_os = "windows")]
@@ -629,8 +998,8 @@ fn tap_name_from_qualified_package(package_name: &str) -> Option<String> {
     Some(format!("{}/{}", user, repo))
 }
 
-fn hint_user_prefix_path_if_needed(install_mode: InstallMode, quiet: bool) {
-    if quiet || install_mode != InstallMode::User {
+fn hint_prefix_bin_path_if_needed(install_mode: InstallMode, quiet: bool) {
+    if quiet {
         return;
     }
     let Ok(prefix) = install_mode.prefix() else {
@@ -640,11 +1009,8 @@ fn hint_user_prefix_path_if_needed(install_mode: InstallMode, quiet: bool) {
     if !bin_dir.exists() {
         return;
     }
-    let Ok(path_var) = std::env::var("PATH") else {
-        return;
-    };
-    let bin_str = bin_dir.to_string_lossy();
-    if path_var.split(':').any(|p| p == bin_str.as_ref()) {
+    let path_var = std::env::var_os("PATH").unwrap_or_default();
+    if path_contains_dir(&path_var, &bin_dir) {
         return;
     }
     println!();
@@ -657,6 +1023,46 @@ fn hint_user_prefix_path_if_needed(install_mode: InstallMode, quiet: bool) {
     println!("  export PATH=\"{}:$PATH\"", bin_dir.display());
 }
 
+/// Whether `dir` appears in a `PATH`-style list, ignoring trailing separators and
+/// resolving symlinked entries (e.g. `/usr/local/bin` → `/opt/homebrew/bin`).
+fn path_contains_dir(path_var: &std::ffi::OsStr, dir: &Path) -> bool {
+    let canonical_dir = dunce::canonicalize(dir).ok();
+    std::env::split_paths(path_var).any(|entry| {
+        if entry.as_os_str().is_empty() {
+            return false;
+        }
+        if entry.components().eq(dir.components()) {
+            return true;
+        }
+        match (&canonical_dir, dunce::canonicalize(&entry)) {
+            (Some(canonical_dir), Ok(canonical_entry)) => &canonical_entry == canonical_dir,
+            _ => false,
+        }
+    })
+}
+
+/// `deprecated (reason)` / `disabled (reason)` for a formula upstream has retired.
+pub(crate) fn lifecycle_status(formula: &crate::api::Formula) -> Option<String> {
+    let (label, reason) = if formula.disabled {
+        ("disabled", formula.disable_reason.as_deref())
+    } else if formula.deprecated {
+        ("deprecated", formula.deprecation_reason.as_deref())
+    } else {
+        return None;
+    };
+    Some(match reason {
+        Some(reason) => format!("{} ({})", label, reason.replace('_', " ")),
+        None => label.to_string(),
+    })
+}
+
+fn disabled_refusal(formula: &crate::api::Formula) -> String {
+    format!(
+        "{} upstream; pass --force to install it anyway",
+        lifecycle_status(formula).unwrap_or_else(|| "disabled".to_string())
+    )
+}
+
 #[cfg_attr(target_os = "windows", allow(unreachable_code, unused_variables))]
 pub(crate) async fn install_impl(
     cache: &Cache,
@@ -673,7 +1079,7 @@ pub(crate) async fn install_impl(
 
     #[cfg(target_os = "windows")]
     {
-        if args.cask || args.head || args.build_from_source {
+        if args.cask || args.head || args.build_from_source || !args.options.is_empty() {
             return Err(crate::error::homebrew_unavailable());
         }
         return install_windows_packages(cache, package_names, args.dry_run, args.quiet).await;
@@ -690,12 +1096,21 @@ pub(crate) async fn install_impl(
         run_scripts,
         quiet,
         force_reinstall,
+        options,
+        ignore_dependencies,
+        only_dependencies,
+        no_verify,
+        no_relocate,
+        platform,
+        overwrite,
         external_pb,
     } = args;
 
-    cache.ensure_fresh().await?;
-
     if cask {
+        cache.ensure_fresh().await?;
+        if !quiet {
+            cache.warn_if_index_old().await;
+        }
         return install_casks(cache, package_names, dry_run, ask, quiet, force_reinstall).await;
     }
 
@@ -706,6 +1121,12 @@ pub(crate) async fn install_impl(
 
     install_mode.validate()?;
 
+    let platform = match platform {
+        Some(tag) => foreign_platform(tag, quiet)?,
+        None => detect_platform(),
+    };
+    let foreign = platform != detect_platform();
+
     let mut tap_manager = TapManager::new()?;
     tap_manager.load().await?;
 
@@ -739,7 +1160,6 @@ pub(crate) async fn install_impl(
         }
     }
 
-    let formulae = cache.load_all_formulae().await?;
     let state = InstallState::new()?;
     state.sync_from_cellar().await.ok();
     let installed_packages = state.load().await?;
@@ -753,10 +1173,37 @@ pub(crate) async fn install_impl(
             }
         })
         .collect();
+    let installed_cask_names: HashSet<String> =
+        CaskState::new()?.load().await?.into_keys().collect();
+
+    let needs_index = head || build_from_source || !options.is_empty() || only_dependencies;
+    let single = if needs_index || cache.is_initialized() || local_core_tap().is_some() {
+        None
+    } else {
+        fetch_single_formula_without_index(
+            cache,
+            package_names,
+            &installed,
+            ignore_dependencies,
+            &platform,
+        )
+        .await
+    };
+    let formulae = match single {
+        Some(formula) => vec![formula],
+        None => {
+            cache.ensure_fresh().await?;
+            if !quiet {
+                cache.warn_if_index_old().await;
+            }
+            cache.load_all_formulae().await?
+        }
+    };
 
     // Pre-build lookup maps for O(1) formula resolution instead of O(n) linear scans
-    let by_name: std::collections::HashMap<&str, &crate::api::Formula> =
+    let mut by_name: std::collections::HashMap<&str, &crate::api::Formula> =
         formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+    add_alias_names(&mut by_name, &formulae);
     let by_full_name: std::collections::HashMap<&str, &crate::api::Formula> =
         formulae.iter().map(|f| (f.full_name.as_str(), f)).collect();
 
@@ -766,13 +1213,57 @@ pub(crate) async fn install_impl(
     let mut errors = Vec::new();
     let mut detected_casks: Vec<String> = Vec::new();
     let mut user_direct_formula_names: HashSet<String> = HashSet::new();
+    let mut requested_roots: Vec<(String, &crate::api::Formula)> = Vec::new();
+    let mut adopted = Vec::new();
 
     for package_name in package_names.iter() {
-        if installed.contains(package_name.as_str()) {
+        // With --only-dependencies an installed root still needs its missing deps resolved.
+        if installed.contains(package_name.as_str()) && !only_dependencies {
+            already_installed.push(package_name.clone());
+            continue;
+        }
+
+        // `wax install docker` after `wax install --cask docker`: the cask is what is
+        // installed, so report it rather than adding the formula of the same name.
+        if !force_reinstall
+            && !only_dependencies
+            && installed_cask_names.contains(package_name.as_str())
+            && matches!(
+                resolve_installed_kind(cache, package_name, dry_run).await?,
+                Some(InstalledKind::Cask(_))
+            )
+        {
+            if !quiet {
+                eprintln!(
+                    "{} {} is installed as a cask (use --cask --force to reinstall it)",
+                    style("note:").yellow(),
+                    style(package_name).magenta()
+                );
+            }
             already_installed.push(package_name.clone());
             continue;
         }
 
+        // A keg Homebrew installed is already recorded by `sync_from_cellar`; installing
+        // into the user prefix as well would leave two copies with different link targets.
+        if !force_reinstall && !quiet && !only_dependencies {
+            if let Some(keg) =
+                homebrew_managed_keg(package_name, install_mode, &installed_packages, |mode| {
+                    mode.cellar_path()
+                })?
+            {
+                eprintln!(
+                    "{} {} is already installed in the Homebrew Cellar ({}); using that keg \
+                     (pass --force to install a separate copy)",
+                    style("note:").yellow(),
+                    style(package_name).magenta(),
+                    keg.display()
+                );
+                adopted.push(package_name.clone());
+                continue;
+            }
+        }
+
         let formula = if package_name.contains('/') {
             by_full_name
                 .get(package_name.as_str())
@@ -794,11 +1285,16 @@ pub(crate) async fn install_impl(
             Some(f) => f,
             None => {
                 let casks = cache.load_casks().await?;
-                let cask_exists = casks
-                    .iter()
-                    .any(|c| &c.token == package_name || &c.full_token == package_name);
+                let cask_exists = |token: &str| {
+                    casks
+                        .iter()
+                        .any(|c| c.token == token || c.full_token == token)
+                };
+                // `font-foo@2`: a versioned token of a cask that is not also a formula.
+                let versioned_cask = versioned_token_base(package_name)
+                    .is_some_and(|base| !by_name.contains_key(base) && cask_exists(base));
 
-                if cask_exists {
+                if cask_exists(package_name) || versioned_cask {
                     // Collect for batch install — all casks will be downloaded concurrently below
                     detected_casks.push(package_name.clone());
                     continue;
@@ -849,22 +1345,80 @@ pub(crate) async fn install_impl(
             }
         };
 
-        match resolve_dependencies(formula, &formulae, &installed) {
-            Ok(deps) => {
-                user_direct_formula_names.insert(formula.name.clone());
-                for dep in deps {
-                    if all_to_install_set.insert(dep.clone()) {
-                        all_to_install.push(dep);
-                    }
+        if formula.disabled && !force_reinstall {
+            errors.push((package_name.clone(), disabled_refusal(formula)));
+            continue;
+        }
+        if (formula.disabled || formula.deprecated) && !quiet {
+            eprintln!(
+                "{} {} is {}",
+                style("warning:").yellow(),
+                style(&formula.name).magenta(),
+                lifecycle_status(formula).unwrap_or_default()
+            );
+        }
+
+        requested_roots.push((package_name.clone(), formula));
+    }
+
+    // Resolve every requested formula in one graph so shared dependencies are
+    // walked once. If that fails, fall back to per-root resolution so the error
+    // is attributed to the package that caused it.
+    let root_formulae: Vec<&crate::api::Formula> =
+        requested_roots.iter().map(|(_, f)| *f).collect();
+    let resolve_started = std::time::Instant::now();
+    let combined = if root_formulae.is_empty() {
+        Ok(Vec::new())
+    } else if ignore_dependencies {
+        if !quiet {
+            warn_skipped_dependencies(&root_formulae, &installed);
+        }
+        Ok(root_formulae.iter().map(|f| f.name.clone()).collect())
+    } else {
+        resolve_dependencies_for_roots(&root_formulae, &by_name, &installed)
+    };
+    match combined {
+        Ok(deps) => {
+            user_direct_formula_names.extend(root_formulae.iter().map(|f| f.name.clone()));
+            for dep in deps {
+                if all_to_install_set.insert(dep.clone()) {
+                    all_to_install.push(dep);
                 }
             }
-            Err(e) => {
-                errors.push((package_name.clone(), format!("{}", e)));
-                continue;
+        }
+        Err(_) => {
+            for (package_name, formula) in &requested_roots {
+                match resolve_dependencies_for_roots(&[formula], &by_name, &installed) {
+                    Ok(deps) => {
+                        user_direct_formula_names.insert(formula.name.clone());
+                        for dep in deps {
+                            if all_to_install_set.insert(dep.clone()) {
+                                all_to_install.push(dep);
+                            }
+                        }
+                    }
+                    Err(e) => errors.push((package_name.clone(), format!("{}", e))),
+                }
             }
         }
     }
 
+    crate::ui::record_phase("(all)", "resolve", resolve_started.elapsed());
+
+    if only_dependencies {
+        let roots: HashSet<&str> = requested_roots
+            .iter()
+            .map(|(_, f)| f.name.as_str())
+            .collect();
+        all_to_install.retain(|name| !roots.contains(name.as_str()));
+        if all_to_install.is_empty() && !roots.is_empty() && !quiet {
+            println!(
+                "dependencies of {} are already installed",
+                package_names.join(", ")
+            );
+        }
+    }
+
     if !already_installed.is_empty() && !quiet {
         for pkg in &already_installed {
             println!("{} is already installed", style(pkg).magenta());
@@ -873,6 +1427,18 @@ pub(crate) async fn install_impl(
 
     check_already_installed_formula_linkages(&already_installed, &installed_packages)?;
 
+    // Naming an installed dependency is a request for it; keep it when its dependents go.
+    if !only_dependencies {
+        let requested_again: Vec<String> = already_installed
+            .iter()
+            .filter(|name| installed.contains(name.as_str()))
+            .cloned()
+            .collect();
+        state
+            .set_installed_as_dependency(&requested_again, false)
+            .await?;
+    }
+
     if !errors.is_empty() && !quiet {
         for (pkg, err) in &errors {
             eprintln!("{}: {}", pkg, err);
@@ -888,19 +1454,39 @@ pub(crate) async fn install_impl(
         if !detected_casks.is_empty() {
             install_casks(cache, &detected_casks, dry_run, ask, quiet, false).await?;
         }
-        hint_user_prefix_path_if_needed(install_mode, quiet);
+        hint_prefix_bin_path_if_needed(install_mode, quiet);
         return Ok(());
     }
 
     let requested: Vec<&str> = package_names
         .iter()
-        .filter(|p| !already_installed.contains(p) && !errors.iter().any(|(e, _)| e == *p))
+        .filter(|p| {
+            !already_installed.contains(p)
+                && !adopted.contains(p)
+                && !errors.iter().any(|(e, _)| e == *p)
+        })
         .map(|s| s.as_str())
         .collect();
     let package_list = requested.join(", ");
 
-    let dep_count = all_to_install.len().saturating_sub(requested.len());
-    if dep_count > 0 && !quiet {
+    let dep_count = if only_dependencies {
+        all_to_install.len()
+    } else {
+        all_to_install.len().saturating_sub(requested.len())
+    };
+    if only_dependencies && !quiet {
+        println!();
+        println!(
+            "installing {} {} of {}",
+            dep_count,
+            if dep_count == 1 {
+                "dependency"
+            } else {
+                "dependencies"
+            },
+            package_list
+        );
+    } else if dep_count > 0 && !quiet {
         println!();
         println!(
             "installing {} + {} {}",
@@ -916,10 +1502,26 @@ pub(crate) async fn install_impl(
 
     if dry_run || ask {
         if !quiet {
+            let plan = install_plan(
+                &all_to_install,
+                &by_name,
+                &user_direct_formula_names,
+                &platform,
+                head,
+                build_from_source,
+                !options.is_empty(),
+                foreign,
+            )
+            .await?;
             println!();
             println!("{} install plan", style("→").cyan().bold());
-            for name in &all_to_install {
-                println!("  {} {}", style("+").green(), style(name).magenta());
+            for (name, how) in &plan {
+                println!(
+                    "  {} {} {}",
+                    style("+").green(),
+                    style(name).magenta(),
+                    style(format!("({})", how)).dim()
+                );
             }
             if dry_run {
                 println!("\n{}", style("dry run - no changes made").dim());
@@ -928,7 +1530,7 @@ pub(crate) async fn install_impl(
         if dry_run {
             return Ok(());
         }
-        let proceed = confirm_prompt("Proceed with install?")?;
+        let proceed = confirm_prompt(false, "Proceed with install?", false)?;
         if !proceed {
             println!("{} install cancelled", style("✗").red());
             return Ok(());
@@ -945,8 +1547,7 @@ pub(crate) async fn install_impl(
         }))
     };
 
-    let platform = detect_platform();
-    debug!("Detected platform: {}", platform);
+    info!("Platform tag: {}", platform);
 
     let cellar = install_mode.cellar_path()?;
 
@@ -959,46 +1560,40 @@ pub(crate) async fn install_impl(
         None
     };
 
-    let packages_to_install: Vec<_> = all_to_install
-        .iter()
-        .map(|name| {
-            by_name
-                .get(name.as_str())
-                .copied()
-                .ok_or_else(|| WaxError::FormulaNotFound(name.clone()))
-        })
-        .collect::<Result<_>>()?;
+    // Resolution only yields indexed names, but one bad entry should cost that package
+    // and whatever depends on it, not the whole install.
+    let (packages_to_install, unresolved) = indexed_formulae(&all_to_install, &by_name);
 
+    // Formulae whose bottle is the same artifact (URL and checksum) download it once.
     let formula_bottle_count = packages_to_install
         .iter()
-        .filter(|pkg| {
-            !(head || build_from_source)
-                && pkg
-                    .bottle
-                    .as_ref()
-                    .and_then(|b| b.stable.as_ref())
-                    .and_then(|s| s.file_for_platform(&platform))
-                    .is_some()
+        .filter(|_| !(head || build_from_source))
+        .filter_map(|pkg| {
+            let file = pkg
+                .bottle
+                .as_ref()?
+                .stable
+                .as_ref()?
+                .file_for_platform(&platform)?;
+            Some((file.url.as_str(), file.sha256.as_str()))
         })
-        .count();
+        .collect::<HashSet<_>>()
+        .len();
 
     let user_direct_formula_count = user_direct_formula_names.len();
 
     // "All downloads" only for multiple *user-requested* formulae with multiple bottle
     // downloads. One requested formula (plus deps), or a single bottle, stays per-row only
     // — same idea as one cask, and keeps `wax install one_formula one_cask` uncluttered.
-    let formula_pipeline_totals = if quiet
+    let show_formula_overall = !(quiet
         || external_pb.is_some()
         || user_direct_formula_count <= 1
-        || formula_bottle_count <= 1
-    {
-        None
-    } else {
-        Some(DownloadTotals::default())
-    };
+        || formula_bottle_count <= 1);
+    // Bytes are tallied even without the "All downloads" row, for the closing summary.
+    let formula_pipeline_totals = DownloadTotals::default();
     let hide_formula_overall = Arc::new(AtomicBool::new(false));
     let formula_net_phase_done = Arc::new(AtomicUsize::new(0));
-    let formula_overall_poller = if let Some(totals) = formula_pipeline_totals.as_ref() {
+    let formula_overall_poller = if show_formula_overall {
         let overall_pb = multi.insert(0, ProgressBar::new(0));
         overall_pb.set_style(
             ProgressStyle::default_bar()
@@ -1007,7 +1602,7 @@ pub(crate) async fn install_impl(
                 .progress_chars(PROGRESS_BAR_CHARS),
         );
         overall_pb.set_message("All downloads");
-        let totals_w = totals.clone();
+        let totals_w = formula_pipeline_totals.clone();
         let overall_w = overall_pb.clone();
         let hide_w = Arc::clone(&hide_formula_overall);
         Some(tokio::spawn(async move {
@@ -1035,6 +1630,7 @@ pub(crate) async fn install_impl(
     let downloader = Arc::new(BottleDownloader::new());
 
     // Collect (name, url) for every package that has a bottle on this platform.
+    let mut probed_urls = HashSet::new();
     let bottle_urls: Vec<(String, String)> = packages_to_install
         .iter()
         .filter(|_pkg| !build_from_source)
@@ -1043,6 +1639,7 @@ pub(crate) async fn install_impl(
             let file = f.file_for_platform(&platform)?;
             Some((pkg.name.clone(), file.url.clone()))
         })
+        .filter(|(_, url)| probed_urls.insert(url.clone()))
         .collect();
 
     // Probe all bottle URLs concurrently to get file sizes, then allocate
@@ -1101,53 +1698,86 @@ pub(crate) async fn install_impl(
 
     let semaphore = Arc::new(Semaphore::new(concurrent_limit));
     let mut tasks = JoinSet::new();
+    // Bottles handed to `tasks`, in dependency order; kegs are linked in this order.
+    let mut link_order: Vec<String> = Vec::new();
+    // First formula to claim each (url, sha256), and the later formulae that reuse its
+    // download and extracted directory instead of fetching the same artifact again.
+    let mut artifact_owner: HashMap<(String, String), String> = HashMap::new();
+    let mut shared_bottles: HashMap<String, Vec<(String, String, u32)>> = HashMap::new();
 
     let temp_dir = Arc::new(TempDir::new()?);
+    let downloads_started = std::time::Instant::now();
+    // Milliseconds from `downloads_started` until the last bottle finished downloading.
+    let download_window_ms = Arc::new(AtomicU64::new(0));
 
     for pkg in packages_to_install {
-        let has_bottle = pkg
-            .bottle
-            .as_ref()
-            .and_then(|b| b.stable.as_ref())
-            .and_then(|s| s.file_for_platform(&platform))
-            .is_some();
-
-        if head {
-            check_cancelled()?;
-            if !quiet {
-                println!();
-                println!("installing {} from HEAD", pkg.name);
-            }
-            install_from_head_task(pkg.clone(), &cellar, install_mode, &state, &platform).await?;
-            continue;
-        }
-
-        if !has_bottle || build_from_source {
-            check_cancelled()?;
+        // Options only apply to the formulae the user asked for, not their dependencies.
+        let pkg_options: &[String] = if user_direct_formula_names.contains(&pkg.name) {
+            options
+        } else {
+            &[]
+        };
 
-            if build_from_source && has_bottle && !quiet {
-                println!();
-                println!("building {} from source", pkg.name);
+        let (url, sha256) = match planned_build(
+            pkg,
+            &platform,
+            head,
+            build_from_source,
+            !pkg_options.is_empty(),
+            foreign,
+        )? {
+            PlannedBuild::Head => {
+                check_cancelled()?;
+                if !quiet {
+                    println!();
+                    println!("installing {} from HEAD", pkg.name);
+                }
+                install_from_head_task(
+                    pkg.clone(),
+                    &cellar,
+                    install_mode,
+                    &state,
+                    &platform,
+                    pkg_options,
+                    no_verify,
+                    overwrite,
+                )
+                .await?;
+                continue;
             }
+            PlannedBuild::Source => {
+                check_cancelled()?;
 
-            install_from_source_task(pkg.clone(), &cellar, install_mode, &state, &platform).await?;
-            continue;
-        }
-
-        let bottle_info = pkg
-            .bottle
-            .as_ref()
-            .and_then(|b| b.stable.as_ref())
-            .ok_or_else(|| {
-                WaxError::BottleNotAvailable(format!("{} (no bottle info)", pkg.name))
-            })?;
+                if !quiet {
+                    println!();
+                    match missing_bottle_reason(pkg, &platform) {
+                        Some(reason) if !build_from_source && pkg_options.is_empty() => println!(
+                            "{}: {} {}",
+                            style(&pkg.name).magenta(),
+                            reason,
+                            style("(building from source)").dim()
+                        ),
+                        None => println!("building {} from source", pkg.name),
+                        _ => {}
+                    }
+                }
 
-        let bottle_file = bottle_info.file_for_platform(&platform).ok_or_else(|| {
-            WaxError::BottleNotAvailable(format!("{} for platform {}", pkg.name, platform))
-        })?;
+                install_from_source_task(
+                    pkg.clone(),
+                    &cellar,
+                    install_mode,
+                    &state,
+                    &platform,
+                    pkg_options,
+                    no_verify,
+                    overwrite,
+                )
+                .await?;
+                continue;
+            }
+            PlannedBuild::Bottle { url, sha256 } => (url, sha256),
+        };
 
-        let url = bottle_file.url.clone();
-        let sha256 = bottle_file.sha256.clone();
         let name = pkg.name.clone();
         let version = pkg.versions.stable.clone();
         let rebuild = pkg.bottle_rebuild();
@@ -1155,26 +1785,28 @@ pub(crate) async fn install_impl(
         let pkg_connections = connections_map.get(&name).copied().unwrap_or(1);
 
         if let Some(ext_pb) = external_pb {
-            let tarball_path = temp_dir.path().join(format!("{}-{}.tar.gz", name, version));
+            let tarball_path = temp_dir
+                .path()
+                .join(archive_file_name(&format!("{}-{}", name, version), &url));
 
+            let started = std::time::Instant::now();
             downloader
                 .download(&url, &tarball_path, Some(ext_pb), pkg_connections, None)
                 .await?;
-
-            crate::digest::verify_sha256_file(&tarball_path, &sha256)?;
-
-            let extract_dir = temp_dir.path().join(&name);
-            BottleDownloader::extract(&tarball_path, &extract_dir)?;
+            crate::ui::record_phase(&name, "download", started.elapsed());
 
             // Transition download bar → install spinner in-place by cloning the handle
             // (indicatif clones share the same underlying state).
-            ext_pb.set_style(
-                ProgressStyle::default_spinner()
-                    .template("{spinner:.cyan} {msg}")
-                    .unwrap()
-                    .tick_chars(crate::ui::SPINNER_TICK_CHARS),
-            );
+            ext_pb.set_style(spinner_style());
             ext_pb.enable_steady_tick(std::time::Duration::from_millis(80));
+            ext_pb.set_message(format!(
+                "{} {}",
+                style(&name).magenta(),
+                style("extracting...").dim()
+            ));
+
+            let extract_dir =
+                verify_and_extract(&name, &url, &tarball_path, &sha256, &temp_dir).await?;
 
             install_extracted_bottle(
                 &name,
@@ -1188,6 +1820,8 @@ pub(crate) async fn install_impl(
                 &state,
                 false,
                 run_scripts,
+                !no_relocate,
+                overwrite,
                 None,
                 Some(ext_pb.clone()),
             )
@@ -1195,16 +1829,30 @@ pub(crate) async fn install_impl(
             continue;
         }
 
+        let artifact = (url.clone(), sha256.clone());
+        if let Some(owner) = artifact_owner.get(&artifact) {
+            debug!("{} reuses the bottle downloaded for {}", name, owner);
+            shared_bottles
+                .entry(owner.clone())
+                .or_default()
+                .push((name.clone(), version, rebuild));
+            link_order.push(name);
+            continue;
+        }
+        artifact_owner.insert(artifact, name.clone());
+
         let downloader = Arc::clone(&downloader);
         let semaphore = Arc::clone(&semaphore);
         let temp_dir = Arc::clone(&temp_dir);
         let conns = pkg_connections;
         let pipe_totals = formula_pipeline_totals.clone();
+        let window_ms = Arc::clone(&download_window_ms);
         let net_done_f = Arc::clone(&formula_net_phase_done);
         let hide_f = Arc::clone(&hide_formula_overall);
         let n_bottle_formula = formula_bottle_count;
 
         let multi = multi.clone();
+        link_order.push(name.clone());
         tasks.spawn(async move {
             let permit = semaphore
                 .acquire()
@@ -1227,99 +1875,214 @@ pub(crate) async fn install_impl(
                 pb
             };
 
-            let tarball_path = temp_dir.path().join(format!("{}-{}.tar.gz", name, version));
+            let tarball_path = temp_dir
+                .path()
+                .join(archive_file_name(&format!("{}-{}", name, version), &url));
 
+            let started = std::time::Instant::now();
             let dl = downloader
-                .download(&url, &tarball_path, Some(&pb), conns, pipe_totals.as_ref())
+                .download(&url, &tarball_path, Some(&pb), conns, Some(&pipe_totals))
                 .await;
             pb.finish_and_clear();
+            crate::ui::record_phase(&name, "download", started.elapsed());
+            window_ms.fetch_max(
+                downloads_started.elapsed().as_millis() as u64,
+                Ordering::Relaxed,
+            );
 
             // Release the download permit before extraction so the next package
             // can start downloading immediately rather than waiting for CPU-bound work.
             drop(permit);
 
-            if pipe_totals.is_some() {
+            if show_formula_overall {
                 note_aggregate_download_row_done(&net_done_f, n_bottle_formula, &hide_f);
             }
 
             dl?;
 
-            crate::digest::verify_sha256_file(&tarball_path, &sha256)?;
-
-            let extract_dir = temp_dir.path().join(&name);
-            BottleDownloader::extract(&tarball_path, &extract_dir)?;
+            let extracting = if quiet {
+                ProgressBar::hidden()
+            } else {
+                let pb = multi.add(ProgressBar::new_spinner());
+                pb.set_style(spinner_style());
+                pb.set_message(format!(
+                    "{} {}",
+                    style(&name).magenta(),
+                    style("extracting...").dim()
+                ));
+                pb.enable_steady_tick(std::time::Duration::from_millis(80));
+                pb
+            };
+            let extracted =
+                verify_and_extract(&name, &url, &tarball_path, &sha256, &temp_dir).await;
+            extracting.finish_and_clear();
+            let extract_dir = extracted?;
 
             Ok::<_, WaxError>((name, version, extract_dir, sha256, rebuild))
         });
     }
 
-    // Collect results; abort remaining tasks immediately on cancellation.
-    // Install each extracted bottle as soon as it becomes available.
-    let mut failed_packages = Vec::new();
+    // Collect results; abort remaining downloads immediately on cancellation.
+    // Each extracted bottle is poured into the Cellar in its own task as soon as it is
+    // available, so hashing, extraction and copying of independent packages overlap.
+    // Pours already running are allowed to finish. Kegs are linked one at a time in
+    // dependency order: a keg waits until everything before it in `link_order` is done.
+    // Once cancelled, finished pours are never linked or recorded; their kegs are removed.
+    let mut failed_packages = unresolved;
     let mut cancelled = false;
+    let mut bottles_installed = 0usize;
+    let mut pours = JoinSet::new();
+    let mut poured: HashMap<String, Option<PouredBottle>> = HashMap::new();
+    let mut next_link = 0usize;
+
+    loop {
+        tokio::select! {
+            Some(handle) = tasks.join_next() => {
+                if cancelled || crate::signal::is_shutdown_requested() {
+                    tasks.abort_all();
+                    cancelled = true;
+                    continue;
+                }
+                match handle {
+                    Ok(Ok((name, version, extract_dir, bottle_sha, bottle_rebuild))) => {
+                        let sharers = shared_bottles.remove(&name).unwrap_or_default();
+                        let consumers = std::iter::once((name, version, bottle_rebuild))
+                            .chain(sharers);
+                        for (name, version, bottle_rebuild) in consumers {
+                            let multi = multi.clone();
+                            let cellar = cellar.clone();
+                            let extract_dir = extract_dir.clone();
+                            let bottle_sha = bottle_sha.clone();
+                            pours.spawn(async move {
+                                let spinner = if quiet {
+                                    ProgressBar::hidden()
+                                } else {
+                                    let pb = multi.add(ProgressBar::new_spinner());
+                                    pb.set_style(spinner_style());
+                                    pb.enable_steady_tick(std::time::Duration::from_millis(80));
+                                    pb
+                                };
+                                let result = pour_bottle(
+                                    &name,
+                                    &version,
+                                    &extract_dir,
+                                    bottle_rebuild,
+                                    &cellar,
+                                    install_mode,
+                                    quiet,
+                                    !no_relocate,
+                                    None,
+                                    Some(&spinner),
+                                )
+                                .await;
+                                let result = match result {
+                                    Ok(cellar_version) => Ok(PouredBottle {
+                                        cellar_version,
+                                        sha256: bottle_sha,
+                                        rebuild: bottle_rebuild,
+                                        spinner,
+                                    }),
+                                    Err(e) => {
+                                        spinner.finish_and_clear();
+                                        Err(e)
+                                    }
+                                };
+                                (name, result)
+                            });
+                        }
+                    }
+                    Ok(Err(WaxError::Interrupted)) => {
+                        cancelled = true;
+                    }
+                    Ok(Err(e)) => {
+                        failed_packages.push(format!("{}", e));
+                    }
+                    Err(e) if e.is_cancelled() => {
+                        cancelled = true;
+                    }
+                    Err(e) => {
+                        failed_packages.push(format!("Task error: {}", e));
+                    }
+                }
+            }
+            Some(handle) = pours.join_next() => {
+                match handle {
+                    Ok((name, Ok(bottle))) => {
+                        poured.insert(name, Some(bottle));
+                    }
+                    Ok((name, Err(e))) => {
+                        failed_packages.push(format!("{}", e));
+                        poured.insert(name, None);
+                    }
+                    Err(e) => {
+                        failed_packages.push(format!("Task error: {}", e));
+                    }
+                }
+                if cancelled || crate::signal::is_shutdown_requested() {
+                    cancelled = true;
+                    continue;
+                }
+                while let Some(bottle) =
+                    link_order.get(next_link).and_then(|n| poured.remove(n))
+                {
+                    let name = &link_order[next_link];
+                    next_link += 1;
+                    if let Some(bottle) = bottle {
+                        let linked = link_poured(
+                            name,
+                            bottle,
+                            &cellar,
+                            install_mode,
+                            &platform,
+                            &state,
+                            quiet,
+                            run_scripts,
+                            overwrite,
+                        )
+                        .await;
+                        match linked {
+                            Ok(()) => bottles_installed += 1,
+                            Err(e) => failed_packages.push(format!("{}", e)),
+                        }
+                    }
+                }
+            }
+            else => break,
+        }
+    }
 
-    while let Some(handle) = tasks.join_next().await {
-        if cancelled || crate::signal::is_shutdown_requested() {
-            tasks.abort_all();
-            cancelled = true;
-            continue;
+    // Formulae sharing a bottle whose download failed were never poured.
+    for (owner, sharers) in shared_bottles.drain() {
+        for (name, _, _) in sharers {
+            failed_packages.push(format!(
+                "{}: shares its bottle with {}, which failed to download",
+                name, owner
+            ));
         }
-        match handle {
-            Ok(Ok((name, version, extract_dir, bottle_sha, bottle_rebuild))) => {
-                let spinner = if quiet {
-                    ProgressBar::hidden()
-                } else {
-                    let pb = multi.add(ProgressBar::new_spinner());
-                    pb.set_style(
-                        ProgressStyle::default_spinner()
-                            .template("{spinner:.cyan} {msg}")
-                            .unwrap()
-                            .tick_chars(crate::ui::SPINNER_TICK_CHARS),
-                    );
-                    pb.enable_steady_tick(std::time::Duration::from_millis(80));
-                    pb
-                };
-                match install_extracted_bottle(
-                    &name,
-                    &version,
-                    &extract_dir,
-                    bottle_sha,
-                    bottle_rebuild,
+    }
+
+    // Whatever is left waited behind a package that failed before it was poured.
+    // After Ctrl-C nothing more is linked or recorded.
+    if !cancelled {
+        for name in &link_order[next_link..] {
+            if let Some(Some(bottle)) = poured.remove(name) {
+                match link_poured(
+                    name,
+                    bottle,
                     &cellar,
                     install_mode,
                     &platform,
                     &state,
                     quiet,
                     run_scripts,
-                    None,
-                    Some(spinner.clone()),
+                    overwrite,
                 )
                 .await
                 {
-                    Ok(()) => {
-                        spinner.finish_and_clear();
-                        if !quiet {
-                            println!("+ {}@{}", style(&name).magenta(), style(&version).dim());
-                        }
-                    }
-                    Err(e) => {
-                        spinner.finish_and_clear();
-                        failed_packages.push(format!("{}", e));
-                    }
+                    Ok(()) => bottles_installed += 1,
+                    Err(e) => failed_packages.push(format!("{}", e)),
                 }
             }
-            Ok(Err(WaxError::Interrupted)) => {
-                cancelled = true;
-            }
-            Ok(Err(e)) => {
-                failed_packages.push(format!("{}", e));
-            }
-            Err(e) if e.is_cancelled() => {
-                cancelled = true;
-            }
-            Err(e) => {
-                failed_packages.push(format!("Task error: {}", e));
-            }
         }
     }
 
@@ -1329,6 +2092,22 @@ pub(crate) async fn install_impl(
     }
 
     if cancelled {
+        for (name, bottle) in poured.drain() {
+            let Some(bottle) = bottle else { continue };
+            bottle.spinner.finish_and_clear();
+            let formula_dir = cellar.join(&name);
+            let keg = formula_dir.join(&bottle.cellar_version);
+            if let Err(e) = tokio::fs::remove_dir_all(&keg).await {
+                eprintln!(
+                    "{} could not remove unlinked keg {}: {}",
+                    style("warning:").yellow(),
+                    keg.display(),
+                    e
+                );
+            }
+            // Only succeeds when no other version is left in the formula's directory.
+            let _ = tokio::fs::remove_dir(&formula_dir).await;
+        }
         return Err(WaxError::Interrupted);
     }
 
@@ -1346,6 +2125,15 @@ pub(crate) async fn install_impl(
     check_cancelled()?;
     drop(multi);
 
+    let dependencies: Vec<String> = all_to_install
+        .iter()
+        .filter(|name| !user_direct_formula_names.contains(name.as_str()))
+        .cloned()
+        .collect();
+    state
+        .set_installed_as_dependency(&dependencies, true)
+        .await?;
+
     let state_snapshot = state.load().await?;
     let installed_names: std::collections::HashSet<String> =
         state_snapshot.keys().cloned().collect();
@@ -1377,14 +2165,48 @@ pub(crate) async fn install_impl(
             }
         }
     }
+    if !quiet && bottles_installed > 0 {
+        println!(
+            "\n{}",
+            style(install_summary(
+                bottles_installed,
+                formula_pipeline_totals.downloaded.load(Ordering::Relaxed),
+                std::time::Duration::from_millis(download_window_ms.load(Ordering::Relaxed)),
+            ))
+            .dim()
+        );
+    }
+
     if let Some(task) = cask_task {
         task.await
             .map_err(|e| WaxError::InstallError(format!("cask task failed: {}", e)))??;
     }
-    hint_user_prefix_path_if_needed(install_mode, quiet);
+    hint_prefix_bin_path_if_needed(install_mode, quiet);
     Ok(())
 }
 
+/// `3 packages installed, 412.0 MB in 18.2s (22.6 MB/s)`. The time is the download
+/// window only, so a fast rate with a slow overall install points at extraction.
+fn install_summary(installed: usize, bytes: u64, download_time: std::time::Duration) -> String {
+    let packages = if installed == 1 {
+        "package"
+    } else {
+        "packages"
+    };
+    let secs = download_time.as_secs_f64();
+    if bytes == 0 || secs <= 0.0 {
+        return format!("{} {} installed", installed, packages);
+    }
+    format!(
+        "{} {} installed, {} in {:.1}s ({}/s)",
+        installed,
+        packages,
+        format_bytes(bytes),
+        secs,
+        format_bytes((bytes as f64 / secs) as u64)
+    )
+}
+
 fn infer_artifact_type_from_cask_artifacts(
     details: &crate::api::CaskDetails,
 ) -> Option<&'static str> {
@@ -1424,6 +2246,33 @@ fn infer_artifact_type_from_cask_artifacts(
     None
 }
 
+/// The Homebrew keg of `package_name` when it is recorded from the global Cellar but the
+/// install targets the user prefix, i.e. installing would duplicate a brew-managed keg.
+fn homebrew_managed_keg<F>(
+    package_name: &str,
+    install_mode: InstallMode,
+    installed_packages: &HashMap<String, InstalledPackage>,
+    mut cellar_for_mode: F,
+) -> Result<Option<PathBuf>>
+where
+    F: FnMut(InstallMode) -> Result<PathBuf>,
+{
+    if install_mode != InstallMode::User {
+        return Ok(None);
+    }
+    let name = package_name.rsplit('/').next().unwrap_or(package_name);
+    let Some(existing) = installed_packages.get(name) else {
+        return Ok(None);
+    };
+    if existing.install_mode != InstallMode::Global {
+        return Ok(None);
+    }
+    let keg = cellar_for_mode(InstallMode::Global)?
+        .join(&existing.name)
+        .join(&existing.version);
+    Ok(keg.is_dir().then_some(keg))
+}
+
 fn check_already_installed_formula_linkages(
     packages: &[String],
     installed_packages: &HashMap<String, InstalledPackage>,
@@ -1481,17 +2330,162 @@ fn cellar_version_from_bottle_layout(name_dir: &Path, stable: &str, bottle_rebui
             if entry_name == stable || entry_name.starts_with(&format!("{stable}_")) {
                 candidates.push(entry_name);
             }
-        }
-    }
-    if !candidates.is_empty() {
-        crate::version::sort_versions(&mut candidates);
-        return candidates.pop().expect("non-empty");
-    }
-    if bottle_rebuild > 0 {
-        format!("{stable}_{bottle_rebuild}")
-    } else {
-        stable.to_string()
+        }
+    }
+    if !candidates.is_empty() {
+        crate::version::sort_versions(&mut candidates);
+        return candidates.pop().expect("non-empty");
+    }
+    if bottle_rebuild > 0 {
+        format!("{stable}_{bottle_rebuild}")
+    } else {
+        stable.to_string()
+    }
+}
+
+/// Bottles hashed, unpacked or copied into the Cellar at once. Separate from the
+/// download semaphore so a burst of large extractions cannot starve downloads.
+fn unpack_permits() -> &'static Semaphore {
+    static PERMITS: OnceLock<Semaphore> = OnceLock::new();
+    PERMITS.get_or_init(|| {
+        Semaphore::new(
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4),
+        )
+    })
+}
+
+/// Run synchronous extract/copy work on the blocking pool, bounded by
+/// [`unpack_permits`], so it never stalls the async executor.
+async fn run_unpack<T, F>(work: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let _permit = unpack_permits()
+        .acquire()
+        .await
+        .map_err(|e| WaxError::InstallError(format!("unpack semaphore closed: {e}")))?;
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| WaxError::InstallError(format!("Extraction task failed: {}", e)))?
+}
+
+/// Check a downloaded bottle's checksum and unpack it under `temp_dir/<name>`.
+async fn verify_and_extract(
+    name: &str,
+    url: &str,
+    tarball_path: &Path,
+    sha256: &str,
+    temp_dir: &TempDir,
+) -> Result<PathBuf> {
+    let name = name.to_string();
+    let url = url.to_string();
+    let tarball_path = tarball_path.to_path_buf();
+    let sha256 = sha256.to_string();
+    let extract_dir = temp_dir.path().join(&name);
+    run_unpack(move || {
+        let started = std::time::Instant::now();
+        crate::digest::verify_digest_file(&tarball_path, &sha256)
+            .map_err(|e| e.for_download(&name, &url))?;
+        crate::ui::record_phase(&name, "verify", started.elapsed());
+
+        let started = std::time::Instant::now();
+        BottleDownloader::extract(&tarball_path, &extract_dir)?;
+        crate::ui::record_phase(&name, "extract", started.elapsed());
+        Ok(extract_dir)
+    })
+    .await
+}
+
+/// [`copy_dir_all`] off the async executor.
+async fn copy_dir_all_blocking(src: &Path, dst: &Path) -> Result<()> {
+    let (src, dst) = (src.to_path_buf(), dst.to_path_buf());
+    run_unpack(move || copy_dir_all(&src, &dst)).await
+}
+
+/// Look up each resolved name in the index, in order. A name missing from the index is
+/// reported rather than aborting the install, and so is every formula that needs it;
+/// `names` lists dependencies first, so a dependent is checked after what it needs.
+fn indexed_formulae<'a>(
+    names: &[String],
+    by_name: &HashMap<&str, &'a crate::api::Formula>,
+) -> (Vec<&'a crate::api::Formula>, Vec<String>) {
+    let mut unresolved = Vec::new();
+    let mut unavailable: HashSet<&str> = HashSet::new();
+    let formulae = names
+        .iter()
+        .filter_map(|name| {
+            let Some(formula) = by_name.get(name.as_str()).copied() else {
+                unresolved.push(format!("{}: not found in the formula index", name));
+                unavailable.insert(name.as_str());
+                return None;
+            };
+            let missing = formula
+                .dependencies
+                .iter()
+                .chain(&formula.build_dependencies)
+                .flatten()
+                .map(|dep| by_name.get(dep.as_str()).map_or(dep.as_str(), |f| &f.name))
+                .find(|dep| unavailable.contains(dep));
+            if let Some(dep) = missing {
+                unresolved.push(format!(
+                    "{}: not installed because its dependency {} is unavailable",
+                    name, dep
+                ));
+                unavailable.insert(name.as_str());
+                return None;
+            }
+            Some(formula)
+        })
+        .collect();
+    (formulae, unresolved)
+}
+
+fn spinner_style() -> ProgressStyle {
+    ProgressStyle::default_spinner()
+        .template("{spinner:.cyan} {msg}")
+        .unwrap()
+        .tick_chars(crate::ui::SPINNER_TICK_CHARS)
+}
+
+/// Bar that counts files as they land in the Cellar. Reuses the caller's bar when
+/// there is one (switching it to a counting style until the copy finishes);
+/// otherwise adds a transient spinner.
+fn copy_progress(
+    name: &str,
+    quiet: bool,
+    multi: Option<&MultiProgress>,
+    existing: Option<&ProgressBar>,
+) -> Option<ProgressBar> {
+    if quiet {
+        return None;
     }
+    let counting = ProgressStyle::default_spinner()
+        .template("{spinner:.cyan} {msg} {pos:.dim} files")
+        .unwrap()
+        .tick_chars(crate::ui::SPINNER_TICK_CHARS);
+    let pb = match existing {
+        Some(pb) => pb.clone(),
+        None => {
+            let pb = ProgressBar::new_spinner();
+            let pb = match multi {
+                Some(m) => m.add(pb),
+                None => pb,
+            };
+            pb.set_message(format!(
+                "{} {}",
+                style(name).magenta(),
+                style("copying to cellar...").dim()
+            ));
+            pb.enable_steady_tick(std::time::Duration::from_millis(80));
+            pb
+        }
+    };
+    pb.set_style(counting);
+    pb.set_position(0);
+    Some(pb)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -1507,40 +2501,145 @@ pub async fn install_extracted_bottle(
     state: &InstallState,
     quiet: bool,
     run_scripts: bool,
+    relocate: bool,
+    overwrite: bool,
     multi: Option<&MultiProgress>,
     existing_pb: Option<ProgressBar>,
 ) -> Result<()> {
-    crate::signal::set_current_op(format!("installing {}", name));
     let _critical = CriticalSection::new();
+    let cellar_version = pour_bottle(
+        name,
+        version,
+        extract_dir,
+        bottle_rebuild,
+        cellar,
+        install_mode,
+        quiet,
+        relocate,
+        multi,
+        existing_pb.as_ref(),
+    )
+    .await?;
+    link_poured_bottle(
+        name,
+        &cellar_version,
+        bottle_sha,
+        bottle_rebuild,
+        cellar,
+        install_mode,
+        platform,
+        state,
+        quiet,
+        run_scripts,
+        overwrite,
+        multi,
+        existing_pb.as_ref(),
+    )
+    .await
+}
 
-    // Step messages are printed immediately (not via spinner) so they always
-    // show even when the operation completes in <80ms. When a MultiProgress
-    // is active we use multi.println() to avoid clobbering its render area;
-    // when an existing_pb is provided (reinstall path) we update its message
-    // so the single bar transitions from "downloading" to each install step.
-    macro_rules! step {
-        ($msg:expr) => {
-            if !quiet {
-                if let Some(ref pb) = existing_pb {
-                    pb.set_message(format!("{} {}", style(name).magenta(), style($msg).dim()));
-                    pb.tick();
-                } else {
-                    let line = format!("  {} {}", style(name).magenta(), style($msg).dim());
-                    if let Some(ref m) = multi {
-                        let _ = m.println(&line);
-                    } else {
-                        println!("{}", line);
-                    }
-                }
-            }
-        };
+/// A bottle copied into the Cellar by [`pour_bottle`], waiting for its turn to be linked.
+struct PouredBottle {
+    cellar_version: String,
+    sha256: String,
+    rebuild: u32,
+    spinner: ProgressBar,
+}
+
+/// Link a poured bottle during a parallel install and report it.
+#[allow(clippy::too_many_arguments)]
+async fn link_poured(
+    name: &str,
+    bottle: PouredBottle,
+    cellar: &Path,
+    install_mode: InstallMode,
+    platform: &str,
+    state: &InstallState,
+    quiet: bool,
+    run_scripts: bool,
+    overwrite: bool,
+) -> Result<()> {
+    let linked = link_poured_bottle(
+        name,
+        &bottle.cellar_version,
+        bottle.sha256,
+        bottle.rebuild,
+        cellar,
+        install_mode,
+        platform,
+        state,
+        quiet,
+        run_scripts,
+        overwrite,
+        None,
+        Some(&bottle.spinner),
+    )
+    .await;
+    bottle.spinner.finish_and_clear();
+    if linked.is_ok() && !quiet {
+        println!(
+            "+ {}@{}",
+            style(name).magenta(),
+            style(&bottle.cellar_version).dim()
+        );
+    }
+    linked
+}
+
+/// Step messages are printed immediately (not via spinner) so they always show even
+/// when the operation completes in <80ms. When a MultiProgress is active we use
+/// multi.println() to avoid clobbering its render area; when an existing_pb is provided
+/// (reinstall path) we update its message so the single bar transitions from
+/// "downloading" to each install step.
+fn install_step(
+    name: &str,
+    msg: &str,
+    quiet: bool,
+    multi: Option<&MultiProgress>,
+    existing_pb: Option<&ProgressBar>,
+) {
+    if quiet {
+        return;
+    }
+    if let Some(pb) = existing_pb {
+        pb.set_message(format!("{} {}", style(name).magenta(), style(msg).dim()));
+        pb.tick();
+    } else {
+        let line = format!("  {} {}", style(name).magenta(), style(msg).dim());
+        if let Some(m) = multi {
+            let _ = m.println(&line);
+        } else {
+            println!("{}", line);
+        }
     }
-    step!("resolving...");
+}
+
+/// Copy an extracted bottle into the Cellar and relocate it, returning the keg's version
+/// directory. Nothing outside the keg is touched, so independent packages can pour in
+/// parallel; [`link_poured_bottle`] finishes the install.
+#[allow(clippy::too_many_arguments)]
+async fn pour_bottle(
+    name: &str,
+    version: &str,
+    extract_dir: &std::path::Path,
+    bottle_rebuild: u32,
+    cellar: &std::path::Path,
+    install_mode: InstallMode,
+    quiet: bool,
+    relocate: bool,
+    multi: Option<&MultiProgress>,
+    existing_pb: Option<&ProgressBar>,
+) -> Result<String> {
+    crate::signal::set_current_op(format!("installing {}", name));
+    let _critical = CriticalSection::new();
+    let step = |msg: &str| install_step(name, msg, quiet, multi, existing_pb);
+    step("resolving...");
 
     // Detect the actual version directory from what's in the extracted bottle.
     // Homebrew bottles embed {version}_{rebuild} paths, but the API's rebuild
     // field can lag behind. Scanning the extracted dir gives us the ground truth.
-    let name_dir = extract_dir.join(name);
+    let bottle_name = crate::bottle::bottle_dir_name(extract_dir, name);
+    let name_dir = extract_dir.join(&bottle_name);
     let cellar_version: String = if name_dir.exists() {
         cellar_version_from_bottle_layout(&name_dir, version, bottle_rebuild)
     } else if bottle_rebuild > 0 {
@@ -1551,7 +2650,7 @@ pub async fn install_extracted_bottle(
 
     let formula_cellar = cellar.join(name).join(&cellar_version);
     if formula_cellar.exists() {
-        step!("cleaning old version...");
+        step("cleaning old version...");
         tokio::fs::remove_dir_all(&formula_cellar)
             .await
             .or_else(|_| crate::sudo::sudo_remove(&formula_cellar).map(|_| ()))
@@ -1574,30 +2673,83 @@ pub async fn install_extracted_bottle(
             ))
         })?;
 
-    step!("copying to cellar...");
-    crate::bottle::copy_extracted_bottle_to_cellar(
-        extract_dir,
-        name,
-        &cellar_version,
-        &formula_cellar,
-    )?;
-
-    step!("relocating...");
+    step("copying to cellar...");
+    let started = std::time::Instant::now();
     {
+        let progress = copy_progress(name, quiet, multi, existing_pb);
+        let (extract_dir, bottle_name, cellar_version, formula_cellar) = (
+            extract_dir.to_path_buf(),
+            bottle_name.clone(),
+            cellar_version.clone(),
+            formula_cellar.clone(),
+        );
+        let counter = progress.clone();
+        let copied = run_unpack(move || {
+            crate::bottle::copy_extracted_bottle_to_cellar(
+                &extract_dir,
+                &bottle_name,
+                &cellar_version,
+                &formula_cellar,
+                counter.as_ref(),
+            )
+        })
+        .await;
+        if let Some(pb) = progress {
+            if existing_pb.is_some() {
+                pb.set_style(spinner_style());
+            } else {
+                pb.finish_and_clear();
+            }
+        }
+        copied?;
+    }
+    crate::ui::record_phase(name, "copy", started.elapsed());
+
+    if relocate {
+        step("relocating...");
+        let started = std::time::Instant::now();
         let prefix = install_mode.prefix()?;
         let default_prefix = if cfg!(target_os = "macos") {
             "/opt/homebrew"
         } else {
             "/home/linuxbrew/.linuxbrew"
         };
-        BottleDownloader::relocate_bottle(
-            &formula_cellar,
-            prefix.to_str().unwrap_or(default_prefix),
-        )?;
+        let prefix = prefix.to_str().unwrap_or(default_prefix).to_string();
+        let keg = formula_cellar.clone();
+        run_unpack(move || BottleDownloader::relocate_bottle(&keg, &prefix)).await?;
+        crate::ui::record_phase(name, "relocate", started.elapsed());
+    } else {
+        debug!("Skipping relocation of {} (--no-relocate)", name);
     }
 
-    step!("symlinking...");
-    create_symlinks(name, &cellar_version, cellar, false, install_mode).await?;
+    Ok(cellar_version)
+}
+
+/// Link a keg placed by [`pour_bottle`], run its post-install step and record it in the
+/// install state.
+#[allow(clippy::too_many_arguments)]
+async fn link_poured_bottle(
+    name: &str,
+    cellar_version: &str,
+    bottle_sha: String,
+    bottle_rebuild: u32,
+    cellar: &std::path::Path,
+    install_mode: InstallMode,
+    platform: &str,
+    state: &InstallState,
+    quiet: bool,
+    run_scripts: bool,
+    overwrite: bool,
+    multi: Option<&MultiProgress>,
+    existing_pb: Option<&ProgressBar>,
+) -> Result<()> {
+    crate::signal::set_current_op(format!("linking {}", name));
+    let _critical = CriticalSection::new();
+
+    install_step(name, "symlinking...", quiet, multi, existing_pb);
+    let started = std::time::Instant::now();
+    create_symlinks(name, cellar_version, cellar, false, overwrite, install_mode).await?;
+    crate::ui::record_phase(name, "link", started.elapsed());
 
     if run_scripts && state.load().await?.contains_key(name) {
         // Auto-run postinstall if possible
@@ -1615,7 +2767,7 @@ pub async fn install_extracted_bottle(
 
     let package = InstalledPackage {
         name: name.to_string(),
-        version: cellar_version.clone(),
+        version: cellar_version.to_string(),
         platform: platform.to_string(),
         install_date: std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -1626,6 +2778,9 @@ pub async fn install_extracted_bottle(
         bottle_rebuild,
         bottle_sha256: Some(bottle_sha),
         pinned: false,
+        options: Vec::new(),
+        installed_as_dependency: false,
+        unverified_source: false,
     };
     state.add(package).await?;
 
@@ -1633,7 +2788,7 @@ pub async fn install_extracted_bottle(
         println!(
             "+ {}@{}",
             style(name).magenta(),
-            style(&cellar_version).dim()
+            style(cellar_version).dim()
         );
     }
 
@@ -1717,10 +2872,23 @@ async fn install_casks(
         if installed_casks.contains_key(cask_name) && !force_reinstall {
             already_installed.push(cask_name.clone());
         } else if cfg!(target_os = "macos") {
-            if casks
-                .iter()
-                .any(|c| &c.token == cask_name || &c.full_token == cask_name)
-            {
+            let in_index = |token: &str| {
+                casks
+                    .iter()
+                    .any(|c| c.token == token || c.full_token == token)
+            };
+            if in_index(cask_name) {
+                to_install.push(cask_name.clone());
+            } else if versioned_token_base(cask_name).is_some_and(in_index) {
+                // Versioned tokens missing from the bulk index are still served by the
+                // per-cask endpoint; fetching details below fails clearly if not.
+                if !quiet {
+                    eprintln!(
+                        "{} {} is not in the cask index; fetching it directly",
+                        style("note:").yellow(),
+                        style(cask_name).magenta()
+                    );
+                }
                 to_install.push(cask_name.clone());
             } else {
                 eprintln!("{}: cask not found", style(cask_name).magenta());
@@ -1756,7 +2924,7 @@ async fn install_casks(
             let _ = multi.println(format!("{}", style("dry run - no changes made").dim()));
             return Ok(());
         }
-        let proceed = confirm_prompt("Proceed with install?")?;
+        let proceed = confirm_prompt(false, "Proceed with install?", false)?;
         if !proceed {
             let _ = multi.println(format!("{} install cancelled", style("✗").red()));
             return Ok(());
@@ -1947,7 +3115,9 @@ async fn install_casks(
 
             let installed_cask = {
                 let _line_done = FinishProgressLine(&pb);
-                if let Err(e) = crate::digest::verify_sha256_file(&download_path, &details.sha256) {
+                if let Err(e) = crate::digest::verify_file(&download_path, &details.checksum())
+                    .map_err(|e| e.for_download(&name, &details.url))
+                {
                     note_aggregate_download_row_done(&net_done, cask_count, &hide_dl);
                     return Err(CaskPipelineFail::Checksum { name, err: e });
                 }
@@ -1984,6 +3154,10 @@ async fn install_casks(
         }
     }
 
+    let linked_binaries = successful_casks
+        .iter()
+        .any(|(_, cask, _)| cask.binary_paths.as_ref().is_some_and(|p| !p.is_empty()));
+
     // Serialize cask state updates to avoid file corruption from concurrent writes.
     if !successful_casks.is_empty() {
         let cask_state = CaskState::new().map_err(|e| WaxError::InstallError(e.to_string()))?;
@@ -2005,6 +3179,10 @@ async fn install_casks(
         let _ = poller.await;
     }
 
+    if linked_binaries {
+        hint_prefix_bin_path_if_needed(InstallMode::detect(), quiet);
+    }
+
     check_cancelled()?;
 
     let mut installed_count = 0;
@@ -2195,7 +3373,7 @@ async fn install_from_downloaded(
     rollback.add(version_dir.clone());
 
     let mut binary_paths: Vec<String> = Vec::new();
-    let mut installed_app_name: Option<String> = None;
+    let mut installed_apps: Vec<String> = Vec::new();
 
     if let Some(artifacts) = &cask.artifacts {
         for artifact in artifacts {
@@ -2206,7 +3384,7 @@ async fn install_from_downloaded(
                         installer
                             .install_app(&staging, &mut rollback, source)
                             .await?;
-                        installed_app_name = Some(source.to_string());
+                        installed_apps.push(source.to_string());
                     }
                 }
                 CaskArtifact::Pkg { pkg } => {
@@ -2444,7 +3622,7 @@ async fn install_from_downloaded(
         }
     } else {
         // Fallback if no artifacts are explicitly defined (try to guess .app)
-        if artifact_type == "dmg" || artifact_type == "zip" {
+        if matches!(artifact_type, "dmg" | "zip" | "7z") {
             let mut entries = tokio::fs::read_dir(&staging.staging_root).await?;
             while let Some(entry) = entries.next_entry().await? {
                 let path = entry.path();
@@ -2456,7 +3634,7 @@ async fn install_from_downloaded(
                     installer
                         .install_app(&staging, &mut rollback, app_name)
                         .await?;
-                    installed_app_name = Some(app_name.to_string());
+                    installed_apps.push(app_name.to_string());
                     break;
                 }
             }
@@ -2466,6 +3644,10 @@ async fn install_from_downloaded(
     step!("registering...");
     rollback.commit();
 
+    let mut installed_apps = installed_apps.into_iter();
+    let app_name = installed_apps.next();
+    let additional_apps: Vec<String> = installed_apps.collect();
+
     Ok(InstalledCask {
         name: cask.token.clone(),
         version: cask.version.clone(),
@@ -2479,19 +3661,215 @@ async fn install_from_downloaded(
         } else {
             Some(binary_paths)
         },
-        app_name: installed_app_name,
+        app_name,
+        additional_apps: if additional_apps.is_empty() {
+            None
+        } else {
+            Some(additional_apps)
+        },
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        check_already_installed_formula_linkages_with_cellar, stage_binary_release_download,
-        tap_name_from_qualified_package,
+        bottle_is_self_contained, build_options,
+        check_already_installed_formula_linkages_with_cellar, check_source_checksum,
+        disabled_refusal, homebrew_managed_keg, indexed_formulae, install_summary,
+        lifecycle_status, missing_bottle_reason, path_contains_dir, planned_build,
+        stage_binary_release_download, tap_name_from_qualified_package, PlannedBuild,
     };
     use crate::install::{InstallMode, InstalledPackage};
+    use crate::test_support::{formula, formula_with, installed_package};
     use std::collections::HashMap;
 
+    #[test]
+    fn formulae_needing_an_unindexed_dependency_are_reported_not_installed() {
+        let formulae = [
+            formula("libz", &[]),
+            formula("app", &["libgone"]),
+            formula("tool", &["app-alias"]),
+            formula("other", &["libz"]),
+        ];
+        let mut by_name: HashMap<&str, &crate::api::Formula> =
+            formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+        by_name.insert("app-alias", &formulae[1]);
+        let names: Vec<String> = ["libz", "libgone", "app", "tool", "other"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+
+        let (found, unresolved) = indexed_formulae(&names, &by_name);
+        let found: Vec<&str> = found.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(found, vec!["libz", "other"]);
+        assert_eq!(
+            unresolved,
+            vec![
+                "libgone: not found in the formula index",
+                "app: not installed because its dependency libgone is unavailable",
+                "tool: not installed because its dependency app is unavailable",
+            ]
+        );
+    }
+
+    #[test]
+    fn retired_formulae_carry_their_upstream_reason() {
+        let formula = |extra: serde_json::Value| formula_with("old", extra);
+
+        assert_eq!(lifecycle_status(&formula(serde_json::json!({}))), None);
+        assert_eq!(
+            lifecycle_status(&formula(serde_json::json!({
+                "deprecated": true,
+                "deprecation_reason": "unmaintained",
+            }))),
+            Some("deprecated (unmaintained)".to_string())
+        );
+        let disabled = formula(serde_json::json!({
+            "deprecated": true,
+            "disabled": true,
+            "disable_reason": "does_not_build",
+        }));
+        assert_eq!(
+            disabled_refusal(&disabled),
+            "disabled (does not build) upstream; pass --force to install it anyway"
+        );
+    }
+
+    #[test]
+    fn single_bottle_needs_no_index_only_when_dependencies_are_installed() {
+        let formula = formula_with(
+            "jq",
+            serde_json::json!({
+                "versions": { "stable": "1.7.1", "bottle": true },
+                "dependencies": ["oniguruma"],
+                "bottle": { "stable": { "files": {
+                    "arm64_sonoma": { "url": "https://example.com/jq.tar.gz", "sha256": "abc" }
+                } } },
+            }),
+        );
+        let mut installed = std::collections::HashSet::new();
+
+        assert!(!bottle_is_self_contained(
+            &formula,
+            "arm64_sonoma",
+            &installed,
+            false
+        ));
+        assert!(bottle_is_self_contained(
+            &formula,
+            "arm64_sonoma",
+            &installed,
+            true
+        ));
+        installed.insert("oniguruma".to_string());
+        assert!(bottle_is_self_contained(
+            &formula,
+            "arm64_sonoma",
+            &installed,
+            false
+        ));
+        assert!(!bottle_is_self_contained(
+            &formula,
+            "x86_64_linux",
+            &installed,
+            false
+        ));
+    }
+
+    #[test]
+    fn planned_build_matches_install_decision() {
+        let formula = formula_with(
+            "jq",
+            serde_json::json!({
+                "versions": { "stable": "1.7.1", "bottle": true },
+                "bottle": { "stable": { "files": {
+                    "arm64_sonoma": { "url": "https://example.com/jq.tar.gz", "sha256": "abc" }
+                } } },
+            }),
+        );
+        let bottle = PlannedBuild::Bottle {
+            url: "https://example.com/jq.tar.gz".to_string(),
+            sha256: "abc".to_string(),
+        };
+        let plan = |platform, head, from_source, options, foreign| {
+            planned_build(&formula, platform, head, from_source, options, foreign)
+        };
+
+        assert_eq!(
+            plan("arm64_sonoma", false, false, false, false).unwrap(),
+            bottle
+        );
+        assert_eq!(
+            plan("x86_64_linux", false, false, false, false).unwrap(),
+            PlannedBuild::Source
+        );
+        assert_eq!(
+            plan("arm64_sonoma", false, true, false, false).unwrap(),
+            PlannedBuild::Source
+        );
+        assert_eq!(
+            plan("arm64_sonoma", false, false, true, false).unwrap(),
+            PlannedBuild::Source
+        );
+        assert_eq!(
+            plan("arm64_sonoma", true, false, false, false).unwrap(),
+            PlannedBuild::Head
+        );
+        // `--platform` pours the foreign bottle, but never builds one from source.
+        assert_eq!(
+            plan("arm64_sonoma", false, false, false, true).unwrap(),
+            bottle
+        );
+        assert!(matches!(
+            plan("x86_64_linux", false, false, false, true),
+            Err(crate::error::WaxError::BottleNotAvailable(_))
+        ));
+    }
+
+    #[test]
+    fn missing_bottle_reason_names_the_platform_only_when_others_exist() {
+        let formula = |bottle: serde_json::Value| {
+            formula_with(
+                "jq",
+                serde_json::json!({
+                    "versions": { "stable": "1.7.1", "bottle": true },
+                    "bottle": bottle,
+                }),
+            )
+        };
+        let sonoma_only = formula(serde_json::json!({ "stable": { "files": {
+            "arm64_sonoma": { "url": "https://example.com/jq.tar.gz", "sha256": "abc" }
+        } } }));
+
+        assert_eq!(missing_bottle_reason(&sonoma_only, "arm64_sonoma"), None);
+        assert_eq!(
+            missing_bottle_reason(&sonoma_only, "arm64_sequoia").as_deref(),
+            Some("no bottle for arm64_sequoia")
+        );
+        assert_eq!(
+            missing_bottle_reason(&formula(serde_json::json!({})), "arm64_sequoia").as_deref(),
+            Some("no bottle")
+        );
+        assert_eq!(
+            missing_bottle_reason(&formula(serde_json::Value::Null), "arm64_sequoia").as_deref(),
+            Some("no bottle")
+        );
+    }
+
+    #[test]
+    fn source_checksum_requires_no_verify_when_formula_declares_none() {
+        let err = check_source_checksum("foo", "", &"".into(), "abc123", false).unwrap_err();
+        assert!(err.to_string().contains("--no-verify"));
+        assert!(check_source_checksum("foo", "", &"  ".into(), "abc123", true).is_ok());
+        assert!(check_source_checksum("foo", "", &":no_check".into(), "abc123", false).is_ok());
+
+        assert!(check_source_checksum("foo", "", &"ABC123".into(), "abc123", false).is_ok());
+        assert!(matches!(
+            check_source_checksum("foo", "", &"def456".into(), "abc123", true),
+            Err(crate::error::WaxError::ChecksumMismatch { .. })
+        ));
+    }
+
     #[test]
     fn tap_name_from_qualified_package_uses_first_two_segments() {
         assert_eq!(
@@ -2506,6 +3884,77 @@ mod tests {
         assert_eq!(tap_name_from_qualified_package("user/tap"), None);
     }
 
+    #[test]
+    fn homebrew_managed_keg_only_applies_to_user_installs_of_global_kegs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let brew_cellar = tmp.path().join("Cellar");
+        std::fs::create_dir_all(brew_cellar.join("jq/1.7.1")).unwrap();
+        let mut installed = HashMap::new();
+        installed.insert(
+            "jq".to_string(),
+            InstalledPackage {
+                platform: "test".to_string(),
+                install_mode: InstallMode::Global,
+                ..installed_package("jq", "1.7.1")
+            },
+        );
+        let cellar = |_| Ok(brew_cellar.clone());
+
+        assert_eq!(
+            homebrew_managed_keg("jq", InstallMode::User, &installed, cellar).unwrap(),
+            Some(brew_cellar.join("jq/1.7.1"))
+        );
+        assert_eq!(
+            homebrew_managed_keg("homebrew/core/jq", InstallMode::User, &installed, cellar)
+                .unwrap(),
+            Some(brew_cellar.join("jq/1.7.1"))
+        );
+        assert_eq!(
+            homebrew_managed_keg("jq", InstallMode::Global, &installed, cellar).unwrap(),
+            None
+        );
+        assert_eq!(
+            homebrew_managed_keg("yq", InstallMode::User, &installed, cellar).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn build_options_adds_with_and_without_prefixes() {
+        let with = vec!["x11".to_string(), "--with-gtk".to_string()];
+        let without = vec!["docs".to_string()];
+        assert_eq!(
+            build_options(&with, &without),
+            vec!["with-x11", "with-gtk", "without-docs"]
+        );
+    }
+
+    #[test]
+    fn install_summary_reports_size_and_throughput() {
+        assert_eq!(
+            install_summary(3, 40 * 1_048_576, std::time::Duration::from_secs(4)),
+            "3 packages installed, 40.0 MB in 4.0s (10.0 MB/s)"
+        );
+        assert_eq!(
+            install_summary(1, 0, std::time::Duration::ZERO),
+            "1 package installed"
+        );
+    }
+
+    #[test]
+    fn path_contains_dir_ignores_trailing_separator_and_empty_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bin = tmp.path().join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+
+        let with_slash = format!("/usr/bin::{}/", bin.display());
+        assert!(path_contains_dir(std::ffi::OsStr::new(&with_slash), &bin));
+        assert!(!path_contains_dir(
+            std::ffi::OsStr::new("/usr/bin:/bin"),
+            &bin
+        ));
+    }
+
     #[test]
     fn already_installed_linkage_check_uses_recorded_install_location() {
         let tmp = tempfile::tempdir().unwrap();
@@ -2517,17 +3966,7 @@ mod tests {
         let mut installed = HashMap::new();
         installed.insert(
             "ripgrep".to_string(),
-            InstalledPackage {
-                name: "ripgrep".to_string(),
-                version: "14.1.1".to_string(),
-                platform: "x86_64_linux".to_string(),
-                install_date: 0,
-                install_mode: InstallMode::User,
-                from_source: false,
-                bottle_rebuild: 0,
-                bottle_sha256: None,
-                pinned: false,
-            },
+            installed_package("ripgrep", "14.1.1"),
         );
 
         let checked = check_already_installed_formula_linkages_with_cellar(
@@ -4,7 +4,10 @@ use crate::error::{Result, WaxError};
 use crate::install::{create_symlinks, remove_symlinks, InstallState};
 use console::style;
 
-pub async fn link(packages: &[String]) -> Result<()> {
+/// `wax link`; `overwrite` replaces files and other kegs' links in the way. With
+/// `relink` (also `wax relink`) a formula's links are dropped first and rebuilt from
+/// the keg recorded in the install state, repairing missing or dangling links.
+pub async fn link(packages: &[String], overwrite: bool, relink: bool) -> Result<()> {
     if packages.is_empty() {
         return Err(WaxError::InvalidInput(
             "Specify package name(s) to link".to_string(),
@@ -19,14 +22,36 @@ pub async fn link(packages: &[String]) -> Result<()> {
 
     for name in packages {
         validate_package_name(name)?;
+        let verb = if relink { "relinked" } else { "linked" };
         if let Some(pkg) = installed.get(name.as_str()) {
             let cellar = pkg.install_mode.cellar_path()?;
-            let links =
-                create_symlinks(&pkg.name, &pkg.version, &cellar, false, pkg.install_mode).await?;
+            if relink {
+                let keg = cellar.join(&pkg.name).join(&pkg.version);
+                if !keg.is_dir() {
+                    return Err(WaxError::InstallError(format!(
+                        "{} {} is recorded as installed but its keg {} is missing; run `wax reinstall {}`",
+                        pkg.name,
+                        pkg.version,
+                        keg.display(),
+                        pkg.name
+                    )));
+                }
+                remove_symlinks(&pkg.name, &pkg.version, &cellar, false, pkg.install_mode).await?;
+            }
+            let links = create_symlinks(
+                &pkg.name,
+                &pkg.version,
+                &cellar,
+                false,
+                overwrite,
+                pkg.install_mode,
+            )
+            .await?;
             println!(
-                "{} {} ({} links)",
-                style("linked").green(),
+                "{} {}@{} ({} links)",
+                style(verb).green(),
                 style(name).magenta(),
+                style(&pkg.version).dim(),
                 links.len()
             );
             continue;
@@ -36,13 +61,16 @@ pub async fn link(packages: &[String]) -> Result<()> {
             let links = relink_installed_cask(cask).await?;
             println!(
                 "{} {} ({} links)",
-                style("linked").green(),
+                style(verb).green(),
                 style(name).magenta(),
                 links.len()
             );
             continue;
         }
 
+        if relink {
+            return Err(WaxError::NotInstalled(name.to_string()));
+        }
         eprintln!(
             "{}: {} is not installed",
             style("warning").yellow(),
@@ -1,14 +1,15 @@
-use crate::bottle::homebrew_prefix;
 use crate::cache::Cache;
 use crate::cask::CaskState;
-use crate::commands::upgrade::{get_outdated_packages, upgrade as run_upgrade};
+use crate::commands::upgrade::{get_outdated_packages, upgrade as run_upgrade, UpgradeArgs};
 use crate::error::{Result, WaxError};
-use crate::install::{InstallMode, InstallState};
+use crate::install::{InstallMode, InstallState, InstalledPackage};
+use crate::ui::{confirm_prompt, dir_size, format_bytes};
+use crate::version::sort_versions;
 use console::style;
-use inquire::{Confirm, Select};
-use std::collections::HashMap;
+use inquire::Select;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, IsTerminal};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::instrument;
 
 #[cfg(target_os = "windows")]
@@ -51,10 +52,11 @@ fn validate_cellar_path(path: &std::path::Path) -> Result<PathBuf> {
 async fn collect_installed_rows(
     _cache: &Cache,
     scope: Option<InstallMode>,
+    sizes: bool,
 ) -> Result<Vec<InstalledRow>> {
     #[cfg(target_os = "windows")]
     {
-        let _ = scope;
+        let _ = (scope, sizes);
         let mut rows = Vec::new();
         for manifest in windows_state::list_manifests()? {
             let qualified = format!("{}/{}", manifest.ecosystem.label(), manifest.id);
@@ -75,43 +77,18 @@ async fn collect_installed_rows(
         return Ok(rows);
     }
 
-    let test_cellar = std::env::var_os(WAX_TEST_CELLAR_ENV);
-
-    let (cellar_path, skip_casks) = if let Some(ref raw) = test_cellar {
-        let pb = PathBuf::from(raw);
-        validate_cellar_path(&pb)?;
-        (pb, true)
-    } else if scope == Some(InstallMode::User) {
-        (
-            crate::ui::dirs::home_dir()?
-                .join(".local")
-                .join("wax")
-                .join("Cellar"),
-            true,
-        )
-    } else if scope == Some(InstallMode::Global) {
-        (homebrew_prefix().join("Cellar"), false)
-    } else {
-        let candidates = [
-            homebrew_prefix().join("Cellar"),
-            crate::ui::dirs::home_dir()
-                .unwrap_or_else(|_| homebrew_prefix())
-                .join(".local/wax/Cellar"),
-        ];
-        let cellar_path = candidates
-            .iter()
-            .find(|p| p.exists())
-            .cloned()
-            .unwrap_or_else(|| homebrew_prefix().join("Cellar"));
-        (cellar_path, false)
+    let test_cellar = match std::env::var_os(WAX_TEST_CELLAR_ENV) {
+        Some(raw) => Some(validate_cellar_path(&PathBuf::from(raw))?),
+        None => None,
     };
 
     let cask_state = CaskState::new()?;
-    let installed_casks: HashMap<_, _> = if skip_casks || scope == Some(InstallMode::User) {
-        HashMap::new()
-    } else {
-        cask_state.load().await?
-    };
+    let installed_casks: HashMap<_, _> =
+        if test_cellar.is_some() || scope == Some(InstallMode::User) {
+            HashMap::new()
+        } else {
+            cask_state.load().await?
+        };
 
     // External cask discovery is handled by sync/lock commands
     // which save discovered casks to CaskState for persistence.
@@ -120,85 +97,8 @@ async fn collect_installed_rows(
     let install_state = InstallState::new()?;
     let installed_packages = install_state.load().await?;
 
-    let mut rows = Vec::new();
-
-    if skip_casks || scope.is_some() {
-        if cellar_path.exists() {
-            let mut entries = tokio::fs::read_dir(&cellar_path).await?;
-
-            while let Some(entry) = entries.next_entry().await? {
-                if entry.file_type().await?.is_dir() {
-                    let package_name = entry.file_name().to_string_lossy().to_string();
-
-                    let mut versions = Vec::new();
-                    let mut version_entries = tokio::fs::read_dir(entry.path()).await?;
-                    while let Some(version_entry) = version_entries.next_entry().await? {
-                        if version_entry.file_type().await?.is_dir() {
-                            versions.push(version_entry.file_name().to_string_lossy().to_string());
-                        }
-                    }
-
-                    let from_source = installed_packages
-                        .get(&package_name)
-                        .filter(|p| scope.is_none() || Some(p.install_mode) == scope)
-                        .map(|p| p.from_source)
-                        .unwrap_or(false);
-
-                    let version_str = versions.join(", ");
-                    let line = if from_source {
-                        format!(
-                            "{} {} {}",
-                            style(&package_name).magenta(),
-                            style(&version_str).dim(),
-                            style("(source)").yellow()
-                        )
-                    } else {
-                        format!(
-                            "{} {}",
-                            style(&package_name).magenta(),
-                            style(&version_str).dim()
-                        )
-                    };
-
-                    rows.push(InstalledRow {
-                        name: package_name,
-                        line,
-                        is_cask: false,
-                        is_windows: false,
-                    });
-                }
-            }
-        }
-    } else {
-        let mut package_list: Vec<_> = installed_packages
-            .iter()
-            .filter(|(_, package)| scope.is_none() || Some(package.install_mode) == scope)
-            .collect();
-        package_list.sort_by_key(|(name, _)| *name);
-        for (package_name, package) in package_list {
-            let line = if package.from_source {
-                format!(
-                    "{} {} {}",
-                    style(package_name.as_str()).magenta(),
-                    style(&package.version).dim(),
-                    style("(source)").yellow()
-                )
-            } else {
-                format!(
-                    "{} {}",
-                    style(package_name.as_str()).magenta(),
-                    style(&package.version).dim()
-                )
-            };
-
-            rows.push(InstalledRow {
-                name: package_name.clone(),
-                line,
-                is_cask: false,
-                is_windows: false,
-            });
-        }
-    }
+    let mut rows =
+        collect_formula_rows(&installed_packages, scope, test_cellar.as_deref(), sizes).await?;
 
     let mut cask_list: Vec<_> = installed_casks.iter().collect();
     cask_list.sort_by_key(|(name, _)| *name);
@@ -240,6 +140,136 @@ async fn collect_installed_rows(
     Ok(rows)
 }
 
+/// Formula rows for the union of `InstallState` and the Cellar of every install mode
+/// in `scope`. Each row names its mode; a recorded formula whose keg is gone is
+/// flagged `(missing)` and a keg wax has no record of `(untracked)`.
+async fn collect_formula_rows(
+    installed_packages: &HashMap<String, InstalledPackage>,
+    scope: Option<InstallMode>,
+    test_cellar: Option<&Path>,
+    sizes: bool,
+) -> Result<Vec<InstalledRow>> {
+    let cellar_for = |mode: InstallMode| match test_cellar {
+        Some(cellar) => Ok(cellar.to_path_buf()),
+        None => mode.cellar_path(),
+    };
+    let modes = match scope {
+        Some(mode) => vec![mode],
+        None => vec![InstallMode::Global, InstallMode::User],
+    };
+    let mut cellars: Vec<(InstallMode, PathBuf)> = Vec::new();
+    for mode in modes {
+        let cellar = cellar_for(mode)?;
+        if !cellars.iter().any(|(_, c)| *c == cellar) {
+            cellars.push((mode, cellar));
+        }
+    }
+
+    let mut rows = Vec::new();
+    let mut on_disk: HashSet<&str> = HashSet::new();
+
+    for (cellar_mode, cellar) in &cellars {
+        if !cellar.exists() {
+            continue;
+        }
+        let mut entries = tokio::fs::read_dir(cellar).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_dir() {
+                continue;
+            }
+            let package_name = entry.file_name().to_string_lossy().to_string();
+
+            let mut versions = Vec::new();
+            let mut version_entries = tokio::fs::read_dir(entry.path()).await?;
+            while let Some(version_entry) = version_entries.next_entry().await? {
+                if version_entry.file_type().await?.is_dir() {
+                    versions.push(version_entry.file_name().to_string_lossy().to_string());
+                }
+            }
+            sort_versions(&mut versions);
+
+            let recorded = installed_packages
+                .get_key_value(&package_name)
+                .filter(|(_, p)| cellar_for(p.install_mode).is_ok_and(|c| c == *cellar));
+            if let Some((name, _)) = recorded {
+                on_disk.insert(name.as_str());
+            }
+            let recorded = recorded.map(|(_, p)| p);
+
+            let mut line = formula_line(&package_name, &versions.join(", "), recorded);
+            if sizes {
+                // The recorded keg, or the newest one for a formula wax does not track.
+                let keg_version = recorded
+                    .map(|p| &p.version)
+                    .filter(|v| versions.contains(v))
+                    .or(versions.last());
+                if let Some(version) = keg_version {
+                    push_size(&mut line, &entry.path().join(version));
+                }
+            }
+            let mode = recorded.map_or(*cellar_mode, |p| p.install_mode);
+            push_mode(&mut line, mode, recorded.is_some(), true);
+
+            rows.push(InstalledRow {
+                name: package_name,
+                line,
+                is_cask: false,
+                is_windows: false,
+            });
+        }
+    }
+
+    for (package_name, package) in installed_packages {
+        if on_disk.contains(package_name.as_str())
+            || scope.is_some_and(|mode| mode != package.install_mode)
+        {
+            continue;
+        }
+        let mut line = formula_line(package_name, &package.version, Some(package));
+        push_mode(&mut line, package.install_mode, true, false);
+        rows.push(InstalledRow {
+            name: package_name.clone(),
+            line,
+            is_cask: false,
+            is_windows: false,
+        });
+    }
+
+    Ok(rows)
+}
+
+/// Append the install mode, plus `(missing)` for a recorded formula with no keg or
+/// `(untracked)` for a keg missing from the install state.
+fn push_mode(line: &mut String, mode: InstallMode, recorded: bool, on_disk: bool) {
+    let label = match mode {
+        InstallMode::User => "user",
+        InstallMode::Global => "global",
+    };
+    line.push_str(&format!(" {}", style(format!("[{}]", label)).dim()));
+    if !on_disk {
+        line.push_str(&format!(" {}", style("(missing)").red()));
+    } else if !recorded {
+        line.push_str(&format!(" {}", style("(untracked)").yellow()));
+    }
+}
+
+/// `name versions`, then `(source)` for source builds and `(dep)` for dependencies.
+fn formula_line(name: &str, versions: &str, recorded: Option<&InstalledPackage>) -> String {
+    let mut line = format!("{} {}", style(name).magenta(), style(versions).dim());
+    if recorded.is_some_and(|p| p.from_source) {
+        line.push_str(&format!(" {}", style("(source)").yellow()));
+    }
+    if recorded.is_some_and(|p| p.installed_as_dependency) {
+        line.push_str(&format!(" {}", style("(dep)").dim()));
+    }
+    line
+}
+
+/// Append the on-disk size of `keg` to a list line.
+fn push_size(line: &mut String, keg: &Path) {
+    line.push_str(&format!(" {}", style(format_bytes(dir_size(keg))).dim()));
+}
+
 fn matches_query(row: &InstalledRow, query: &str) -> bool {
     let q = query.to_lowercase();
     if q.is_empty() {
@@ -326,7 +356,10 @@ async fn offer_upgrade_for_selection(cache: &Cache, choice: &InstalledRow) -> Re
     }
 
     let outdated = get_outdated_packages(cache).await?;
-    let Some(pkg) = outdated.iter().find(|p| p.name == choice.name) else {
+    let Some(pkg) = outdated
+        .iter()
+        .find(|p| p.name == choice.name && !p.unknown)
+    else {
         println!(
             "{} is already on the latest version.",
             style(&choice.name).magenta()
@@ -345,19 +378,13 @@ async fn offer_upgrade_for_selection(cache: &Cache, choice: &InstalledRow) -> Re
         choice.name, cask_note, pkg.installed_version, pkg.latest_version
     );
 
-    let should_upgrade = Confirm::new(prompt.as_str())
-        .with_default(true)
-        .prompt_skippable()
-        .map_err(map_inquire_err)?
-        .unwrap_or(false);
+    let should_upgrade = confirm_prompt(false, &prompt, true)?;
 
     if should_upgrade {
         run_upgrade(
             cache,
             std::slice::from_ref(&choice.name),
-            false,
-            false,
-            None,
+            UpgradeArgs::default(),
         )
         .await?;
         println!(
@@ -374,7 +401,7 @@ async fn run_interactive_list(cache: &Cache, initial_query: Option<String>) -> R
     let mut first_prompt = true;
 
     loop {
-        let rows = collect_installed_rows(cache, None).await?;
+        let rows = collect_installed_rows(cache, None, false).await?;
         if rows.is_empty() {
             println!("no packages installed");
             return Ok(());
@@ -407,11 +434,7 @@ async fn run_interactive_list(cache: &Cache, initial_query: Option<String>) -> R
 
         offer_upgrade_for_selection(cache, &choice).await?;
 
-        let again = Confirm::new("Select another package?")
-            .with_default(false)
-            .prompt_skippable()
-            .map_err(map_inquire_err)?
-            .unwrap_or(false);
+        let again = confirm_prompt(false, "Select another package?", false)?;
         if !again {
             break;
         }
@@ -420,9 +443,158 @@ async fn run_interactive_list(cache: &Cache, initial_query: Option<String>) -> R
     Ok(())
 }
 
+/// A formula with every version directory present in its Cellar.
+struct VersionedFormula {
+    name: String,
+    versions: Vec<String>,
+    linked: Option<String>,
+}
+
+/// The version the prefix currently points at: the `opt/<name>` link target if it
+/// resolves into this Cellar, else the version recorded in `InstallState`.
+fn linked_version(
+    cellar: &Path,
+    name: &str,
+    versions: &[String],
+    recorded: Option<&str>,
+) -> Option<String> {
+    let opt_link = cellar.parent()?.join("opt").join(name);
+    if let Ok(target) = std::fs::canonicalize(&opt_link) {
+        let keg_root = std::fs::canonicalize(cellar.join(name)).ok();
+        if target.parent() == keg_root.as_deref() {
+            if let Some(version) = target.file_name().and_then(|v| v.to_str()) {
+                if versions.iter().any(|v| v == version) {
+                    return Some(version.to_string());
+                }
+            }
+        }
+    }
+
+    recorded
+        .filter(|r| versions.iter().any(|v| v == r))
+        .map(str::to_string)
+}
+
+async fn collect_versioned_formulae(scope: Option<InstallMode>) -> Result<Vec<VersionedFormula>> {
+    let cellars: Vec<PathBuf> = if let Some(raw) = std::env::var_os(WAX_TEST_CELLAR_ENV) {
+        vec![validate_cellar_path(&PathBuf::from(raw))?]
+    } else {
+        let modes = match scope {
+            Some(mode) => vec![mode],
+            None => vec![InstallMode::Global, InstallMode::User],
+        };
+        let mut cellars = Vec::new();
+        for mode in modes {
+            let cellar = mode.cellar_path()?;
+            if !cellars.contains(&cellar) {
+                cellars.push(cellar);
+            }
+        }
+        cellars
+    };
+
+    let installed_packages = InstallState::new()?.load().await?;
+    let mut formulae = Vec::new();
+
+    for cellar in cellars {
+        if !cellar.exists() {
+            continue;
+        }
+        let mut entries = tokio::fs::read_dir(&cellar).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_dir() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            let mut versions = Vec::new();
+            let mut version_entries = tokio::fs::read_dir(entry.path()).await?;
+            while let Some(version_entry) = version_entries.next_entry().await? {
+                if version_entry.file_type().await?.is_dir() {
+                    versions.push(version_entry.file_name().to_string_lossy().to_string());
+                }
+            }
+            if versions.is_empty() {
+                continue;
+            }
+            sort_versions(&mut versions);
+            let recorded = installed_packages.get(&name).map(|p| p.version.as_str());
+            let linked = linked_version(&cellar, &name, &versions, recorded);
+            formulae.push(VersionedFormula {
+                name,
+                versions,
+                linked,
+            });
+        }
+    }
+
+    formulae.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(formulae)
+}
+
+async fn list_versions(scope: Option<InstallMode>, query: Option<&str>) -> Result<()> {
+    let q = query.unwrap_or("").to_lowercase();
+    let formulae: Vec<_> = collect_versioned_formulae(scope)
+        .await?
+        .into_iter()
+        .filter(|f| f.name.to_lowercase().contains(&q))
+        .collect();
+
+    if formulae.is_empty() {
+        if q.is_empty() {
+            println!("no formulae installed");
+        } else {
+            println!("no installed formulae match '{}'", q);
+        }
+        return Ok(());
+    }
+
+    println!();
+    for formula in &formulae {
+        println!("{}", style(&formula.name).magenta());
+        for version in &formula.versions {
+            if formula.linked.as_deref() == Some(version.as_str()) {
+                println!("  {} {}", version, style("(linked)").green());
+            } else {
+                println!("  {}", style(version).dim());
+            }
+        }
+    }
+
+    let version_count: usize = formulae.iter().map(|f| f.versions.len()).sum();
+    println!(
+        "\n{} {}, {} {}",
+        style(formulae.len()).cyan(),
+        if formulae.len() == 1 {
+            "formula"
+        } else {
+            "formulae"
+        },
+        version_count,
+        if version_count == 1 {
+            "version"
+        } else {
+            "versions"
+        }
+    );
+
+    Ok(())
+}
+
 #[instrument(skip(cache))]
-pub async fn list(cache: &Cache, query: Option<String>, scope: Option<InstallMode>) -> Result<()> {
-    let rows = collect_installed_rows(cache, scope).await?;
+pub async fn list(
+    cache: &Cache,
+    query: Option<String>,
+    scope: Option<InstallMode>,
+    versions: bool,
+    sizes: bool,
+) -> Result<()> {
+    if versions {
+        #[cfg(target_os = "windows")]
+        crate::error::reject_homebrew_cli("list --versions")?;
+        return list_versions(scope, query.as_deref()).await;
+    }
+
+    let rows = collect_installed_rows(cache, scope, sizes).await?;
 
     if rows.is_empty() {
         println!("no packages installed");
@@ -432,7 +604,7 @@ pub async fn list(cache: &Cache, query: Option<String>, scope: Option<InstallMod
     let use_ui =
         io::stdin().is_terminal() && io::stdout().is_terminal() && std::env::var_os("CI").is_none();
 
-    if use_ui && scope.is_none() {
+    if use_ui && scope.is_none() && !sizes {
         return run_interactive_list(cache, query).await;
     }
 
@@ -457,6 +629,8 @@ pub async fn list(cache: &Cache, query: Option<String>, scope: Option<InstallMod
 
 #[cfg(test)]
 mod tests {
+    use super::formula_line;
+    use super::linked_version;
     use super::matches_query;
     use super::InstalledRow;
 
@@ -469,6 +643,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn formula_line_marks_source_builds_and_dependencies() {
+        let mut pkg = crate::test_support::installed_package("oniguruma", "6.9.9");
+        assert!(!pkg.installed_as_dependency);
+        assert!(
+            !console::strip_ansi_codes(&formula_line("oniguruma", "6.9.9", Some(&pkg)))
+                .contains("(dep)")
+        );
+
+        pkg.installed_as_dependency = true;
+        pkg.from_source = true;
+        assert_eq!(
+            console::strip_ansi_codes(&formula_line("oniguruma", "6.9.9", Some(&pkg))),
+            "oniguruma 6.9.9 (source) (dep)"
+        );
+    }
+
     #[test]
     fn matches_query_empty_string_matches_all() {
         let r = row("tree", "tree 2.0");
@@ -494,4 +685,30 @@ mod tests {
         let r = row("x", "x 1 (source) something");
         assert!(matches_query(&r, "source"));
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn linked_version_prefers_opt_link_over_recorded_version() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cellar = tmp.path().join("Cellar");
+        std::fs::create_dir_all(cellar.join("tree/2.1.0")).unwrap();
+        std::fs::create_dir_all(cellar.join("tree/2.1.1")).unwrap();
+        let versions = vec!["2.1.0".to_string(), "2.1.1".to_string()];
+
+        assert_eq!(
+            linked_version(&cellar, "tree", &versions, Some("2.1.1")),
+            Some("2.1.1".to_string())
+        );
+        assert_eq!(
+            linked_version(&cellar, "tree", &versions, Some("9.9")),
+            None
+        );
+
+        std::fs::create_dir_all(tmp.path().join("opt")).unwrap();
+        std::os::unix::fs::symlink("../Cellar/tree/2.1.0", tmp.path().join("opt/tree")).unwrap();
+        assert_eq!(
+            linked_version(&cellar, "tree", &versions, Some("2.1.1")),
+            Some("2.1.0".to_string())
+        );
+    }
 }
@@ -4,8 +4,50 @@ use crate::discovery::{discover_linux_system_packages, discover_manually_install
 use crate::error::Result;
 use crate::install::InstallState;
 use crate::lockfile::{Lockfile, LockfileCask, LockfilePackage};
+use console::style;
 use tracing::instrument;
 
+/// `wax install --lock`: run `install`, then write what it changed into `wax.lock` if
+/// the lockfile already exists. Whatever did get installed is recorded even when part
+/// of the install failed.
+pub async fn recording_changes(
+    install: impl std::future::Future<Output = Result<()>>,
+) -> Result<()> {
+    let before = Lockfile::generate().await?;
+    let installed = install.await;
+    record_changes(&before).await?;
+    installed
+}
+
+async fn record_changes(before: &Lockfile) -> Result<()> {
+    let after = Lockfile::generate().await?;
+    let path = Lockfile::default_path();
+    let mut changed = 0;
+    let updated = Lockfile::update_existing(&path, |lockfile| {
+        changed = lockfile.apply_changes(before, &after);
+        changed > 0
+    })
+    .await?;
+    if !updated {
+        eprintln!(
+            "{} no wax.lock to update; run `wax lock` to create one",
+            style("note:").yellow()
+        );
+    } else if changed > 0 {
+        println!(
+            "updated {} {}",
+            style("wax.lock").dim(),
+            style(format!(
+                "({} {})",
+                changed,
+                if changed == 1 { "entry" } else { "entries" }
+            ))
+            .dim()
+        );
+    }
+    Ok(())
+}
+
 #[instrument(skip(cache))]
 pub async fn lock(cache: &Cache) -> Result<()> {
     let formulae = cache.load_formulae().await?;
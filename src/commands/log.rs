@@ -0,0 +1,18 @@
+use crate::error::Result;
+use crate::logging::{tail_lines, KEEP_ROTATED_LOGS, LOG_FILE_NAME};
+use crate::ui::dirs;
+use console::style;
+
+pub async fn log(lines: usize) -> Result<()> {
+    let path = dirs::wax_logs_dir()?.join(LOG_FILE_NAME);
+    if !path.exists() {
+        println!("no log yet at {}", style(path.display()).dim());
+        return Ok(());
+    }
+
+    for line in tail_lines(&path, lines, KEEP_ROTATED_LOGS) {
+        println!("{}", line);
+    }
+    eprintln!("{}", style(path.display()).dim());
+    Ok(())
+}
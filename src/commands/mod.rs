@@ -1,16 +1,21 @@
 pub mod audit;
 
+pub mod cache;
+pub mod cat;
 pub mod cleanup;
 pub mod completions;
 pub mod doctor;
+pub mod edit;
 pub mod info;
 pub mod install;
 pub mod leaves;
 pub mod link;
 pub mod list;
 pub mod lock;
+pub mod log;
 pub mod outdated;
 pub mod pin;
+pub mod prefix;
 pub mod reinstall;
 pub mod search;
 pub mod self_update;
@@ -24,3 +29,4 @@ pub mod update;
 pub mod upgrade;
 pub mod uses;
 pub mod version_install;
+pub mod warm;
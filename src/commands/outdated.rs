@@ -10,25 +10,45 @@ pub async fn outdated(cache: &Cache, scope: Option<InstallMode>) -> Result<()> {
     let start = std::time::Instant::now();
 
     cache.ensure_fresh().await?;
+    cache.warn_if_index_old().await;
 
-    let outdated = get_outdated_packages_scoped(cache, scope).await?;
+    let (unknown, outdated): (Vec<_>, Vec<_>) = get_outdated_packages_scoped(cache, scope)
+        .await?
+        .into_iter()
+        .partition(|pkg| pkg.unknown);
+
+    if !unknown.is_empty() {
+        println!();
+        println!(
+            "{} {}",
+            style("no longer in index").yellow(),
+            style("— consider pinning or removing").dim()
+        );
+        for pkg in &unknown {
+            println!(
+                "{}{} {}",
+                style(&pkg.name).magenta(),
+                cask_indicator(pkg.is_cask),
+                style(&pkg.installed_version).dim()
+            );
+        }
+    }
 
     if outdated.is_empty() {
-        println!("all packages are up to date");
+        if unknown.is_empty() {
+            println!("all packages are up to date");
+        } else {
+            println!("\nnothing else to upgrade");
+        }
         return Ok(());
     }
 
     println!();
     for pkg in &outdated {
-        let cask_indicator = if pkg.is_cask {
-            format!(" {}", style("(cask)").yellow())
-        } else {
-            String::new()
-        };
         println!(
             "{}{} {} → {}",
             style(&pkg.name).magenta(),
-            cask_indicator,
+            cask_indicator(pkg.is_cask),
             style(&pkg.installed_version).dim(),
             style(&pkg.latest_version).green()
         );
@@ -44,3 +64,11 @@ pub async fn outdated(cache: &Cache, scope: Option<InstallMode>) -> Result<()> {
 
     Ok(())
 }
+
+fn cask_indicator(is_cask: bool) -> String {
+    if is_cask {
+        format!(" {}", style("(cask)").yellow())
+    } else {
+        String::new()
+    }
+}
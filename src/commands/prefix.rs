@@ -0,0 +1,18 @@
+use crate::error::Result;
+use crate::install::InstallMode;
+
+fn resolve_mode(scope: Option<InstallMode>) -> InstallMode {
+    scope.unwrap_or_else(InstallMode::detect)
+}
+
+/// `wax prefix`: the active install prefix, bare, for `$(wax prefix)/lib/pkgconfig`.
+pub fn prefix(scope: Option<InstallMode>) -> Result<()> {
+    println!("{}", resolve_mode(scope).prefix()?.display());
+    Ok(())
+}
+
+/// `wax cellar`: the Cellar under the active prefix.
+pub fn cellar(scope: Option<InstallMode>) -> Result<()> {
+    println!("{}", resolve_mode(scope).cellar_path()?.display());
+    Ok(())
+}
@@ -1,13 +1,15 @@
+use crate::api::Formula;
 use crate::cache::Cache;
 use crate::cask::{CaskState, InstalledCask};
 use crate::commands::{install, uninstall};
+use crate::deps::{add_alias_names, resolve_dependencies_for_roots};
 use crate::error::{Result, WaxError};
 use crate::install::{InstallMode, InstallState, InstalledPackage};
 use crate::signal::{clear_active_multi, clear_current_op, set_active_multi, set_current_op};
 use crate::ui::{PROGRESS_BAR_CHARS, PROGRESS_BAR_TEMPLATE, SPINNER_TICK_CHARS};
 use console::style;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
 struct ReinstallSignalGuard;
@@ -71,6 +73,68 @@ fn check_missing_packages<T, U>(
     Ok(())
 }
 
+/// Installed formulae that were built from source, dependencies before dependents, so
+/// each rebuild links against already-rebuilt libraries. Formulae missing from the
+/// index keep their place at the end, alphabetically. If the graph as a whole does not
+/// resolve (a dependency gone from the index, a cycle), each formula is ordered after
+/// its own dependencies instead, and the ones that fail on their own go at the end.
+fn source_built_in_dependency_order(
+    installed: &HashMap<String, InstalledPackage>,
+    formulae: &[Formula],
+) -> Vec<String> {
+    let mut index: HashMap<&str, &Formula> =
+        formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+    add_alias_names(&mut index, formulae);
+
+    let mut source_built: Vec<&str> = installed
+        .values()
+        .filter(|p| p.from_source)
+        .map(|p| p.name.as_str())
+        .collect();
+    source_built.sort_unstable();
+
+    let roots: Vec<&Formula> = source_built
+        .iter()
+        .filter_map(|name| index.get(name).copied())
+        .collect();
+    let resolved = match resolve_dependencies_for_roots(&roots, &index, &HashSet::new()) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            eprintln!(
+                "{} could not order source-built formulae by dependency: {}",
+                style("warning:").yellow(),
+                e
+            );
+            let mut resolved: Vec<String> = Vec::new();
+            for root in &roots {
+                let Ok(chain) = resolve_dependencies_for_roots(
+                    std::slice::from_ref(root),
+                    &index,
+                    &HashSet::new(),
+                ) else {
+                    continue;
+                };
+                for name in chain {
+                    if !resolved.contains(&name) {
+                        resolved.push(name);
+                    }
+                }
+            }
+            resolved
+        }
+    };
+    let mut ordered: Vec<String> = resolved
+        .into_iter()
+        .filter(|name| source_built.contains(&name.as_str()))
+        .collect();
+    for name in source_built {
+        if !ordered.iter().any(|n| n == name) {
+            ordered.push(name.to_string());
+        }
+    }
+    ordered
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn reinstall_package(
     cache: &Cache,
@@ -78,6 +142,7 @@ async fn reinstall_package(
     i: usize,
     total: usize,
     cask: bool,
+    build_from_source: bool,
     installed: &HashMap<String, InstalledPackage>,
     installed_casks: &HashMap<String, InstalledCask>,
     multi: &MultiProgress,
@@ -86,6 +151,13 @@ async fn reinstall_package(
     let is_cask = cask || installed_casks.contains_key(name.as_str());
 
     let install_mode = installed.get(name.as_str()).map(|p| p.install_mode);
+    let options = installed
+        .get(name.as_str())
+        .map(|p| p.options.clone())
+        .unwrap_or_default();
+    let no_verify = installed
+        .get(name.as_str())
+        .is_some_and(|p| p.unverified_source);
     let (user_flag, global_flag) = match install_mode {
         Some(InstallMode::User) => (true, false),
         Some(InstallMode::Global) => (false, true),
@@ -137,6 +209,13 @@ async fn reinstall_package(
                 run_scripts: true,
                 quiet: true,
                 force_reinstall: true,
+                options: &[],
+                ignore_dependencies: false,
+                only_dependencies: false,
+                no_verify: false,
+                no_relocate: false,
+                platform: None,
+                overwrite: false,
                 external_pb: None,
             },
         )
@@ -163,16 +242,33 @@ async fn reinstall_package(
                 cask: false,
                 user: user_flag,
                 global: global_flag,
-                build_from_source: false,
+                build_from_source,
                 head: false,
                 run_scripts: true,
                 quiet: true,
                 force_reinstall: true,
+                options: &options,
+                ignore_dependencies: false,
+                only_dependencies: false,
+                no_verify,
+                no_relocate: false,
+                platform: None,
+                overwrite: false,
                 external_pb: Some(&pb),
             },
         )
         .await?;
         pb.finish_and_clear();
+
+        // A reinstalled dependency is still a dependency, not a new request.
+        if installed
+            .get(name.as_str())
+            .is_some_and(|p| p.installed_as_dependency)
+        {
+            InstallState::new()?
+                .set_installed_as_dependency(std::slice::from_ref(name), true)
+                .await?;
+        }
     }
     println!(
         "{} {}{}@{}{}",
@@ -191,7 +287,13 @@ async fn reinstall_package(
     Ok(())
 }
 
-pub async fn reinstall(cache: &Cache, packages: &[String], cask: bool, all: bool) -> Result<()> {
+pub async fn reinstall(
+    cache: &Cache,
+    packages: &[String],
+    cask: bool,
+    all: bool,
+    all_source: bool,
+) -> Result<()> {
     let state = InstallState::new()?;
     state.sync_from_cellar().await.ok();
     let installed = state.load().await?;
@@ -199,8 +301,20 @@ pub async fn reinstall(cache: &Cache, packages: &[String], cask: bool, all: bool
     let cask_state = CaskState::new()?;
     let installed_casks = cask_state.load().await?;
 
-    let resolved = resolve_packages(packages, cask, all, &installed, &installed_casks)?;
-    check_missing_packages(&resolved, cask, &installed, &installed_casks)?;
+    let resolved = if all_source {
+        cache.ensure_fresh().await?;
+        let formulae = cache.load_all_formulae().await?;
+        let resolved = source_built_in_dependency_order(&installed, &formulae);
+        if resolved.is_empty() {
+            println!("no packages were built from source");
+            return Ok(());
+        }
+        resolved
+    } else {
+        let resolved = resolve_packages(packages, cask, all, &installed, &installed_casks)?;
+        check_missing_packages(&resolved, cask, &installed, &installed_casks)?;
+        resolved
+    };
 
     let total = resolved.len();
     let start = Instant::now();
@@ -212,26 +326,102 @@ pub async fn reinstall(cache: &Cache, packages: &[String], cask: bool, all: bool
         println!("reinstalling {} packages\n", style(total).bold());
     }
 
+    // `--all-source` rebuilds everything it can and reports failures at the end;
+    // an explicit list stops at the first failure.
+    let mut failed_names = Vec::new();
     for (i, name) in resolved.iter().enumerate() {
-        reinstall_package(
+        let result = reinstall_package(
             cache,
             name,
             i,
             total,
             cask,
+            all_source,
             &installed,
             &installed_casks,
             &multi,
         )
-        .await?;
+        .await;
+        match result {
+            Ok(()) => {}
+            Err(e) if all_source && !matches!(e, WaxError::Interrupted) => {
+                eprintln!(
+                    "{} {} failed: {}",
+                    style("✗").red(),
+                    style(name).magenta(),
+                    e
+                );
+                failed_names.push(name.clone());
+            }
+            Err(e) => return Err(e),
+        }
     }
 
+    let reinstalled = total - failed_names.len();
     println!(
         "\n{} {} reinstalled{}",
-        style(total).bold(),
-        if total == 1 { "package" } else { "packages" },
+        style(reinstalled).bold(),
+        if reinstalled == 1 {
+            "package"
+        } else {
+            "packages"
+        },
         crate::ui::elapsed_suffix(start.elapsed())
     );
+    if !failed_names.is_empty() {
+        return Err(WaxError::InstallError(format!(
+            "{} package{} failed to rebuild: {}",
+            failed_names.len(),
+            if failed_names.len() == 1 { "" } else { "s" },
+            failed_names.join(", ")
+        )));
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{formula, installed_package};
+
+    #[test]
+    fn source_builds_are_rebuilt_dependencies_first() {
+        // app (source) → libmid (bottle) → libbase (source)
+        let formulae = vec![
+            formula("app", &["libmid"]),
+            formula("libmid", &["libbase"]),
+            formula("libbase", &[]),
+        ];
+        let package = |name: &str, from_source: bool| {
+            let pkg = InstalledPackage {
+                from_source,
+                ..installed_package(name, "1.0")
+            };
+            (name.to_string(), pkg)
+        };
+        let installed: HashMap<String, InstalledPackage> = [
+            package("app", true),
+            package("libmid", false),
+            package("libbase", true),
+            package("orphan", true),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            source_built_in_dependency_order(&installed, &formulae),
+            vec!["libbase", "app", "orphan"]
+        );
+
+        // A dependency gone from the index only costs the formula that needs it its place.
+        let mut formulae = formulae;
+        formulae.push(formula("tool", &["ghost"]));
+        let mut installed = installed;
+        installed.extend([package("tool", true)]);
+        assert_eq!(
+            source_built_in_dependency_order(&installed, &formulae),
+            vec!["libbase", "app", "orphan", "tool"]
+        );
+    }
+}
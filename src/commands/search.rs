@@ -14,15 +14,24 @@ use crate::remote_search::{
     collect_remote_hits, dedupe_remote_by_speed, print_remote_hits, windows_search_plan,
 };
 
+/// Per-section result caps when `--all` is not given.
+#[cfg(not(target_os = "windows"))]
+const FORMULA_RESULT_LIMIT: usize = 20;
+#[cfg(not(target_os = "windows"))]
+const TAP_RESULT_LIMIT: usize = 10;
+#[cfg(not(target_os = "windows"))]
+const CASK_RESULT_LIMIT: usize = 20;
+
 #[instrument(skip(cache))]
-pub async fn search(cache: &Cache, query: &str) -> Result<()> {
+pub async fn search(cache: &Cache, query: &str, all: bool, installed: bool) -> Result<()> {
     #[cfg(target_os = "windows")]
     {
+        let _ = (all, installed);
         search_windows(cache, query).await
     }
     #[cfg(not(target_os = "windows"))]
     {
-        search_unix(cache, query).await
+        search_unix(cache, query, all, installed).await
     }
 }
 
@@ -79,8 +88,9 @@ async fn search_windows(cache: &Cache, query: &str) -> Result<()> {
 }
 
 #[cfg(not(target_os = "windows"))]
-async fn search_unix(cache: &Cache, query: &str) -> Result<()> {
+async fn search_unix(cache: &Cache, query: &str, all: bool, installed_only: bool) -> Result<()> {
     cache.ensure_fresh().await?;
+    cache.warn_if_index_old().await;
 
     let formulae = cache.load_all_formulae().await?;
     let casks = cache.load_casks().await?;
@@ -131,20 +141,50 @@ async fn search_unix(cache: &Cache, query: &str) -> Result<()> {
         })
         .collect();
 
-    formula_matches.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name.cmp(&b.0.name)));
+    if installed_only {
+        formula_matches.retain(|(f, _)| installed_packages.contains_key(&f.name));
+        tap_matches.retain(|(f, _)| installed_packages.contains_key(&f.name));
+        cask_matches.retain(|(c, _)| installed_casks.contains_key(&c.token));
+    }
+
+    formula_matches
+        .sort_by(|a, b| crate::catalog_match::compare_ranked((&a.0.name, a.1), (&b.0.name, b.1)));
     tap_matches.sort_by(|a, b| {
-        b.1.cmp(&a.1)
-            .then_with(|| a.0.full_name.cmp(&b.0.full_name))
+        crate::catalog_match::compare_ranked((&a.0.full_name, a.1), (&b.0.full_name, b.1))
     });
-    cask_matches.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.token.cmp(&b.0.token)));
+    cask_matches
+        .sort_by(|a, b| crate::catalog_match::compare_ranked((&a.0.token, a.1), (&b.0.token, b.1)));
 
-    let formula_matches: Vec<_> = formula_matches.iter().take(20).map(|(f, _)| f).collect();
-    let tap_matches: Vec<_> = tap_matches.iter().take(10).map(|(f, _)| f).collect();
-    let cask_matches: Vec<_> = cask_matches.iter().take(20).map(|(c, _)| c).collect();
+    let cap = |limit: usize| if all { usize::MAX } else { limit };
+    let hidden = formula_matches
+        .len()
+        .saturating_sub(cap(FORMULA_RESULT_LIMIT))
+        + tap_matches.len().saturating_sub(cap(TAP_RESULT_LIMIT))
+        + cask_matches.len().saturating_sub(cap(CASK_RESULT_LIMIT));
+
+    let formula_matches: Vec<_> = formula_matches
+        .iter()
+        .take(cap(FORMULA_RESULT_LIMIT))
+        .map(|(f, _)| f)
+        .collect();
+    let tap_matches: Vec<_> = tap_matches
+        .iter()
+        .take(cap(TAP_RESULT_LIMIT))
+        .map(|(f, _)| f)
+        .collect();
+    let cask_matches: Vec<_> = cask_matches
+        .iter()
+        .take(cap(CASK_RESULT_LIMIT))
+        .map(|(c, _)| c)
+        .collect();
 
     let total = formula_matches.len() + tap_matches.len() + cask_matches.len();
 
     if total == 0 {
+        if installed_only {
+            println!("no installed packages match '{}'", query);
+            return Ok(());
+        }
         println!("no results for '{}'", query);
         return Ok(());
     }
@@ -197,6 +237,12 @@ async fn search_unix(cache: &Cache, query: &str) -> Result<()> {
         ));
     }
     println!("\n{}", style(parts.join(", ")).dim());
+    if hidden > 0 {
+        println!(
+            "{}",
+            style(format!("… and {} more (use --all)", hidden)).dim()
+        );
+    }
 
     Ok(())
 }
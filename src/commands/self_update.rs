@@ -2,8 +2,8 @@ use crate::error::{Result, WaxError};
 use crate::ui::create_spinner;
 use crate::version::WAX_VERSION as CURRENT_VERSION;
 use console::style;
-use inquire::Confirm;
 use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use tracing::{info, instrument};
 
 const GITHUB_REPO_URL: &str = "https://github.com/plyght/wax";
@@ -43,9 +43,11 @@ fn is_newer(current: &str, latest: &str) -> bool {
     }
 }
 
+const CRATES_API_URL: &str = "https://crates.io/api/v1/crates/waxpkg";
+
 async fn fetch_latest_crate_version(client: &reqwest::Client) -> Result<String> {
     let resp = client
-        .get("https://crates.io/api/v1/crates/waxpkg")
+        .get(CRATES_API_URL)
         .header("User-Agent", "wax-self-update")
         .send()
         .await
@@ -94,7 +96,7 @@ pub async fn self_update(
 }
 
 pub async fn available_stable_update() -> Result<Option<String>> {
-    let client = crate::http_client::api();
+    let client = crate::http_client::api(CRATES_API_URL);
     let latest_version = fetch_latest_crate_version(client).await?;
 
     if is_newer(CURRENT_VERSION, &latest_version) {
@@ -105,7 +107,7 @@ pub async fn available_stable_update() -> Result<Option<String>> {
 }
 
 async fn update_from_crates(force: bool) -> Result<()> {
-    let client = crate::http_client::api();
+    let client = crate::http_client::api(CRATES_API_URL);
     let spinner = create_spinner("Checking for updates…");
     let latest_version = fetch_latest_crate_version(client).await?;
     spinner.finish_and_clear();
@@ -161,10 +163,107 @@ async fn update_from_crates(force: bool) -> Result<()> {
         style("✓").green(),
         style(format!("v{latest_version}")).cyan()
     );
+    verify_installed_binary(Some(&latest_version));
 
     Ok(())
 }
 
+/// Where `cargo install` puts binaries: `$CARGO_INSTALL_ROOT/bin`, else
+/// `$CARGO_HOME/bin`, else `~/.cargo/bin`.
+fn cargo_bin_dir() -> Option<PathBuf> {
+    if let Some(root) = std::env::var_os("CARGO_INSTALL_ROOT") {
+        return Some(PathBuf::from(root).join("bin"));
+    }
+    if let Some(home) = std::env::var_os("CARGO_HOME") {
+        return Some(PathBuf::from(home).join("bin"));
+    }
+    crate::ui::dirs::home_dir()
+        .ok()
+        .map(|home| home.join(".cargo").join("bin"))
+}
+
+/// `wax 0.14.0` → `0.14.0`.
+fn version_from_output(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .find(|word| parse_version(word).is_some())
+        .map(|word| word.trim_start_matches('v').to_string())
+}
+
+fn reported_version(binary: &Path) -> Option<String> {
+    let output = std::process::Command::new(binary)
+        .arg("--version")
+        .output()
+        .ok()?;
+    version_from_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Confirm the freshly installed binary reports `expected` and is the `wax` that PATH
+/// resolves to; cargo's bin dir is often missing from PATH or shadowed by an older copy.
+fn verify_installed_binary(expected: Option<&str>) {
+    let binary_name = format!("wax{}", std::env::consts::EXE_SUFFIX);
+    let Some(installed) = cargo_bin_dir().map(|dir| dir.join(&binary_name)) else {
+        return;
+    };
+    if !installed.exists() {
+        eprintln!(
+            "{} cargo reported success but {} does not exist",
+            style("warning:").yellow(),
+            installed.display()
+        );
+        return;
+    }
+
+    let installed_version = reported_version(&installed);
+    if let (Some(expected), Some(actual)) = (expected, installed_version.as_deref()) {
+        if expected.trim_start_matches('v') != actual {
+            eprintln!(
+                "{} {} reports {}, expected {}",
+                style("warning:").yellow(),
+                installed.display(),
+                actual,
+                expected
+            );
+        }
+    }
+
+    let same_file = |a: &Path, b: &Path| match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    };
+    match crate::ui::find_in_path("wax") {
+        Some(resolved) if !same_file(&resolved, &installed) => {
+            eprintln!(
+                "{} `wax` on PATH is {} ({}), which shadows the updated {}",
+                style("warning:").yellow(),
+                resolved.display(),
+                reported_version(&resolved).unwrap_or_else(|| "unknown version".to_string()),
+                installed.display()
+            );
+            eprintln!(
+                "  {} put {} earlier on PATH or remove the old binary",
+                style("hint:").dim(),
+                installed.parent().unwrap_or(&installed).display()
+            );
+        }
+        Some(_) => {
+            if let Some(version) = installed_version {
+                println!(
+                    "  {} {} reports {}",
+                    style("verify:").dim(),
+                    installed.display(),
+                    style(version).cyan()
+                );
+            }
+        }
+        None => eprintln!(
+            "{} {} is not on PATH; add it to run the updated wax",
+            style("warning:").yellow(),
+            installed.parent().unwrap_or(&installed).display()
+        ),
+    }
+}
+
 fn cleanup_nightly_artifacts() -> Result<usize> {
     let home = crate::ui::dirs::home_dir()?;
     let mut removed = 0usize;
@@ -203,10 +302,11 @@ fn should_cleanup_nightly(nightly_cleanup: Option<bool>) -> Result<bool> {
                 );
                 return Ok(false);
             }
-            Confirm::new("Clean Cargo git cache for wax nightly sources?")
-                .with_default(false)
-                .prompt()
-                .map_err(|e| WaxError::SelfUpdateError(format!("Failed to read prompt input: {e}")))
+            crate::ui::confirm_prompt(
+                false,
+                "Clean Cargo git cache for wax nightly sources?",
+                false,
+            )
         }
     }
 }
@@ -259,6 +359,7 @@ async fn update_from_source(force: bool, nightly_cleanup: Option<bool>) -> Resul
     }
 
     println!("{} installed nightly build from HEAD", style("✓").green());
+    verify_installed_binary(None);
     Ok(())
 }
 
@@ -286,6 +387,16 @@ mod tests {
         assert_eq!(parse_version("1.2.3-beta.1"), Some((1, 2, 3)));
     }
 
+    #[test]
+    fn version_is_read_from_version_output() {
+        assert_eq!(
+            version_from_output("wax 0.14.0\n").as_deref(),
+            Some("0.14.0")
+        );
+        assert_eq!(version_from_output("wax v1.2.3").as_deref(), Some("1.2.3"));
+        assert_eq!(version_from_output("error: unknown flag"), None);
+    }
+
     #[test]
     fn parse_version_invalid() {
         assert_eq!(parse_version("not-a-version"), None);
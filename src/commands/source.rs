@@ -3,6 +3,7 @@ use crate::error::{Result, WaxError};
 use console::style;
 use std::collections::HashMap;
 use tracing::instrument;
+
 fn is_safe_url(url_str: &str) -> bool {
     // Prevent command injection via shell metacharacters
     let dangerous_chars = [
@@ -19,6 +20,34 @@ fn is_safe_url(url_str: &str) -> bool {
     }
 }
 
+/// Open `url` in the default browser (`open`, `xdg-open`, or `explorer`). Returns false
+/// when there is no opener to hand it to, e.g. on a headless server without a display,
+/// so the caller can print the URL instead.
+pub(crate) fn open_in_browser(url: &str) -> Result<bool> {
+    if !is_safe_url(url) {
+        return Err(WaxError::InvalidInput(format!(
+            "Invalid or unsafe homepage URL: {}",
+            url
+        )));
+    }
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(target_os = "windows") {
+        "explorer"
+    } else if std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some()
+    {
+        "xdg-open"
+    } else {
+        return Ok(false);
+    };
+    Ok(std::process::Command::new(opener)
+        .arg(url)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .is_ok())
+}
+
 #[instrument(skip(cache))]
 pub async fn source(cache: &Cache, formula_name: &str) -> Result<()> {
     cache.ensure_fresh().await?;
@@ -49,16 +78,7 @@ pub async fn source(cache: &Cache, formula_name: &str) -> Result<()> {
             style(formula_name).magenta(),
             style(homepage).cyan().underlined()
         );
-
-        #[cfg(target_os = "macos")]
-        {
-            let _ = std::process::Command::new("open").arg(homepage).spawn();
-        }
-
-        #[cfg(target_os = "linux")]
-        {
-            let _ = std::process::Command::new("xdg-open").arg(homepage).spawn();
-        }
+        open_in_browser(homepage)?;
 
         return Ok(());
     }
@@ -77,16 +97,7 @@ pub async fn source(cache: &Cache, formula_name: &str) -> Result<()> {
             style("(cask)").yellow(),
             style(homepage).cyan().underlined()
         );
-
-        #[cfg(target_os = "macos")]
-        {
-            let _ = std::process::Command::new("open").arg(homepage).spawn();
-        }
-
-        #[cfg(target_os = "linux")]
-        {
-            let _ = std::process::Command::new("xdg-open").arg(homepage).spawn();
-        }
+        open_in_browser(homepage)?;
 
         return Ok(());
     }
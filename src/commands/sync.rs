@@ -1,22 +1,24 @@
 use crate::bottle::{detect_platform, BottleDownloader};
 use crate::cache::Cache;
 use crate::cask::CaskState;
+use crate::deps::DependencyGraph;
 use crate::discovery::{discover_linux_system_packages, discover_manually_installed_casks};
 use crate::error::{Result, WaxError};
 use crate::install::{create_symlinks, InstallMode, InstallState, InstalledPackage};
 use crate::lockfile::Lockfile;
 use crate::signal::{check_cancelled, CriticalSection};
 use crate::ui::{PROGRESS_BAR_CHARS, PROGRESS_BAR_TEMPLATE};
+use crate::version::{upgrade_status, UpgradeStatus};
 use console::style;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tempfile::TempDir;
 use tokio::sync::Semaphore;
 use tracing::instrument;
 
 #[instrument(skip(cache))]
-pub async fn sync(cache: &Cache) -> Result<()> {
+pub async fn sync(cache: &Cache, frozen: bool) -> Result<()> {
     let start = std::time::Instant::now();
 
     let lockfile_path = Lockfile::default_path();
@@ -42,6 +44,17 @@ pub async fn sync(cache: &Cache) -> Result<()> {
 
     let actions = compute_sync_actions(&lockfile, &installed_packages, &installed_casks);
 
+    if frozen {
+        let violations =
+            frozen_violations(&lockfile, &actions, &formulae, &casks, &current_platform);
+        if !violations.is_empty() {
+            return Err(WaxError::LockfileError(format!(
+                "--frozen: the lockfile cannot be applied exactly:\n  {}",
+                violations.join("\n  ")
+            )));
+        }
+    }
+
     if print_sync_preview(&actions) {
         return Ok(());
     }
@@ -51,12 +64,14 @@ pub async fn sync(cache: &Cache) -> Result<()> {
     if sync_package_count > 0 {
         let entries =
             build_sync_entries(actions.packages_to_install, &formulae, &current_platform)?;
+        let entries = in_dependency_order(entries, &formulae)?;
+        let dependencies = locked_dependencies(&lockfile, &formulae);
 
         let temp_dir = Arc::new(TempDir::new()?);
         let extracted_packages =
             download_and_extract_packages(entries, Arc::clone(&temp_dir)).await?;
 
-        install_extracted_packages(extracted_packages, &state).await?;
+        install_extracted_packages(extracted_packages, &dependencies, &state).await?;
     }
 
     if !actions.casks_to_install.is_empty() {
@@ -75,6 +90,13 @@ pub async fn sync(cache: &Cache) -> Result<()> {
                 run_scripts: true,
                 quiet: true,
                 force_reinstall: false,
+                options: &[],
+                ignore_dependencies: false,
+                only_dependencies: false,
+                no_verify: false,
+                no_relocate: false,
+                platform: None,
+                overwrite: false,
                 external_pb: None,
             },
         )
@@ -100,6 +122,97 @@ pub async fn sync(cache: &Cache) -> Result<()> {
     Ok(())
 }
 
+/// `wax bundle check`: compare the lockfile with what is installed, changing nothing.
+/// Fails (non-zero exit) and lists the drift when `wax sync` would have work to do.
+#[instrument(skip(cache))]
+pub async fn check(cache: &Cache) -> Result<()> {
+    let lockfile = Lockfile::load(&Lockfile::default_path()).await?;
+    if lockfile.packages.is_empty() && lockfile.casks.is_empty() {
+        println!("no packages or casks in lockfile");
+        return Ok(());
+    }
+
+    // Read-only counterparts of `load_installed_packages`/`load_installed_casks`.
+    let mut installed_packages = InstallState::new()?.load().await?;
+    if cfg!(target_os = "linux") {
+        let formulae = cache.load_formulae().await?;
+        for (name, package) in discover_linux_system_packages(&formulae).await? {
+            installed_packages.entry(name).or_insert(package);
+        }
+    }
+    let mut installed_casks = CaskState::new()?.load().await?;
+    if cfg!(target_os = "macos") && !lockfile.casks.is_empty() {
+        let casks = cache.load_casks().await?;
+        for (name, cask) in discover_manually_installed_casks(&casks).await? {
+            installed_casks.entry(name).or_insert(cask);
+        }
+    }
+
+    let actions = compute_sync_actions(&lockfile, &installed_packages, &installed_casks);
+    let drift = drift_report(&lockfile, &actions, &installed_packages, &installed_casks);
+    if drift.is_empty() {
+        let total = lockfile.packages.len() + lockfile.casks.len();
+        println!(
+            "{} {} {} match the lockfile",
+            style("✓").green(),
+            total,
+            if total == 1 {
+                "package/cask"
+            } else {
+                "packages/casks"
+            }
+        );
+        return Ok(());
+    }
+
+    for line in &drift {
+        println!("  {}", line);
+    }
+    Err(WaxError::LockfileError(format!(
+        "{} {} out of sync with the lockfile; run `wax sync`",
+        drift.len(),
+        if drift.len() == 1 { "entry" } else { "entries" }
+    )))
+}
+
+/// One line per lockfile entry that `wax sync` would install or change, sorted by name.
+fn drift_report(
+    lockfile: &Lockfile,
+    actions: &SyncActions,
+    installed_packages: &HashMap<String, InstalledPackage>,
+    installed_casks: &HashMap<String, crate::cask::InstalledCask>,
+) -> Vec<String> {
+    let mut lines: Vec<(String, String)> = Vec::new();
+    for (name, lock_pkg) in &actions.packages_to_install {
+        let detail = match installed_packages.get(name) {
+            None => format!("missing (locked {})", lock_pkg.version),
+            Some(installed) if !same_version(&installed.version, &lock_pkg.version) => format!(
+                "installed {}, locked {}",
+                installed.version, lock_pkg.version
+            ),
+            Some(installed) => format!(
+                "installed for {}, locked for {}",
+                installed.platform, lock_pkg.bottle
+            ),
+        };
+        lines.push((name.clone(), format!("{}: {}", name, detail)));
+    }
+    for name in &actions.casks_to_install {
+        let locked = lockfile
+            .casks
+            .get(name)
+            .map(|c| c.version.as_str())
+            .unwrap_or("?");
+        let detail = match installed_casks.get(name) {
+            None => format!("missing (locked {})", locked),
+            Some(installed) => format!("installed {}, locked {}", installed.version, locked),
+        };
+        lines.push((name.clone(), format!("{} (cask): {}", name, detail)));
+    }
+    lines.sort();
+    lines.into_iter().map(|(_, line)| line).collect()
+}
+
 async fn load_installed_packages(
     state: &InstallState,
     formulae: &[crate::api::Formula],
@@ -141,7 +254,7 @@ fn compute_sync_actions(
 
     for (name, lock_pkg) in &lockfile.packages {
         match installed_packages.get(name) {
-            Some(installed) if installed.version != lock_pkg.version => {
+            Some(installed) if !same_version(&installed.version, &lock_pkg.version) => {
                 upgrades.push((
                     name.clone(),
                     installed.version.clone(),
@@ -149,7 +262,9 @@ fn compute_sync_actions(
                 ));
                 packages_to_install.push((name.clone(), lock_pkg.clone()));
             }
-            Some(installed) if installed.platform != lock_pkg.bottle => {
+            // A source build records no bottle tag, so only a bottle install can be
+            // for the wrong platform.
+            Some(installed) if !installed.from_source && installed.platform != lock_pkg.bottle => {
                 packages_to_install.push((name.clone(), lock_pkg.clone()));
             }
             Some(_) => {
@@ -193,6 +308,64 @@ fn compute_sync_actions(
     }
 }
 
+/// `1.7.1` and `1.7.1_0` are the same version.
+fn same_version(installed: &str, locked: &str) -> bool {
+    upgrade_status(installed, locked) == UpgradeStatus::UpToDate
+}
+
+/// The index still carries the locked version (recorded with or without its `_N` revision).
+fn offers_locked_version(formula: &crate::api::Formula, locked: &str) -> bool {
+    formula.versions.stable == locked || formula.full_version() == locked
+}
+
+/// Everything `--frozen` refuses to paper over for the packages about to be installed:
+/// a locked formula or cask version the index no longer offers (installing would pick
+/// up something newer), or a lockfile written for another platform.
+fn frozen_violations(
+    lockfile: &Lockfile,
+    actions: &SyncActions,
+    formulae: &[crate::api::Formula],
+    casks: &[crate::api::Cask],
+    current_platform: &str,
+) -> Vec<String> {
+    let mut violations = Vec::new();
+    for (name, lock_pkg) in &actions.packages_to_install {
+        match formulae.iter().find(|f| &f.name == name) {
+            None => violations.push(format!("{} is not in the package index", name)),
+            Some(formula) if !offers_locked_version(formula, &lock_pkg.version) => {
+                violations.push(format!(
+                    "{} is locked at {} but the index has {}",
+                    name,
+                    lock_pkg.version,
+                    formula.full_version()
+                ))
+            }
+            Some(_) => {}
+        }
+        if lock_pkg.bottle != current_platform {
+            violations.push(format!(
+                "{} is locked for platform {} but this machine is {}",
+                name, lock_pkg.bottle, current_platform
+            ));
+        }
+    }
+    for name in &actions.casks_to_install {
+        let Some(lock_cask) = lockfile.casks.get(name) else {
+            continue;
+        };
+        match casks.iter().find(|c| &c.token == name) {
+            None => violations.push(format!("{} (cask) is not in the cask index", name)),
+            Some(cask) if cask.version != lock_cask.version => violations.push(format!(
+                "{} (cask) is locked at {} but the index has {}",
+                name, lock_cask.version, cask.version
+            )),
+            Some(_) => {}
+        }
+    }
+    violations.sort();
+    violations
+}
+
 fn print_sync_preview(actions: &SyncActions) -> bool {
     if !actions.packages_to_install.is_empty() || !actions.upgrades.is_empty() {
         let upgrade_index: HashMap<_, _> = actions
@@ -247,9 +420,9 @@ fn print_sync_preview(actions: &SyncActions) -> bool {
         }
     }
 
+    let total_up_to_date = actions.up_to_date.len() + actions.casks_up_to_date.len();
     if actions.packages_to_install.is_empty() && actions.casks_to_install.is_empty() {
-        if !actions.up_to_date.is_empty() || !actions.casks_up_to_date.is_empty() {
-            let total_up_to_date = actions.up_to_date.len() + actions.casks_up_to_date.len();
+        if total_up_to_date > 0 {
             println!(
                 "{} {} packages/casks up to date",
                 style("✓").green(),
@@ -259,6 +432,14 @@ fn print_sync_preview(actions: &SyncActions) -> bool {
         return true;
     }
 
+    if total_up_to_date > 0 {
+        println!(
+            "  {} {}",
+            style("✓").green(),
+            style(format!("{} already synced, skipping", total_up_to_date)).dim()
+        );
+    }
+
     false
 }
 
@@ -282,7 +463,7 @@ fn build_sync_entries(
             .find(|f| f.name == name)
             .ok_or_else(|| WaxError::FormulaNotFound(name.clone()))?;
 
-        if formula.versions.stable != lock_pkg.version {
+        if !offers_locked_version(formula, &lock_pkg.version) {
             return Err(WaxError::LockfileError(format!(
                 "Package {} version mismatch: lockfile specifies {} but latest available is {}. The locked version may no longer be available.",
                 name, lock_pkg.version, formula.versions.stable
@@ -319,6 +500,59 @@ fn build_sync_entries(
     Ok(entries)
 }
 
+/// `entries` with every package after the locked packages it depends on, so each keg
+/// is linked only once its dependencies' kegs are in place. The lockfile records no
+/// dependencies; they come from the index. Dependencies outside `entries` are already
+/// installed (or not locked) and do not affect the order.
+fn in_dependency_order(
+    entries: Vec<SyncEntry>,
+    formulae: &[crate::api::Formula],
+) -> Result<Vec<SyncEntry>> {
+    let mut index: HashMap<&str, &crate::api::Formula> =
+        formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+    crate::deps::add_alias_names(&mut index, formulae);
+
+    let mut graph = DependencyGraph::new();
+    for entry in &entries {
+        let deps = index
+            .get(entry.name.as_str())
+            .and_then(|f| f.dependencies.as_ref())
+            .into_iter()
+            .flatten()
+            .map(|dep| index.get(dep.as_str()).map_or(dep.as_str(), |f| &f.name))
+            .filter(|dep| entries.iter().any(|e| e.name == *dep))
+            .map(str::to_string)
+            .collect();
+        graph.add_node(entry.name.clone(), deps);
+    }
+
+    let mut by_name: HashMap<String, SyncEntry> =
+        entries.into_iter().map(|e| (e.name.clone(), e)).collect();
+    Ok(graph
+        .topological_sort()?
+        .into_iter()
+        .filter_map(|name| by_name.remove(&name))
+        .collect())
+}
+
+/// Locked packages that another locked package depends on, per the index. They are
+/// recorded as installed as dependencies, as `wax install` would have recorded them.
+fn locked_dependencies(lockfile: &Lockfile, formulae: &[crate::api::Formula]) -> HashSet<String> {
+    let mut index: HashMap<&str, &crate::api::Formula> =
+        formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+    crate::deps::add_alias_names(&mut index, formulae);
+
+    lockfile
+        .packages
+        .keys()
+        .filter_map(|name| index.get(name.as_str()))
+        .flat_map(|f| f.dependencies.iter().flatten())
+        .map(|dep| index.get(dep.as_str()).map_or(dep.as_str(), |f| &f.name))
+        .filter(|dep| lockfile.packages.contains_key(*dep))
+        .map(str::to_string)
+        .collect()
+}
+
 async fn download_and_extract_packages(
     entries: Vec<SyncEntry>,
     temp_dir: Arc<TempDir>,
@@ -376,9 +610,10 @@ async fn download_and_extract_packages(
             pb.set_style(style);
             pb.set_message(name);
 
-            let tarball_path = temp_dir
-                .path()
-                .join(format!("{}-{}.tar.gz", entry.name, entry.version));
+            let tarball_path = temp_dir.path().join(crate::bottle::archive_file_name(
+                &format!("{}-{}", entry.name, entry.version),
+                &entry.url,
+            ));
 
             downloader
                 .download(&entry.url, &tarball_path, Some(&pb), conns, None)
@@ -388,7 +623,8 @@ async fn download_and_extract_packages(
             // Release permit before extraction so another download can start.
             drop(permit);
 
-            crate::digest::verify_sha256_file(&tarball_path, &entry.sha256)?;
+            crate::digest::verify_digest_file(&tarball_path, &entry.sha256)
+                .map_err(|e| e.for_download(&entry.name, &entry.url))?;
 
             let extract_dir = temp_dir.path().join(&entry.name);
             BottleDownloader::extract(&tarball_path, &extract_dir)?;
@@ -419,6 +655,7 @@ async fn download_and_extract_packages(
 
 async fn install_extracted_packages(
     extracted_packages: Vec<(String, String, String, std::path::PathBuf)>,
+    dependencies: &HashSet<String>,
     state: &InstallState,
 ) -> Result<()> {
     let install_mode = InstallMode::detect();
@@ -439,6 +676,7 @@ async fn install_extracted_packages(
             &name,
             &version,
             &formula_cellar,
+            None,
         )?;
 
         create_symlinks(
@@ -446,6 +684,7 @@ async fn install_extracted_packages(
             &version,
             &cellar,
             false, /* dry_run */
+            false, /* overwrite */
             install_mode,
         )
         .await?;
@@ -463,6 +702,9 @@ async fn install_extracted_packages(
             bottle_rebuild: 0,
             bottle_sha256: None,
             pinned: false,
+            options: Vec::new(),
+            installed_as_dependency: dependencies.contains(&name),
+            unverified_source: false,
         };
         state.add(package).await?;
 
@@ -487,3 +729,248 @@ async fn load_installed_casks(
 
     Ok(installed_casks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compute_sync_actions, drift_report, frozen_violations, in_dependency_order,
+        locked_dependencies, SyncEntry,
+    };
+    use crate::lockfile::{Lockfile, LockfileCask, LockfilePackage};
+    use crate::test_support::{formula, formula_with, installed_package};
+    use std::collections::HashMap;
+
+    #[test]
+    fn frozen_reports_drift_and_platform_mismatch() {
+        let formulae = vec![
+            formula_with(
+                "jq",
+                serde_json::json!({
+                    "versions": { "stable": "1.7.1", "bottle": true },
+                    "revision": 1,
+                }),
+            ),
+            formula_with(
+                "tree",
+                serde_json::json!({ "versions": { "stable": "2.2.1", "bottle": true } }),
+            ),
+        ];
+        let casks: Vec<crate::api::Cask> = vec![serde_json::from_value(serde_json::json!({
+            "token": "firefox",
+            "full_token": "firefox",
+            "name": ["Firefox"],
+            "homepage": "",
+            "version": "131.0",
+        }))
+        .unwrap()];
+
+        let mut lockfile = Lockfile::new();
+        for (name, version, bottle) in [
+            ("jq", "1.7.1_1", "arm64_sonoma"),
+            ("tree", "2.1.1", "arm64_sonoma"),
+        ] {
+            lockfile.packages.insert(
+                name.to_string(),
+                LockfilePackage {
+                    version: version.to_string(),
+                    bottle: bottle.to_string(),
+                },
+            );
+        }
+        lockfile.casks.insert(
+            "firefox".to_string(),
+            LockfileCask {
+                version: "130.0".to_string(),
+            },
+        );
+
+        let actions = compute_sync_actions(&lockfile, &HashMap::new(), &HashMap::new());
+        assert_eq!(
+            frozen_violations(&lockfile, &actions, &formulae, &casks, "arm64_sonoma"),
+            vec![
+                "firefox (cask) is locked at 130.0 but the index has 131.0",
+                "tree is locked at 2.1.1 but the index has 2.2.1",
+            ]
+        );
+
+        let on_linux = frozen_violations(&lockfile, &actions, &formulae, &casks, "x86_64_linux");
+        assert!(on_linux.contains(
+            &"jq is locked for platform arm64_sonoma but this machine is x86_64_linux".to_string()
+        ));
+    }
+
+    #[test]
+    fn source_builds_at_the_locked_version_count_as_synced() {
+        let mut lockfile = Lockfile::new();
+        for (name, version) in [("jq", "1.7.1"), ("tree", "2.2.1_0"), ("wget", "1.24.5")] {
+            lockfile.packages.insert(
+                name.to_string(),
+                LockfilePackage {
+                    version: version.to_string(),
+                    bottle: "arm64_sonoma".to_string(),
+                },
+            );
+        }
+
+        let installed: HashMap<String, crate::install::InstalledPackage> = [
+            ("jq", "1.7.1", "macos-aarch64", true),
+            ("tree", "2.2.1", "arm64_sonoma", false),
+            ("wget", "1.24.5", "x86_64_linux", false),
+        ]
+        .into_iter()
+        .map(|(name, version, platform, from_source)| {
+            let pkg = crate::install::InstalledPackage {
+                platform: platform.to_string(),
+                from_source,
+                ..installed_package(name, version)
+            };
+            (name.to_string(), pkg)
+        })
+        .collect();
+
+        let actions = compute_sync_actions(&lockfile, &installed, &HashMap::new());
+        assert_eq!(actions.up_to_date, vec!["jq", "tree"]);
+        let to_install: Vec<&str> = actions
+            .packages_to_install
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(to_install, vec!["wget"]);
+        assert!(actions.upgrades.is_empty());
+    }
+
+    #[test]
+    fn locked_dependencies_come_from_the_index_graph() {
+        let mut pkgconf = formula("pkgconf", &[]);
+        pkgconf.aliases = vec!["pkg-config".to_string()];
+        let formulae = vec![
+            formula("wget", &["openssl@3", "libidn2"]),
+            formula("openssl@3", &["ca-certificates"]),
+            formula("ca-certificates", &[]),
+            formula("tool", &["pkg-config"]),
+            pkgconf,
+        ];
+        let mut lockfile = Lockfile::new();
+        for name in ["wget", "openssl@3", "ca-certificates", "tool", "pkgconf"] {
+            lockfile.packages.insert(
+                name.to_string(),
+                LockfilePackage {
+                    version: "1.0".to_string(),
+                    bottle: "arm64_sonoma".to_string(),
+                },
+            );
+        }
+
+        let mut dependencies: Vec<String> = locked_dependencies(&lockfile, &formulae)
+            .into_iter()
+            .collect();
+        dependencies.sort();
+        assert_eq!(
+            dependencies,
+            vec!["ca-certificates", "openssl@3", "pkgconf"]
+        );
+    }
+
+    #[test]
+    fn entries_are_ordered_dependencies_first() {
+        let formulae: Vec<crate::api::Formula> = [
+            ("wget", vec!["openssl@3", "libidn2"]),
+            ("openssl@3", vec!["ca-certificates"]),
+            ("libidn2", vec!["libunistring"]),
+            ("ca-certificates", vec![]),
+            ("aaa-tool", vec!["pkg-config"]),
+            ("pkgconf", vec![]),
+        ]
+        .into_iter()
+        .map(|(name, deps)| {
+            let mut formula = formula(name, &deps);
+            if name == "pkgconf" {
+                formula.aliases = vec!["pkg-config".to_string()];
+            }
+            formula
+        })
+        .collect();
+
+        // A `HashMap`-ordered lockfile can list dependents first.
+        let entries = [
+            "wget",
+            "aaa-tool",
+            "libidn2",
+            "openssl@3",
+            "pkgconf",
+            "ca-certificates",
+        ]
+        .into_iter()
+        .map(|name| SyncEntry {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            platform: "arm64_sonoma".to_string(),
+            url: String::new(),
+            sha256: String::new(),
+        })
+        .collect();
+
+        let order: Vec<String> = in_dependency_order(entries, &formulae)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        let position = |name: &str| order.iter().position(|n| n == name).unwrap();
+        assert_eq!(order.len(), 6);
+        assert!(position("ca-certificates") < position("openssl@3"));
+        assert!(position("openssl@3") < position("wget"));
+        assert!(position("libidn2") < position("wget"));
+        assert!(position("pkgconf") < position("aaa-tool"));
+    }
+
+    #[test]
+    fn drift_report_lists_missing_changed_and_platform_entries() {
+        let mut lockfile = Lockfile::new();
+        for (name, version, bottle) in [
+            ("jq", "1.7.1", "arm64_sonoma"),
+            ("tree", "2.2.1", "arm64_sonoma"),
+            ("wget", "1.24.5", "arm64_sonoma"),
+            ("zstd", "1.5.6", "arm64_sonoma"),
+        ] {
+            lockfile.packages.insert(
+                name.to_string(),
+                LockfilePackage {
+                    version: version.to_string(),
+                    bottle: bottle.to_string(),
+                },
+            );
+        }
+        lockfile.casks.insert(
+            "firefox".to_string(),
+            LockfileCask {
+                version: "131.0".to_string(),
+            },
+        );
+
+        let installed: HashMap<String, crate::install::InstalledPackage> = [
+            ("tree", "2.1.1", "arm64_sonoma"),
+            ("wget", "1.24.5", "x86_64_linux"),
+            ("zstd", "1.5.6", "arm64_sonoma"),
+        ]
+        .into_iter()
+        .map(|(name, version, platform)| {
+            let pkg = crate::install::InstalledPackage {
+                platform: platform.to_string(),
+                ..installed_package(name, version)
+            };
+            (name.to_string(), pkg)
+        })
+        .collect();
+
+        let actions = compute_sync_actions(&lockfile, &installed, &HashMap::new());
+        assert_eq!(
+            drift_report(&lockfile, &actions, &installed, &HashMap::new()),
+            vec![
+                "firefox (cask): missing (locked 131.0)",
+                "jq: missing (locked 1.7.1)",
+                "tree: installed 2.1.1, locked 2.2.1",
+                "wget: installed for x86_64_linux, locked for arm64_sonoma",
+            ]
+        );
+    }
+}
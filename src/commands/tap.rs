@@ -1,13 +1,51 @@
 use crate::cache::Cache;
 use crate::error::Result;
-use crate::tap::{TapKind, TapManager};
+use crate::tap::{Tap, TapChanges, TapKind, TapManager};
+use clap::Subcommand;
 use console::style;
 
-pub async fn tap(
-    action: Option<crate::TapAction>,
-    repair: bool,
-    cache: Option<&Cache>,
-) -> Result<()> {
+#[derive(Subcommand)]
+pub enum TapAction {
+    #[command(about = "Add a custom tap")]
+    Add {
+        #[arg(help = "Tap specification: user/repo, Git URL, local directory, or .rb file path")]
+        tap: String,
+        #[arg(long, help = "Trust this tap for formula discovery and installs")]
+        trust: bool,
+    },
+    #[command(
+        about = "Remove a custom tap",
+        visible_alias = "rm",
+        alias = "uninstall",
+        alias = "delete"
+    )]
+    Remove {
+        #[arg(help = "Tap specification: user/repo, Git URL, local directory, or .rb file path")]
+        tap: String,
+    },
+    #[command(about = "List installed taps", visible_alias = "ls")]
+    List,
+    #[command(about = "Update a tap", visible_alias = "up")]
+    Update {
+        #[arg(help = "Tap specification: user/repo, Git URL, local directory, or .rb file path")]
+        tap: String,
+    },
+    #[command(about = "Trust a tap for formula discovery and installs")]
+    Trust {
+        #[arg(help = "Tap specification: user/repo, Git URL, local directory, or .rb file path")]
+        tap: String,
+    },
+    #[command(about = "Remove trust from a tap")]
+    Untrust {
+        #[arg(help = "Tap specification: user/repo, Git URL, local directory, or .rb file path")]
+        tap: String,
+    },
+    /// Bare `wax tap user/repo` — treated as an add.
+    #[command(external_subcommand)]
+    External(Vec<String>),
+}
+
+pub async fn tap(action: Option<TapAction>, repair: bool, cache: Option<&Cache>) -> Result<()> {
     let mut manager = TapManager::new()?;
     manager.load().await?;
 
@@ -24,8 +62,9 @@ pub async fn tap(
     }
 
     match action {
-        Some(crate::TapAction::Add { tap, trust }) => {
+        Some(TapAction::Add { tap, trust }) => {
             manager.add_tap_with_trust(&tap, trust).await?;
+            let full_name = Tap::from_spec(&tap)?.full_name;
             if let Some(cache) = cache {
                 cache.invalidate_all_tap_caches().await?;
             }
@@ -44,8 +83,9 @@ pub async fn tap(
                     style("(untrusted; run `wax tap trust` to enable formula installs)").yellow()
                 );
             }
+            warn_if_no_formulae(&manager, &full_name).await;
         }
-        Some(crate::TapAction::External(args)) => {
+        Some(TapAction::External(args)) => {
             // `wax tap user/repo` without the `add` subcommand — treat as add.
             let tap = args.into_iter().next().unwrap_or_default();
             if tap.is_empty() {
@@ -58,8 +98,9 @@ pub async fn tap(
                 cache.invalidate_all_tap_caches().await?;
             }
             println!("{} tap {}", style("+").green(), style(&tap).magenta());
+            warn_if_no_formulae(&manager, &Tap::from_spec(&tap)?.full_name).await;
         }
-        Some(crate::TapAction::Remove { tap }) => {
+        Some(TapAction::Remove { tap }) => {
             let tap_spec = crate::tap::Tap::from_spec(&tap)?;
             let full_name = tap_spec.full_name.clone();
             manager.remove_tap(&tap).await?;
@@ -68,7 +109,7 @@ pub async fn tap(
             }
             println!("{} tap {}", style("-").red(), style(&tap).magenta());
         }
-        Some(crate::TapAction::Trust { tap }) => {
+        Some(TapAction::Trust { tap }) => {
             manager.set_trust(&tap, true).await?;
             if let Some(cache) = cache {
                 cache.invalidate_all_tap_caches().await?;
@@ -80,7 +121,7 @@ pub async fn tap(
                 style("(trusted)").green()
             );
         }
-        Some(crate::TapAction::Untrust { tap }) => {
+        Some(TapAction::Untrust { tap }) => {
             manager.set_trust(&tap, false).await?;
             if let Some(cache) = cache {
                 cache.invalidate_all_tap_caches().await?;
@@ -92,14 +133,14 @@ pub async fn tap(
                 style("(untrusted)").yellow()
             );
         }
-        Some(crate::TapAction::Update { tap }) => {
+        Some(TapAction::Update { tap }) => {
             let tap_spec = crate::tap::Tap::from_spec(&tap)?;
             let is_local = matches!(
                 tap_spec.kind,
                 TapKind::LocalDir { .. } | TapKind::LocalFile { .. }
             );
 
-            manager.update_tap(&tap).await?;
+            let changes = manager.update_tap(&tap).await?;
             if let Some(cache) = cache {
                 cache.invalidate_tap_cache(&tap_spec.full_name).await?;
             }
@@ -112,13 +153,17 @@ pub async fn tap(
                 );
             } else {
                 println!(
-                    "{} updated tap {}",
+                    "{} updated tap {} {}",
                     style("✓").green(),
-                    style(&tap).magenta()
+                    style(&tap).magenta(),
+                    style(describe_changes(&changes)).dim()
                 );
             }
+            if changes.total == 0 {
+                warn_no_formulae(&tap);
+            }
         }
-        Some(crate::TapAction::List) | None => {
+        Some(TapAction::List) | None => {
             let taps = manager.list_taps();
 
             if taps.is_empty() {
@@ -157,3 +202,44 @@ pub async fn tap(
 
     Ok(())
 }
+
+/// A tap that clones fine but has no `Formula/*.rb` is almost always the wrong repo;
+/// say so now rather than at install time.
+async fn warn_if_no_formulae(manager: &TapManager, full_name: &str) {
+    let Some(tap) = manager.get_tap(full_name) else {
+        return;
+    };
+    if manager
+        .load_formulae_from_tap(tap)
+        .await
+        .map(|formulae| formulae.is_empty())
+        .unwrap_or(true)
+    {
+        warn_no_formulae(full_name);
+    }
+}
+
+fn warn_no_formulae(tap: &str) {
+    eprintln!(
+        "{} tap {} has no formulae (no Formula/*.rb found); it may not be a Homebrew tap",
+        style("warning:").yellow(),
+        style(tap).magenta()
+    );
+}
+
+fn describe_changes(changes: &TapChanges) -> String {
+    if changes.is_empty() {
+        return format!("(no formula changes, {} total)", changes.total);
+    }
+    let mut parts = Vec::new();
+    if changes.added > 0 {
+        parts.push(format!("{} added", changes.added));
+    }
+    if changes.updated > 0 {
+        parts.push(format!("{} updated", changes.updated));
+    }
+    if changes.removed > 0 {
+        parts.push(format!("{} removed", changes.removed));
+    }
+    format!("({}, {} total)", parts.join(", "), changes.total)
+}
@@ -1,28 +1,37 @@
+use crate::api::{CaskArtifact, CaskDetails};
 use crate::cache::Cache;
-use crate::cask::CaskState;
-use crate::discovery::discover_manually_installed_casks;
+use crate::cask::{CaskState, InstalledCask};
+use crate::discovery::{discover_manually_installed_casks, resolve_installed_kind, InstalledKind};
 use crate::error::{Result, WaxError};
-use crate::install::{remove_symlinks, InstallState};
+use crate::install::{
+    prune_empty_dirs, remove_dangling_opt_link, remove_dangling_symlinks, remove_symlinks,
+    InstallState, InstalledPackage,
+};
 use crate::lockfile::Lockfile;
 use crate::signal::{clear_current_op, set_current_op};
-use crate::ui::dirs;
+use crate::ui::confirm_prompt;
 use crate::ui::SPINNER_TICK_CHARS;
+use crate::ui::{dir_size, dirs, format_bytes};
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
-use inquire::Confirm;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 #[cfg(target_os = "windows")]
 use crate::windows_state::{self, WindowsPackageManifest};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn uninstall(
     cache: &Cache,
     formulae: &[String],
     dry_run: bool,
     cask: bool,
+    zap: bool,
     yes: bool,
     all: bool,
+    state_only: bool,
+    autoremove: bool,
 ) -> Result<()> {
     let names: Vec<String> = if all {
         #[cfg(target_os = "windows")]
@@ -55,6 +64,10 @@ pub async fn uninstall(
         formulae.to_vec()
     };
 
+    if cask && !state_only && !cfg!(target_os = "windows") {
+        return uninstall_casks(cache, &names, dry_run, zap).await;
+    }
+
     let total = names.len();
     let start = Instant::now();
 
@@ -68,7 +81,11 @@ pub async fn uninstall(
         } else {
             String::new()
         };
-        uninstall_impl(cache, name, dry_run, cask, yes, false, &prefix).await?;
+        if state_only {
+            uninstall_state_only(name, dry_run, cask, &prefix).await?;
+        } else {
+            uninstall_impl(cache, name, dry_run, cask, zap, yes, false, &prefix).await?;
+        }
     }
     clear_current_op();
 
@@ -81,19 +98,215 @@ pub async fn uninstall(
         );
     }
 
+    if autoremove && !cask && !state_only && !cfg!(target_os = "windows") {
+        autoremove_orphans(cache, &names, dry_run, yes).await?;
+    }
+
     Ok(())
 }
 
+/// Dependencies of `removed` that nothing else still needs: installed only as a
+/// dependency, not pinned, and not required by any package that stays. Removing one can
+/// orphan its own dependencies in turn, so this repeats until nothing new turns up.
+fn orphaned_dependencies(
+    removed: &[String],
+    installed: &HashMap<String, InstalledPackage>,
+    dependencies: &HashMap<&str, &[String]>,
+) -> Vec<String> {
+    let deps_of = |name: &str| dependencies.get(name).copied().unwrap_or_default();
+    let mut gone: HashSet<&str> = removed.iter().map(String::as_str).collect();
+    let mut orphans = Vec::new();
+    loop {
+        let required: HashSet<&str> = installed
+            .keys()
+            .filter(|name| !gone.contains(name.as_str()))
+            .flat_map(|name| deps_of(name))
+            .map(String::as_str)
+            .collect();
+        let mut found: Vec<&str> = gone
+            .iter()
+            .flat_map(|name| deps_of(name))
+            .map(String::as_str)
+            .filter(|dep| !gone.contains(dep) && !required.contains(dep))
+            .filter(|dep| {
+                installed
+                    .get(*dep)
+                    .is_some_and(|pkg| pkg.installed_as_dependency && !pkg.pinned)
+            })
+            .collect();
+        if found.is_empty() {
+            break;
+        }
+        found.sort_unstable();
+        found.dedup();
+        for dep in found {
+            gone.insert(dep);
+            orphans.push(dep.to_string());
+        }
+    }
+    orphans
+}
+
+/// Packages that stay installed but have no index entry, e.g. from an untapped tap. Their
+/// dependencies are unknown, so any dependency-only package could still be theirs.
+fn unindexed_dependents<'a>(
+    removed: &[String],
+    installed: &'a HashMap<String, InstalledPackage>,
+    dependencies: &HashMap<&str, &[String]>,
+) -> Vec<&'a str> {
+    let mut unindexed: Vec<&str> = installed
+        .keys()
+        .map(String::as_str)
+        .filter(|name| !removed.iter().any(|r| r == name) && !dependencies.contains_key(name))
+        .collect();
+    unindexed.sort_unstable();
+    unindexed
+}
+
+/// `wax uninstall --autoremove`: after removing `removed`, offer to remove the
+/// dependencies they leave behind, like `apt --auto-remove`.
+async fn autoremove_orphans(
+    cache: &Cache,
+    removed: &[String],
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    let installed = InstallState::new()?.load().await?;
+    // A package whose uninstall was cancelled is still installed and still needs its deps.
+    let removed: Vec<String> = removed
+        .iter()
+        .filter(|name| dry_run || !installed.contains_key(*name))
+        .cloned()
+        .collect();
+    let formulae = cache.load_all_formulae().await?;
+    let dependencies: HashMap<&str, &[String]> = formulae
+        .iter()
+        .map(|f| {
+            (
+                f.name.as_str(),
+                f.dependencies.as_deref().unwrap_or_default(),
+            )
+        })
+        .collect();
+    let unindexed = unindexed_dependents(&removed, &installed, &dependencies);
+    if !unindexed.is_empty() {
+        eprintln!(
+            "{} skipping autoremove: no index entry for {}, so the dependencies {} still \
+             needs are unknown",
+            style("note:").yellow(),
+            unindexed.join(", "),
+            if unindexed.len() == 1 { "it" } else { "they" }
+        );
+        return Ok(());
+    }
+    let orphans = orphaned_dependencies(&removed, &installed, &dependencies);
+    if orphans.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "\n{} no longer needed by any installed package:",
+        if orphans.len() == 1 {
+            "1 dependency is".to_string()
+        } else {
+            format!("{} dependencies are", orphans.len())
+        }
+    );
+    for name in &orphans {
+        let version = installed
+            .get(name)
+            .map(|p| p.version.as_str())
+            .unwrap_or("");
+        println!(
+            "  - {}{}",
+            style(name).magenta(),
+            style(format!("@{}", version)).dim()
+        );
+    }
+    if !dry_run && !confirm_prompt(yes, "Remove them too?", false)? {
+        println!(
+            "kept {} {}",
+            if orphans.len() == 1 { "it" } else { "them" },
+            style("(pass --yes to remove them without asking)").dim()
+        );
+        return Ok(());
+    }
+
+    for name in &orphans {
+        uninstall_impl(cache, name, dry_run, false, false, true, false, "").await?;
+    }
+    Ok(())
+}
+
+/// Drop the recorded install state for a package without touching the Cellar, symlinks,
+/// or the lockfile. Used to reconcile `installed.json` after manual edits or crashes.
+async fn uninstall_state_only(name: &str, dry_run: bool, cask: bool, prefix: &str) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = (name, dry_run, cask, prefix);
+        return crate::error::reject_homebrew_cli("uninstall --state-only");
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let state = InstallState::new()?;
+        let cask_state = CaskState::new()?;
+        let (version, is_cask) = if !cask {
+            match state.load().await?.get(name) {
+                Some(pkg) => (pkg.version.clone(), false),
+                None => match cask_state.load().await?.get(name) {
+                    Some(c) => (c.version.clone(), true),
+                    None => return Err(WaxError::NotInstalled(name.to_string())),
+                },
+            }
+        } else {
+            match cask_state.load().await?.get(name) {
+                Some(c) => (c.version.clone(), true),
+                None => return Err(WaxError::NotInstalled(name.to_string())),
+            }
+        };
+
+        if dry_run {
+            println!(
+                "{}would forget {}@{} {}",
+                prefix,
+                style(name).magenta(),
+                style(&version).dim(),
+                style("(state only)").dim()
+            );
+            return Ok(());
+        }
+
+        if is_cask {
+            cask_state.remove(name).await?;
+        } else {
+            state.remove(name).await?;
+        }
+
+        println!(
+            "{} {}{}{} {}",
+            style("✗").red().bold(),
+            prefix,
+            style(name).magenta(),
+            style(format!("@{}", version)).dim(),
+            style("(state only, files kept)").dim(),
+        );
+        Ok(())
+    }
+}
+
 pub async fn uninstall_quiet(cache: &Cache, formula_name: &str, cask: bool) -> Result<()> {
-    uninstall_impl(cache, formula_name, false, cask, true, true, "").await
+    uninstall_impl(cache, formula_name, false, cask, false, true, true, "").await
 }
 
 #[cfg_attr(target_os = "windows", allow(unused_variables, unreachable_code))]
+#[allow(clippy::too_many_arguments)]
 async fn uninstall_impl(
     cache: &Cache,
     formula_name: &str,
     dry_run: bool,
     cask: bool,
+    zap: bool,
     yes: bool,
     quiet: bool,
     prefix: &str,
@@ -112,30 +325,29 @@ async fn uninstall_impl(
     }
 
     if cask {
-        return uninstall_cask(cache, formula_name, dry_run, start, quiet).await;
+        return uninstall_cask(cache, formula_name, dry_run, zap, start, quiet).await;
     }
 
-    let state = InstallState::new()?;
-    let installed_packages = state.load().await?;
-
-    let package = if let Some(pkg) = installed_packages.get(formula_name) {
-        pkg.clone()
-    } else {
-        let cask_state = CaskState::new()?;
-        let installed_casks = cask_state.load().await?;
-
-        if installed_casks.contains_key(formula_name) {
-            return uninstall_cask(cache, formula_name, dry_run, start, quiet).await;
+    let formula_name = match resolve_installed_kind(cache, formula_name, dry_run).await? {
+        Some(InstalledKind::Formula(key)) => key,
+        Some(InstalledKind::Cask(key)) => {
+            return uninstall_cask(cache, &key, dry_run, zap, start, quiet).await;
         }
+        None => return Err(WaxError::NotInstalled(formula_name.to_string())),
+    };
+    let formula_name = formula_name.as_str();
 
-        state.sync_from_cellar().await?;
-        let updated_packages = state.load().await?;
-
-        updated_packages
-            .get(formula_name)
-            .cloned()
-            .ok_or_else(|| WaxError::NotInstalled(formula_name.to_string()))?
+    let state = InstallState::new()?;
+    // A dry run saw the Cellar without saving it, so look at it the same way here.
+    let installed_packages = if dry_run {
+        state.load_with_cellar().await?
+    } else {
+        state.load().await?
     };
+    let package = installed_packages
+        .get(formula_name)
+        .cloned()
+        .ok_or_else(|| WaxError::NotInstalled(formula_name.to_string()))?;
 
     let formulae = cache.load_formulae().await?;
     let dependents: Vec<String> = formulae
@@ -157,19 +369,12 @@ async fn uninstall_impl(
             println!("  - {}", dep);
         }
 
-        if !dry_run && !yes {
-            let confirm = Confirm::new("Continue with uninstall?")
-                .with_default(false)
-                .prompt();
-
-            match confirm {
-                Ok(true) => {}
-                Ok(false) => {
-                    println!("uninstall cancelled");
-                    return Ok(());
-                }
-                Err(_) => return Ok(()),
-            }
+        if !dry_run && !confirm_prompt(yes, "Continue with uninstall?", false)? {
+            println!(
+                "uninstall cancelled {}",
+                style("(pass --yes to remove it anyway)").dim()
+            );
+            return Ok(());
         }
     }
 
@@ -185,13 +390,35 @@ async fn uninstall_package_direct(
     quiet: bool,
     prefix: &str,
 ) -> Result<()> {
+    let install_mode = package.install_mode;
+    let cellar = install_mode.cellar_path()?;
+    let keg_missing = !cellar.join(formula_name).join(&package.version).exists();
+
     if dry_run {
         if !quiet {
+            let links = if keg_missing {
+                remove_dangling_symlinks(formula_name, &cellar, true, install_mode).await?
+            } else {
+                remove_symlinks(formula_name, &package.version, &cellar, true, install_mode).await?
+            };
+            let size = dir_size(&cellar.join(formula_name));
             println!(
-                "{}would remove {}@{}",
+                "{}would remove {}@{} {}{}",
                 prefix,
                 style(formula_name).magenta(),
-                style(&package.version).dim()
+                style(&package.version).dim(),
+                style(format!(
+                    "({} symlink{}, {})",
+                    links.len(),
+                    if links.len() == 1 { "" } else { "s" },
+                    format_bytes(size)
+                ))
+                .dim(),
+                if keg_missing {
+                    format!(" {}", style("(keg already gone, state only)").dim())
+                } else {
+                    String::new()
+                }
             );
         }
         return Ok(());
@@ -219,9 +446,6 @@ async fn uninstall_package_direct(
         None
     };
 
-    let install_mode = package.install_mode;
-    let cellar = install_mode.cellar_path()?;
-
     if let Some(ref pb) = spinner {
         pb.set_message(format!(
             "{}removing {} {}",
@@ -238,6 +462,11 @@ async fn uninstall_package_direct(
         install_mode,
     )
     .await?;
+    let stale_links = if keg_missing {
+        remove_dangling_symlinks(formula_name, &cellar, false, install_mode).await?
+    } else {
+        Vec::new()
+    };
 
     if let Some(ref pb) = spinner {
         pb.set_message(format!(
@@ -257,6 +486,12 @@ async fn uninstall_package_direct(
             ))
         })?;
     }
+    // `remove_symlinks` only matches the recorded version, so an opt link left pointing at
+    // an older keg would dangle now that the whole formula directory is gone.
+    remove_dangling_opt_link(formula_name, &cellar, false, install_mode).await?;
+    if let Some(parent) = formula_dir.parent() {
+        prune_empty_dirs(parent, &cellar).await;
+    }
 
     state.remove(formula_name).await?;
 
@@ -273,13 +508,90 @@ async fn uninstall_package_direct(
     }
 
     if !quiet {
-        println!(
-            "{} {}{}{}{}",
-            style("✗").red().bold(),
-            prefix,
-            style(formula_name).magenta(),
-            style(format!("@{}", package.version)).dim(),
-            style(crate::ui::elapsed_suffix(start.elapsed())).dim(),
+        if keg_missing {
+            println!(
+                "{} {}cleaned up stale state for {}{} {}{}",
+                style("✓").green().bold(),
+                prefix,
+                style(formula_name).magenta(),
+                style(format!("@{}", package.version)).dim(),
+                style(format!(
+                    "(keg was already gone, {} dangling {} removed)",
+                    stale_links.len(),
+                    if stale_links.len() == 1 {
+                        "link"
+                    } else {
+                        "links"
+                    }
+                ))
+                .dim(),
+                style(crate::ui::elapsed_suffix(start.elapsed())).dim(),
+            );
+        } else {
+            println!(
+                "{} {}{}{}{}",
+                style("✗").red().bold(),
+                prefix,
+                style(formula_name).magenta(),
+                style(format!("@{}", package.version)).dim(),
+                style(crate::ui::elapsed_suffix(start.elapsed())).dim(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn app_bundle_basename(name: &str) -> String {
+    let basename = std::path::Path::new(name)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(name);
+    if basename.ends_with(".app") {
+        basename.to_string()
+    } else {
+        format!("{}.app", basename)
+    }
+}
+
+/// Remove an installed `.app` bundle from the Applications directories.
+async fn remove_app_bundle(app_basename: &str, quiet: bool) -> Result<()> {
+    // On macOS: check /Applications, then ~/Applications.
+    // On Linux: check ~/Applications only (no system /Applications).
+    #[cfg(target_os = "macos")]
+    let candidates: Vec<std::path::PathBuf> = vec![
+        std::path::PathBuf::from("/Applications").join(app_basename),
+        dirs::home_dir()
+            .map(|h| h.join("Applications").join(app_basename))
+            .unwrap_or_default(),
+    ];
+    #[cfg(not(target_os = "macos"))]
+    let candidates: Vec<std::path::PathBuf> = vec![dirs::home_dir()
+        .map(|h| h.join("Applications").join(app_basename))
+        .unwrap_or_default()];
+
+    let mut removed = false;
+    for app_path in &candidates {
+        if app_path.exists() {
+            #[cfg(target_os = "macos")]
+            if tokio::fs::remove_dir_all(app_path).await.is_err() {
+                // Fall back to sudo for system-installed apps.
+                crate::sudo::sudo_remove(app_path)?;
+                removed = true;
+                break;
+            }
+            #[cfg(not(target_os = "macos"))]
+            tokio::fs::remove_dir_all(app_path).await?;
+            removed = true;
+            break;
+        }
+    }
+
+    if !removed && !quiet {
+        eprintln!(
+            "warning: could not find {} in Applications — \
+            you may need to remove it manually",
+            app_basename
         );
     }
 
@@ -293,15 +605,7 @@ async fn resolve_cask_app_name(
     stored_app_name: Option<&str>,
 ) -> String {
     if let Some(name) = stored_app_name {
-        let basename = std::path::Path::new(name)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or(name);
-        return if basename.ends_with(".app") {
-            basename.to_string()
-        } else {
-            format!("{}.app", basename)
-        };
+        return app_bundle_basename(name);
     }
 
     if let Some(app_name) = find_app_in_caskroom(cask_name, version) {
@@ -313,15 +617,7 @@ async fn resolve_cask_app_name(
             for artifact in artifacts {
                 if let crate::api::CaskArtifact::App { app } = artifact {
                     if let Some(source) = app.first().and_then(|v| v.as_str()) {
-                        let basename = std::path::Path::new(source)
-                            .file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or(source);
-                        return if basename.ends_with(".app") {
-                            basename.to_string()
-                        } else {
-                            format!("{}.app", basename)
-                        };
+                        return app_bundle_basename(source);
                     }
                 }
             }
@@ -331,18 +627,17 @@ async fn resolve_cask_app_name(
     format!("{}.app", cask_name)
 }
 
-async fn uninstall_cask(
+/// Installed casks, plus any of `tokens` wax did not record itself: apps matching a
+/// known cask, and as a last resort a bare `.app` bundle in Applications.
+async fn load_installed_casks(
     cache: &Cache,
-    cask_name: &str,
-    dry_run: bool,
-    start: std::time::Instant,
-    quiet: bool,
-) -> Result<()> {
-    let state = CaskState::new()?;
+    state: &CaskState,
+    tokens: &[String],
+) -> Result<HashMap<String, InstalledCask>> {
     let mut installed_casks = state.load().await?;
 
-    // If cask not found, try discovering manually installed apps
-    if !installed_casks.contains_key(cask_name) {
+    // If a cask is not found, try discovering manually installed apps
+    if tokens.iter().any(|t| !installed_casks.contains_key(t)) {
         let casks = cache.load_casks().await?;
         if let Ok(discovered) = discover_manually_installed_casks(&casks).await {
             for (name, cask) in discovered {
@@ -352,7 +647,10 @@ async fn uninstall_cask(
     }
 
     // Last resort: check /Applications for a matching .app bundle
-    if !installed_casks.contains_key(cask_name) {
+    for cask_name in tokens {
+        if installed_casks.contains_key(cask_name) {
+            continue;
+        }
         let app_name = resolve_cask_app_name(cache, cask_name, "unknown", None).await;
         let app_candidates = [
             std::path::PathBuf::from("/Applications").join(&app_name),
@@ -367,7 +665,7 @@ async fn uninstall_cask(
                     .unwrap_or_else(|| "unknown".to_string());
                 installed_casks.insert(
                     cask_name.to_string(),
-                    crate::cask::InstalledCask {
+                    InstalledCask {
                         name: cask_name.to_string(),
                         version,
                         install_date: std::time::SystemTime::now()
@@ -382,6 +680,7 @@ async fn uninstall_cask(
                                 .map(|n| n.to_string_lossy().into_owned())
                                 .unwrap_or_default(),
                         ),
+                        additional_apps: None,
                     },
                 );
                 break;
@@ -389,35 +688,146 @@ async fn uninstall_cask(
         }
     }
 
+    Ok(installed_casks)
+}
+
+async fn uninstall_cask(
+    cache: &Cache,
+    cask_name: &str,
+    dry_run: bool,
+    zap: bool,
+    start: std::time::Instant,
+    quiet: bool,
+) -> Result<()> {
+    let state = CaskState::new()?;
+    let installed_casks = load_installed_casks(cache, &state, &[cask_name.to_string()]).await?;
     let cask = installed_casks
         .get(cask_name)
         .ok_or_else(|| WaxError::NotInstalled(cask_name.to_string()))?;
 
+    remove_cask(
+        cache, &state, cask_name, cask, zap, dry_run, start, quiet, "",
+    )
+    .await?;
+
+    if dry_run && !quiet {
+        println!(
+            "\ndry run - no changes made{}",
+            crate::ui::elapsed_suffix(start.elapsed())
+        );
+    }
+    Ok(())
+}
+
+/// `wax uninstall --cask a b c`: the cask state is loaded once and every token is
+/// processed even when an earlier one fails; failures are summarised at the end.
+async fn uninstall_casks(cache: &Cache, tokens: &[String], dry_run: bool, zap: bool) -> Result<()> {
+    if let [token] = tokens {
+        return uninstall_cask(cache, token, dry_run, zap, Instant::now(), false).await;
+    }
+
+    let start = Instant::now();
+    let state = CaskState::new()?;
+    let installed_casks = load_installed_casks(cache, &state, tokens).await?;
+
+    let total = tokens.len();
+    let mut removed = 0usize;
+    let mut failed_names = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let prefix = format!("[{}/{}] ", i + 1, total);
+        set_current_op(format!("removing {}", token));
+        let result = match installed_casks.get(token) {
+            Some(cask) => {
+                remove_cask(
+                    cache,
+                    &state,
+                    token,
+                    cask,
+                    zap,
+                    dry_run,
+                    Instant::now(),
+                    false,
+                    &prefix,
+                )
+                .await
+            }
+            None => Err(WaxError::NotInstalled(token.clone())),
+        };
+        match result {
+            Ok(()) => removed += 1,
+            Err(e) => {
+                eprintln!(
+                    "{} {} failed: {}",
+                    style("✗").red(),
+                    style(token).magenta(),
+                    e
+                );
+                failed_names.push(token.clone());
+            }
+        }
+    }
+    clear_current_op();
+
+    if dry_run {
+        println!(
+            "\ndry run - no changes made{}",
+            crate::ui::elapsed_suffix(start.elapsed())
+        );
+    } else if removed > 0 {
+        println!(
+            "\n{} {} removed{}",
+            style(removed).bold(),
+            if removed == 1 { "cask" } else { "casks" },
+            crate::ui::elapsed_suffix(start.elapsed())
+        );
+    }
+    if !failed_names.is_empty() {
+        return Err(WaxError::InstallError(format!(
+            "{} cask{} failed to uninstall: {}",
+            failed_names.len(),
+            if failed_names.len() == 1 { "" } else { "s" },
+            failed_names.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn remove_cask(
+    cache: &Cache,
+    state: &CaskState,
+    cask_name: &str,
+    cask: &InstalledCask,
+    zap: bool,
+    dry_run: bool,
+    start: std::time::Instant,
+    quiet: bool,
+    prefix: &str,
+) -> Result<()> {
     if dry_run {
         if !quiet {
-            println!("- {} (cask)", cask_name);
-            let elapsed = start.elapsed();
-            println!(
-                "\ndry run - no changes made{}",
-                crate::ui::elapsed_suffix(elapsed)
-            );
+            println!("{}- {} (cask)", prefix, cask_name);
+            if zap {
+                zap_cask(cache, cask_name, true, quiet).await;
+            }
         }
         return Ok(());
     }
+    // Binary symlinks can accompany any primary artifact (e.g. an app plus its
+    // CLI), so remove them regardless of the recorded artifact type.
+    if let Some(binary_paths) = &cask.binary_paths {
+        for binary_path in binary_paths {
+            let path = std::path::PathBuf::from(binary_path);
+            if tokio::fs::symlink_metadata(&path).await.is_ok() {
+                tokio::fs::remove_file(&path).await?;
+            }
+        }
+    }
 
     let artifact_type = cask.artifact_type.as_deref().unwrap_or("dmg");
 
     match artifact_type {
-        "tar.gz" | "binary" => {
-            if let Some(binary_paths) = &cask.binary_paths {
-                for binary_path in binary_paths {
-                    let path = std::path::PathBuf::from(binary_path);
-                    if path.exists() {
-                        tokio::fs::remove_file(&path).await?;
-                    }
-                }
-            }
-        }
+        "tar.gz" | "binary" => {}
         "pkg" => {
             if !quiet {
                 println!(
@@ -426,51 +836,27 @@ async fn uninstall_cask(
             }
         }
         _ => {
-            let app_basename =
+            let primary =
                 resolve_cask_app_name(cache, cask_name, &cask.version, cask.app_name.as_deref())
                     .await;
+            let mut app_basenames = vec![primary];
+            app_basenames.extend(
+                cask.additional_apps
+                    .iter()
+                    .flatten()
+                    .map(|name| app_bundle_basename(name)),
+            );
 
-            // On macOS: check /Applications, then ~/Applications.
-            // On Linux: check ~/Applications only (no system /Applications).
-            #[cfg(target_os = "macos")]
-            let candidates: Vec<std::path::PathBuf> = vec![
-                std::path::PathBuf::from("/Applications").join(&app_basename),
-                dirs::home_dir()
-                    .map(|h| h.join("Applications").join(&app_basename))
-                    .unwrap_or_default(),
-            ];
-            #[cfg(not(target_os = "macos"))]
-            let candidates: Vec<std::path::PathBuf> = vec![dirs::home_dir()
-                .map(|h| h.join("Applications").join(&app_basename))
-                .unwrap_or_default()];
-
-            let mut removed = false;
-            for app_path in &candidates {
-                if app_path.exists() {
-                    #[cfg(target_os = "macos")]
-                    if tokio::fs::remove_dir_all(app_path).await.is_err() {
-                        // Fall back to sudo for system-installed apps.
-                        crate::sudo::sudo_remove(app_path)?;
-                        removed = true;
-                        break;
-                    }
-                    #[cfg(not(target_os = "macos"))]
-                    tokio::fs::remove_dir_all(app_path).await?;
-                    removed = true;
-                    break;
-                }
-            }
-
-            if !removed && !quiet {
-                eprintln!(
-                    "warning: could not find {} in Applications — \
-                    you may need to remove it manually",
-                    app_basename
-                );
+            for app_basename in &app_basenames {
+                remove_app_bundle(app_basename, quiet).await?;
             }
         }
     }
 
+    if zap {
+        zap_cask(cache, cask_name, false, quiet).await;
+    }
+
     state.remove(cask_name).await?;
 
     let lockfile_path = Lockfile::default_path();
@@ -483,8 +869,9 @@ async fn uninstall_cask(
 
     if !quiet {
         println!(
-            "{} {}{}{}",
+            "{} {}{}{}{}",
             style("✗").red().bold(),
+            prefix,
             style(cask_name).magenta(),
             style(format!("@{} (cask)", cask.version)).dim(),
             style(crate::ui::elapsed_suffix(start.elapsed())).dim(),
@@ -494,6 +881,126 @@ async fn uninstall_cask(
     Ok(())
 }
 
+/// Paths named by a cask's `zap` stanza: `trash` and `delete` entries are removed,
+/// `rmdir` entries only when empty. Directives wax cannot carry out (`launchctl`,
+/// `pkgutil`, `signal`, …) are skipped.
+fn zap_targets(details: &CaskDetails) -> (Vec<String>, Vec<String>) {
+    let mut remove = Vec::new();
+    let mut rmdir = Vec::new();
+    for artifact in details.artifacts.iter().flatten() {
+        let CaskArtifact::Zap { zap } = artifact else {
+            continue;
+        };
+        for (key, value) in zap.iter().filter_map(|d| d.as_object()).flatten() {
+            let list = match key.as_str() {
+                "trash" | "delete" => &mut remove,
+                "rmdir" => &mut rmdir,
+                _ => continue,
+            };
+            match value {
+                serde_json::Value::String(path) => list.push(path.clone()),
+                serde_json::Value::Array(paths) => {
+                    list.extend(paths.iter().filter_map(|p| p.as_str().map(str::to_string)))
+                }
+                _ => {}
+            }
+        }
+    }
+    (remove, rmdir)
+}
+
+/// Existing paths for one zap entry. `~` is expanded and a `*` in the last path
+/// component matches any run of characters (`~/Library/Caches/org.mozilla.*`).
+fn expand_zap_path(pattern: &str) -> Vec<PathBuf> {
+    let path = PathBuf::from(shellexpand::tilde(pattern).into_owned());
+    let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+        return Vec::new();
+    };
+    if !file_name.contains('*') {
+        return if path.symlink_metadata().is_ok() {
+            vec![path]
+        } else {
+            Vec::new()
+        };
+    }
+
+    let escaped: Vec<String> = file_name.split('*').map(regex::escape).collect();
+    let Ok(re) = regex::Regex::new(&format!("^{}$", escaped.join(".*"))) else {
+        return Vec::new();
+    };
+    let Some(Ok(entries)) = path.parent().map(std::fs::read_dir) else {
+        return Vec::new();
+    };
+    let mut matches: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_name().to_str().is_some_and(|n| re.is_match(n)))
+        .map(|e| e.path())
+        .collect();
+    matches.sort();
+    matches
+}
+
+/// Remove the preferences, caches and support files a cask's `zap` stanza lists.
+/// Best effort: a path that cannot be removed is reported and skipped.
+async fn zap_cask(cache: &Cache, cask_name: &str, dry_run: bool, quiet: bool) {
+    let details = match cache.fetch_cask_details(cask_name).await {
+        Ok(details) => details,
+        Err(e) => {
+            eprintln!(
+                "{} could not load the zap stanza for {}: {}",
+                style("warning:").yellow(),
+                style(cask_name).magenta(),
+                e
+            );
+            return;
+        }
+    };
+    let (remove, rmdir) = zap_targets(&details);
+
+    let mut zapped = 0usize;
+    for path in remove.iter().flat_map(|p| expand_zap_path(p)) {
+        if dry_run {
+            println!("  would zap {}", style(path.display()).dim());
+            continue;
+        }
+        let is_dir = path.symlink_metadata().is_ok_and(|m| m.is_dir());
+        let removed = if is_dir {
+            tokio::fs::remove_dir_all(&path).await
+        } else {
+            tokio::fs::remove_file(&path).await
+        };
+        match removed {
+            Ok(()) => zapped += 1,
+            Err(e) => eprintln!(
+                "{} could not zap {}: {}",
+                style("warning:").yellow(),
+                path.display(),
+                e
+            ),
+        }
+    }
+    for path in rmdir.iter().flat_map(|p| expand_zap_path(p)) {
+        if dry_run {
+            println!(
+                "  would zap {} {}",
+                style(path.display()).dim(),
+                style("(if empty)").dim()
+            );
+        } else if tokio::fs::remove_dir(&path).await.is_ok() {
+            zapped += 1;
+        }
+    }
+
+    if !dry_run && !quiet && zapped > 0 {
+        println!(
+            "  {} zapped {} {}",
+            style(cask_name).magenta(),
+            zapped,
+            if zapped == 1 { "path" } else { "paths" }
+        );
+    }
+}
+
 fn find_app_in_caskroom(cask_name: &str, version: &str) -> Option<String> {
     let caskroom = CaskState::caskroom_dir();
     let version_dir = caskroom.join(cask_name).join(version);
@@ -600,9 +1107,128 @@ async fn uninstall_windows_package(
 mod tests {
     use super::*;
 
+    fn package(name: &str, as_dependency: bool, pinned: bool) -> (String, InstalledPackage) {
+        let pkg = InstalledPackage {
+            pinned,
+            installed_as_dependency: as_dependency,
+            ..crate::test_support::installed_package(name, "1.0")
+        };
+        (name.to_string(), pkg)
+    }
+
+    #[test]
+    fn orphaned_dependencies_follow_chains_and_keep_shared_ones() {
+        // app → libfoo → libbase; tool → libshared; app → libshared; app → libpinned.
+        let installed: HashMap<String, InstalledPackage> = [
+            package("tool", false, false),
+            package("libfoo", true, false),
+            package("libbase", true, false),
+            package("libshared", true, false),
+            package("libpinned", true, true),
+            package("wanted", false, false),
+        ]
+        .into_iter()
+        .collect();
+        let deps = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let (app, libfoo, tool) = (
+            deps(&["libfoo", "libshared", "libpinned", "wanted"]),
+            deps(&["libbase"]),
+            deps(&["libshared"]),
+        );
+        let dependencies: HashMap<&str, &[String]> = [
+            ("app", app.as_slice()),
+            ("libfoo", libfoo.as_slice()),
+            ("tool", tool.as_slice()),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            orphaned_dependencies(&["app".to_string()], &installed, &dependencies),
+            vec!["libfoo", "libbase"]
+        );
+        assert_eq!(
+            orphaned_dependencies(
+                &["app".to_string(), "tool".to_string()],
+                &installed,
+                &dependencies
+            ),
+            vec!["libfoo", "libshared", "libbase"]
+        );
+    }
+
+    #[test]
+    fn autoremove_is_held_back_by_dependents_missing_from_the_index() {
+        let installed: HashMap<String, InstalledPackage> = [
+            package("app", false, false),
+            package("tapped", false, false),
+            package("libfoo", true, false),
+        ]
+        .into_iter()
+        .collect();
+        let app = vec!["libfoo".to_string()];
+        let dependencies: HashMap<&str, &[String]> = [("app", app.as_slice()), ("libfoo", &[][..])]
+            .into_iter()
+            .collect();
+
+        // `tapped` has no index entry, so it may well need libfoo.
+        assert_eq!(
+            unindexed_dependents(&["app".to_string()], &installed, &dependencies),
+            vec!["tapped"]
+        );
+        assert!(
+            unindexed_dependents(&["tapped".to_string()], &installed, &dependencies).is_empty()
+        );
+    }
+
     #[test]
     fn test_find_app_in_caskroom_nonexistent() {
         let result = find_app_in_caskroom("nonexistent", "1.0.0");
         assert_eq!(result, None);
     }
+
+    #[test]
+    fn zap_stanza_paths_expand_wildcards() {
+        let details: CaskDetails = serde_json::from_value(serde_json::json!({
+            "token": "firefox",
+            "name": ["Firefox"],
+            "homepage": "",
+            "version": "128.0",
+            "url": "https://example.com/firefox.dmg",
+            "artifacts": [
+                { "app": ["Firefox.app"] },
+                { "zap": [{
+                    "trash": ["/tmp/prefs", "/tmp/caches/org.mozilla.*"],
+                    "delete": "/tmp/state",
+                    "rmdir": "/tmp/support",
+                    "launchctl": "org.mozilla.updater",
+                }] },
+            ],
+        }))
+        .unwrap();
+        let (remove, rmdir) = zap_targets(&details);
+        assert_eq!(
+            remove,
+            vec!["/tmp/state", "/tmp/prefs", "/tmp/caches/org.mozilla.*"]
+        );
+        assert_eq!(rmdir, vec!["/tmp/support"]);
+
+        let tmp = tempfile::tempdir().unwrap();
+        for name in [
+            "org.mozilla.firefox",
+            "org.mozilla.plugincontainer",
+            "org.other",
+        ] {
+            std::fs::create_dir(tmp.path().join(name)).unwrap();
+        }
+        let pattern = format!("{}/org.mozilla.*", tmp.path().display());
+        assert_eq!(
+            expand_zap_path(&pattern),
+            vec![
+                tmp.path().join("org.mozilla.firefox"),
+                tmp.path().join("org.mozilla.plugincontainer"),
+            ]
+        );
+        assert!(expand_zap_path(&format!("{}/missing", tmp.path().display())).is_empty());
+    }
 }
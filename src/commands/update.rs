@@ -1,13 +1,65 @@
+use crate::api::Formula;
 use crate::cache::{Cache, CacheMetadata};
 use crate::error::Result;
 use crate::signal::check_cancelled;
 use crate::tap::TapManager;
 use crate::ui::create_spinner;
 use console::style;
+use std::collections::HashMap;
 use tracing::instrument;
 
+/// Difference between the cached formula index and a freshly fetched one.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FormulaChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// `(name, old_version, new_version)`
+    pub updated: Vec<(String, String, String)>,
+}
+
+impl FormulaChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+}
+
+pub fn diff_formulae(old: &[Formula], new: &[Formula]) -> FormulaChanges {
+    let old_versions: HashMap<&str, String> = old
+        .iter()
+        .map(|f| (f.name.as_str(), f.full_version()))
+        .collect();
+    let new_versions: HashMap<&str, String> = new
+        .iter()
+        .map(|f| (f.name.as_str(), f.full_version()))
+        .collect();
+
+    let mut changes = FormulaChanges::default();
+    for (name, new_version) in &new_versions {
+        match old_versions.get(name) {
+            None => changes.added.push(name.to_string()),
+            Some(old_version) if old_version != new_version => {
+                changes
+                    .updated
+                    .push((name.to_string(), old_version.clone(), new_version.clone()))
+            }
+            Some(_) => {}
+        }
+    }
+    for name in old_versions.keys() {
+        if !new_versions.contains_key(name) {
+            changes.removed.push(name.to_string());
+        }
+    }
+
+    changes.added.sort();
+    changes.removed.sort();
+    changes.updated.sort();
+    changes
+}
+
 #[instrument(skip(cache))]
-pub async fn update(cache: &Cache) -> Result<()> {
+pub async fn update(cache: &Cache, show_changes: bool) -> Result<()> {
+    cache.ensure_cache_dir().await?;
     let spinner = create_spinner("Updating package index...");
 
     let start = std::time::Instant::now();
@@ -37,9 +89,15 @@ pub async fn update(cache: &Cache) -> Result<()> {
     let mut formulae_fetch = formulae_result?;
     let mut casks_fetch = casks_result?;
 
+    let mut formula_changes = None;
     let formula_count = if formulae_fetch.not_modified {
         cache.load_formulae().await?.len()
     } else if let Some(data) = formulae_fetch.data.take() {
+        if show_changes && cache.is_initialized() {
+            if let Ok(previous) = cache.load_formulae().await {
+                formula_changes = Some(diff_formulae(&previous, &data));
+            }
+        }
         let count = data.len();
         cache.save_formulae(&data).await?;
         count
@@ -100,9 +158,56 @@ pub async fn update(cache: &Cache) -> Result<()> {
 
     print_status(core_status, formula_count, cask_count, tap_count, elapsed);
 
+    if show_changes {
+        match formula_changes {
+            Some(changes) => print_changes(&changes),
+            None => println!("  {}", style("no formula changes").dim()),
+        }
+    }
+
     Ok(())
 }
 
+fn print_changes(changes: &FormulaChanges) {
+    if changes.is_empty() {
+        println!("  {}", style("no formula changes").dim());
+        return;
+    }
+
+    if !changes.updated.is_empty() {
+        println!(
+            "\n{} {}",
+            style("updated").cyan().bold(),
+            changes.updated.len()
+        );
+        for (name, old, new) in &changes.updated {
+            println!(
+                "  {} {} {} {}",
+                style(name).magenta(),
+                style(old).dim(),
+                style("→").dim(),
+                style(new).green()
+            );
+        }
+    }
+    if !changes.added.is_empty() {
+        println!("\n{} {}", style("new").green().bold(), changes.added.len());
+        for name in &changes.added {
+            println!("  {} {}", style("+").green(), style(name).magenta());
+        }
+    }
+    if !changes.removed.is_empty() {
+        println!(
+            "\n{} {}",
+            style("removed").red().bold(),
+            changes.removed.len()
+        );
+        for name in &changes.removed {
+            println!("  {} {}", style("-").red(), style(name).magenta());
+        }
+    }
+}
+
 async fn update_taps(cache: &Cache) -> Result<usize> {
     let mut tap_manager = TapManager::new()?;
     tap_manager.load().await?;
@@ -161,3 +266,52 @@ fn print_status(
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formula(name: &str, version: &str, revision: u32) -> Formula {
+        crate::test_support::formula_with(
+            name,
+            serde_json::json!({
+                "versions": { "stable": version, "bottle": true },
+                "revision": revision,
+            }),
+        )
+    }
+
+    #[test]
+    fn diff_formulae_reports_added_removed_and_bumped() {
+        let old = vec![
+            formula("jq", "1.7.0", 0),
+            formula("tree", "2.1.1", 0),
+            formula("wget", "1.24.5", 0),
+        ];
+        let new = vec![
+            formula("jq", "1.7.1", 0),
+            formula("tree", "2.1.1", 1),
+            formula("wget", "1.24.5", 0),
+            formula("zoxide", "0.9.4", 0),
+        ];
+        let changes = diff_formulae(&old, &new);
+        assert_eq!(changes.added, vec!["zoxide"]);
+        assert!(changes.removed.is_empty());
+        assert_eq!(
+            changes.updated,
+            vec![
+                ("jq".into(), "1.7.0".into(), "1.7.1".into()),
+                ("tree".into(), "2.1.1".into(), "2.1.1_1".into()),
+            ]
+        );
+
+        let changes = diff_formulae(&new, &old);
+        assert_eq!(changes.removed, vec!["zoxide"]);
+    }
+
+    #[test]
+    fn diff_formulae_identical_is_empty() {
+        let index = vec![formula("jq", "1.7.1", 0)];
+        assert!(diff_formulae(&index, &index).is_empty());
+    }
+}
@@ -1,11 +1,16 @@
-use crate::bottle::{detect_platform, homebrew_prefix, BottleDownloader, DownloadTotals};
+use crate::bottle::{
+    archive_file_name, detect_platform, homebrew_prefix, BottleDownloader, DownloadTotals,
+};
 use crate::cache::Cache;
-use crate::cask::{CaskState, InstalledCask};
+use crate::cask::versioned_token_base;
 use crate::commands::self_update::{self_update, Channel};
 use crate::commands::{install, uninstall};
-use crate::discovery::{discover_manually_installed_casks, normalize_package_token};
+use crate::discovery::{resolve_installed_kind, sync_cask_state, InstalledKind};
 use crate::error::{Result, WaxError};
-use crate::install::{is_writable, InstallMode, InstallState};
+use crate::install::{
+    is_writable, reconcile_installed_versions, relink_dangling_symlinks, InstallMode, InstallState,
+    VersionDrift,
+};
 use crate::signal::{
     check_cancelled, clear_active_multi, clear_current_op, set_active_multi, set_current_op,
     CriticalSection,
@@ -15,7 +20,9 @@ use crate::ui::{
     confirm_prompt, PROGRESS_BAR_CHARS, PROGRESS_BAR_PREFIX_TEMPLATE, PROGRESS_BAR_TEMPLATE,
     SPINNER_TICK_CHARS,
 };
-use crate::version::{is_same_or_newer, WAX_VERSION};
+use crate::version::{
+    is_head_version, is_same_or_newer, upgrade_status, UpgradeStatus, WAX_VERSION,
+};
 use console::style;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use std::collections::{HashMap, HashSet};
@@ -25,7 +32,7 @@ use tempfile::TempDir;
 use tokio::sync::mpsc;
 use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
-use tracing::instrument;
+use tracing::{debug, info, instrument};
 
 #[derive(Debug, Clone)]
 pub struct OutdatedPackage {
@@ -34,6 +41,28 @@ pub struct OutdatedPackage {
     pub latest_version: String,
     pub is_cask: bool,
     pub install_mode: Option<InstallMode>,
+    /// Index entry the outdated check resolved, reused by the upgrade itself.
+    pub formula: Option<crate::api::Formula>,
+    /// Cask details the outdated check fetched, reused by the upgrade itself.
+    pub cask: Option<crate::api::CaskDetails>,
+    /// Installed but missing from the index (renamed or removed upstream), so there is
+    /// nothing to upgrade to; `latest_version` is empty.
+    pub unknown: bool,
+}
+
+impl OutdatedPackage {
+    fn unknown(name: &str, installed_version: &str, is_cask: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            installed_version: installed_version.to_string(),
+            latest_version: String::new(),
+            is_cask,
+            install_mode: None,
+            formula: None,
+            cask: None,
+            unknown: true,
+        }
+    }
 }
 
 struct PreDownloaded {
@@ -77,34 +106,45 @@ impl Drop for UpgradeMultiGuard {
     }
 }
 
+/// The flags of `wax upgrade`.
+#[derive(Debug, Default)]
+pub struct UpgradeArgs {
+    pub dry_run: bool,
+    pub ask: bool,
+    /// Only upgrade packages in this prefix (`--user` / `--global`).
+    pub scope: Option<InstallMode>,
+    /// Upgrade source-built formulae from bottles instead of rebuilding them.
+    pub force_bottle: bool,
+}
+
 #[instrument(skip(cache))]
-pub async fn upgrade(
-    cache: &Cache,
-    packages: &[String],
-    dry_run: bool,
-    ask: bool,
-    scope: Option<InstallMode>,
-) -> Result<()> {
+pub async fn upgrade(cache: &Cache, packages: &[String], args: UpgradeArgs) -> Result<()> {
+    let UpgradeArgs {
+        dry_run,
+        ask,
+        scope,
+        force_bottle,
+    } = args;
     let start = std::time::Instant::now();
 
     cache.ensure_fresh().await?;
     refresh_taps(cache).await?;
 
     if packages.is_empty() {
-        upgrade_all(cache, dry_run, ask, start, scope).await
+        upgrade_all(cache, dry_run, ask, start, scope, force_bottle).await
     } else {
-        let installed_casks = sync_cask_state(cache).await?;
+        let installed_casks = sync_cask_state(cache, dry_run).await?;
         if ask && !dry_run {
             for package in packages {
                 if package == "wax" {
-                    upgrade_single(cache, package, true).await?;
+                    upgrade_single(cache, package, true, force_bottle).await?;
                 } else if installed_casks.contains_key(package) {
                     upgrade_cask_single(cache, package, true).await?;
                 } else {
-                    upgrade_single(cache, package, true).await?;
+                    upgrade_single(cache, package, true, force_bottle).await?;
                 }
             }
-            let proceed = confirm_prompt("Proceed with upgrade?")?;
+            let proceed = confirm_prompt(false, "Proceed with upgrade?", false)?;
             if !proceed {
                 println!("{} upgrade cancelled", style("✗").red());
                 return Ok(());
@@ -113,11 +153,11 @@ pub async fn upgrade(
         let mut failed_names = Vec::new();
         for package in packages {
             if let Err(e) = if package == "wax" {
-                upgrade_single(cache, package, dry_run).await
+                upgrade_single(cache, package, dry_run, force_bottle).await
             } else if installed_casks.contains_key(package) {
                 upgrade_cask_single(cache, package, dry_run).await
             } else {
-                upgrade_single(cache, package, dry_run).await
+                upgrade_single(cache, package, dry_run, force_bottle).await
             } {
                 eprintln!(
                     "{} {} failed: {}",
@@ -157,81 +197,6 @@ async fn refresh_taps(cache: &Cache) -> Result<()> {
     Ok(())
 }
 
-fn merge_discovered_casks(
-    installed_casks: &mut HashMap<String, InstalledCask>,
-    discovered_casks: HashMap<String, InstalledCask>,
-    caskroom_synced_names: &HashSet<String>,
-) {
-    for (name, discovered) in discovered_casks {
-        if let Some(app_key) = manual_app_key(&discovered) {
-            let stale_names = installed_casks
-                .iter()
-                .filter_map(|(installed_name, installed)| {
-                    if installed_name == &name || caskroom_synced_names.contains(installed_name) {
-                        return None;
-                    }
-                    (manual_app_key(installed).as_deref() == Some(app_key.as_str()))
-                        .then(|| installed_name.clone())
-                })
-                .collect::<Vec<_>>();
-            for stale_name in stale_names {
-                installed_casks.remove(&stale_name);
-            }
-        }
-
-        installed_casks
-            .entry(name.clone())
-            .and_modify(|installed| {
-                if !caskroom_synced_names.contains(&name) && discovered.version != "unknown" {
-                    installed.version = discovered.version.clone();
-                }
-                if !caskroom_synced_names.contains(&name) && discovered.install_date > 0 {
-                    installed.install_date = discovered.install_date;
-                }
-                if installed.artifact_type.is_none() {
-                    installed.artifact_type = discovered.artifact_type.clone();
-                }
-                if installed.binary_paths.is_none() {
-                    installed.binary_paths = discovered.binary_paths.clone();
-                }
-                if installed.app_name.is_none() {
-                    installed.app_name = discovered.app_name.clone();
-                }
-            })
-            .or_insert(discovered);
-    }
-}
-
-fn manual_app_key(cask: &InstalledCask) -> Option<String> {
-    if cask.artifact_type.as_deref() != Some("app") {
-        return None;
-    }
-
-    cask.app_name
-        .as_deref()
-        .map(normalize_package_token)
-        .filter(|name| !name.is_empty())
-}
-
-async fn sync_cask_state(cache: &Cache) -> Result<HashMap<String, InstalledCask>> {
-    let cask_state = CaskState::new()?;
-    let caskroom_synced_names = cask_state.sync_from_caskrooms().await?;
-
-    let mut installed_casks = cask_state.load().await?;
-    if cfg!(target_os = "macos") {
-        let casks = cache.load_casks().await?;
-        let discovered_casks = discover_manually_installed_casks(&casks).await?;
-        merge_discovered_casks(
-            &mut installed_casks,
-            discovered_casks,
-            &caskroom_synced_names,
-        );
-        cask_state.save(&installed_casks).await?;
-    }
-
-    Ok(installed_casks)
-}
-
 fn package_name_from_qualified_name(package_name: &str) -> &str {
     package_name.rsplit('/').next().unwrap_or(package_name)
 }
@@ -262,10 +227,15 @@ async fn apply_one_formula_package_upgrade(
     install_mode_global: InstallMode,
     platform: &str,
     install_state: &InstallState,
+    force_bottle: bool,
 ) -> Result<()> {
     check_cancelled()?;
 
     let label = pkg.name.to_string();
+    let options = installed_options(install_state, &pkg.name).await;
+    let was_dependency = installed_as_dependency(install_state, &pkg.name).await;
+    let from_source = !force_bottle && installed_from_source(install_state, &pkg.name).await;
+    let no_verify = installed_unverified_source(install_state, &pkg.name).await;
 
     let spinner = multi.insert_from_back(1, ProgressBar::new_spinner());
     spinner.set_style(
@@ -318,6 +288,8 @@ async fn apply_one_formula_package_upgrade(
                     install_state,
                     false,
                     true,
+                    true,
+                    false,
                     Some(multi),
                     Some(install_pb.clone()),
                 )
@@ -350,11 +322,18 @@ async fn apply_one_formula_package_upgrade(
                         cask: false,
                         user: user_flag,
                         global: global_flag,
-                        build_from_source: false,
+                        build_from_source: from_source,
                         head: false,
                         run_scripts: true,
                         quiet: true,
                         force_reinstall: false,
+                        options: &options,
+                        ignore_dependencies: false,
+                        only_dependencies: false,
+                        no_verify,
+                        no_relocate: false,
+                        platform: None,
+                        overwrite: false,
                         external_pb: Some(&pb),
                     },
                 )
@@ -366,18 +345,109 @@ async fn apply_one_formula_package_upgrade(
         Err(e) => Err(e),
     };
 
+    if result.is_ok() && was_dependency {
+        let _ = install_state
+            .set_installed_as_dependency(std::slice::from_ref(&pkg.name), true)
+            .await;
+    }
+
     clear_current_op();
     result
 }
 
+/// Build options recorded for an installed formula, reapplied when it is rebuilt.
+async fn installed_options(state: &InstallState, name: &str) -> Vec<String> {
+    state
+        .load()
+        .await
+        .ok()
+        .and_then(|installed| installed.get(name).map(|p| p.options.clone()))
+        .unwrap_or_default()
+}
+
+/// Whether an installed formula was built from source, so its upgrade rebuilds it too.
+async fn installed_from_source(state: &InstallState, name: &str) -> bool {
+    state
+        .load()
+        .await
+        .ok()
+        .and_then(|installed| installed.get(name).map(|p| p.from_source))
+        .unwrap_or(false)
+}
+
+/// Whether an installed formula was built from an unchecksummed source with `--no-verify`,
+/// so its rebuild keeps that opt-out instead of refusing the download.
+async fn installed_unverified_source(state: &InstallState, name: &str) -> bool {
+    state
+        .load()
+        .await
+        .ok()
+        .and_then(|installed| installed.get(name).map(|p| p.unverified_source))
+        .unwrap_or(false)
+}
+
+/// Whether an installed formula was pulled in as a dependency, kept across its rebuild.
+async fn installed_as_dependency(state: &InstallState, name: &str) -> bool {
+    state
+        .load()
+        .await
+        .ok()
+        .and_then(|installed| installed.get(name).map(|p| p.installed_as_dependency))
+        .unwrap_or(false)
+}
+
+/// Repoint prefix links the uninstall step missed at the freshly installed kegs, so the
+/// prefix never keeps links into the versions that were just removed. Every upgraded
+/// formula is handled in one sweep per prefix.
+async fn reconcile_upgraded_links(state: &InstallState, names: &[String]) {
+    if names.is_empty() {
+        return;
+    }
+    let Ok(installed) = state.load().await else {
+        return;
+    };
+    for mode in [InstallMode::Global, InstallMode::User] {
+        let kegs: Vec<(&str, &str)> = names
+            .iter()
+            .filter_map(|name| installed.get_key_value(name))
+            .filter(|(_, pkg)| pkg.install_mode == mode)
+            .map(|(name, pkg)| (name.as_str(), pkg.version.as_str()))
+            .collect();
+        if kegs.is_empty() {
+            continue;
+        }
+        let Ok(cellar) = mode.cellar_path() else {
+            continue;
+        };
+        match relink_dangling_symlinks(&kegs, &cellar, false, mode).await {
+            Ok(found) => {
+                for (name, found) in found {
+                    debug!(
+                        "{}: relinked {} and removed {} dangling links after upgrade",
+                        name,
+                        found.relinked.len(),
+                        found.removed.len()
+                    );
+                }
+            }
+            Err(e) => debug!("failed to reconcile links after upgrade: {}", e),
+        }
+    }
+}
+
 async fn upgrade_all(
     cache: &Cache,
     dry_run: bool,
     ask: bool,
     start: std::time::Instant,
     scope: Option<InstallMode>,
+    force_bottle: bool,
 ) -> Result<()> {
-    let outdated = get_outdated_packages_scoped(cache, scope).await?;
+    let outdated: Vec<OutdatedPackage> = get_outdated_packages_scoped(cache, scope)
+        .await?
+        .into_iter()
+        .filter(|pkg| !pkg.unknown)
+        .collect();
 
     if outdated.is_empty() {
         println!("all packages are up to date");
@@ -428,15 +498,13 @@ async fn upgrade_all(
             println!("\n{}", style("dry run - no changes made").dim());
             return Ok(());
         }
-        let proceed = confirm_prompt("Proceed with upgrade?")?;
+        let proceed = confirm_prompt(false, "Proceed with upgrade?", false)?;
         if !proceed {
             println!("{} upgrade cancelled", style("✗").red());
             return Ok(());
         }
     }
 
-    let formulae = cache.load_all_formulae().await?;
-
     let total = outdated.len();
 
     // Print plan summary
@@ -465,17 +533,25 @@ async fn upgrade_all(
 
     // --- Phase 0: pre-download all formula bottles concurrently ---
     let platform = detect_platform();
-    let formula_by_name: HashMap<&str, &crate::api::Formula> =
-        formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+    info!("Platform tag: {}", platform);
+
+    // Formulae installed with build options or built from source are rebuilt from
+    // source (unless bottles are forced), so they skip the bottle pre-download and
+    // take the fallback install path.
+    let source_rebuilds: HashSet<String> = InstallState::new()?
+        .load()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, p)| !p.options.is_empty() || (p.from_source && !force_bottle))
+        .map(|(name, _)| name)
+        .collect();
 
     let upgrade_formulae: Arc<HashMap<String, crate::api::Formula>> = Arc::new(
         formula_packages
             .iter()
-            .filter_map(|p| {
-                formula_by_name
-                    .get(p.name.as_str())
-                    .map(|f| (p.name.clone(), (*f).clone()))
-            })
+            .filter(|p| !source_rebuilds.contains(&p.name))
+            .filter_map(|p| Some((p.name.clone(), p.formula.clone()?)))
             .collect(),
     );
 
@@ -484,8 +560,9 @@ async fn upgrade_all(
     // Collect (name, url) for all formula bottles to be downloaded.
     let formula_bottle_urls: Vec<(String, String)> = formula_packages
         .iter()
+        .filter(|pkg| !source_rebuilds.contains(&pkg.name))
         .filter_map(|pkg| {
-            let formula = formula_by_name.get(pkg.name.as_str())?;
+            let formula = upgrade_formulae.get(&pkg.name)?;
             let bottle_info = formula.bottle.as_ref()?.stable.as_ref()?;
             let bottle_file = bottle_info.file_for_platform(&platform)?;
             Some((pkg.name.clone(), bottle_file.url.clone()))
@@ -671,7 +748,9 @@ async fn upgrade_all(
                     );
                     pb.set_prefix(name.clone());
 
-                    let tarball = tmp.path().join(format!("{}-{}.tar.gz", name, version));
+                    let tarball = tmp
+                        .path()
+                        .join(archive_file_name(&format!("{}-{}", name, version), &url));
 
                     let download_result = dl
                         .download(&url, &tarball, Some(&pb), conns, Some(totals.as_ref()))
@@ -680,7 +759,8 @@ async fn upgrade_all(
 
                     drop(permit);
 
-                    crate::digest::verify_sha256_file(&tarball, &sha256)?;
+                    crate::digest::verify_digest_file(&tarball, &sha256)
+                        .map_err(|e| e.for_download(&name, &url))?;
 
                     let extract_dir = tmp.path().join(&name);
                     BottleDownloader::extract(&tarball, &extract_dir)?;
@@ -748,6 +828,7 @@ async fn upgrade_all(
             let mut succ = 0usize;
             let mut fail = 0usize;
             let mut fails: Vec<String> = Vec::new();
+            let mut upgraded: Vec<String> = Vec::new();
             while let Some(msg) = rx.recv().await {
                 check_cancelled()?;
                 match msg {
@@ -771,6 +852,7 @@ async fn upgrade_all(
                             install_mode_global,
                             &platform,
                             &install_state,
+                            force_bottle,
                         )
                         .await
                         {
@@ -783,6 +865,7 @@ async fn upgrade_all(
                                     style(&pkg.latest_version).green()
                                 ));
                                 succ += 1;
+                                upgraded.push(pkg.name.clone());
                             }
                             Err(e) => {
                                 fail += 1;
@@ -806,6 +889,7 @@ async fn upgrade_all(
                             install_mode_global,
                             &platform,
                             &install_state,
+                            force_bottle,
                         )
                         .await
                         {
@@ -818,6 +902,7 @@ async fn upgrade_all(
                                     style(&pkg.latest_version).green()
                                 ));
                                 succ += 1;
+                                upgraded.push(pkg.name.clone());
                             }
                             Err(e) => {
                                 fail += 1;
@@ -836,6 +921,7 @@ async fn upgrade_all(
             producer_handle.await.map_err(|e| {
                 WaxError::InstallError(format!("formula upgrade producer task: {}", e))
             })??;
+            reconcile_upgraded_links(&install_state, &upgraded).await;
             Ok::<_, WaxError>((succ, fail, fails))
         }
     };
@@ -851,6 +937,11 @@ async fn upgrade_all(
                 check_cancelled()?;
                 let cask_names: Vec<String> =
                     cask_packages.iter().map(|p| p.name.clone()).collect();
+                for pkg in &cask_packages {
+                    if let Some(details) = &pkg.cask {
+                        cache.remember_cask_details(&pkg.name, details.clone());
+                    }
+                }
                 set_current_op(format!(
                     "upgrading {} casks",
                     cask_only_total_display.max(1)
@@ -869,6 +960,13 @@ async fn upgrade_all(
                         run_scripts: true,
                         quiet: true,
                         force_reinstall: true,
+                        options: &[],
+                        ignore_dependencies: false,
+                        only_dependencies: false,
+                        no_verify: false,
+                        no_relocate: false,
+                        platform: None,
+                        overwrite: false,
                         external_pb: None,
                     },
                 )
@@ -966,35 +1064,18 @@ async fn upgrade_all(
     Ok(())
 }
 
-async fn upgrade_single(cache: &Cache, formula_name: &str, dry_run: bool) -> Result<()> {
-    let state = InstallState::new()?;
-    state.sync_from_cellar().await?;
-    let installed_packages = state.load().await?;
+async fn upgrade_single(
+    cache: &Cache,
+    formula_name: &str,
+    dry_run: bool,
+    force_bottle: bool,
+) -> Result<()> {
     let installed_name = package_name_from_qualified_name(formula_name);
 
-    let installed = if let Some(pkg) = installed_packages
-        .get(formula_name)
-        .or_else(|| installed_packages.get(installed_name))
-    {
-        pkg.clone()
-    } else {
-        let installed_casks = sync_cask_state(cache).await?;
-
-        if installed_casks.contains_key(formula_name)
-            || installed_casks.contains_key(installed_name)
-        {
-            return upgrade_cask_single(cache, installed_name, dry_run).await;
-        }
-
-        let updated_packages = state.load().await?;
-
-        if let Some(pkg) = updated_packages
-            .get(formula_name)
-            .or_else(|| updated_packages.get(installed_name))
-            .cloned()
-        {
-            pkg
-        } else if formula_name == "wax" {
+    let key = match resolve_installed_kind(cache, formula_name, dry_run).await? {
+        Some(InstalledKind::Formula(key)) => key,
+        Some(InstalledKind::Cask(key)) => return upgrade_cask_single(cache, &key, dry_run).await,
+        None if formula_name == "wax" => {
             if dry_run {
                 println!(
                     "{}: {} → latest (self-update)",
@@ -1005,12 +1086,45 @@ async fn upgrade_single(cache: &Cache, formula_name: &str, dry_run: bool) -> Res
                 return Ok(());
             }
             return self_update(Channel::Stable, false, None).await;
-        } else {
-            return Err(WaxError::NotInstalled(formula_name.to_string()));
         }
+        None => return Err(WaxError::NotInstalled(formula_name.to_string())),
     };
 
-    upgrade_resolved_formula(cache, formula_name, installed_name, &installed, dry_run).await
+    let state = InstallState::new()?;
+    let mut installed_packages = if dry_run {
+        state.load_with_cellar().await?
+    } else {
+        state.load().await?
+    };
+    report_version_drift(&reconcile_installed_versions(
+        &mut installed_packages,
+        &HashMap::new(),
+    ));
+    let installed = installed_packages
+        .get(&key)
+        .cloned()
+        .ok_or_else(|| WaxError::NotInstalled(formula_name.to_string()))?;
+
+    upgrade_resolved_formula(
+        cache,
+        formula_name,
+        installed_name,
+        &installed,
+        dry_run,
+        force_bottle,
+    )
+    .await
+}
+
+fn up_to_date_notice(installed: &str, latest: &str) -> String {
+    if installed == latest {
+        format!("already up to date ({})", installed)
+    } else {
+        format!(
+            "already up to date (installed {} ≥ latest {})",
+            installed, latest
+        )
+    }
 }
 
 async fn upgrade_resolved_formula(
@@ -1019,6 +1133,7 @@ async fn upgrade_resolved_formula(
     installed_name: &str,
     installed: &crate::install::InstalledPackage,
     dry_run: bool,
+    force_bottle: bool,
 ) -> Result<()> {
     if installed.pinned {
         println!(
@@ -1030,20 +1145,31 @@ async fn upgrade_resolved_formula(
         return Ok(());
     }
 
+    if is_head_version(&installed.version) {
+        println!(
+            "{}@{} is a HEAD build — skipping (reinstall with `wax install --head {}` to rebuild from the tip)",
+            style(formula_name).magenta(),
+            style(&installed.version).dim(),
+            installed_name
+        );
+        return Ok(());
+    }
+
     let formulae = cache.load_all_formulae().await?;
-    let formula = formulae
-        .iter()
-        .find(|f| f.name == formula_name || f.full_name == formula_name)
+    let formula = crate::api::index_formulae(&formulae)
+        .get(formula_name)
+        .copied()
         .ok_or_else(|| WaxError::FormulaNotFound(formula_name.to_string()))?;
 
     let latest_version = formula.full_version();
     let installed_version = &installed.version;
 
-    if is_same_or_newer(installed_version, &latest_version) {
+    let status = upgrade_status(installed_version, &latest_version);
+    if status != UpgradeStatus::Outdated {
         println!(
-            "{} is already on the latest version ({}).",
+            "{} {}",
             style(formula_name).magenta(),
-            style(installed_version).dim()
+            up_to_date_notice(installed_version, &latest_version)
         );
         if dry_run {
             println!("\ndry run - no changes made");
@@ -1074,6 +1200,7 @@ async fn upgrade_resolved_formula(
         &installed.name,
         &formula.full_name,
         Some(installed.install_mode),
+        installed.from_source && !force_bottle,
     )
     .await?;
 
@@ -1087,29 +1214,34 @@ async fn upgrade_resolved_formula(
 }
 
 async fn upgrade_cask_single(cache: &Cache, cask_name: &str, dry_run: bool) -> Result<()> {
-    let installed_casks = sync_cask_state(cache).await?;
+    let installed_casks = sync_cask_state(cache, dry_run).await?;
 
     let installed = installed_casks
         .get(cask_name)
         .ok_or_else(|| WaxError::NotInstalled(cask_name.to_string()))?;
 
     let casks = cache.load_casks().await?;
-    let cask_summary = casks
+    let cask_index: HashMap<_, _> = casks
         .iter()
-        .find(|c| c.token == cask_name || c.full_token == cask_name)
+        .map(|c| (c.token.as_str(), c))
+        .chain(casks.iter().map(|c| (c.full_token.as_str(), c)))
+        .collect();
+    let token = cask_details_token(cask_name, &cask_index)
         .ok_or_else(|| WaxError::CaskNotFound(cask_name.to_string()))?;
 
-    let cask_details = cache.fetch_cask_details(&cask_summary.token).await?;
+    let cask_details = cache.fetch_cask_details(&token).await?;
+    cache.remember_cask_details(cask_name, cask_details.clone());
 
     let latest_version = &cask_details.version;
     let installed_version = &installed.version;
 
-    if is_same_or_newer(installed_version, latest_version) {
+    let status = upgrade_status(installed_version, latest_version);
+    if status != UpgradeStatus::Outdated {
         println!(
-            "{} {} is already on the latest version ({}).",
+            "{} {} {}",
             style(cask_name).magenta(),
             style("(cask)").yellow(),
-            style(installed_version).dim()
+            up_to_date_notice(installed_version, latest_version)
         );
         if dry_run {
             println!("\ndry run - no changes made");
@@ -1154,9 +1286,14 @@ async fn upgrade_formula_internal(
     installed_name: &str,
     formula_name: &str,
     install_mode: Option<InstallMode>,
+    build_from_source: bool,
 ) -> Result<()> {
     let _critical = CriticalSection::new();
 
+    let state = InstallState::new()?;
+    let options = installed_options(&state, installed_name).await;
+    let no_verify = installed_unverified_source(&state, installed_name).await;
+    let was_dependency = installed_as_dependency(&state, installed_name).await;
     uninstall::uninstall_quiet(cache, installed_name, false).await?;
 
     let (user_flag, global_flag) = match install_mode {
@@ -1175,16 +1312,30 @@ async fn upgrade_formula_internal(
             cask: false,
             user: user_flag,
             global: global_flag,
-            build_from_source: false,
+            build_from_source,
             head: false,
             run_scripts: true,
             quiet: true,
             force_reinstall: false,
+            options: &options,
+            ignore_dependencies: false,
+            only_dependencies: false,
+            no_verify,
+            no_relocate: false,
+            platform: None,
+            overwrite: false,
             external_pb: None,
         },
     )
     .await?;
 
+    reconcile_upgraded_links(&state, &[installed_name.to_string()]).await;
+    if was_dependency {
+        state
+            .set_installed_as_dependency(&[installed_name.to_string()], true)
+            .await?;
+    }
+
     Ok(())
 }
 
@@ -1206,6 +1357,13 @@ async fn upgrade_cask_internal(cache: &Cache, cask_name: &str) -> Result<()> {
             run_scripts: true,
             quiet: true,
             force_reinstall: true,
+            options: &[],
+            ignore_dependencies: false,
+            only_dependencies: false,
+            no_verify: false,
+            no_relocate: false,
+            platform: None,
+            overwrite: false,
             external_pb: None,
         },
     )
@@ -1257,33 +1415,104 @@ async fn load_packages_from_scope(
                 bottle_rebuild: 0,
                 bottle_sha256: None,
                 pinned: false,
+                options: Vec::new(),
+                installed_as_dependency: false,
+                unverified_source: false,
             },
         );
     }
     Ok(packages)
 }
 
+/// The version label to upgrade an installed formula to, or `None` when it is
+/// current. A newer bottle rebuild or a changed bottle checksum only counts when
+/// the installed version equals the latest; an installed version that sorts newer
+/// than the index is never "upgraded" (that would be a downgrade).
+fn formula_upgrade_target(
+    installed: &crate::install::InstalledPackage,
+    formula: &crate::api::Formula,
+    platform: &str,
+) -> Option<String> {
+    let latest = formula.full_version();
+    match upgrade_status(&installed.version, &latest) {
+        UpgradeStatus::Outdated => return Some(latest),
+        UpgradeStatus::Newer => return None,
+        UpgradeStatus::UpToDate => {}
+    }
+    if installed.version != latest {
+        return None;
+    }
+
+    if installed.bottle_rebuild < formula.bottle_rebuild() {
+        return Some(format!("{} (rebuild {})", latest, formula.bottle_rebuild()));
+    }
+
+    let sha_changed = installed.bottle_sha256.is_some()
+        && formula
+            .bottle
+            .as_ref()
+            .and_then(|b| b.stable.as_ref())
+            .and_then(|s| s.file_for_platform(platform))
+            .map(|f| Some(&f.sha256) != installed.bottle_sha256.as_ref())
+            .unwrap_or(false);
+    sha_changed.then(|| format!("{} (bottle updated)", latest))
+}
+
+/// Outdated decisions use the Cellar's active keg; say so when the recorded state disagreed.
+fn report_version_drift(drift: &[VersionDrift]) {
+    for d in drift {
+        let actual = d.on_disk.as_deref().unwrap_or(&d.recorded);
+        let mut detail = format!("recorded {}, installed {}", d.recorded, actual);
+        if let Some(reported) = &d.reported {
+            detail.push_str(&format!(", api reports {}", reported));
+        }
+        eprintln!(
+            "{} {} version drift ({}); using {}",
+            style("note:").yellow(),
+            style(&d.name).magenta(),
+            detail,
+            actual
+        );
+    }
+}
+
 pub async fn get_outdated_packages(cache: &Cache) -> Result<Vec<OutdatedPackage>> {
     get_outdated_packages_scoped(cache, None).await
 }
 
+/// Token whose API entry an installed cask is checked against. A versioned token
+/// (`firefox@esr`) installed without being in the index has its own entry, so it is
+/// compared against that, never the base cask's latest release.
+fn cask_details_token(name: &str, cask_index: &HashMap<&str, &crate::api::Cask>) -> Option<String> {
+    if let Some(cask) = cask_index.get(name) {
+        return Some(cask.token.clone());
+    }
+    versioned_token_base(name)
+        .filter(|base| cask_index.contains_key(base))
+        .map(|_| name.to_string())
+}
+
 pub async fn get_outdated_packages_scoped(
     cache: &Cache,
     scope: Option<InstallMode>,
 ) -> Result<Vec<OutdatedPackage>> {
     let state = InstallState::new()?;
     state.sync_from_cellar().await?;
-    let installed_packages = if let Some(mode) = scope {
+    let mut installed_packages = if let Some(mode) = scope {
         load_packages_from_scope(mode).await?
     } else {
         state.load().await?
     };
 
-    let installed_casks = sync_cask_state(cache).await?;
+    let installed_casks = sync_cask_state(cache, false).await?;
 
     let formulae = cache.load_all_formulae().await?;
     let casks = cache.load_casks().await?;
-    let formula_index: HashMap<_, _> = formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+    let formula_index = crate::api::index_formulae(&formulae);
+    report_version_drift(&reconcile_installed_versions(
+        &mut installed_packages,
+        &formula_index,
+    ));
     let cask_index: HashMap<_, _> = casks
         .iter()
         .map(|c| (c.token.as_str(), c))
@@ -1297,43 +1526,27 @@ pub async fn get_outdated_packages_scoped(
         if scope.is_some() && Some(installed.install_mode) != scope {
             continue;
         }
-        if installed.pinned {
+        // HEAD builds track the repository tip, not the stable release.
+        if installed.pinned || is_head_version(&installed.version) {
             continue;
         }
         if let Some(formula) = formula_index.get(name.as_str()) {
-            let latest = formula.full_version();
-            let version_outdated = !is_same_or_newer(&installed.version, &latest);
-
-            let rebuild_outdated = !version_outdated
-                && installed.version == latest
-                && installed.bottle_rebuild < formula.bottle_rebuild();
-
-            let sha_outdated = !version_outdated
-                && !rebuild_outdated
-                && installed.bottle_sha256.is_some()
-                && formula
-                    .bottle
-                    .as_ref()
-                    .and_then(|b| b.stable.as_ref())
-                    .and_then(|s| s.file_for_platform(&platform))
-                    .map(|f| Some(&f.sha256) != installed.bottle_sha256.as_ref())
-                    .unwrap_or(false);
-
-            if version_outdated || rebuild_outdated || sha_outdated {
+            if let Some(latest_version) = formula_upgrade_target(installed, formula, &platform) {
                 outdated.push(OutdatedPackage {
                     name: name.clone(),
                     installed_version: installed.version.clone(),
-                    latest_version: if rebuild_outdated {
-                        format!("{} (rebuild {})", latest, formula.bottle_rebuild())
-                    } else if sha_outdated {
-                        format!("{} (bottle updated)", latest)
-                    } else {
-                        latest
-                    },
+                    latest_version,
                     is_cask: false,
                     install_mode: Some(installed.install_mode),
+                    formula: Some((*formula).clone()),
+                    cask: None,
+                    unknown: false,
                 });
             }
+        } else {
+            let mut pkg = OutdatedPackage::unknown(name, &installed.version, false);
+            pkg.install_mode = Some(installed.install_mode);
+            outdated.push(pkg);
         }
     }
 
@@ -1341,19 +1554,37 @@ pub async fn get_outdated_packages_scoped(
         outdated.sort_by(|a, b| a.name.cmp(&b.name));
         return Ok(outdated);
     }
+    let tokens: Vec<String> = installed_casks
+        .keys()
+        .filter_map(|name| cask_details_token(name, &cask_index))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let details_by_token: HashMap<String, crate::api::CaskDetails> = cache
+        .fetch_cask_details_many(&tokens)
+        .await
+        .into_iter()
+        .filter_map(|(token, details)| Some((token, details.ok()?)))
+        .collect();
     for (name, installed) in &installed_casks {
-        if let Some(cask) = cask_index.get(name.as_str()) {
-            if let Ok(details) = cache.fetch_cask_details(&cask.token).await {
-                if !is_same_or_newer(&installed.version, &details.version) {
-                    outdated.push(OutdatedPackage {
-                        name: name.clone(),
-                        installed_version: installed.version.clone(),
-                        latest_version: details.version,
-                        is_cask: true,
-                        install_mode: None,
-                    });
-                }
-            }
+        let Some(token) = cask_details_token(name, &cask_index) else {
+            outdated.push(OutdatedPackage::unknown(name, &installed.version, true));
+            continue;
+        };
+        let Some(details) = details_by_token.get(&token) else {
+            continue;
+        };
+        if !is_same_or_newer(&installed.version, &details.version) {
+            outdated.push(OutdatedPackage {
+                name: name.clone(),
+                installed_version: installed.version.clone(),
+                latest_version: details.version.clone(),
+                is_cask: true,
+                install_mode: None,
+                formula: None,
+                cask: Some(details.clone()),
+                unknown: false,
+            });
         }
     }
 
@@ -1364,9 +1595,34 @@ pub async fn get_outdated_packages_scoped(
 
 #[cfg(test)]
 mod tests {
-    use super::{merge_discovered_casks, package_name_from_qualified_name};
-    use crate::cask::InstalledCask;
-    use std::collections::{HashMap, HashSet};
+    use super::{
+        cask_details_token, formula_upgrade_target, package_name_from_qualified_name,
+        up_to_date_notice,
+    };
+    use crate::test_support::{formula_with, installed_package};
+
+    #[test]
+    fn versioned_casks_missing_from_the_index_are_checked_against_their_own_entry() {
+        let firefox: crate::api::Cask = serde_json::from_value(serde_json::json!({
+            "token": "firefox",
+            "full_token": "firefox",
+            "name": ["Firefox"],
+            "homepage": "",
+            "version": "131.0",
+        }))
+        .unwrap();
+        let index = std::collections::HashMap::from([("firefox", &firefox)]);
+
+        assert_eq!(
+            cask_details_token("firefox", &index).as_deref(),
+            Some("firefox")
+        );
+        assert_eq!(
+            cask_details_token("firefox@esr", &index).as_deref(),
+            Some("firefox@esr")
+        );
+        assert_eq!(cask_details_token("chrome@beta", &index), None);
+    }
 
     #[test]
     fn package_name_from_qualified_name_uses_last_segment() {
@@ -1377,140 +1633,99 @@ mod tests {
         assert_eq!(package_name_from_qualified_name("vro"), "vro");
     }
 
-    #[test]
-    fn merge_discovered_casks_updates_existing_versions() {
-        let mut installed = HashMap::from([(
-            "example-cask".to_string(),
-            InstalledCask {
-                name: "example-cask".to_string(),
-                version: "1.0.0".to_string(),
-                install_date: 1,
-                artifact_type: Some("dmg".to_string()),
-                binary_paths: None,
-                app_name: Some("Example.app".to_string()),
-            },
-        )]);
-        let discovered = HashMap::from([(
-            "example-cask".to_string(),
-            InstalledCask {
-                name: "example-cask".to_string(),
-                version: "2.0.0".to_string(),
-                install_date: 2,
-                artifact_type: Some("app".to_string()),
-                binary_paths: None,
-                app_name: Some("Example".to_string()),
-            },
-        )]);
-
-        merge_discovered_casks(&mut installed, discovered, &HashSet::new());
+    fn installed(version: &str, bottle_sha256: Option<&str>) -> crate::install::InstalledPackage {
+        crate::install::InstalledPackage {
+            bottle_sha256: bottle_sha256.map(str::to_string),
+            ..installed_package("jq", version)
+        }
+    }
 
-        let cask = installed.get("example-cask").unwrap();
-        assert_eq!(cask.version, "2.0.0");
-        assert_eq!(cask.install_date, 2);
-        assert_eq!(cask.artifact_type.as_deref(), Some("dmg"));
-        assert_eq!(cask.app_name.as_deref(), Some("Example.app"));
+    fn formula(version: &str, bottle_sha256: &str) -> crate::api::Formula {
+        formula_with(
+            "jq",
+            serde_json::json!({
+                "versions": { "stable": version, "bottle": true },
+                "bottle": { "stable": { "rebuild": 0, "files": {
+                    "x86_64_linux": { "url": "https://example.invalid/jq", "sha256": bottle_sha256 }
+                } } },
+            }),
+        )
     }
 
     #[test]
-    fn merge_discovered_casks_preserves_caskroom_synced_versions() {
-        let mut installed = HashMap::from([(
-            "example-cask".to_string(),
-            InstalledCask {
-                name: "example-cask".to_string(),
-                version: "2.0.0".to_string(),
-                install_date: 2,
-                artifact_type: Some("dmg".to_string()),
-                binary_paths: None,
-                app_name: Some("Example.app".to_string()),
-            },
-        )]);
-        let discovered = HashMap::from([(
-            "example-cask".to_string(),
-            InstalledCask {
-                name: "example-cask".to_string(),
-                version: "1.0.0".to_string(),
-                install_date: 1,
-                artifact_type: Some("app".to_string()),
-                binary_paths: None,
-                app_name: Some("Example".to_string()),
-            },
-        )]);
-
-        merge_discovered_casks(
-            &mut installed,
-            discovered,
-            &HashSet::from(["example-cask".to_string()]),
+    fn formula_upgrade_target_never_downgrades_newer_installs() {
+        // Installed is ahead of the index and the bottle checksum differs; this
+        // must not be treated as "bottle updated" and reinstall the older version.
+        let target = formula_upgrade_target(
+            &installed("1.8.0", Some("aaaa")),
+            &formula("1.7.1", "bbbb"),
+            "x86_64_linux",
+        );
+        assert_eq!(target, None);
+        assert_eq!(
+            up_to_date_notice("1.8.0", "1.7.1"),
+            "already up to date (installed 1.8.0 ≥ latest 1.7.1)"
         );
-
-        let cask = installed.get("example-cask").unwrap();
-        assert_eq!(cask.version, "2.0.0");
-        assert_eq!(cask.install_date, 2);
     }
 
     #[test]
-    fn merge_discovered_casks_replaces_stale_manual_app_token() {
-        let mut installed = HashMap::from([(
-            "example".to_string(),
-            InstalledCask {
-                name: "example".to_string(),
-                version: "1.0.0".to_string(),
-                install_date: 1,
-                artifact_type: Some("app".to_string()),
-                binary_paths: None,
-                app_name: Some("Example".to_string()),
-            },
-        )]);
-        let discovered = HashMap::from([(
-            "vendor-example".to_string(),
-            InstalledCask {
-                name: "vendor-example".to_string(),
-                version: "2.0.0".to_string(),
-                install_date: 2,
-                artifact_type: Some("app".to_string()),
-                binary_paths: None,
-                app_name: Some("Example.app".to_string()),
-            },
-        )]);
-
-        merge_discovered_casks(&mut installed, discovered, &HashSet::new());
-
-        assert!(!installed.contains_key("example"));
-        assert_eq!(installed.get("vendor-example").unwrap().version, "2.0.0");
+    fn formula_upgrade_target_reports_version_and_bottle_changes() {
+        assert_eq!(
+            formula_upgrade_target(
+                &installed("1.7.0", None),
+                &formula("1.7.1", "bbbb"),
+                "x86_64_linux"
+            ),
+            Some("1.7.1".to_string())
+        );
+        assert_eq!(
+            formula_upgrade_target(
+                &installed("1.7.1", Some("aaaa")),
+                &formula("1.7.1", "bbbb"),
+                "x86_64_linux"
+            ),
+            Some("1.7.1 (bottle updated)".to_string())
+        );
+        assert_eq!(
+            formula_upgrade_target(
+                &installed("1.7.1", Some("bbbb")),
+                &formula("1.7.1", "bbbb"),
+                "x86_64_linux"
+            ),
+            None
+        );
     }
 
     #[test]
-    fn merge_discovered_casks_keeps_caskroom_synced_same_app_token() {
-        let mut installed = HashMap::from([(
-            "example".to_string(),
-            InstalledCask {
-                name: "example".to_string(),
-                version: "1.0.0".to_string(),
-                install_date: 1,
-                artifact_type: Some("app".to_string()),
-                binary_paths: None,
-                app_name: Some("Example".to_string()),
-            },
-        )]);
-        let discovered = HashMap::from([(
-            "vendor-example".to_string(),
-            InstalledCask {
-                name: "vendor-example".to_string(),
-                version: "2.0.0".to_string(),
-                install_date: 2,
-                artifact_type: Some("app".to_string()),
-                binary_paths: None,
-                app_name: Some("Example".to_string()),
-            },
-        )]);
+    fn versioned_formula_revision_bump_is_outdated() {
+        // `brew outdated` reported `openssl@3 (3.6.0) < 3.6.1` while wax said up to date:
+        // a same-named tap formula shadowed core in the index.
+        let openssl = |full_name: &str, stable: &str, revision: u32| -> crate::api::Formula {
+            formula_with(
+                "openssl@3",
+                serde_json::json!({
+                    "full_name": full_name,
+                    "versions": { "stable": stable, "bottle": true },
+                    "revision": revision,
+                }),
+            )
+        };
+        let pkg = installed_package("openssl@3", "3.6.0");
 
-        merge_discovered_casks(
-            &mut installed,
-            discovered,
-            &HashSet::from(["example".to_string()]),
+        let formulae = vec![
+            openssl("openssl@3", "3.6.1", 0),
+            openssl("someone/tap/openssl@3", "3.6.0", 0),
+        ];
+        let index = crate::api::index_formulae(&formulae);
+        assert_eq!(
+            formula_upgrade_target(&pkg, index["openssl@3"], "x86_64_linux"),
+            Some("3.6.1".to_string())
         );
 
-        assert!(installed.contains_key("example"));
-        assert!(installed.contains_key("vendor-example"));
+        assert_eq!(
+            formula_upgrade_target(&pkg, &openssl("openssl@3", "3.6.0", 1), "x86_64_linux"),
+            Some("3.6.0_1".to_string())
+        );
     }
 
     static HOME_MUTEX: std::sync::Mutex<()> = std::sync::Mutex::new(());
@@ -1542,6 +1757,7 @@ mod tests {
             ("pkg-rebuild", "1.0.0"),
             ("pkg-sha", "1.0.0"),
             ("pkg-pinned", "1.0.0"),
+            ("pkg-gone", "1.0.0"),
         ] {
             fs::create_dir_all(cellar_dir.join(name).join(version)).unwrap();
         }
@@ -1549,15 +1765,11 @@ mod tests {
         let mut installed = HashMap::new();
 
         let make_installed = |name: &str, sha: &str, pinned: bool| InstalledPackage {
-            name: name.to_string(),
-            version: "1.0.0".to_string(),
             platform: "arm64_mac".to_string(),
-            install_date: 0,
             install_mode: InstallMode::Global,
-            from_source: false,
-            bottle_rebuild: 0,
             bottle_sha256: Some(sha.to_string()),
             pinned,
+            ..installed_package(name, "1.0.0")
         };
 
         for (name, sha, pinned) in [
@@ -1566,6 +1778,7 @@ mod tests {
             ("pkg-rebuild", "sha1", false),
             ("pkg-sha", "sha_old", false),
             ("pkg-pinned", "sha1", true),
+            ("pkg-gone", "sha1", false),
         ] {
             installed.insert(name.to_string(), make_installed(name, sha, pinned));
         }
@@ -1606,6 +1819,9 @@ mod tests {
                 deprecation_reason: None,
                 disable_reason: None,
                 post_install_defined: false,
+                popularity: None,
+                aliases: Vec::new(),
+                oldnames: Vec::new(),
                 rb_path: None,
                 keg_only: None,
                 keg_only_reason: None,
@@ -1623,8 +1839,15 @@ mod tests {
         fs::write(cache_dir.join("casks.json"), "[]").unwrap();
 
         let cache = Cache::new().unwrap();
-        let outdated = get_outdated_packages(&cache).await.unwrap();
+        let (unknown, outdated): (Vec<_>, Vec<_>) = get_outdated_packages(&cache)
+            .await
+            .unwrap()
+            .into_iter()
+            .partition(|p| p.unknown);
 
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].name, "pkg-gone");
+        assert_eq!(unknown[0].latest_version, "");
         assert_eq!(outdated.len(), 3);
 
         let names: Vec<&str> = outdated.iter().map(|p| p.name.as_str()).collect();
@@ -208,7 +208,7 @@ pub async fn version_install(
         style(version).cyan()
     );
 
-    let client = crate::http_client::default_client();
+    let client = crate::http_client::default_client(GHCR_BASE);
 
     let spinner = ProgressBar::new_spinner();
     spinner.set_style(
@@ -260,9 +260,10 @@ pub async fn version_install(
     check_cancelled()?;
 
     let temp_dir = tempfile::TempDir::new()?;
-    let tarball_path = temp_dir
-        .path()
-        .join(format!("{}-{}.tar.gz", formula_name, version));
+    let tarball_path = temp_dir.path().join(crate::bottle::archive_file_name(
+        &format!("{}-{}", formula_name, version),
+        &blob_url,
+    ));
 
     let downloader = BottleDownloader::new();
     let pb = ProgressBar::new(0);
@@ -285,7 +286,8 @@ pub async fn version_install(
         .await?;
     pb.finish_and_clear();
 
-    crate::digest::verify_sha256_file(&tarball_path, &sha256)?;
+    crate::digest::verify_digest_file(&tarball_path, &sha256)
+        .map_err(|e| e.for_download(formula_name, &blob_url))?;
 
     let extract_dir = temp_dir.path().join(formula_name);
     BottleDownloader::extract(&tarball_path, &extract_dir)?;
@@ -303,6 +305,8 @@ pub async fn version_install(
         &state,
         false,
         true,
+        true,
+        false,
         None,
         None,
     )
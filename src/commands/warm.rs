@@ -0,0 +1,51 @@
+use crate::cache::Cache;
+use crate::cask::CaskState;
+use crate::error::Result;
+use console::style;
+use std::collections::HashSet;
+use tracing::instrument;
+
+/// Prefetch what the first `outdated`/`upgrade` would otherwise fetch one by one: make
+/// sure the indices exist, then pull details for every installed cask concurrently.
+#[instrument(skip(cache))]
+pub async fn warm(cache: &Cache) -> Result<()> {
+    let start = std::time::Instant::now();
+    cache.ensure_fresh().await?;
+
+    let installed = CaskState::new()?.load().await?;
+    let known: HashSet<String> = cache
+        .load_casks()
+        .await?
+        .into_iter()
+        .flat_map(|c| [c.token, c.full_token])
+        .collect();
+    let mut tokens: Vec<String> = installed
+        .keys()
+        .filter(|token| known.contains(token.as_str()))
+        .cloned()
+        .collect();
+    tokens.sort();
+
+    if tokens.is_empty() {
+        println!("index is ready; no installed casks to prefetch");
+        return Ok(());
+    }
+
+    let (cached, failed) = cache.warm_cask_details(&tokens).await;
+    for (token, error) in &failed {
+        eprintln!(
+            "{} could not fetch details for {}: {}",
+            style("warning:").yellow(),
+            style(token).magenta(),
+            error
+        );
+    }
+    println!(
+        "{} cached details for {} cask{}{}",
+        style("✓").green(),
+        style(cached).cyan(),
+        if cached == 1 { "" } else { "s" },
+        crate::ui::elapsed_suffix(start.elapsed())
+    );
+    Ok(())
+}
@@ -43,19 +43,24 @@ impl DependencyGraph {
             *in_degree.entry(node.as_str()).or_insert(0) = count;
         }
 
-        let mut queue: VecDeque<&str> = in_degree
+        // Seed and expand in alphabetical order so the result doesn't depend on
+        // HashMap iteration order; install order and dry-run output stay stable.
+        let mut seeds: Vec<&str> = in_degree
             .iter()
             .filter(|(_, &count)| count == 0)
             .map(|(&node, _)| node)
             .collect();
+        seeds.sort_unstable();
+        let mut queue: VecDeque<&str> = seeds.into();
 
         let mut result = Vec::new();
 
         while let Some(node) = queue.pop_front() {
             result.push(node.to_string());
 
-            if let Some(neighbors) = adj_list.get(node) {
-                for &neighbor in neighbors {
+            if let Some(neighbors) = adj_list.get_mut(node) {
+                neighbors.sort_unstable();
+                for &neighbor in neighbors.iter() {
                     if let Some(count) = in_degree.get_mut(neighbor) {
                         *count -= 1;
                         if *count == 0 {
@@ -83,38 +88,81 @@ impl Default for DependencyGraph {
     }
 }
 
-#[instrument(skip(formulae))]
+/// Add every formula's `aliases` and `oldnames` to a by-name index, so a dependency
+/// spelled `pkg-config` finds the formula that provides it (`pkgconf`). Names already
+/// in the index win over aliases.
+pub fn add_alias_names<'a>(index: &mut HashMap<&'a str, &'a Formula>, formulae: &'a [Formula]) {
+    for formula in formulae {
+        for alias in formula.aliases.iter().chain(&formula.oldnames) {
+            index.entry(alias.as_str()).or_insert(formula);
+        }
+    }
+}
+
+/// Dependencies of `formula` not already in `installed`, in install order
+/// (dependencies before dependents), ending with `formula` itself.
 pub fn resolve_dependencies(
     formula: &Formula,
-    formulae: &[Formula],
+    index: &HashMap<&str, &Formula>,
+    installed: &HashSet<String>,
+) -> Result<Vec<String>> {
+    resolve_dependencies_for_roots(&[formula], index, installed)
+}
+
+/// Resolve the combined dependency closure of several requested formulae in a
+/// single graph, so shared dependencies are walked once and ordered consistently.
+#[instrument(skip_all, fields(roots = roots.len()))]
+pub fn resolve_dependencies_for_roots(
+    roots: &[&Formula],
+    index: &HashMap<&str, &Formula>,
     installed: &HashSet<String>,
 ) -> Result<Vec<String>> {
-    debug!("Resolving dependencies for {}", formula.name);
+    debug!(
+        "Resolving dependencies for {:?}",
+        roots.iter().map(|f| f.name.as_str()).collect::<Vec<_>>()
+    );
 
     let mut graph = DependencyGraph::new();
     let mut visited = HashSet::new();
-    let mut queue = VecDeque::new();
-
-    queue.push_back(formula.name.clone());
-
-    while let Some(name) = queue.pop_front() {
-        if visited.contains(&name) || installed.contains(&name) {
+    // (name as written, formula that depends on it)
+    let mut queue: VecDeque<(String, Option<String>)> =
+        roots.iter().map(|f| (f.name.clone(), None)).collect();
+    // Aliases (`pkg-config`) resolve to the formula's real name (`pkgconf`).
+    let canonical = |name: &str| {
+        index
+            .get(name)
+            .map(|f| f.name.clone())
+            .unwrap_or_else(|| name.to_string())
+    };
+
+    while let Some((name, parent)) = queue.pop_front() {
+        let name = canonical(&name);
+        // A root is walked even when installed, so its missing dependencies are found
+        // (`install --only-dependencies`); it is dropped from the result below.
+        if visited.contains(&name) || (parent.is_some() && installed.contains(&name)) {
             continue;
         }
         visited.insert(name.clone());
 
-        let f = formulae
-            .iter()
-            .find(|f| f.name == name)
-            .ok_or_else(|| WaxError::FormulaNotFound(name.clone()))?;
+        let f = index.get(name.as_str()).ok_or_else(|| {
+            WaxError::FormulaNotFound(match &parent {
+                Some(parent) => format!("{} (required by {})", name, parent),
+                None => name.clone(),
+            })
+        })?;
 
-        let deps = f.dependencies.clone().unwrap_or_default();
+        let deps: Vec<String> = f
+            .dependencies
+            .iter()
+            .flatten()
+            .map(|dep| canonical(dep))
+            .collect();
 
         graph.add_node(name.clone(), deps.clone());
 
         for dep in deps {
-            if !installed.contains(&dep) {
-                queue.push_back(dep);
+            if !installed.contains(&dep) && !visited.contains(&dep) {
+                queue.push_back((dep, Some(name.clone())));
             }
         }
     }
@@ -133,6 +181,7 @@ pub fn resolve_dependencies(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::formula;
 
     #[test]
     fn test_empty_graph() {
@@ -179,6 +228,28 @@ mod tests {
         assert_eq!(result.len(), 4);
     }
 
+    #[test]
+    fn test_topological_sort_is_deterministic() {
+        let build = || {
+            let mut graph = DependencyGraph::new();
+            graph.add_node(
+                "app".to_string(),
+                vec!["zlib".to_string(), "curl".to_string()],
+            );
+            graph.add_node("curl".to_string(), vec!["openssl".to_string()]);
+            graph.add_node("openssl".to_string(), vec!["ca-certs".to_string()]);
+            graph.add_node("zlib".to_string(), vec![]);
+            graph.add_node("ca-certs".to_string(), vec![]);
+            graph.add_node("tool".to_string(), vec!["zlib".to_string()]);
+            graph
+        };
+
+        let expected = vec!["ca-certs", "zlib", "openssl", "tool", "curl", "app"];
+        for _ in 0..20 {
+            assert_eq!(build().topological_sort().unwrap(), expected);
+        }
+    }
+
     #[test]
     fn test_disconnected_graphs() {
         let mut graph = DependencyGraph::new();
@@ -198,6 +269,78 @@ mod tests {
         assert!(pos_c < pos_d);
     }
 
+    #[test]
+    fn resolve_for_roots_walks_shared_dependencies_once() {
+        let formulae = [
+            formula("app-a", &["openssl", "zlib"]),
+            formula("app-b", &["openssl"]),
+            formula("openssl", &["ca-certs"]),
+            formula("ca-certs", &[]),
+            formula("zlib", &[]),
+        ];
+        let index: HashMap<&str, &Formula> =
+            formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+        let installed: HashSet<String> = ["zlib".to_string()].into_iter().collect();
+
+        let order =
+            resolve_dependencies_for_roots(&[index["app-a"], index["app-b"]], &index, &installed)
+                .unwrap();
+
+        assert_eq!(order.len(), 4);
+        assert!(!order.contains(&"zlib".to_string()));
+        let pos = |n: &str| order.iter().position(|x| x == n).unwrap();
+        assert!(pos("ca-certs") < pos("openssl"));
+        assert!(pos("openssl") < pos("app-a"));
+        assert!(pos("openssl") < pos("app-b"));
+    }
+
+    #[test]
+    fn resolve_for_installed_root_still_finds_its_missing_dependencies() {
+        let formulae = [
+            formula("app", &["libfoo", "zlib"]),
+            formula("libfoo", &[]),
+            formula("zlib", &[]),
+        ];
+        let index: HashMap<&str, &Formula> =
+            formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+        let installed: HashSet<String> = ["app".to_string(), "zlib".to_string()]
+            .into_iter()
+            .collect();
+
+        let order = resolve_dependencies_for_roots(&[index["app"]], &index, &installed).unwrap();
+        assert_eq!(order, vec!["libfoo"]);
+    }
+
+    #[test]
+    fn resolve_for_roots_reports_missing_dependency() {
+        let formulae = [formula("app", &["ghost"])];
+        let index: HashMap<&str, &Formula> =
+            formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+
+        match resolve_dependencies_for_roots(&[index["app"]], &index, &HashSet::new()) {
+            Err(WaxError::FormulaNotFound(name)) => assert_eq!(name, "ghost (required by app)"),
+            other => panic!("expected FormulaNotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn resolve_for_roots_follows_dependency_aliases() {
+        let mut pkgconf = formula("pkgconf", &[]);
+        pkgconf.aliases = vec!["pkg-config".to_string()];
+        let formulae = [formula("app", &["pkg-config"]), pkgconf];
+        let mut index: HashMap<&str, &Formula> =
+            formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+        add_alias_names(&mut index, &formulae);
+
+        let order =
+            resolve_dependencies_for_roots(&[index["app"]], &index, &HashSet::new()).unwrap();
+        assert_eq!(order, vec!["pkgconf", "app"]);
+
+        let installed: HashSet<String> = ["pkgconf".to_string()].into_iter().collect();
+        let order = resolve_dependencies_for_roots(&[index["app"]], &index, &installed).unwrap();
+        assert_eq!(order, vec!["app"]);
+    }
+
     #[test]
     fn test_cycle_detection() {
         let mut graph = DependencyGraph::new();
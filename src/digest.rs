@@ -1,14 +1,155 @@
 use crate::error::{Result, WaxError};
-use sha2::{Digest, Sha256};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256, Sha512};
 use std::io::Read;
 use std::path::Path;
 use tracing::{debug, warn};
 
-/// Verify a file against an expected SHA256 hex digest.
+/// Hash function behind a declared digest. Homebrew declares sha256 everywhere, but
+/// some taps and third-party manifests use sha512.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Algorithm {
+    #[default]
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    /// Split an explicit `sha256:`/`sha512:` prefix off a declared digest. Without one,
+    /// a 128-character digest is sha512 and anything else sha256.
+    pub fn detect(declared: &str) -> (Self, &str) {
+        let declared = declared.trim();
+        if let Some(hex) = declared.strip_prefix("sha512:") {
+            (Self::Sha512, hex)
+        } else if let Some(hex) = declared.strip_prefix("sha256:") {
+            (Self::Sha256, hex)
+        } else if declared.len() == 128 {
+            (Self::Sha512, declared)
+        } else {
+            (Self::Sha256, declared)
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+        }
+    }
+
+    /// Lowercase hex digest of `bytes`.
+    pub fn hex_digest(self, bytes: &[u8]) -> String {
+        match self {
+            Self::Sha256 => format!("{:x}", Sha256::digest(bytes)),
+            Self::Sha512 => format!("{:x}", Sha512::digest(bytes)),
+        }
+    }
+
+    /// Lowercase hex digest of a file, read in chunks.
+    pub fn hex_digest_file(self, path: &Path) -> Result<String> {
+        fn stream<D: Digest>(path: &Path) -> Result<String> {
+            let mut file = std::fs::File::open(path)?;
+            let mut hasher = D::new();
+            let mut buffer = [0u8; 8192];
+            loop {
+                let n = file.read(&mut buffer)?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..n]);
+            }
+            Ok(hasher
+                .finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect())
+        }
+        match self {
+            Self::Sha256 => stream::<Sha256>(path),
+            Self::Sha512 => stream::<Sha512>(path),
+        }
+    }
+}
+
+/// The checksum a formula source or cask declares. Serialized as the declared digest,
+/// `"no_check"`, or an empty string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Checksum {
+    /// A hex digest, optionally prefixed with its algorithm (see [`Algorithm::detect`]).
+    Digest(String),
+    /// `sha256 :no_check`, or a `version :latest` cask with no digest: the download
+    /// changes over time, so there is nothing to verify against.
+    NoCheck,
+    /// Nothing declared at all.
+    #[default]
+    Missing,
+}
+
+impl Checksum {
+    /// `"abc…"`, `"no_check"`/`":no_check"` or `""`.
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "" => Self::Missing,
+            "no_check" | ":no_check" => Self::NoCheck,
+            hex => Self::Digest(hex.to_string()),
+        }
+    }
+
+    /// Algorithm the declared digest uses; sha256 when nothing is declared.
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            Self::Digest(hex) => Algorithm::detect(hex).0,
+            Self::NoCheck | Self::Missing => Algorithm::Sha256,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Digest(hex) => hex,
+            Self::NoCheck => "no_check",
+            Self::Missing => "",
+        }
+    }
+}
+
+impl From<&str> for Checksum {
+    fn from(value: &str) -> Self {
+        Self::parse(value)
+    }
+}
+
+impl Serialize for Checksum {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Checksum {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = Option::<String>::deserialize(deserializer)?;
+        Ok(value.as_deref().map(Self::parse).unwrap_or_default())
+    }
+}
+
+/// Verify a file against a declared checksum. `NoCheck` passes with a warning; a
+/// missing checksum is an error, since nothing vouches for the download.
+pub fn verify_file(path: &Path, checksum: &Checksum) -> Result<()> {
+    match checksum {
+        Checksum::Digest(hex) => verify_digest_file(path, hex),
+        Checksum::NoCheck => verify_digest_file(path, "no_check"),
+        Checksum::Missing => Err(WaxError::InstallError(format!(
+            "no checksum declared for {}; refusing to use an unverified download",
+            path.display()
+        ))),
+    }
+}
+
+/// Verify a file against an expected hex digest, sha256 unless the digest says
+/// otherwise (see [`Algorithm::detect`]).
 ///
 /// Homebrew uses `"no_check"` to skip verification; wax logs a warning when that happens.
-pub fn verify_sha256_file(path: &Path, expected_sha256: &str) -> Result<()> {
-    if expected_sha256 == "no_check" {
+pub fn verify_digest_file(path: &Path, expected: &str) -> Result<()> {
+    if expected == "no_check" {
         warn!("Skipping checksum verification (no_check) for {:?}", path);
         eprintln!(
             "warning: skipping checksum verification (no_check) for {}",
@@ -17,29 +158,76 @@ pub fn verify_sha256_file(path: &Path, expected_sha256: &str) -> Result<()> {
         return Ok(());
     }
 
-    debug!("Verifying checksum for {:?}", path);
-
-    let mut file = std::fs::File::open(path)?;
-    let mut hasher = Sha256::new();
-    let mut buffer = [0u8; 8192];
-
-    loop {
-        let n = file.read(&mut buffer)?;
-        if n == 0 {
-            break;
-        }
-        hasher.update(&buffer[..n]);
-    }
+    let (algorithm, expected) = Algorithm::detect(expected);
+    debug!("Verifying {} checksum for {:?}", algorithm.name(), path);
+    let hash = algorithm.hex_digest_file(path)?;
 
-    let hash = format!("{:x}", hasher.finalize());
-
-    if hash != expected_sha256 {
+    if !hash.eq_ignore_ascii_case(expected) {
         return Err(WaxError::ChecksumMismatch {
-            expected: expected_sha256.to_string(),
+            expected: expected.to_string(),
             actual: hash,
+            name: String::new(),
+            url: String::new(),
         });
     }
 
     debug!("Checksum verified: {}", hash);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_sentinels_parse_and_round_trip() {
+        assert_eq!(Checksum::parse(":no_check"), Checksum::NoCheck);
+        assert_eq!(Checksum::parse("no_check"), Checksum::NoCheck);
+        assert_eq!(Checksum::parse("  "), Checksum::Missing);
+        assert_eq!(Checksum::parse("abc"), Checksum::Digest("abc".to_string()));
+
+        let json = serde_json::to_string(&Checksum::NoCheck).unwrap();
+        assert_eq!(json, "\"no_check\"");
+        let missing: Checksum = serde_json::from_str("null").unwrap();
+        assert_eq!(missing, Checksum::Missing);
+    }
+
+    #[test]
+    fn verify_file_skips_no_check_and_rejects_missing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("download");
+        std::fs::write(&path, b"payload").unwrap();
+
+        assert!(verify_file(&path, &Checksum::NoCheck).is_ok());
+        assert!(verify_file(&path, &Checksum::Missing).is_err());
+        assert!(matches!(
+            verify_file(&path, &Checksum::Digest("00".to_string())),
+            Err(WaxError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn algorithm_follows_prefix_then_digest_length() {
+        let sha256 = Algorithm::Sha256.hex_digest(b"payload");
+        let sha512 = Algorithm::Sha512.hex_digest(b"payload");
+        assert_eq!(sha512.len(), 128);
+        assert_eq!(
+            Algorithm::detect(&sha256),
+            (Algorithm::Sha256, sha256.as_str())
+        );
+        assert_eq!(
+            Algorithm::detect(&sha512),
+            (Algorithm::Sha512, sha512.as_str())
+        );
+        assert_eq!(Algorithm::detect("sha512:ab"), (Algorithm::Sha512, "ab"));
+        assert_eq!(Algorithm::detect("sha256:ab"), (Algorithm::Sha256, "ab"));
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("download");
+        std::fs::write(&path, b"payload").unwrap();
+        assert!(verify_digest_file(&path, &sha256).is_ok());
+        assert!(verify_digest_file(&path, &sha512).is_ok());
+        assert!(verify_digest_file(&path, &format!("sha512:{}", sha512.to_uppercase())).is_ok());
+        assert!(verify_digest_file(&path, &format!("sha512:{}", sha256)).is_err());
+    }
+}
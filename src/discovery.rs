@@ -8,13 +8,15 @@
 use crate::api::{Cask, Formula};
 #[cfg_attr(not(target_os = "linux"), allow(unused_imports))]
 use crate::bottle::detect_platform;
-use crate::cask::InstalledCask;
+use crate::cache::Cache;
+use crate::cask::{CaskState, InstalledCask};
 use crate::error::Result;
 #[cfg_attr(not(target_os = "linux"), allow(unused_imports))]
-use crate::install::{InstallMode, InstalledPackage};
+use crate::install::InstallMode;
+use crate::install::{InstallState, InstalledPackage};
 #[cfg(target_os = "macos")]
 use crate::ui::dirs;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 #[cfg(target_os = "macos")]
 use std::path::PathBuf;
@@ -112,6 +114,7 @@ pub async fn discover_manually_installed_casks(
                         artifact_type: Some("app".to_string()),
                         binary_paths: None,
                         app_name: Some(app.bundle_name),
+                        additional_apps: None,
                     });
             }
         }
@@ -163,6 +166,9 @@ pub async fn discover_linux_system_packages(
                     bottle_rebuild: 0,
                     bottle_sha256: None,
                     pinned: false,
+                    options: Vec::new(),
+                    installed_as_dependency: false,
+                    unverified_source: false,
                 });
         }
 
@@ -691,9 +697,285 @@ fn unix_seconds_now() -> i64 {
     system_time_to_unix_seconds(SystemTime::now()).unwrap_or(0)
 }
 
+pub(crate) fn merge_discovered_casks(
+    installed_casks: &mut HashMap<String, InstalledCask>,
+    discovered_casks: HashMap<String, InstalledCask>,
+    caskroom_synced_names: &HashSet<String>,
+) {
+    for (name, discovered) in discovered_casks {
+        if let Some(app_key) = manual_app_key(&discovered) {
+            let stale_names = installed_casks
+                .iter()
+                .filter_map(|(installed_name, installed)| {
+                    if installed_name == &name || caskroom_synced_names.contains(installed_name) {
+                        return None;
+                    }
+                    (manual_app_key(installed).as_deref() == Some(app_key.as_str()))
+                        .then(|| installed_name.clone())
+                })
+                .collect::<Vec<_>>();
+            for stale_name in stale_names {
+                installed_casks.remove(&stale_name);
+            }
+        }
+
+        installed_casks
+            .entry(name.clone())
+            .and_modify(|installed| {
+                if !caskroom_synced_names.contains(&name) && discovered.version != "unknown" {
+                    installed.version = discovered.version.clone();
+                }
+                if !caskroom_synced_names.contains(&name) && discovered.install_date > 0 {
+                    installed.install_date = discovered.install_date;
+                }
+                if installed.artifact_type.is_none() {
+                    installed.artifact_type = discovered.artifact_type.clone();
+                }
+                if installed.binary_paths.is_none() {
+                    installed.binary_paths = discovered.binary_paths.clone();
+                }
+                if installed.app_name.is_none() {
+                    installed.app_name = discovered.app_name.clone();
+                }
+            })
+            .or_insert(discovered);
+    }
+}
+
+fn manual_app_key(cask: &InstalledCask) -> Option<String> {
+    if cask.artifact_type.as_deref() != Some("app") {
+        return None;
+    }
+
+    cask.app_name
+        .as_deref()
+        .map(normalize_package_token)
+        .filter(|name| !name.is_empty())
+}
+
+/// Installed casks from wax's cask state, refreshed from the Caskrooms and, on macOS,
+/// merged with apps matched in `/Applications`. The merged view is saved back unless
+/// `dry_run` is set.
+pub(crate) async fn sync_cask_state(
+    cache: &Cache,
+    dry_run: bool,
+) -> Result<HashMap<String, InstalledCask>> {
+    let cask_state = CaskState::new()?;
+    let (mut installed_casks, caskroom_synced_names) = if dry_run {
+        cask_state.load_with_caskrooms().await?
+    } else {
+        let synced_names = cask_state.sync_from_caskrooms().await?;
+        (cask_state.load().await?, synced_names)
+    };
+    if cfg!(target_os = "macos") {
+        let casks = cache.load_casks().await?;
+        let discovered_casks = discover_manually_installed_casks(&casks).await?;
+        merge_discovered_casks(
+            &mut installed_casks,
+            discovered_casks,
+            &caskroom_synced_names,
+        );
+        if !dry_run {
+            cask_state.save(&installed_casks).await?;
+        }
+    }
+
+    Ok(installed_casks)
+}
+
+/// What an installed package name refers to, with the key it is recorded under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstalledKind {
+    Formula(String),
+    Cask(String),
+}
+
+/// Decide whether `name` (bare or tap-qualified) is an installed formula or cask, so
+/// `install`, `uninstall` and `upgrade` agree without `--cask`. Checks formula state,
+/// then cask state (including Caskroom and `/Applications` discovery), then rescans the
+/// Cellar. A name installed as both resolves to the formula. With `dry_run` the
+/// refreshed state is not saved.
+pub async fn resolve_installed_kind(
+    cache: &Cache,
+    name: &str,
+    dry_run: bool,
+) -> Result<Option<InstalledKind>> {
+    let short_name = name.rsplit('/').next().unwrap_or(name);
+    let state = InstallState::new()?;
+    let find_formula = |packages: &HashMap<String, InstalledPackage>| {
+        [name, short_name]
+            .into_iter()
+            .find(|key| packages.contains_key(*key))
+            .map(|key| InstalledKind::Formula(key.to_string()))
+    };
+
+    if let Some(kind) = find_formula(&state.load().await?) {
+        return Ok(Some(kind));
+    }
+
+    let casks = sync_cask_state(cache, dry_run).await?;
+    if let Some(key) = [name, short_name]
+        .into_iter()
+        .find(|key| casks.contains_key(*key))
+    {
+        return Ok(Some(InstalledKind::Cask(key.to_string())));
+    }
+
+    if dry_run {
+        return Ok(find_formula(&state.load_with_cellar().await?));
+    }
+    state.sync_from_cellar().await?;
+    Ok(find_formula(&state.load().await?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn merge_discovered_casks_updates_existing_versions() {
+        let mut installed = HashMap::from([(
+            "example-cask".to_string(),
+            InstalledCask {
+                name: "example-cask".to_string(),
+                version: "1.0.0".to_string(),
+                install_date: 1,
+                artifact_type: Some("dmg".to_string()),
+                binary_paths: None,
+                app_name: Some("Example.app".to_string()),
+                additional_apps: None,
+            },
+        )]);
+        let discovered = HashMap::from([(
+            "example-cask".to_string(),
+            InstalledCask {
+                name: "example-cask".to_string(),
+                version: "2.0.0".to_string(),
+                install_date: 2,
+                artifact_type: Some("app".to_string()),
+                binary_paths: None,
+                app_name: Some("Example".to_string()),
+                additional_apps: None,
+            },
+        )]);
+
+        merge_discovered_casks(&mut installed, discovered, &HashSet::new());
+
+        let cask = installed.get("example-cask").unwrap();
+        assert_eq!(cask.version, "2.0.0");
+        assert_eq!(cask.install_date, 2);
+        assert_eq!(cask.artifact_type.as_deref(), Some("dmg"));
+        assert_eq!(cask.app_name.as_deref(), Some("Example.app"));
+    }
+
+    #[test]
+    fn merge_discovered_casks_preserves_caskroom_synced_versions() {
+        let mut installed = HashMap::from([(
+            "example-cask".to_string(),
+            InstalledCask {
+                name: "example-cask".to_string(),
+                version: "2.0.0".to_string(),
+                install_date: 2,
+                artifact_type: Some("dmg".to_string()),
+                binary_paths: None,
+                app_name: Some("Example.app".to_string()),
+                additional_apps: None,
+            },
+        )]);
+        let discovered = HashMap::from([(
+            "example-cask".to_string(),
+            InstalledCask {
+                name: "example-cask".to_string(),
+                version: "1.0.0".to_string(),
+                install_date: 1,
+                artifact_type: Some("app".to_string()),
+                binary_paths: None,
+                app_name: Some("Example".to_string()),
+                additional_apps: None,
+            },
+        )]);
+
+        merge_discovered_casks(
+            &mut installed,
+            discovered,
+            &HashSet::from(["example-cask".to_string()]),
+        );
+
+        let cask = installed.get("example-cask").unwrap();
+        assert_eq!(cask.version, "2.0.0");
+        assert_eq!(cask.install_date, 2);
+    }
+
+    #[test]
+    fn merge_discovered_casks_replaces_stale_manual_app_token() {
+        let mut installed = HashMap::from([(
+            "example".to_string(),
+            InstalledCask {
+                name: "example".to_string(),
+                version: "1.0.0".to_string(),
+                install_date: 1,
+                artifact_type: Some("app".to_string()),
+                binary_paths: None,
+                app_name: Some("Example".to_string()),
+                additional_apps: None,
+            },
+        )]);
+        let discovered = HashMap::from([(
+            "vendor-example".to_string(),
+            InstalledCask {
+                name: "vendor-example".to_string(),
+                version: "2.0.0".to_string(),
+                install_date: 2,
+                artifact_type: Some("app".to_string()),
+                binary_paths: None,
+                app_name: Some("Example.app".to_string()),
+                additional_apps: None,
+            },
+        )]);
+
+        merge_discovered_casks(&mut installed, discovered, &HashSet::new());
+
+        assert!(!installed.contains_key("example"));
+        assert_eq!(installed.get("vendor-example").unwrap().version, "2.0.0");
+    }
+
+    #[test]
+    fn merge_discovered_casks_keeps_caskroom_synced_same_app_token() {
+        let mut installed = HashMap::from([(
+            "example".to_string(),
+            InstalledCask {
+                name: "example".to_string(),
+                version: "1.0.0".to_string(),
+                install_date: 1,
+                artifact_type: Some("app".to_string()),
+                binary_paths: None,
+                app_name: Some("Example".to_string()),
+                additional_apps: None,
+            },
+        )]);
+        let discovered = HashMap::from([(
+            "vendor-example".to_string(),
+            InstalledCask {
+                name: "vendor-example".to_string(),
+                version: "2.0.0".to_string(),
+                install_date: 2,
+                artifact_type: Some("app".to_string()),
+                binary_paths: None,
+                app_name: Some("Example".to_string()),
+                additional_apps: None,
+            },
+        )]);
+
+        merge_discovered_casks(
+            &mut installed,
+            discovered,
+            &HashSet::from(["example".to_string()]),
+        );
+
+        assert!(installed.contains_key("example"));
+        assert!(installed.contains_key("vendor-example"));
+    }
 
     #[test]
     fn normalizes_common_app_names() {
@@ -20,8 +20,14 @@ pub enum WaxError {
     #[error("Cache error: {0}")]
     CacheError(String),
 
-    #[error("Checksum mismatch: expected {expected}, got {actual}")]
-    ChecksumMismatch { expected: String, actual: String },
+    #[error("{}", checksum_mismatch_message(.name, .url, .expected, .actual))]
+    ChecksumMismatch {
+        expected: String,
+        actual: String,
+        /// Package the download belongs to; empty until `for_download` fills it in.
+        name: String,
+        url: String,
+    },
 
     #[error("Bottle not available for platform: {0}")]
     BottleNotAvailable(String),
@@ -117,6 +123,41 @@ pub fn validate_package_name(name: &str) -> Result<()> {
     Ok(())
 }
 
+impl WaxError {
+    /// A checksum mismatch from `digest` knows only the file; name the package and URL
+    /// it came from. Other errors pass through unchanged.
+    pub fn for_download(self, package: &str, source_url: &str) -> Self {
+        match self {
+            WaxError::ChecksumMismatch {
+                expected, actual, ..
+            } => WaxError::ChecksumMismatch {
+                expected,
+                actual,
+                name: package.to_string(),
+                url: source_url.to_string(),
+            },
+            other => other,
+        }
+    }
+}
+
+fn checksum_mismatch_message(name: &str, url: &str, expected: &str, actual: &str) -> String {
+    let mut message = if name.is_empty() {
+        "Checksum mismatch".to_string()
+    } else {
+        format!("Checksum mismatch for {}", name)
+    };
+    message.push_str(&format!(": expected {}, got {}", expected, actual));
+    if !url.is_empty() {
+        message.push_str(&format!("\n  url: {}", url));
+    }
+    message.push_str(
+        "\n  the file no longer matches its published checksum (a rebuilt bottle, a re-rolled \
+         release or a stale mirror); run `wax update` and retry",
+    );
+    message
+}
+
 #[cfg(target_os = "windows")]
 pub const BREW_UNAVAILABLE_MSG: &str =
     "Homebrew formulae and casks are not supported on Windows; use scoop/, winget/, or choco/ prefixes";
@@ -145,6 +186,24 @@ pub fn reject_brew_ecosystem(force: Option<crate::package_spec::Ecosystem>) -> R
 mod tests {
     use super::*;
 
+    #[test]
+    fn checksum_mismatch_names_package_and_url() {
+        let err = WaxError::ChecksumMismatch {
+            expected: "aaaa".to_string(),
+            actual: "bbbb".to_string(),
+            name: String::new(),
+            url: String::new(),
+        }
+        .for_download(
+            "jq",
+            "https://ghcr.io/v2/homebrew/core/jq/blobs/sha256:aaaa",
+        );
+        let message = err.to_string();
+        assert!(message.starts_with("Checksum mismatch for jq: expected aaaa, got bbbb"));
+        assert!(message.contains("url: https://ghcr.io/v2/homebrew/core/jq/blobs/sha256:aaaa"));
+        assert!(message.contains("wax update"));
+    }
+
     #[test]
     fn test_validate_package_name_valid() {
         assert!(validate_package_name("foo").is_ok());
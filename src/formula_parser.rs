@@ -1,8 +1,9 @@
+use crate::digest::Checksum;
 use crate::error::{Result, WaxError};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::sync::OnceLock;
-use tracing::{debug, instrument};
+use tracing::{debug, info, instrument};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -18,8 +19,39 @@ pub enum BuildSystem {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FormulaSource {
     pub url: String,
-    pub sha256: String,
+    /// The stable source's digest, or `:no_check`.
+    #[serde(default)]
+    pub sha256: Checksum,
     pub version: String,
+    /// Git tag to check out when the stable `url` is a git repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Git commit the tag is expected to resolve to (or to check out directly).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// Set for `using: :git` sources that don't otherwise look like git.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub using_git: bool,
+    /// `mirror "..."` URLs for the stable source, tried in order when `url` fails.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
+}
+
+impl FormulaSource {
+    /// Whether the stable source is a git checkout rather than a tarball.
+    /// Git sources have no tarball checksum; `revision` pins them instead.
+    pub fn is_git(&self) -> bool {
+        !self.url.is_empty()
+            && (self.using_git
+                || self.tag.is_some()
+                || self.revision.is_some()
+                || self.url.ends_with(".git"))
+    }
+
+    /// `url` followed by its mirrors, in the order downloads should try them.
+    pub fn download_urls(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.url.as_str()).chain(self.mirrors.iter().map(String::as_str))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,15 +71,64 @@ pub struct ParsedFormula {
     /// Files to copy to `bin/` via `bin.install "..."` (binary-release formulas).
     pub bin_installs: Vec<String>,
     pub bin_install_targets: Vec<BinInstall>,
+    /// Build options declared with `option "with-foo"`, without the leading `--`.
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// OS, macOS release and CPU constraints from `depends_on macos:`/`arch:`/`:linux`.
+    #[serde(default)]
+    pub requirements: PlatformRequirements,
+    /// Toolchain hints for source builds: `env :std` and the compiler to use.
+    #[serde(default)]
+    pub build_env: BuildEnvironment,
+}
+
+impl ParsedFormula {
+    /// Validate user-selected build options against the declared ones and append them
+    /// to `configure_args` as `--<option>`.
+    pub fn apply_options(&mut self, options: &[String]) -> Result<()> {
+        for option in options {
+            if !self.options.contains(option) {
+                let available = if self.options.is_empty() {
+                    "none".to_string()
+                } else {
+                    self.options
+                        .iter()
+                        .map(|o| format!("--{}", o))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                return Err(WaxError::InvalidInput(format!(
+                    "{} has no option --{} (available: {})",
+                    self.name, option, available
+                )));
+            }
+            self.configure_args.push(format!("--{}", option));
+        }
+        Ok(())
+    }
 }
 
 pub struct FormulaParser;
 
 static RE_FIELD: OnceLock<Regex> = OnceLock::new();
 static RE_DEPENDS: OnceLock<Regex> = OnceLock::new();
+static RE_DEPENDS_TAG: OnceLock<Regex> = OnceLock::new();
+static RE_DEPENDS_QUOTED: OnceLock<Regex> = OnceLock::new();
 static RE_SYSTEM: OnceLock<Regex> = OnceLock::new();
 static RE_VERSION: OnceLock<Regex> = OnceLock::new();
 static RE_HEAD: OnceLock<Regex> = OnceLock::new();
+static RE_OPTION: OnceLock<Regex> = OnceLock::new();
+static RE_URL_TAG: OnceLock<Regex> = OnceLock::new();
+static RE_URL_REVISION: OnceLock<Regex> = OnceLock::new();
+static RE_URL_USING_GIT: OnceLock<Regex> = OnceLock::new();
+static RE_MIRROR: OnceLock<Regex> = OnceLock::new();
+static RE_SHA256: OnceLock<Regex> = OnceLock::new();
+static RE_DEPENDS_OS: OnceLock<Regex> = OnceLock::new();
+static RE_DEPENDS_MACOS: OnceLock<Regex> = OnceLock::new();
+static RE_DEPENDS_ARCH: OnceLock<Regex> = OnceLock::new();
+static RE_ENV_STD: OnceLock<Regex> = OnceLock::new();
+static RE_ENV_CC: OnceLock<Regex> = OnceLock::new();
+static RE_FAILS_WITH: OnceLock<Regex> = OnceLock::new();
 static RE_CASK_URL: OnceLock<Regex> = OnceLock::new();
 static RE_CASK_SHA: OnceLock<Regex> = OnceLock::new();
 
@@ -67,6 +148,129 @@ pub struct BinInstall {
     pub optional: bool,
 }
 
+/// macOS codenames in release order, as used by `depends_on macos:` and bottle tags.
+pub(crate) const MACOS_RELEASES: &[&str] = &[
+    "yosemite",
+    "el_capitan",
+    "sierra",
+    "high_sierra",
+    "mojave",
+    "catalina",
+    "big_sur",
+    "monterey",
+    "ventura",
+    "sonoma",
+    "sequoia",
+    "tahoe",
+];
+
+/// Top-level platform constraints a formula declares. Constraints inside `on_macos`,
+/// `on_linux`, `on_arm` or `on_intel` blocks are conditional and not recorded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlatformRequirements {
+    /// `depends_on :linux` or `depends_on :macos`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+    /// `depends_on macos: ">= :monterey"` as `(">=", "monterey")`; a bare `:monterey`
+    /// means `>=`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub macos: Option<(String, String)>,
+    /// `depends_on arch: :arm64` or `:x86_64` (`:intel` is recorded as `x86_64`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+}
+
+impl PlatformRequirements {
+    /// Why a `detect_platform()` tag such as `arm64_sonoma` or `x86_64_linux` cannot
+    /// build this formula, or `None` when it can (or the platform is unknown).
+    pub fn unmet(&self, platform: &str) -> Option<String> {
+        if platform == "unknown" {
+            return None;
+        }
+        let is_linux = platform.ends_with("_linux");
+        let arch = if platform.starts_with("arm64_") {
+            "arm64"
+        } else {
+            "x86_64"
+        };
+
+        match self.os.as_deref() {
+            Some("linux") if !is_linux => return Some("requires Linux".to_string()),
+            Some("macos") if is_linux => return Some("requires macOS".to_string()),
+            _ => {}
+        }
+
+        if let Some(required) = self.arch.as_deref() {
+            if required != arch {
+                return Some(format!("requires an {} CPU (this is {})", required, arch));
+            }
+        }
+
+        // Like Homebrew, a macOS release constraint only restricts macOS itself.
+        if let (Some((op, release)), false) = (&self.macos, is_linux) {
+            let current = platform.trim_start_matches("arm64_");
+            let position = |name: &str| MACOS_RELEASES.iter().position(|r| *r == name);
+            let (Some(required), Some(running)) = (position(release), position(current)) else {
+                return None;
+            };
+            let satisfied = match op.as_str() {
+                ">" => running > required,
+                "<=" => running <= required,
+                "<" => running < required,
+                "==" => running == required,
+                _ => running >= required,
+            };
+            if !satisfied {
+                let symbol = match op.as_str() {
+                    ">=" => "≥",
+                    "<=" => "≤",
+                    "==" => "=",
+                    other => other,
+                };
+                return Some(format!(
+                    "requires macOS {} {} (this is {})",
+                    symbol, release, current
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// How a formula asks its source build to be set up.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildEnvironment {
+    /// `env :std`: build without wax's tweaks (no ccache wrapping) and with
+    /// optimization flags stripped from an inherited `CFLAGS`/`CXXFLAGS`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub std_env: bool,
+    /// C compiler to build with: a literal `ENV["CC"] = "gcc-13"`, or the other
+    /// compiler family when the formula declares a bare `fails_with :clang` / `:gcc`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiler: Option<String>,
+}
+
+impl BuildEnvironment {
+    /// C++ driver matching `compiler`: `gcc-13` → `g++-13`, `clang` → `clang++`.
+    pub fn cxx_compiler(&self) -> Option<String> {
+        let cc = self.compiler.as_deref()?;
+        let (dir, file) = match cc.rfind('/') {
+            Some(i) => cc.split_at(i + 1),
+            None => ("", cc),
+        };
+        let cxx = if let Some(rest) = file.strip_prefix("gcc") {
+            format!("g++{}", rest)
+        } else if let Some(rest) = file.strip_prefix("clang") {
+            format!("clang++{}", rest)
+        } else if file == "cc" {
+            "c++".to_string()
+        } else {
+            return None;
+        };
+        Some(format!("{}{}", dir, cxx))
+    }
+}
+
 impl FormulaParser {
     #[instrument(skip(ruby_content))]
     pub fn parse_ruby_formula(name: &str, ruby_content: &str) -> Result<ParsedFormula> {
@@ -80,31 +284,60 @@ impl FormulaParser {
                 Err(e)
             }
         })?;
-        let sha256 = Self::extract_field(ruby_content, "sha256").or_else(|e| {
-            if head_url.is_some() {
-                Ok(String::new())
-            } else {
-                Err(e)
+        let (tag, revision, using_git) = Self::extract_url_options(ruby_content);
+        let mut source = FormulaSource {
+            url,
+            sha256: Checksum::Missing,
+            version: String::new(),
+            tag,
+            revision,
+            using_git,
+            mirrors: Self::extract_mirrors(ruby_content),
+        };
+        source.sha256 = match Self::extract_sha256(ruby_content) {
+            Some(checksum) => checksum,
+            None if head_url.is_some() || source.is_git() => Checksum::Missing,
+            None => {
+                return Err(WaxError::ParseError(
+                    "Field 'sha256' not found in formula".to_string(),
+                ))
             }
-        })?;
+        };
         let desc = Self::extract_field(ruby_content, "desc").ok();
         let homepage = Self::extract_field(ruby_content, "homepage").ok();
         let license = Self::extract_field(ruby_content, "license").ok();
 
         // Prefer an explicit `version "x.y.z"` field; fall back to parsing from URL.
-        let version = Self::extract_field(ruby_content, "version")
+        source.version = Self::extract_field(ruby_content, "version")
             .ok()
             .filter(|v| !v.is_empty())
             .unwrap_or_else(|| {
-                if url.is_empty() {
+                if source.url.is_empty() {
                     "HEAD".to_string()
+                } else if let Some(tag_version) = source
+                    .tag
+                    .as_deref()
+                    .and_then(Self::extract_version_from_tag)
+                {
+                    tag_version
                 } else {
-                    Self::extract_version_from_url(&url)
+                    Self::extract_version_from_url(&source.url)
                 }
             });
 
+        // `url "https://…/foo-#{version}.tar.gz"` builds on the version stanza.
+        source.url = Self::interpolate_source(&source.url, name, &source.version);
+        source.mirrors = source
+            .mirrors
+            .iter()
+            .map(|mirror| Self::interpolate_source(mirror, name, &source.version))
+            .collect();
+
+        let options = Self::extract_options(ruby_content);
         let runtime_dependencies = Self::extract_dependencies(ruby_content, false);
         let build_dependencies = Self::extract_dependencies(ruby_content, true);
+        let requirements = Self::extract_platform_requirements(ruby_content);
+        let build_env = Self::extract_build_env(ruby_content);
 
         let install_block = Self::extract_install_block(ruby_content)?;
         let build_system = Self::detect_build_system(&install_block);
@@ -121,11 +354,7 @@ impl FormulaParser {
             desc,
             homepage,
             license,
-            source: FormulaSource {
-                url,
-                sha256,
-                version,
-            },
+            source,
             head_url,
             runtime_dependencies,
             build_dependencies,
@@ -134,12 +363,117 @@ impl FormulaParser {
             configure_args,
             bin_installs,
             bin_install_targets,
+            options,
+            requirements,
+            build_env,
         })
     }
 
+    /// `env :std`, a literal `ENV["CC"] = "..."` and `fails_with :clang`/`:gcc`.
+    /// An explicit `ENV["CC"]` wins over the compiler implied by `fails_with`. Only an
+    /// unqualified `fails_with` switches compilers: `fails_with gcc: "5"` or a
+    /// `fails_with :clang do build 1400 end` block names old releases, not the one
+    /// installed here, and the other compiler may not even exist on this machine.
+    fn extract_build_env(content: &str) -> BuildEnvironment {
+        let re_std = RE_ENV_STD.get_or_init(|| Regex::new(r"(?m)^\s*env\s+:std\b").unwrap());
+        let re_cc = RE_ENV_CC
+            .get_or_init(|| Regex::new(r##"(?m)^\s*ENV\[\s*"CC"\s*\]\s*=\s*"([^"#]+)""##).unwrap());
+        let re_fails = RE_FAILS_WITH
+            .get_or_init(|| Regex::new(r"(?m)^\s*fails_with\s+:(clang|gcc)\s*$").unwrap());
+
+        let explicit = re_cc.captures(content).map(|c| c[1].trim().to_string());
+        let failing: Vec<&str> = re_fails
+            .captures_iter(content)
+            .map(|c| c.get(1).unwrap().as_str())
+            .collect();
+        let fallback = match (failing.contains(&"clang"), failing.contains(&"gcc")) {
+            (true, false) => Some("gcc".to_string()),
+            (false, true) => Some("clang".to_string()),
+            _ => None,
+        };
+
+        BuildEnvironment {
+            std_env: re_std.is_match(content),
+            compiler: explicit.or(fallback),
+        }
+    }
+
+    /// Names from `option "with-foo", "description"` lines.
+    fn extract_options(content: &str) -> Vec<String> {
+        let re = RE_OPTION.get_or_init(|| Regex::new(r#"(?m)^\s*option\s+"([^"]+)""#).unwrap());
+        re.captures_iter(content)
+            .map(|c| c[1].trim_start_matches("--").to_string())
+            .collect()
+    }
+
+    /// HEAD URL from either `head "git-url"` or a `head do ... url "git-url" ... end` block.
     fn extract_head_url(content: &str) -> Option<String> {
         let re = RE_HEAD.get_or_init(|| Regex::new(r#"(?m)^\s*head\s+"([^"]+)""#).unwrap());
-        re.captures(content).map(|c| c[1].to_string())
+        if let Some(c) = re.captures(content) {
+            return Some(c[1].to_string());
+        }
+        let block = Self::extract_named_block(content, "head do")?;
+        Self::extract_field(&block, "url").ok()
+    }
+
+    /// Parse the `tag:`, `revision:` and `using: :git` options that may follow the
+    /// first `url "..."` line, including continuation lines after trailing commas.
+    fn extract_url_options(content: &str) -> (Option<String>, Option<String>, bool) {
+        let mut url_stanza = String::new();
+        let mut in_stanza = false;
+        for line in content.lines() {
+            let trimmed = line.trim();
+            if !in_stanza {
+                if !trimmed.starts_with("url ") {
+                    continue;
+                }
+                in_stanza = true;
+            }
+            url_stanza.push_str(trimmed);
+            url_stanza.push(' ');
+            if !trimmed.ends_with(',') {
+                break;
+            }
+        }
+
+        let re_tag =
+            RE_URL_TAG.get_or_init(|| Regex::new(r#"(?:\btag:|:tag\s*=>)\s*"([^"]+)""#).unwrap());
+        let re_revision = RE_URL_REVISION
+            .get_or_init(|| Regex::new(r#"(?:\brevision:|:revision\s*=>)\s*"([^"]+)""#).unwrap());
+        let re_using = RE_URL_USING_GIT
+            .get_or_init(|| Regex::new(r"(?:\busing:|:using\s*=>)\s*:git\b").unwrap());
+
+        let tag = re_tag.captures(&url_stanza).map(|c| c[1].to_string());
+        let revision = re_revision.captures(&url_stanza).map(|c| c[1].to_string());
+        (tag, revision, re_using.is_match(&url_stanza))
+    }
+
+    /// `mirror "..."` lines belonging to the stable source. Stops at the first
+    /// `resource`, `patch`, `head` or method definition, whose URLs are not mirrors
+    /// of the main tarball.
+    fn extract_mirrors(content: &str) -> Vec<String> {
+        let re = RE_MIRROR.get_or_init(|| Regex::new(r#"^mirror\s+"([^"]+)""#).unwrap());
+        let mut mirrors = Vec::new();
+        for line in content.lines() {
+            let trimmed = line.trim();
+            if ["resource ", "patch", "head ", "head do", "def "]
+                .iter()
+                .any(|stop| trimmed.starts_with(stop))
+            {
+                break;
+            }
+            if let Some(c) = re.captures(trimmed) {
+                mirrors.push(c[1].to_string());
+            }
+        }
+        mirrors
+    }
+
+    /// `v1.2.3` / `release-1.2.3` → `1.2.3`; `None` when the tag has no version.
+    fn extract_version_from_tag(tag: &str) -> Option<String> {
+        let start = tag.find(|c: char| c.is_ascii_digit())?;
+        let version = &tag[start..];
+        version.contains('.').then(|| version.to_string())
     }
 
     fn extract_field(content: &str, field: &str) -> Result<String> {
@@ -160,6 +494,36 @@ impl FormulaParser {
         )))
     }
 
+    /// The first top-level `sha256 "…"` or `sha256 :no_check`. Bottle `sha256 cellar: …`
+    /// lines don't match.
+    fn extract_sha256(content: &str) -> Option<Checksum> {
+        let re = RE_SHA256.get_or_init(|| {
+            Regex::new(r#"(?m)^\s*sha256\s+(?:"(?P<hex>[^"]+)"|(?P<no_check>:no_check)\b)"#)
+                .unwrap()
+        });
+        let c = re.captures(content)?;
+        Some(match c.name("hex") {
+            Some(hex) => Checksum::Digest(hex.as_str().to_string()),
+            None => Checksum::NoCheck,
+        })
+    }
+
+    /// Substitute `#{name}`, `#{version}`, `#{version.major}` and `#{version.major_minor}`
+    /// in a source URL. Other interpolations are left as written.
+    fn interpolate_source(template: &str, name: &str, version: &str) -> String {
+        if !template.contains("#{") {
+            return template.to_string();
+        }
+        let parts: Vec<&str> = version.split('.').collect();
+        let major_minor = parts[..parts.len().min(2)].join(".");
+        let short_name = name.rsplit('/').next().unwrap_or(name);
+        template
+            .replace("#{version.major_minor}", &major_minor)
+            .replace("#{version.major}", parts[0])
+            .replace("#{version}", version)
+            .replace("#{name}", short_name)
+    }
+
     fn extract_version_from_url(url: &str) -> String {
         let re = RE_VERSION.get_or_init(|| {
             Regex::new(r"(?:[-_/]|^)(?P<version>\d+\.\d+(?:\.\d+)*(?:[_-][a-z\d]+)*)").unwrap()
@@ -173,25 +537,101 @@ impl FormulaParser {
         "unknown".to_string()
     }
 
+    /// Dependency names from `depends_on` lines, build-time ones if `build_only`, else
+    /// runtime ones. Understands `depends_on "a"`, `depends_on "a" => :build`,
+    /// `depends_on "a" => [:build, :test]` and arrays (`%w[a b]`, `["a", "b"]`), each
+    /// optionally tagged. Test-only and `:optional` dependencies are in neither bucket.
     fn extract_dependencies(content: &str, build_only: bool) -> Vec<String> {
         let re = RE_DEPENDS.get_or_init(|| {
-            Regex::new(r#"(?m)^\s*depends_on\s+"(?P<dep>[^"]+)"(?:\s*=>\s*:(?P<type>\w+))?"#)
-                .unwrap()
+            Regex::new(
+                r#"(?m)^\s*depends_on\s+(?P<spec>"[^"]+"|%w[\[(][^\])]*[\])]|\[[^\]]*\])(?:\s*=>\s*(?P<tags>:\w+|\[[^\]]*\]))?"#,
+            )
+            .unwrap()
         });
+        let re_symbol = RE_DEPENDS_TAG.get_or_init(|| Regex::new(r":(\w+)").unwrap());
+        let re_quoted = RE_DEPENDS_QUOTED.get_or_init(|| Regex::new(r#""([^"]+)""#).unwrap());
 
         let mut deps = Vec::new();
         for cap in re.captures_iter(content) {
-            let is_build = cap
-                .name("type")
-                .map(|m| m.as_str() == "build")
-                .unwrap_or(false);
-            if build_only == is_build {
-                deps.push(cap["dep"].to_string());
+            let tags: Vec<&str> = cap
+                .name("tags")
+                .map(|t| {
+                    re_symbol
+                        .captures_iter(t.as_str())
+                        .map(|c| c.get(1).unwrap().as_str())
+                        .collect()
+                })
+                .unwrap_or_default();
+            let is_build = tags.contains(&"build");
+            if !is_build && tags.iter().any(|t| matches!(*t, "test" | "optional")) {
+                continue;
+            }
+            if build_only != is_build {
+                continue;
+            }
+            let spec = &cap["spec"];
+            if let Some(words) = spec.strip_prefix("%w") {
+                deps.extend(
+                    words[1..words.len() - 1]
+                        .split_whitespace()
+                        .map(str::to_string),
+                );
+            } else {
+                deps.extend(re_quoted.captures_iter(spec).map(|c| c[1].to_string()));
             }
         }
         deps
     }
 
+    fn extract_platform_requirements(content: &str) -> PlatformRequirements {
+        let re_os = RE_DEPENDS_OS
+            .get_or_init(|| Regex::new(r"^depends_on\s+:(?P<os>linux|macos)\b").unwrap());
+        let re_macos = RE_DEPENDS_MACOS.get_or_init(|| {
+            Regex::new(
+                r#"^depends_on\s+macos:\s*(?:"(?P<op>[<>=]+)\s*:(?P<release>\w+)"|:(?P<bare>\w+))"#,
+            )
+            .unwrap()
+        });
+        let re_arch = RE_DEPENDS_ARCH
+            .get_or_init(|| Regex::new(r"^depends_on\s+arch:\s*:(?P<arch>\w+)").unwrap());
+
+        let mut requirements = PlatformRequirements::default();
+        // Indentation of the `on_* do` line whose block is being skipped.
+        let mut skipping: Option<usize> = None;
+        for line in content.lines() {
+            let trimmed = line.trim();
+            let indent = line.len() - line.trim_start().len();
+            if let Some(block_indent) = skipping {
+                if trimmed == "end" && indent == block_indent {
+                    skipping = None;
+                }
+                continue;
+            }
+            if trimmed.starts_with("on_") && trimmed.ends_with(" do") {
+                skipping = Some(indent);
+                continue;
+            }
+
+            if let Some(c) = re_os.captures(trimmed) {
+                requirements.os = Some(c["os"].to_string());
+            } else if let Some(c) = re_macos.captures(trimmed) {
+                let (op, release) = match c.name("bare") {
+                    Some(bare) => (">=", bare.as_str()),
+                    None => (&c["op"], &c["release"]),
+                };
+                requirements.macos = Some((op.to_string(), release.to_string()));
+            } else if let Some(c) = re_arch.captures(trimmed) {
+                let arch = match &c["arch"] {
+                    "intel" => "x86_64",
+                    "arm" => "arm64",
+                    other => other,
+                };
+                requirements.arch = Some(arch.to_string());
+            }
+        }
+        requirements
+    }
+
     fn extract_install_block(content: &str) -> Result<String> {
         let start_marker = "def install";
         if let Some(start_idx) = content.find(start_marker) {
@@ -276,6 +716,16 @@ impl FormulaParser {
         "--help",
     ];
 
+    /// Arguments the builder always passes itself; a formula's copy is dropped.
+    const BUILDER_SUPPLIED_ARGS: &'static [&'static str] =
+        &["--prefix=#{prefix}", "-DCMAKE_INSTALL_PREFIX=#{prefix}"];
+
+    /// `#{prefix}` is the one interpolation the builder can fill in (with the keg
+    /// prefix); arguments using anything else are skipped.
+    fn has_only_prefix_interpolation(arg: &str) -> bool {
+        !arg.replace("#{prefix}", "").contains("#{")
+    }
+
     fn extract_configure_args(install_block: &str) -> Vec<String> {
         // Match args in double quotes: "--flag" or "-DFLAG=val"
         let re_quoted =
@@ -289,7 +739,7 @@ impl FormulaParser {
 
         for cap in re_quoted.captures_iter(install_block) {
             let arg = &cap["arg"];
-            if !arg.contains("#{") && !Self::CMAKE_MODE_VERBS.contains(&arg) {
+            if Self::has_only_prefix_interpolation(arg) && !Self::CMAKE_MODE_VERBS.contains(&arg) {
                 args.push(arg.to_string());
             }
         }
@@ -297,16 +747,19 @@ impl FormulaParser {
         for cap in re_word_array.captures_iter(install_block) {
             let body = &cap["body"];
             for token in body.split_whitespace() {
-                if let Some(m) = re_bare_arg.find(token) {
-                    let arg = m.as_str();
-                    // Skip tokens containing interpolation (#{...})
-                    if !token.contains("#{") {
-                        args.push(arg.to_string());
+                if token.contains("#{") {
+                    if (token.starts_with("--") || token.starts_with("-D"))
+                        && Self::has_only_prefix_interpolation(token)
+                    {
+                        args.push(token.to_string());
                     }
+                } else if let Some(m) = re_bare_arg.find(token) {
+                    args.push(m.as_str().to_string());
                 }
             }
         }
 
+        args.retain(|arg| !Self::BUILDER_SUPPLIED_ARGS.contains(&arg.as_str()));
         args
     }
 
@@ -540,6 +993,18 @@ impl FormulaParser {
     }
 
     pub async fn fetch_formula_rb(formula_name: &str) -> Result<String> {
+        if let Some(core) = crate::tap::local_core_tap() {
+            let path = crate::tap::core_formula_path(&core, formula_name).ok_or_else(|| {
+                WaxError::ParseError(format!(
+                    "Formula {} not found in local core tap {}",
+                    formula_name,
+                    core.display()
+                ))
+            })?;
+            info!("Reading formula from: {}", path.display());
+            return Ok(tokio::fs::read_to_string(&path).await?);
+        }
+
         let first_letter = formula_name
             .chars()
             .next()
@@ -551,9 +1016,9 @@ impl FormulaParser {
             first_letter, formula_name
         );
 
-        debug!("Fetching formula from: {}", url);
+        info!("Fetching formula from: {}", url);
 
-        let client = crate::http_client::default_client();
+        let client = crate::http_client::default_client(&url);
         let response = client.get(&url).send().await?;
 
         if !response.status().is_success() {
@@ -579,9 +1044,9 @@ impl FormulaParser {
             first_letter, cask_name
         );
 
-        debug!("Fetching cask from: {}", url);
+        info!("Fetching cask from: {}", url);
 
-        let client = crate::http_client::default_client();
+        let client = crate::http_client::default_client(&url);
         let response = client.get(&url).send().await?;
 
         if !response.status().is_success() {
@@ -624,6 +1089,30 @@ impl FormulaParser {
 mod tests {
     use super::*;
 
+    #[test]
+    fn extract_dependencies_sorts_each_depends_on_form_into_its_bucket() {
+        let ruby = r#"
+  depends_on "pkgconf" => :build
+  depends_on "openssl@3"
+  depends_on "cmake" => [:build, :test]
+  depends_on "python@3.12" => :test
+  depends_on "gettext" => :optional
+  depends_on "libyaml" => :recommended
+  depends_on %w[zlib xz]
+  depends_on %w(autoconf automake) => :build
+  depends_on ["readline", "ncurses"]
+  depends_on :linux
+"#;
+        assert_eq!(
+            FormulaParser::extract_dependencies(ruby, false),
+            ["openssl@3", "libyaml", "zlib", "xz", "readline", "ncurses"]
+        );
+        assert_eq!(
+            FormulaParser::extract_dependencies(ruby, true),
+            ["pkgconf", "cmake", "autoconf", "automake"]
+        );
+    }
+
     #[test]
     fn test_extract_version_from_url() {
         let url = "https://github.com/example/tree/archive/refs/tags/2.2.1.tar.gz";
@@ -942,13 +1431,78 @@ end
         let parsed = FormulaParser::parse_ruby_formula("drift-wallpaper", formula).unwrap();
         assert_eq!(parsed.source.version, "0.1.0");
         assert!(parsed.source.url.is_empty());
-        assert!(parsed.source.sha256.is_empty());
+        assert_eq!(parsed.source.sha256, Checksum::Missing);
         assert_eq!(
             parsed.head_url.as_deref(),
             Some("https://github.com/undivisible/drift-wallpaper.git")
         );
     }
 
+    #[test]
+    fn parse_head_do_block() {
+        let formula = r#"
+class Tool < Formula
+  desc "Tool with a head block"
+  homepage "https://example.com/tool"
+  url "https://example.com/tool-1.0.tar.gz"
+  sha256 "0f24ce73295b9c512033c46e01766a5035e076735e160eafebbdc86db254bdba"
+
+  head do
+    url "https://github.com/example/tool.git", branch: "main"
+
+    depends_on "autoconf" => :build
+  end
+
+  def install
+    system "./configure", "--prefix=#{prefix}"
+    system "make", "install"
+  end
+end
+"#;
+
+        let parsed = FormulaParser::parse_ruby_formula("tool", formula).unwrap();
+        assert_eq!(parsed.source.url, "https://example.com/tool-1.0.tar.gz");
+        assert_eq!(
+            parsed.head_url.as_deref(),
+            Some("https://github.com/example/tool.git")
+        );
+    }
+
+    #[test]
+    fn interpolated_url_and_prefix_args_are_filled_in() {
+        let formula = r#"
+class Libfoo < Formula
+  desc "Foo library"
+  homepage "https://example.com/libfoo"
+  version "2.4.1"
+  url "https://example.com/releases/#{version.major_minor}/#{name}-#{version}.tar.gz"
+  mirror "https://mirror.example.com/#{name}/#{name}-#{version}.tar.gz"
+  sha256 "0f24ce73295b9c512033c46e01766a5035e076735e160eafebbdc86db254bdba"
+
+  def install
+    system "./configure", "--prefix=#{prefix}", "--sysconfdir=#{prefix}/etc",
+                          "--with-docs=#{doc}", "--disable-silent-rules"
+    system "make", "install"
+  end
+end
+        "#;
+
+        let parsed = FormulaParser::parse_ruby_formula("libfoo", formula).unwrap();
+        assert_eq!(parsed.source.version, "2.4.1");
+        assert_eq!(
+            parsed.source.url,
+            "https://example.com/releases/2.4/libfoo-2.4.1.tar.gz"
+        );
+        assert_eq!(
+            parsed.source.mirrors,
+            vec!["https://mirror.example.com/libfoo/libfoo-2.4.1.tar.gz"]
+        );
+        assert_eq!(
+            parsed.configure_args,
+            vec!["--sysconfdir=#{prefix}/etc", "--disable-silent-rules"]
+        );
+    }
+
     #[test]
     fn test_parse_ruby_formula_comprehensive() {
         let formula = r#"
@@ -990,7 +1544,7 @@ end
             "https://github.com/fastfetch-cli/fastfetch/archive/refs/tags/2.11.2.tar.gz"
         );
         assert_eq!(
-            parsed.source.sha256,
+            parsed.source.sha256.as_str(),
             "0f24ce73295b9c512033c46e01766a5035e076735e160eafebbdc86db254bdba"
         );
         assert_eq!(parsed.source.version, "2.11.2");
@@ -1001,6 +1555,220 @@ end
         assert_eq!(parsed.build_system, BuildSystem::CMake);
     }
 
+    #[test]
+    fn parse_git_tag_source_without_sha256() {
+        let formula = r#"
+class Gitsrc < Formula
+  desc "Built from a tagged git checkout"
+  homepage "https://example.com/gitsrc"
+  url "https://github.com/example/gitsrc.git",
+      tag:      "v1.4.2",
+      revision: "0123456789abcdef0123456789abcdef01234567"
+  license "MIT"
+
+  bottle do
+    sha256 cellar: :any, arm64_sonoma: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+  end
+
+  depends_on "cmake" => :build
+
+  def install
+    system "cmake", "-S", ".", "-B", "build", *std_cmake_args
+    system "cmake", "--install", "build"
+  end
+end
+"#;
+
+        let parsed = FormulaParser::parse_ruby_formula("gitsrc", formula).unwrap();
+        assert!(parsed.source.is_git());
+        assert_eq!(parsed.source.url, "https://github.com/example/gitsrc.git");
+        assert_eq!(parsed.source.tag.as_deref(), Some("v1.4.2"));
+        assert_eq!(
+            parsed.source.revision.as_deref(),
+            Some("0123456789abcdef0123456789abcdef01234567")
+        );
+        assert_eq!(parsed.source.version, "1.4.2");
+        assert_eq!(parsed.source.sha256, Checksum::Missing);
+    }
+
+    #[test]
+    fn parse_using_git_hash_rocket_source() {
+        let formula = r#"
+class Legacy < Formula
+  url "https://example.com/legacy", :using => :git, :tag => "release-2.0.1"
+
+  def install
+    system "make", "install"
+  end
+end
+"#;
+
+        let parsed = FormulaParser::parse_ruby_formula("legacy", formula).unwrap();
+        assert!(parsed.source.is_git());
+        assert!(parsed.source.using_git);
+        assert_eq!(parsed.source.tag.as_deref(), Some("release-2.0.1"));
+        assert_eq!(parsed.source.version, "2.0.1");
+    }
+
+    #[test]
+    fn tarball_source_is_not_git() {
+        let source = FormulaSource {
+            url: "https://example.com/foo-1.0.tar.gz".into(),
+            sha256: "abc".into(),
+            version: "1.0".into(),
+            tag: None,
+            revision: None,
+            using_git: false,
+            mirrors: Vec::new(),
+        };
+        assert!(!source.is_git());
+    }
+
+    #[test]
+    fn sha256_no_check_is_a_sentinel_not_a_digest() {
+        let formula = r#"
+class Snapshot < Formula
+  url "https://example.com/snapshot/latest.tar.gz"
+  version "2024.1"
+  sha256 :no_check
+
+  bottle do
+    sha256 cellar: :any, arm64_sonoma: "aaaa"
+  end
+
+  def install
+    system "make", "install"
+  end
+end
+"#;
+        let parsed = FormulaParser::parse_ruby_formula("snapshot", formula).unwrap();
+        assert_eq!(parsed.source.sha256, Checksum::NoCheck);
+    }
+
+    #[test]
+    fn stable_mirrors_are_parsed_in_order() {
+        let formula = r#"
+class Hello < Formula
+  desc "Program providing model for GNU coding standards and practices"
+  homepage "https://www.gnu.org/software/hello/"
+  url "https://ftp.gnu.org/gnu/hello/hello-2.12.1.tar.gz"
+  mirror "https://ftpmirror.gnu.org/hello/hello-2.12.1.tar.gz"
+  mirror "https://mirrors.kernel.org/gnu/hello/hello-2.12.1.tar.gz"
+  sha256 "8d99142afd92576f30b0cd7cb42a8dc6809998bc5d607d88761f512e26c7db20"
+
+  resource "extra" do
+    url "https://example.com/extra.tar.gz"
+    mirror "https://mirror.example.com/extra.tar.gz"
+  end
+
+  def install
+    system "./configure", "--prefix=#{prefix}"
+    system "make", "install"
+  end
+end
+"#;
+        let parsed = FormulaParser::parse_ruby_formula("hello", formula).unwrap();
+        assert_eq!(
+            parsed.source.download_urls().collect::<Vec<_>>(),
+            vec![
+                "https://ftp.gnu.org/gnu/hello/hello-2.12.1.tar.gz",
+                "https://ftpmirror.gnu.org/hello/hello-2.12.1.tar.gz",
+                "https://mirrors.kernel.org/gnu/hello/hello-2.12.1.tar.gz",
+            ]
+        );
+    }
+
+    #[test]
+    fn platform_requirements_are_parsed_and_checked() {
+        let formula = r#"
+class Mactool < Formula
+  url "https://example.com/mactool-1.0.tar.gz"
+  sha256 "8d99142afd92576f30b0cd7cb42a8dc6809998bc5d607d88761f512e26c7db20"
+
+  depends_on arch: :arm64
+  depends_on macos: ">= :monterey"
+
+  on_linux do
+    depends_on arch: :x86_64
+  end
+
+  def install
+    system "make", "install"
+  end
+end
+"#;
+        let req = FormulaParser::parse_ruby_formula("mactool", formula)
+            .unwrap()
+            .requirements;
+        assert_eq!(req.arch.as_deref(), Some("arm64"));
+        assert_eq!(req.macos, Some((">=".to_string(), "monterey".to_string())));
+        assert_eq!(req.unmet("arm64_sonoma"), None);
+        assert_eq!(
+            req.unmet("arm64_big_sur").as_deref(),
+            Some("requires macOS ≥ monterey (this is big_sur)")
+        );
+        assert_eq!(
+            req.unmet("ventura").as_deref(),
+            Some("requires an arm64 CPU (this is x86_64)")
+        );
+        assert_eq!(req.unmet("arm64_linux"), None);
+
+        let linux_only = FormulaParser::extract_platform_requirements("  depends_on :linux\n");
+        assert_eq!(linux_only.unmet("x86_64_linux"), None);
+        assert_eq!(
+            linux_only.unmet("arm64_sonoma").as_deref(),
+            Some("requires Linux")
+        );
+    }
+
+    #[test]
+    fn build_env_hints_are_parsed() {
+        let formula = r#"
+class Oldtool < Formula
+  url "https://example.com/oldtool-1.0.tar.gz"
+  sha256 "8d99142afd92576f30b0cd7cb42a8dc6809998bc5d607d88761f512e26c7db20"
+
+  env :std
+
+  fails_with :clang
+
+  def install
+    system "./configure", "--prefix=#{prefix}"
+    system "make", "install"
+  end
+end
+"#;
+        let env = FormulaParser::parse_ruby_formula("oldtool", formula)
+            .unwrap()
+            .build_env;
+        assert!(env.std_env);
+        assert_eq!(env.compiler.as_deref(), Some("gcc"));
+        assert_eq!(env.cxx_compiler().as_deref(), Some("g++"));
+
+        let explicit = FormulaParser::extract_build_env(
+            "  fails_with gcc: \"7\"\n  def install\n    ENV[\"CC\"] = \"/usr/bin/gcc-13\"\n  end\n",
+        );
+        assert!(!explicit.std_env);
+        assert_eq!(explicit.compiler.as_deref(), Some("/usr/bin/gcc-13"));
+        assert_eq!(explicit.cxx_compiler().as_deref(), Some("/usr/bin/g++-13"));
+
+        for qualified in [
+            "  fails_with gcc: \"5\"\n",
+            "  fails_with :gcc => \"7\"\n",
+            "  fails_with :clang do\n    build 1400\n  end\n",
+        ] {
+            assert_eq!(
+                FormulaParser::extract_build_env(qualified).compiler,
+                None,
+                "{qualified}"
+            );
+        }
+        assert_eq!(
+            FormulaParser::extract_build_env(""),
+            BuildEnvironment::default()
+        );
+    }
+
     #[test]
     fn test_parse_ruby_formula_no_url_or_head() {
         let formula = r#"
@@ -1017,4 +1785,35 @@ end
             "Expected error when formula lacks both url and head"
         );
     }
+
+    #[test]
+    fn test_parse_and_apply_options() {
+        let formula = r#"
+class Foo < Formula
+  url "https://example.com/foo-1.0.tar.gz"
+  sha256 "abc"
+  option "with-x11", "Build with X11 support"
+  option "without-docs"
+  deprecated_option "with-old" => "with-x11"
+
+  def install
+    system "./configure", "--disable-silent-rules"
+    system "make", "install"
+  end
+end
+        "#;
+
+        let mut parsed = FormulaParser::parse_ruby_formula("foo", formula).unwrap();
+        assert_eq!(parsed.options, vec!["with-x11", "without-docs"]);
+
+        parsed.apply_options(&["with-x11".to_string()]).unwrap();
+        assert_eq!(parsed.configure_args.last().unwrap(), "--with-x11");
+
+        let err = parsed
+            .apply_options(&["with-gtk".to_string()])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("--with-gtk"), "{err}");
+        assert!(err.contains("--without-docs"), "{err}");
+    }
 }
@@ -1,22 +1,81 @@
 //! Shared `reqwest` clients to avoid per-call TLS handshakes and builder overhead.
+//!
+//! Every client is HTTPS-only. A URL whose host is this machine (`http://127.0.0.1:8080`,
+//! a local mirror or test server) gets a plain-HTTP variant instead, since its traffic
+//! never leaves the machine; that variant still refuses redirects to remote plain HTTP.
 
 use crate::version::WAX_VERSION;
 use std::sync::OnceLock;
 use std::time::Duration;
 
-static API_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
-static DOWNLOAD_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
-static DEFAULT_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+struct Clients {
+    timeout: Duration,
+    compress: bool,
+    https: OnceLock<reqwest::Client>,
+    loopback: OnceLock<reqwest::Client>,
+}
+
+impl Clients {
+    const fn new(timeout: Duration, compress: bool) -> Self {
+        Self {
+            timeout,
+            compress,
+            https: OnceLock::new(),
+            loopback: OnceLock::new(),
+        }
+    }
+
+    fn for_url(&'static self, url: &str) -> &'static reqwest::Client {
+        if is_loopback_http(url) {
+            self.loopback
+                .get_or_init(|| build_client(self.timeout, self.compress, false))
+        } else {
+            self.https
+                .get_or_init(|| build_client(self.timeout, self.compress, true))
+        }
+    }
+}
+
+static API_CLIENTS: Clients = Clients::new(Duration::from_secs(30), true);
+static DOWNLOAD_CLIENTS: Clients = Clients::new(Duration::from_secs(300), false);
+static DEFAULT_CLIENTS: Clients = Clients::new(Duration::from_secs(60), true);
 
 fn user_agent() -> String {
     format!("waxpkg/{WAX_VERSION} (https://github.com/plyght/wax)")
 }
 
-fn build_client(timeout: Duration, compress: bool) -> reqwest::Client {
+/// Whether `url` is plain HTTP to this machine (`localhost`, `127.0.0.1`, `[::1]`).
+pub(crate) fn is_loopback_http(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url.trim()) else {
+        return false;
+    };
+    url.scheme() == "http"
+        && url.host_str().is_some_and(|host| {
+            host == "localhost"
+                || host
+                    .trim_matches(['[', ']'])
+                    .parse::<std::net::IpAddr>()
+                    .is_ok_and(|ip| ip.is_loopback())
+        })
+}
+
+fn build_client(timeout: Duration, compress: bool, https_only: bool) -> reqwest::Client {
     let mut builder = reqwest::Client::builder()
         .timeout(timeout)
         .user_agent(user_agent())
-        .https_only(true);
+        .https_only(https_only);
+    if !https_only {
+        builder = builder.redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= 10 {
+                attempt.error("too many redirects")
+            } else if attempt.url().scheme() == "https" || is_loopback_http(attempt.url().as_str())
+            {
+                attempt.follow()
+            } else {
+                attempt.error("refusing redirect to plain HTTP")
+            }
+        }));
+    }
     if compress {
         builder = builder.gzip(true).brotli(true);
     } else {
@@ -26,16 +85,54 @@ fn build_client(timeout: Duration, compress: bool) -> reqwest::Client {
 }
 
 /// Homebrew JSON API: 30s timeout, compressed responses.
-pub fn api() -> &'static reqwest::Client {
-    API_CLIENT.get_or_init(|| build_client(Duration::from_secs(30), true))
+pub fn api(url: &str) -> &'static reqwest::Client {
+    API_CLIENTS.for_url(url)
 }
 
 /// Bottle/cask downloads: 5 minute timeout, raw bytes (no double decompression).
-pub fn download() -> &'static reqwest::Client {
-    DOWNLOAD_CLIENT.get_or_init(|| build_client(Duration::from_secs(300), false))
+pub fn download(url: &str) -> &'static reqwest::Client {
+    DOWNLOAD_CLIENTS.for_url(url)
 }
 
 /// General-purpose client (GitHub, GHCR, ecosystem indexes): 60s, compressed.
-pub fn default_client() -> &'static reqwest::Client {
-    DEFAULT_CLIENT.get_or_init(|| build_client(Duration::from_secs(60), true))
+pub fn default_client(url: &str) -> &'static reqwest::Client {
+    DEFAULT_CLIENTS.for_url(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_client, is_loopback_http};
+
+    #[test]
+    fn only_loopback_mirrors_may_use_plain_http() {
+        assert!(is_loopback_http("http://127.0.0.1:8080"));
+        assert!(is_loopback_http("http://localhost:9/api"));
+        assert!(is_loopback_http("http://[::1]:8080/"));
+        assert!(!is_loopback_http("http://brew.corp.example/api"));
+        assert!(!is_loopback_http("https://127.0.0.1:8080"));
+        assert!(!is_loopback_http("not a url"));
+    }
+
+    #[test]
+    fn only_loopback_urls_get_the_plain_http_client() {
+        let remote = default_client("https://ghcr.io/v2/");
+        assert!(std::ptr::eq(
+            remote,
+            default_client("http://mirror.example/")
+        ));
+        assert!(!std::ptr::eq(
+            remote,
+            default_client("http://127.0.0.1:8080/")
+        ));
+    }
+
+    #[tokio::test]
+    async fn remote_plain_http_is_refused() {
+        let err = default_client("http://mirror.example/")
+            .get("http://mirror.example/formula.json")
+            .send()
+            .await
+            .unwrap_err();
+        assert!(err.is_builder(), "{err:?}");
+    }
 }
@@ -1,12 +1,18 @@
 use crate::bottle::{detect_platform, homebrew_prefix, run_command_with_timeout, SafeCommand};
 use crate::error::{Result, WaxError};
+use crate::state_file::{parse_or_quarantine, to_sorted_json, write_atomic};
 use crate::sudo;
 use crate::ui::dirs;
 use crate::version::sort_versions;
+use console::style;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 use tokio::fs;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tracing::{debug, instrument};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -144,6 +150,17 @@ pub struct InstalledPackage {
     pub bottle_sha256: Option<String>,
     #[serde(default)]
     pub pinned: bool,
+    /// Build options (e.g. `with-x11`) chosen at install time, reapplied on upgrade.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    /// Pulled in to satisfy another formula rather than named by the user. Older
+    /// records lack the field and read as requested, so nothing is removed by mistake.
+    #[serde(default)]
+    pub installed_as_dependency: bool,
+    /// Built with `--no-verify` from a source that declares no sha256. Upgrades and
+    /// reinstalls carry the opt-out so they can rebuild it without asking again.
+    #[serde(default)]
+    pub unverified_source: bool,
 }
 
 fn default_install_mode() -> InstallMode {
@@ -162,10 +179,20 @@ impl InstallState {
 
     pub async fn load(&self) -> Result<HashMap<String, InstalledPackage>> {
         match fs::read_to_string(&self.state_path).await {
-            Ok(json) => {
-                let packages: HashMap<String, InstalledPackage> = serde_json::from_str(&json)?;
-                Ok(packages)
-            }
+            Ok(json) => match parse_or_quarantine(&self.state_path, &json) {
+                Some(packages) => Ok(packages),
+                None => {
+                    // Install dates and options are lost, but versions come back from the Cellar.
+                    let packages = self.scan_cellars(HashMap::new()).await?;
+                    self.save(&packages).await?;
+                    eprintln!(
+                        "{} rebuilt install state from the Cellar ({} packages)",
+                        style("note:").yellow(),
+                        packages.len()
+                    );
+                    Ok(packages)
+                }
+            },
             Err(_) => Ok(HashMap::new()),
         }
     }
@@ -177,9 +204,8 @@ impl InstallState {
             .ok_or_else(|| WaxError::CacheError("Cannot determine parent directory".into()))?;
         fs::create_dir_all(parent).await?;
 
-        let json = serde_json::to_string_pretty(packages)?;
-        fs::write(&self.state_path, json).await?;
-        Ok(())
+        let json = to_sorted_json(packages)?;
+        write_atomic(&self.state_path, json).await
     }
 
     pub async fn add(&self, package: InstalledPackage) -> Result<()> {
@@ -205,21 +231,57 @@ impl InstallState {
         Ok(())
     }
 
+    /// Record `names` as installed on request (`false`) or as dependencies (`true`).
+    pub async fn set_installed_as_dependency(
+        &self,
+        names: &[String],
+        as_dependency: bool,
+    ) -> Result<()> {
+        let mut packages = self.load().await?;
+        let mut changed = false;
+        for name in names {
+            if let Some(pkg) = packages.get_mut(name) {
+                changed |= pkg.installed_as_dependency != as_dependency;
+                pkg.installed_as_dependency = as_dependency;
+            }
+        }
+        if changed {
+            self.save(&packages).await?;
+        }
+        Ok(())
+    }
+
     pub async fn load_formulae_from_cache(&self) -> Result<Vec<crate::api::Formula>> {
         let cache = crate::cache::Cache::new()?;
         cache.load_all_formulae().await
     }
 
+    /// Only wax's own `~/.local/wax/Cellar` is a user install; every Homebrew Cellar
+    /// (including Linuxbrew's) is global.
     fn detect_install_mode(&self, cellar: &Path) -> InstallMode {
-        if cellar.starts_with("/opt/homebrew") || cellar.starts_with("/usr/local") {
-            InstallMode::Global
-        } else {
-            InstallMode::User
+        match InstallMode::User.cellar_path() {
+            Ok(user_cellar) if cellar.starts_with(&user_cellar) => InstallMode::User,
+            _ => InstallMode::Global,
         }
     }
 
     pub async fn sync_from_cellar(&self) -> Result<()> {
-        let mut packages = self.load().await?;
+        let packages = self.load_with_cellar().await?;
+        self.save(&packages).await?;
+        Ok(())
+    }
+
+    /// Recorded packages updated from the Cellars without saving.
+    pub async fn load_with_cellar(&self) -> Result<HashMap<String, InstalledPackage>> {
+        let packages = self.load().await?;
+        self.scan_cellars(packages).await
+    }
+
+    /// Update `packages` from every Cellar wax knows about, dropping entries with no keg.
+    async fn scan_cellars(
+        &self,
+        mut packages: HashMap<String, InstalledPackage>,
+    ) -> Result<HashMap<String, InstalledPackage>> {
         let mut found_packages = std::collections::HashSet::new();
 
         let os = std::env::consts::OS;
@@ -261,8 +323,7 @@ impl InstallState {
         }
 
         packages.retain(|name, _| found_packages.contains(name));
-        self.save(&packages).await?;
-        Ok(())
+        Ok(packages)
     }
 
     async fn scan_cellar_and_update(
@@ -271,52 +332,130 @@ impl InstallState {
         packages: &mut HashMap<String, InstalledPackage>,
         found_packages: &mut std::collections::HashSet<String>,
     ) -> Result<()> {
-        let mut entries = tokio::fs::read_dir(cellar).await?;
+        let install_mode = self.detect_install_mode(cellar);
+        for (package_name, version) in scan_cellar(cellar).await? {
+            found_packages.insert(package_name.clone());
+            if let Some(existing) = packages.get_mut(&package_name) {
+                existing.version = version;
+                existing.install_mode = install_mode;
+            } else {
+                packages.insert(
+                    package_name.clone(),
+                    InstalledPackage {
+                        name: package_name,
+                        version,
+                        platform: detect_platform(),
+                        install_date: 0,
+                        install_mode,
+                        from_source: false,
+                        bottle_rebuild: 0,
+                        bottle_sha256: None,
+                        pinned: false,
+                        options: Vec::new(),
+                        installed_as_dependency: false,
+                        unverified_source: false,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+}
 
-        while let Some(entry) = entries.next_entry().await? {
-            if entry.file_type().await?.is_dir() {
-                let package_name = entry.file_name().to_string_lossy().to_string();
-
-                let mut versions = Vec::new();
-                let mut version_entries = tokio::fs::read_dir(entry.path()).await?;
-                while let Some(version_entry) = version_entries.next_entry().await? {
-                    if version_entry.file_type().await?.is_dir() {
-                        versions.push(version_entry.file_name().to_string_lossy().to_string());
-                    }
-                }
+/// Parallel `read_dir`s while scanning one Cellar's kegs.
+const CELLAR_SCAN_CONCURRENCY: usize = 16;
 
-                if !versions.is_empty() {
-                    sort_versions(&mut versions);
-                    let Some(version) = versions.last().cloned() else {
-                        continue;
-                    };
-
-                    found_packages.insert(package_name.clone());
-                    if let Some(existing) = packages.get_mut(&package_name) {
-                        existing.version = version;
-                        existing.install_mode = self.detect_install_mode(cellar);
-                    } else {
-                        packages.insert(
-                            package_name.clone(),
-                            InstalledPackage {
-                                name: package_name,
-                                version,
-                                platform: detect_platform(),
-                                install_date: 0,
-                                install_mode: self.detect_install_mode(cellar),
-                                from_source: false,
-                                bottle_rebuild: 0,
-                                bottle_sha256: None,
-                                pinned: false,
-                            },
-                        );
-                    }
+/// Last scan of a Cellar: its mtime and, per package directory, that directory's mtime
+/// and newest version. A directory whose mtime is unchanged still has the same versions.
+struct CellarScan {
+    mtime: Option<SystemTime>,
+    kegs: HashMap<String, (Option<SystemTime>, Option<String>)>,
+}
+
+static CELLAR_SCANS: Mutex<Option<HashMap<PathBuf, CellarScan>>> = Mutex::new(None);
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// `(package, newest version)` for every keg in `cellar`, sorted by package name.
+/// Package directories are read concurrently, and only those whose mtime moved since
+/// the previous scan in this process are read again.
+async fn scan_cellar(cellar: &Path) -> Result<Vec<(String, String)>> {
+    let cellar_mtime = mtime(cellar);
+    let previous = CELLAR_SCANS
+        .lock()
+        .ok()
+        .and_then(|mut scans| scans.get_or_insert_with(HashMap::new).remove(cellar));
+
+    let names: Vec<String> = match &previous {
+        Some(scan) if scan.mtime.is_some() && scan.mtime == cellar_mtime => {
+            scan.kegs.keys().cloned().collect()
+        }
+        _ => {
+            let mut names = Vec::new();
+            let mut entries = tokio::fs::read_dir(cellar).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_dir() {
+                    names.push(entry.file_name().to_string_lossy().to_string());
                 }
             }
+            names
         }
+    };
+
+    let semaphore = Arc::new(Semaphore::new(CELLAR_SCAN_CONCURRENCY));
+    let mut tasks = JoinSet::new();
+    for name in names {
+        let keg_dir = cellar.join(&name);
+        let cached = previous
+            .as_ref()
+            .and_then(|scan| scan.kegs.get(&name))
+            .cloned();
+        let semaphore = Arc::clone(&semaphore);
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let dir_mtime = mtime(&keg_dir);
+            if let Some((cached_mtime, version)) = cached {
+                if cached_mtime.is_some() && cached_mtime == dir_mtime {
+                    return Ok::<_, std::io::Error>((name, dir_mtime, version));
+                }
+            }
+            let mut versions = Vec::new();
+            let mut entries = tokio::fs::read_dir(&keg_dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_dir() {
+                    versions.push(entry.file_name().to_string_lossy().to_string());
+                }
+            }
+            sort_versions(&mut versions);
+            Ok((name, dir_mtime, versions.pop()))
+        });
+    }
 
-        Ok(())
+    let mut kegs = HashMap::new();
+    while let Some(joined) = tasks.join_next().await {
+        let (name, dir_mtime, version) = joined
+            .map_err(|e| WaxError::InstallError(format!("Cellar scan task failed: {}", e)))??;
+        kegs.insert(name, (dir_mtime, version));
     }
+
+    let mut found: Vec<(String, String)> = kegs
+        .iter()
+        .filter_map(|(name, (_, version))| Some((name.clone(), version.clone()?)))
+        .collect();
+    found.sort();
+
+    if let Ok(mut scans) = CELLAR_SCANS.lock() {
+        scans.get_or_insert_with(HashMap::new).insert(
+            cellar.to_path_buf(),
+            CellarScan {
+                mtime: cellar_mtime,
+                kegs,
+            },
+        );
+    }
+    Ok(found)
 }
 
 impl Default for InstallState {
@@ -325,12 +464,108 @@ impl Default for InstallState {
     }
 }
 
+/// A package whose recorded version disagrees with what is actually installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionDrift {
+    pub name: String,
+    pub recorded: String,
+    /// Active keg in the Cellar; this is what outdated decisions use.
+    pub on_disk: Option<String>,
+    /// Newest version in the API's `installed` field, when it disagrees with the Cellar.
+    pub reported: Option<String>,
+}
+
+/// Version of the keg `name` actually runs from: the `opt/<name>` target when it points
+/// into `<cellar>/<name>`, otherwise the newest version directory.
+pub fn active_keg_version(prefix: &Path, cellar: &Path, name: &str) -> Option<String> {
+    let keg_dir = cellar.join(name);
+    let opt_link = prefix.join("opt").join(name);
+    if let Ok(target) = std::fs::read_link(&opt_link) {
+        let target = normalize_link_target(&opt_link, &target);
+        if target.parent() == Some(keg_dir.as_path()) && target.is_dir() {
+            if let Some(version) = target.file_name() {
+                return Some(version.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    let mut versions: Vec<String> = std::fs::read_dir(&keg_dir)
+        .ok()?
+        .flatten()
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    sort_versions(&mut versions);
+    versions.pop()
+}
+
+/// Cross-check recorded versions against the Cellar and the API's `installed` field.
+/// Recorded versions are corrected to the Cellar's active keg; every disagreement is
+/// returned so callers can surface it.
+pub fn reconcile_installed_versions(
+    packages: &mut HashMap<String, InstalledPackage>,
+    formulae: &HashMap<&str, &crate::api::Formula>,
+) -> Vec<VersionDrift> {
+    let mut drift = Vec::new();
+    for (name, pkg) in packages.iter_mut() {
+        let mode = pkg.install_mode;
+        let on_disk = mode
+            .prefix()
+            .ok()
+            .zip(mode.cellar_path().ok())
+            .and_then(|(prefix, cellar)| active_keg_version(&prefix, &cellar, name));
+        let reported = formulae
+            .get(name.as_str())
+            .and_then(|f| f.installed.as_ref())
+            .and_then(|installed| {
+                let mut versions: Vec<String> =
+                    installed.iter().map(|i| i.version.clone()).collect();
+                sort_versions(&mut versions);
+                versions.pop()
+            });
+
+        let truth = on_disk.as_deref().unwrap_or(&pkg.version);
+        let disk_differs = on_disk.as_deref().is_some_and(|v| v != pkg.version);
+        let api_differs = reported.as_deref().is_some_and(|v| v != truth);
+        if !disk_differs && !api_differs {
+            continue;
+        }
+
+        debug!(
+            "version drift for {}: recorded {}, cellar {:?}, api {:?}",
+            name, pkg.version, on_disk, reported
+        );
+        drift.push(VersionDrift {
+            name: name.clone(),
+            recorded: pkg.version.clone(),
+            on_disk: on_disk.clone(),
+            reported: reported.filter(|_| api_differs),
+        });
+        if let Some(version) = on_disk {
+            pkg.version = version;
+        }
+    }
+    drift.sort_by(|a, b| a.name.cmp(&b.name));
+    drift
+}
+
+/// What linking one keg did, beyond the links it created.
+#[derive(Debug, Default)]
+struct LinkReport {
+    created: Vec<PathBuf>,
+    /// Entries left in place because something else owns them, with that owner.
+    skipped: Vec<(PathBuf, String)>,
+    /// Entries replaced under `--overwrite`, with their previous owner.
+    displaced: Vec<(PathBuf, String)>,
+}
+
 #[instrument(skip(cellar_path))]
 pub async fn create_symlinks(
     formula_name: &str,
     version: &str,
     cellar_path: &Path,
     dry_run: bool,
+    overwrite: bool,
     install_mode: InstallMode,
 ) -> Result<Vec<PathBuf>> {
     debug!(
@@ -349,7 +584,7 @@ pub async fn create_symlinks(
 
     let prefix = install_mode.prefix()?;
 
-    let mut created_links = Vec::new();
+    let mut report = LinkReport::default();
 
     let link_dirs = vec![
         ("bin", prefix.join("bin")),
@@ -378,7 +613,8 @@ pub async fn create_symlinks(
             &target_dir,
             &formula_path,
             dry_run,
-            &mut created_links,
+            overwrite,
+            &mut report,
         )
         .await?;
     }
@@ -390,29 +626,167 @@ pub async fn create_symlinks(
             .or_else(|_| sudo::sudo_mkdir(&opt_dir))?;
     }
     let opt_link = opt_dir.join(formula_name);
-    if !dry_run && opt_link.symlink_metadata().is_ok() {
-        if opt_link.is_dir() && !opt_link.is_symlink() {
-            fs::remove_dir_all(&opt_link)
-                .await
-                .or_else(|_| sudo::sudo_remove(&opt_link).map(|_| ()))?;
-        } else {
-            fs::remove_file(&opt_link)
-                .await
-                .or_else(|_| sudo::sudo_remove(&opt_link).map(|_| ()))?;
+    if !dry_run {
+        place_symlink(&formula_path, &opt_link).await?;
+        report.created.push(opt_link);
+    }
+
+    let shown = |path: &Path| {
+        path.strip_prefix(&prefix)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    for (link, owner) in &report.displaced {
+        eprintln!(
+            "{} {}: replaced {} (was {})",
+            style("note:").yellow(),
+            style(formula_name).magenta(),
+            shown(link),
+            owner
+        );
+    }
+    if !report.skipped.is_empty() {
+        eprintln!(
+            "{} {}: {} not linked because something else is already there:",
+            style("warning:").yellow(),
+            style(formula_name).magenta(),
+            if report.skipped.len() == 1 {
+                "1 file was".to_string()
+            } else {
+                format!("{} files were", report.skipped.len())
+            }
+        );
+        for (link, owner) in &report.skipped {
+            eprintln!("  {} {}", shown(link), style(format!("({})", owner)).dim());
         }
+        eprintln!(
+            "  {}",
+            style(format!(
+                "run `wax link --overwrite {}` to replace them with this keg's files",
+                formula_name
+            ))
+            .dim()
+        );
+    }
+
+    debug!("Created {} symlinks", report.created.len());
+    Ok(report.created)
+}
+
+/// The owner of whatever sits at `link` if linking `keg` must not replace it without
+/// `--overwrite`: a real file or directory, a live link into another formula's keg, or a
+/// live link somewhere outside the Cellar. Dangling links and links into other versions
+/// of the same formula are fair game.
+fn link_conflict(link: &Path, keg: &Path) -> Option<String> {
+    let meta = std::fs::symlink_metadata(link).ok()?;
+    if !meta.is_symlink() {
+        return Some("a file not managed by wax".to_string());
+    }
+    let target = normalize_link_target(link, &std::fs::read_link(link).ok()?);
+    // Compare resolved paths on both sides: the prefix may sit behind a symlink
+    // (`/var` → `/private/var` on macOS).
+    let target = dunce::canonicalize(&target).ok()?;
+    let keg = dunce::canonicalize(keg).unwrap_or_else(|_| keg.to_path_buf());
+    let formula_dir = keg.parent()?;
+    let cellar = formula_dir.parent()?;
+    if target.starts_with(formula_dir) {
+        return None;
+    }
+    match target.strip_prefix(cellar) {
+        Ok(rest) => rest
+            .components()
+            .next()
+            .map(|owner| owner.as_os_str().to_string_lossy().into_owned()),
+        Err(_) => Some("a link not managed by wax".to_string()),
+    }
+}
+
+/// Link `source` at `link` unless something else owns it, in which case the owner is
+/// reported as skipped, or replaced and reported as displaced under `overwrite`.
+async fn link_entry(
+    source: &Path,
+    link: &Path,
+    keg: &Path,
+    dry_run: bool,
+    overwrite: bool,
+    report: &mut LinkReport,
+) -> Result<()> {
+    if let Some(owner) = link_conflict(link, keg) {
+        if !overwrite {
+            debug!("Not linking {:?}: owned by {}", link, owner);
+            report.skipped.push((link.to_path_buf(), owner));
+            return Ok(());
+        }
+        if !dry_run {
+            place_symlink(source, link).await?;
+        }
+        report.displaced.push((link.to_path_buf(), owner));
+        report.created.push(link.to_path_buf());
+        return Ok(());
     }
     if !dry_run {
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::symlink;
-            symlink(&formula_path, &opt_link)
-                .or_else(|_| sudo::sudo_symlink(&formula_path, &opt_link).map(|_| ()))?;
+        place_symlink(source, link).await?;
+    }
+    report.created.push(link.to_path_buf());
+    Ok(())
+}
+
+/// Remove whatever occupies `path` (file, symlink — dangling or not — or real directory).
+async fn remove_existing(path: &Path) -> Result<()> {
+    let is_real_dir = fs::symlink_metadata(path)
+        .await
+        .map(|m| m.is_dir())
+        .unwrap_or(false);
+    let removed = if is_real_dir {
+        fs::remove_dir_all(path).await
+    } else {
+        fs::remove_file(path).await
+    };
+    match removed {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(_) => sudo::sudo_remove(path),
+    }
+}
+
+/// Point `link` at `source`. Existing entries are judged by `symlink_metadata`, so a
+/// dangling link left by a deleted keg is seen and replaced, as is a link into some
+/// other keg; a link already pointing at `source` is kept as is. If another process
+/// recreates `link` between the removal and the `symlink` call, it is replaced once more.
+async fn place_symlink(source: &Path, link: &Path) -> Result<()> {
+    if let Ok(meta) = fs::symlink_metadata(link).await {
+        if meta.is_symlink() {
+            if let Ok(target) = fs::read_link(link).await {
+                if normalize_link_target(link, &target) == source {
+                    return Ok(());
+                }
+            }
         }
-        created_links.push(opt_link);
+        debug!("Replacing existing entry at {:?}", link);
+        remove_existing(link).await?;
     }
 
-    debug!("Created {} symlinks", created_links.len());
-    Ok(created_links)
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::symlink;
+        match symlink(source, link) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                debug!("{:?} reappeared while linking; replacing it", link);
+                remove_existing(link).await?;
+                symlink(source, link).or_else(|_| sudo::sudo_symlink(source, link))
+            }
+            Err(_) => sudo::sudo_symlink(source, link),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = source;
+        Err(WaxError::PlatformNotSupported(
+            "Symlinks not supported on this platform".to_string(),
+        ))
+    }
 }
 
 fn link_directory_recursive<'a>(
@@ -420,7 +794,8 @@ fn link_directory_recursive<'a>(
     target_dir: &'a Path,
     formula_base: &'a Path,
     dry_run: bool,
-    created_links: &'a mut Vec<PathBuf>,
+    overwrite: bool,
+    report: &'a mut LinkReport,
 ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
     Box::pin(async move {
         let mut entries = fs::read_dir(source_dir).await?;
@@ -447,64 +822,38 @@ fn link_directory_recursive<'a>(
                             &target_path,
                             formula_base,
                             dry_run,
-                            created_links,
+                            overwrite,
+                            report,
                         )
                         .await?;
                         continue;
                     }
-                    if !dry_run {
-                        debug!("Removing existing symlink/file at {:?}", target_path);
-                        fs::remove_file(&target_path)
-                            .await
-                            .or_else(|_| sudo::sudo_remove(&target_path).map(|_| ()))?;
-                    }
                 }
 
-                if !dry_run {
-                    #[cfg(unix)]
-                    {
-                        use std::os::unix::fs::symlink;
-                        symlink(&source_path, &target_path).or_else(|_| {
-                            sudo::sudo_symlink(&source_path, &target_path).map(|_| ())
-                        })?;
-                    }
-                    #[cfg(not(unix))]
-                    {
-                        return Err(WaxError::PlatformNotSupported(
-                            "Symlinks not supported on this platform".to_string(),
-                        ));
-                    }
-                }
-                created_links.push(target_path);
+                link_entry(
+                    &source_path,
+                    &target_path,
+                    formula_base,
+                    dry_run,
+                    overwrite,
+                    report,
+                )
+                .await?;
             } else {
-                if target_path.symlink_metadata().is_ok() {
-                    if !dry_run {
-                        debug!("Removing existing symlink/file at {:?}", target_path);
-                        fs::remove_file(&target_path)
-                            .await
-                            .or_else(|_| sudo::sudo_remove(&target_path).map(|_| ()))?;
-                    } else {
-                        debug!("Symlink target already exists: {:?}", target_path);
-                        continue;
-                    }
+                if dry_run && target_path.symlink_metadata().is_ok() {
+                    debug!("Symlink target already exists: {:?}", target_path);
+                    continue;
                 }
 
-                if !dry_run {
-                    #[cfg(unix)]
-                    {
-                        use std::os::unix::fs::symlink;
-                        symlink(&source_path, &target_path).or_else(|_| {
-                            sudo::sudo_symlink(&source_path, &target_path).map(|_| ())
-                        })?;
-                    }
-                    #[cfg(not(unix))]
-                    {
-                        return Err(WaxError::PlatformNotSupported(
-                            "Symlinks not supported on this platform".to_string(),
-                        ));
-                    }
-                }
-                created_links.push(target_path);
+                link_entry(
+                    &source_path,
+                    &target_path,
+                    formula_base,
+                    dry_run,
+                    overwrite,
+                    report,
+                )
+                .await?;
             }
         }
         Ok(())
@@ -524,8 +873,12 @@ pub async fn remove_symlinks(
         formula_name, version, dry_run, install_mode
     );
 
-    let formula_path = cellar_path.join(formula_name).join(version);
-    let formula_path = dunce::canonicalize(&formula_path).unwrap_or(formula_path);
+    let raw_formula_path = cellar_path.join(formula_name).join(version);
+    let formula_path = dunce::canonicalize(&raw_formula_path).unwrap_or(raw_formula_path.clone());
+    let mut kegs = vec![formula_path.clone()];
+    if raw_formula_path != formula_path {
+        kegs.push(raw_formula_path);
+    }
     let prefix = install_mode.prefix()?;
 
     let mut removed_links = Vec::new();
@@ -542,14 +895,8 @@ pub async fn remove_symlinks(
     for (subdir, target_dir) in link_dirs {
         let source_dir = formula_path.join(subdir);
 
-        unlink_directory_recursive(
-            &source_dir,
-            &target_dir,
-            &formula_path,
-            dry_run,
-            &mut removed_links,
-        )
-        .await?;
+        unlink_directory_recursive(&source_dir, &target_dir, &kegs, dry_run, &mut removed_links)
+            .await?;
     }
 
     #[cfg(unix)]
@@ -558,8 +905,7 @@ pub async fn remove_symlinks(
         if let Ok(metadata) = fs::symlink_metadata(&opt_link).await {
             if metadata.is_symlink() {
                 if let Ok(link_target) = fs::read_link(&opt_link).await {
-                    let link_target = dunce::canonicalize(&link_target).unwrap_or(link_target);
-                    if link_target.starts_with(&formula_path) {
+                    if link_points_into(&opt_link, &link_target, &kegs) {
                         if !dry_run {
                             fs::remove_file(&opt_link)
                                 .await
@@ -576,17 +922,322 @@ pub async fn remove_symlinks(
     Ok(removed_links)
 }
 
+/// Remove prefix symlinks that point into `Cellar/<formula_name>` but no longer resolve.
+///
+/// `remove_symlinks` walks the keg to find what it linked, so it cannot help once the keg
+/// directory itself has been deleted by hand; this sweeps the prefix link dirs instead.
+#[instrument(skip(cellar_path))]
+pub async fn remove_dangling_symlinks(
+    formula_name: &str,
+    cellar_path: &Path,
+    dry_run: bool,
+    install_mode: InstallMode,
+) -> Result<Vec<PathBuf>> {
+    let prefix = install_mode.prefix()?;
+    let targets = SweepTargets::new(cellar_path, [(formula_name, None)]);
+
+    let mut found = HashMap::new();
+    for subdir in LINK_SUBDIRS {
+        sweep_dangling_in_dir(&prefix.join(subdir), &targets, dry_run, &mut found).await?;
+    }
+    let mut removed_links = found
+        .remove(formula_name)
+        .map(|links: DanglingLinks| links.removed)
+        .unwrap_or_default();
+
+    removed_links
+        .extend(remove_dangling_opt_link(formula_name, cellar_path, dry_run, install_mode).await?);
+
+    debug!("Removed {} dangling symlinks", removed_links.len());
+    Ok(removed_links)
+}
+
+/// Prefix symlinks found by a dangling-link sweep, split by how they were handled.
+#[derive(Debug, Default)]
+pub struct DanglingLinks {
+    /// Repointed at the same path inside the current keg.
+    pub relinked: Vec<PathBuf>,
+    /// No counterpart in the current keg, so removed.
+    pub removed: Vec<PathBuf>,
+}
+
+impl DanglingLinks {
+    pub fn is_empty(&self) -> bool {
+        self.relinked.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Prefix directories that hold links into kegs.
+const LINK_SUBDIRS: [&str; 6] = ["bin", "lib", "include", "share", "etc", "sbin"];
+
+/// Repair prefix symlinks still pointing at removed kegs of the `(name, version)`
+/// formulae, all in one sweep of the `install_mode` prefix.
+///
+/// An upgrade unlinks by walking the *old* keg, so anything it missed is left pointing at
+/// a deleted version. Links whose path also exists in the `version` keg are repointed
+/// there; the rest are removed. Formulae with nothing to repair are left out of the map.
+#[instrument(skip(kegs, cellar_path))]
+pub async fn relink_dangling_symlinks(
+    kegs: &[(&str, &str)],
+    cellar_path: &Path,
+    dry_run: bool,
+    install_mode: InstallMode,
+) -> Result<HashMap<String, DanglingLinks>> {
+    let prefix = install_mode.prefix()?;
+    let targets = SweepTargets::new(
+        cellar_path,
+        kegs.iter().map(|(name, version)| {
+            let keg = cellar_path.join(name).join(version);
+            (*name, Some(dunce::canonicalize(&keg).unwrap_or(keg)))
+        }),
+    );
+
+    let mut found: HashMap<String, DanglingLinks> = HashMap::new();
+    for subdir in LINK_SUBDIRS {
+        sweep_dangling_in_dir(&prefix.join(subdir), &targets, dry_run, &mut found).await?;
+    }
+
+    for (name, keg) in &targets.kegs {
+        let Some(keg) = keg else { continue };
+        let opt_link = prefix.join("opt").join(name);
+        if fs::symlink_metadata(&opt_link).await.is_err() || fs::metadata(&opt_link).await.is_ok() {
+            continue;
+        }
+        if let Ok(target) = fs::read_link(&opt_link).await {
+            if link_points_into(&opt_link, &target, &keg_roots(name, cellar_path)) {
+                if !dry_run {
+                    replace_symlink(keg, &opt_link).await?;
+                }
+                found
+                    .entry(name.clone())
+                    .or_default()
+                    .relinked
+                    .push(opt_link);
+            }
+        }
+    }
+
+    debug!(
+        "Repaired dangling symlinks of {} of {} formulae",
+        found.len(),
+        kegs.len()
+    );
+    Ok(found)
+}
+
+/// The kegs a dangling-link sweep looks for, indexed by every `Cellar/<name>` root so
+/// each link is matched with a walk up its target rather than a scan of all kegs.
+struct SweepTargets {
+    roots: HashMap<PathBuf, usize>,
+    /// Formula name and, when relinking, the current keg to repoint links into.
+    kegs: Vec<(String, Option<PathBuf>)>,
+}
+
+impl SweepTargets {
+    fn new<'a>(
+        cellar_path: &Path,
+        kegs: impl IntoIterator<Item = (&'a str, Option<PathBuf>)>,
+    ) -> Self {
+        let mut targets = SweepTargets {
+            roots: HashMap::new(),
+            kegs: Vec::new(),
+        };
+        for (name, relink_keg) in kegs {
+            let index = targets.kegs.len();
+            for root in keg_roots(name, cellar_path) {
+                targets.roots.insert(root, index);
+            }
+            targets.kegs.push((name.to_string(), relink_keg));
+        }
+        targets
+    }
+
+    /// The `Cellar/<name>` root `target` lies under and the index of its keg.
+    fn find<'a>(&'a self, target: &'a Path) -> Option<(&'a Path, usize)> {
+        target
+            .ancestors()
+            .find_map(|dir| self.roots.get(dir).map(|&index| (dir, index)))
+    }
+}
+
+/// `Cellar/<formula_name>` as given and, if different, with the Cellar canonicalized.
+fn keg_roots(formula_name: &str, cellar_path: &Path) -> Vec<PathBuf> {
+    let keg_root = cellar_path.join(formula_name);
+    let mut roots = vec![keg_root.clone()];
+    if let Ok(canonical_cellar) = dunce::canonicalize(cellar_path) {
+        let canonical_root = canonical_cellar.join(formula_name);
+        if canonical_root != keg_root {
+            roots.push(canonical_root);
+        }
+    }
+    roots
+}
+
+async fn replace_symlink(source: &Path, link: &Path) -> Result<()> {
+    fs::remove_file(link)
+        .await
+        .or_else(|_| sudo::sudo_remove(link).map(|_| ()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::symlink;
+        symlink(source, link).or_else(|_| sudo::sudo_symlink(source, link).map(|_| ()))?;
+    }
+    Ok(())
+}
+
+/// Remove `opt/<formula_name>` if it points into `Cellar/<formula_name>` but no longer
+/// resolves. Cheap enough to run after every uninstall, unlike a full prefix sweep.
+pub async fn remove_dangling_opt_link(
+    formula_name: &str,
+    cellar_path: &Path,
+    dry_run: bool,
+    install_mode: InstallMode,
+) -> Result<Option<PathBuf>> {
+    let opt_link = install_mode.prefix()?.join("opt").join(formula_name);
+    let Ok(target) = fs::read_link(&opt_link).await else {
+        return Ok(None);
+    };
+    if fs::metadata(&opt_link).await.is_ok() {
+        return Ok(None);
+    }
+
+    if !link_points_into(&opt_link, &target, &keg_roots(formula_name, cellar_path)) {
+        return Ok(None);
+    }
+
+    if !dry_run {
+        fs::remove_file(&opt_link)
+            .await
+            .or_else(|_| sudo::sudo_remove(&opt_link).map(|_| ()))?;
+    }
+    Ok(Some(opt_link))
+}
+
+/// Remove `start` and each parent up to (not including) `stop` while they are empty.
+pub async fn prune_empty_dirs(start: &Path, stop: &Path) {
+    let mut dir = start.to_path_buf();
+    while dir.starts_with(stop) && dir != stop {
+        if fs::remove_dir(&dir).await.is_err() {
+            break;
+        }
+        if !dir.pop() {
+            break;
+        }
+    }
+}
+
+/// Whether a prefix symlink resolves into one of `kegs`. Relative targets are resolved
+/// against the link, and dangling targets are compared lexically.
+fn link_points_into(link: &Path, target: &Path, kegs: &[PathBuf]) -> bool {
+    let target = normalize_link_target(link, target);
+    if kegs.iter().any(|keg| target.starts_with(keg)) {
+        return true;
+    }
+    dunce::canonicalize(&target)
+        .map(|resolved| kegs.iter().any(|keg| resolved.starts_with(keg)))
+        .unwrap_or(false)
+}
+
+/// Walk `dir` for symlinks into any of `targets`' kegs that no longer resolve. When the
+/// keg has a current version to relink into, a link whose path (minus the old version)
+/// exists there is repointed instead of removed.
+fn sweep_dangling_in_dir<'a>(
+    dir: &'a Path,
+    targets: &'a SweepTargets,
+    dry_run: bool,
+    found: &'a mut HashMap<String, DanglingLinks>,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
+    Box::pin(async move {
+        let mut entries = match fs::read_dir(dir).await {
+            Ok(e) => e,
+            Err(_) => return Ok(()),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let meta = match fs::symlink_metadata(&path).await {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+
+            if meta.is_symlink() {
+                let Ok(target) = fs::read_link(&path).await else {
+                    continue;
+                };
+                let target = normalize_link_target(&path, &target);
+                let Some((root, index)) = targets.find(&target) else {
+                    continue;
+                };
+                if fs::metadata(&path).await.is_ok() {
+                    continue;
+                }
+
+                let (name, relink_keg) = &targets.kegs[index];
+                let found = found.entry(name.clone()).or_default();
+                let replacement = relink_keg.as_ref().and_then(|keg| {
+                    let in_keg: PathBuf = target
+                        .strip_prefix(root)
+                        .ok()?
+                        .components()
+                        .skip(1)
+                        .collect();
+                    let candidate = keg.join(in_keg);
+                    candidate.symlink_metadata().is_ok().then_some(candidate)
+                });
+
+                if let Some(source) = replacement {
+                    if !dry_run {
+                        replace_symlink(&source, &path).await?;
+                    }
+                    found.relinked.push(path);
+                } else {
+                    if !dry_run {
+                        fs::remove_file(&path)
+                            .await
+                            .or_else(|_| sudo::sudo_remove(&path).map(|_| ()))?;
+                    }
+                    found.removed.push(path);
+                }
+            } else if meta.is_dir() {
+                sweep_dangling_in_dir(&path, targets, dry_run, found).await?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Resolve a symlink target against the link's parent without touching the filesystem,
+/// so dangling targets can still be compared against a Cellar path.
+fn normalize_link_target(link: &Path, target: &Path) -> PathBuf {
+    let joined = if target.is_absolute() {
+        target.to_path_buf()
+    } else {
+        link.parent().unwrap_or(Path::new("")).join(target)
+    };
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
 fn unlink_directory_recursive<'a>(
     source_dir: &'a Path,
     target_dir: &'a Path,
-    formula_path: &'a Path,
+    kegs: &'a [PathBuf],
     dry_run: bool,
     removed_links: &'a mut Vec<PathBuf>,
 ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
     Box::pin(async move {
         #[cfg(not(unix))]
         {
-            let _ = (source_dir, target_dir, formula_path, dry_run, removed_links);
+            let _ = (source_dir, target_dir, kegs, dry_run, removed_links);
         }
 
         #[cfg(not(unix))]
@@ -611,8 +1262,7 @@ fn unlink_directory_recursive<'a>(
 
                 if target_meta.is_symlink() {
                     if let Ok(link_target) = fs::read_link(&target_path).await {
-                        let link_target = dunce::canonicalize(&link_target).unwrap_or(link_target);
-                        if link_target.starts_with(formula_path) {
+                        if link_points_into(&target_path, &link_target, kegs) {
                             if !dry_run {
                                 fs::remove_file(&target_path)
                                     .await
@@ -625,14 +1275,348 @@ fn unlink_directory_recursive<'a>(
                     unlink_directory_recursive(
                         &source_path,
                         &target_path,
-                        formula_path,
+                        kegs,
                         dry_run,
                         removed_links,
                     )
                     .await?;
+                    // Drop subdirectories linking created once nothing else lives there;
+                    // the top-level prefix dirs are never passed as `target_path`.
+                    if !dry_run {
+                        let _ = fs::remove_dir(&target_path).await;
+                    }
                 }
             }
             Ok(())
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn scan_cellar_picks_newest_versions_and_sees_new_kegs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cellar = tmp.path().join("Cellar");
+        for keg in ["jq/1.6", "jq/1.7.1", "wget/1.21.4", "zlib/1.3"] {
+            std::fs::create_dir_all(cellar.join(keg)).unwrap();
+        }
+        std::fs::create_dir_all(cellar.join("empty")).unwrap();
+
+        let found = scan_cellar(&cellar).await.unwrap();
+        assert_eq!(
+            found,
+            vec![
+                ("jq".to_string(), "1.7.1".to_string()),
+                ("wget".to_string(), "1.21.4".to_string()),
+                ("zlib".to_string(), "1.3".to_string()),
+            ]
+        );
+
+        std::fs::create_dir_all(cellar.join("jq/1.8.0")).unwrap();
+        std::fs::create_dir_all(cellar.join("empty/2.0")).unwrap();
+        let rescanned = scan_cellar(&cellar).await.unwrap();
+        assert!(rescanned.contains(&("jq".to_string(), "1.8.0".to_string())));
+        assert!(rescanned.contains(&("empty".to_string(), "2.0".to_string())));
+        assert_eq!(rescanned.len(), 4);
+    }
+
+    #[test]
+    fn normalize_link_target_resolves_relative_parents() {
+        let link = Path::new("/opt/homebrew/bin/jq");
+        let target = Path::new("../Cellar/jq/1.7.1/bin/jq");
+        assert_eq!(
+            normalize_link_target(link, target),
+            PathBuf::from("/opt/homebrew/Cellar/jq/1.7.1/bin/jq")
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn sweep_dangling_in_dir_only_removes_broken_links_into_keg() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let cellar = tmp.path().join("Cellar");
+        let bin = tmp.path().join("bin");
+        std::fs::create_dir_all(cellar.join("other/1.0/bin")).unwrap();
+        std::fs::write(cellar.join("other/1.0/bin/other"), "").unwrap();
+        std::fs::create_dir_all(&bin).unwrap();
+
+        symlink(cellar.join("jq/1.7.1/bin/jq"), bin.join("jq")).unwrap();
+        symlink("../Cellar/jq/1.7.1/bin/jq-extra", bin.join("jq-extra")).unwrap();
+        symlink(cellar.join("other/1.0/bin/other"), bin.join("other")).unwrap();
+
+        let targets = SweepTargets::new(&cellar, [("jq", None)]);
+        let mut found = HashMap::new();
+        sweep_dangling_in_dir(&bin, &targets, false, &mut found)
+            .await
+            .unwrap();
+
+        assert_eq!(found.len(), 1);
+        let mut removed = found.remove("jq").unwrap().removed;
+        removed.sort();
+        assert_eq!(removed, vec![bin.join("jq"), bin.join("jq-extra")]);
+        assert!(bin.join("other").symlink_metadata().is_ok());
+        assert!(bin.join("jq").symlink_metadata().is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn linking_repairs_dangling_links_but_keeps_foreign_ones() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let keg = tmp.path().join("Cellar/jq/1.8.0");
+        let bin = tmp.path().join("bin");
+        std::fs::create_dir_all(keg.join("bin")).unwrap();
+        for tool in ["jq", "jq-extra", "jq-same"] {
+            std::fs::write(keg.join("bin").join(tool), "").unwrap();
+        }
+        std::fs::create_dir_all(&bin).unwrap();
+        // Left behind by a keg that has since been deleted.
+        symlink("../Cellar/jq/1.7.1/bin/jq", bin.join("jq")).unwrap();
+        std::fs::create_dir_all(tmp.path().join("elsewhere")).unwrap();
+        std::fs::write(tmp.path().join("elsewhere/jq-extra"), "").unwrap();
+        symlink(tmp.path().join("elsewhere/jq-extra"), bin.join("jq-extra")).unwrap();
+        symlink(keg.join("bin/jq-same"), bin.join("jq-same")).unwrap();
+
+        let mut report = LinkReport::default();
+        link_directory_recursive(&keg.join("bin"), &bin, &keg, false, false, &mut report)
+            .await
+            .unwrap();
+
+        assert_eq!(report.created.len(), 2);
+        for tool in ["jq", "jq-same"] {
+            assert_eq!(
+                std::fs::read_link(bin.join(tool)).unwrap(),
+                keg.join("bin").join(tool)
+            );
+        }
+        assert_eq!(
+            report.skipped,
+            vec![(
+                bin.join("jq-extra"),
+                "a link not managed by wax".to_string()
+            )]
+        );
+        assert_eq!(
+            std::fs::read_link(bin.join("jq-extra")).unwrap(),
+            tmp.path().join("elsewhere/jq-extra")
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn linking_skips_other_kegs_files_unless_overwriting() {
+        use std::os::unix::fs::symlink;
+
+        // The prefix is reached through a symlink, as `/var` is on macOS, while the
+        // existing link points at the resolved path.
+        let tmp = tempfile::tempdir().unwrap();
+        let real = tmp.path().join("real");
+        std::fs::create_dir_all(&real).unwrap();
+        symlink(&real, tmp.path().join("alias")).unwrap();
+        let prefix = tmp.path().join("alias");
+        let keg = prefix.join("Cellar/python@3.12/3.12.4");
+        let other = real.join("Cellar/python@3.11/3.11.9");
+        let bin = prefix.join("bin");
+        for dir in [&keg, &other] {
+            std::fs::create_dir_all(dir.join("bin")).unwrap();
+            std::fs::write(dir.join("bin/idle3"), "").unwrap();
+        }
+        std::fs::write(keg.join("bin/pip3"), "").unwrap();
+        std::fs::write(keg.join("bin/python3"), "").unwrap();
+        let system = tmp.path().join("usr/bin");
+        std::fs::create_dir_all(&system).unwrap();
+        std::fs::write(system.join("python3"), "").unwrap();
+        std::fs::create_dir_all(&bin).unwrap();
+        symlink(other.join("bin/idle3"), bin.join("idle3")).unwrap();
+        std::fs::write(bin.join("pip3"), "stray").unwrap();
+        symlink(system.join("python3"), bin.join("python3")).unwrap();
+
+        let mut report = LinkReport::default();
+        link_directory_recursive(&keg.join("bin"), &bin, &keg, false, false, &mut report)
+            .await
+            .unwrap();
+        assert!(report.created.is_empty());
+        let mut skipped = report.skipped.clone();
+        skipped.sort();
+        assert_eq!(
+            skipped,
+            vec![
+                (bin.join("idle3"), "python@3.11".to_string()),
+                (bin.join("pip3"), "a file not managed by wax".to_string()),
+                (bin.join("python3"), "a link not managed by wax".to_string()),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_link(bin.join("idle3")).unwrap(),
+            other.join("bin/idle3")
+        );
+
+        let mut report = LinkReport::default();
+        link_directory_recursive(&keg.join("bin"), &bin, &keg, false, true, &mut report)
+            .await
+            .unwrap();
+        assert!(report.skipped.is_empty());
+        assert_eq!(report.displaced.len(), 3);
+        for tool in ["idle3", "pip3", "python3"] {
+            assert_eq!(
+                std::fs::read_link(bin.join(tool)).unwrap(),
+                keg.join("bin").join(tool)
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unlink_handles_relative_links_and_prunes_empty_subdirs() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let keg = tmp.path().join("Cellar/jq/1.7.1");
+        let share = tmp.path().join("share");
+        std::fs::create_dir_all(keg.join("share/doc/jq")).unwrap();
+        std::fs::write(keg.join("share/doc/jq/README"), "").unwrap();
+        std::fs::create_dir_all(share.join("doc/jq")).unwrap();
+        symlink(
+            "../../../Cellar/jq/1.7.1/share/doc/jq/README",
+            share.join("doc/jq/README"),
+        )
+        .unwrap();
+        std::fs::write(share.join("doc/other"), "").unwrap();
+
+        let mut removed = Vec::new();
+        unlink_directory_recursive(
+            &keg.join("share"),
+            &share,
+            std::slice::from_ref(&keg),
+            false,
+            &mut removed,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(removed, vec![share.join("doc/jq/README")]);
+        assert!(!share.join("doc/jq").exists());
+        assert!(share.join("doc/other").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn sweep_dangling_in_dir_relinks_to_new_keg() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let cellar = tmp.path().join("Cellar");
+        let bin = tmp.path().join("bin");
+        let new_keg = cellar.join("jq/1.8.0");
+        std::fs::create_dir_all(new_keg.join("bin")).unwrap();
+        std::fs::write(new_keg.join("bin/jq"), "").unwrap();
+        std::fs::create_dir_all(&bin).unwrap();
+
+        symlink("../Cellar/jq/1.7.1/bin/jq", bin.join("jq")).unwrap();
+        symlink(cellar.join("jq/1.7.1/bin/jq-old"), bin.join("jq-old")).unwrap();
+        std::fs::create_dir_all(cellar.join("yq/4.1/bin")).unwrap();
+        std::fs::write(cellar.join("yq/4.1/bin/yq"), "").unwrap();
+        symlink("../Cellar/yq/4.0/bin/yq", bin.join("yq")).unwrap();
+
+        // Both formulae are repaired by the same walk of `bin`.
+        let targets = SweepTargets::new(
+            &cellar,
+            [
+                ("jq", Some(new_keg.clone())),
+                ("yq", Some(cellar.join("yq/4.1"))),
+            ],
+        );
+        let mut found = HashMap::new();
+        sweep_dangling_in_dir(&bin, &targets, false, &mut found)
+            .await
+            .unwrap();
+        assert_eq!(found["yq"].relinked, vec![bin.join("yq")]);
+        let found = &found["jq"];
+
+        assert_eq!(found.relinked, vec![bin.join("jq")]);
+        assert_eq!(found.removed, vec![bin.join("jq-old")]);
+        assert_eq!(
+            std::fs::read_link(bin.join("jq")).unwrap(),
+            new_keg.join("bin/jq")
+        );
+        assert!(bin.join("jq-old").symlink_metadata().is_err());
+    }
+
+    #[tokio::test]
+    async fn prune_empty_dirs_stops_at_boundary() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cellar = tmp.path().join("Cellar");
+        std::fs::create_dir_all(cellar.join("user/tap")).unwrap();
+        std::fs::create_dir_all(cellar.join("user/keep")).unwrap();
+
+        prune_empty_dirs(&cellar.join("user/tap"), &cellar).await;
+
+        assert!(!cellar.join("user/tap").exists());
+        assert!(cellar.join("user/keep").exists());
+        assert!(cellar.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn active_keg_version_prefers_opt_link_over_newest_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let prefix = tmp.path();
+        let cellar = prefix.join("Cellar");
+        let keg = cellar.join("openssl@3");
+        std::fs::create_dir_all(keg.join("3.6.0")).unwrap();
+        std::fs::create_dir_all(keg.join("3.6.1")).unwrap();
+        assert_eq!(
+            active_keg_version(prefix, &cellar, "openssl@3").as_deref(),
+            Some("3.6.1")
+        );
+
+        std::fs::create_dir_all(prefix.join("opt")).unwrap();
+        std::os::unix::fs::symlink("../Cellar/openssl@3/3.6.0", prefix.join("opt/openssl@3"))
+            .unwrap();
+        assert_eq!(
+            active_keg_version(prefix, &cellar, "openssl@3").as_deref(),
+            Some("3.6.0")
+        );
+        assert_eq!(active_keg_version(prefix, &cellar, "missing"), None);
+    }
+
+    #[test]
+    fn reconcile_flags_api_installed_disagreement() {
+        let name = "wax-reconcile-test-pkg";
+        let mut packages = HashMap::from([(
+            name.to_string(),
+            InstalledPackage {
+                platform: detect_platform(),
+                install_mode: InstallMode::Global,
+                ..crate::test_support::installed_package(name, "1.0.0")
+            },
+        )]);
+        let formula = crate::test_support::formula_with(
+            name,
+            serde_json::json!({
+                "versions": { "stable": "1.1.0", "bottle": true },
+                "installed": [{ "version": "0.9.0" }, { "version": "1.1.0" }],
+            }),
+        );
+        let index = HashMap::from([(name, &formula)]);
+
+        let drift = reconcile_installed_versions(&mut packages, &index);
+        assert_eq!(
+            drift,
+            vec![VersionDrift {
+                name: name.to_string(),
+                recorded: "1.0.0".to_string(),
+                on_disk: None,
+                reported: Some("1.1.0".to_string()),
+            }]
+        );
+        // Without a keg on disk the recorded version stands.
+        assert_eq!(packages[name].version, "1.0.0");
+    }
+}
@@ -0,0 +1,71 @@
+//! wax as a library, for tools that want Homebrew-compatible resolution and installs
+//! without shelling out to the `wax` binary.
+//!
+//! The stable surface is what this file re-exports:
+//!
+//! - [`Cache`]: the local index and the formulae.brew.sh API client (`ensure_fresh`,
+//!   `load_all_formulae`, `load_casks`, `fetch_formula`, `fetch_cask_details`).
+//! - [`InstallState`], [`InstalledPackage`], [`InstallMode`]: what is installed and where.
+//! - [`resolve_dependencies`], [`resolve_dependencies_for_roots`]: dependency ordering.
+//! - [`TapManager`], [`Tap`]: third-party formula repositories.
+//! - [`BottleDownloader`]: bottle download, checksum and extraction.
+//! - [`Formula`], [`Cask`], [`CaskDetails`], [`WaxError`], [`Result`].
+//!
+//! Modules marked hidden (`commands`, `ui`, …) back the CLI and may change in any release.
+
+pub mod api;
+pub mod bottle;
+pub mod cache;
+pub mod deps;
+pub mod error;
+pub mod install;
+pub mod tap;
+pub mod version;
+
+#[doc(hidden)]
+pub mod commands;
+#[doc(hidden)]
+pub mod logging;
+#[doc(hidden)]
+pub mod signal;
+#[doc(hidden)]
+pub mod system_pm;
+#[doc(hidden)]
+pub mod ui;
+
+mod builder;
+mod cask;
+mod catalog_match;
+mod digest;
+mod discovery;
+mod formula_parser;
+mod http_client;
+mod lockfile;
+mod state_file;
+mod sudo;
+#[cfg(test)]
+mod test_support;
+
+// Windows package manager support (scoop, winget, chocolatey)
+#[cfg(target_os = "windows")]
+mod chocolatey;
+#[cfg(target_os = "windows")]
+mod ecosystem_install;
+#[cfg(target_os = "windows")]
+mod package_spec;
+#[cfg(target_os = "windows")]
+mod remote_search;
+#[cfg(target_os = "windows")]
+mod scoop;
+#[cfg(target_os = "windows")]
+mod windows_state;
+#[cfg(target_os = "windows")]
+mod winget_install;
+
+pub use api::{Cask, CaskDetails, Formula};
+pub use bottle::BottleDownloader;
+pub use cache::Cache;
+pub use deps::{resolve_dependencies, resolve_dependencies_for_roots};
+pub use error::{Result, WaxError};
+pub use install::{InstallMode, InstallState, InstalledPackage};
+pub use tap::{Tap, TapManager};
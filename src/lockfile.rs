@@ -1,20 +1,20 @@
 use crate::cask::CaskState;
 use crate::error::{Result, WaxError};
 use crate::install::InstallState;
+use crate::state_file::write_atomic;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::fs;
 use tracing::{debug, instrument, warn};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LockfilePackage {
     pub version: String,
     pub bottle: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LockfileCask {
     pub version: String,
 }
@@ -22,28 +22,27 @@ pub struct LockfileCask {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Lockfile {
     #[serde(default)]
-    pub packages: HashMap<String, LockfilePackage>,
+    pub packages: BTreeMap<String, LockfilePackage>,
     #[serde(default)]
-    pub casks: HashMap<String, LockfileCask>,
+    pub casks: BTreeMap<String, LockfileCask>,
 }
 
 impl Lockfile {
     pub fn new() -> Self {
         Self {
-            packages: HashMap::new(),
-            casks: HashMap::new(),
+            packages: BTreeMap::new(),
+            casks: BTreeMap::new(),
         }
     }
 
     #[instrument]
-    #[allow(dead_code)]
     pub async fn generate() -> Result<Self> {
         debug!("Generating lockfile from installed packages");
 
         let state = InstallState::new()?;
         let installed_packages = state.load().await?;
 
-        let mut packages = HashMap::new();
+        let mut packages = BTreeMap::new();
         for (name, pkg) in installed_packages {
             packages.insert(
                 name,
@@ -57,7 +56,7 @@ impl Lockfile {
         let cask_state = CaskState::new()?;
         let installed_casks = cask_state.load().await?;
 
-        let mut casks = HashMap::new();
+        let mut casks = BTreeMap::new();
         for (name, pkg) in installed_casks {
             casks.insert(
                 name,
@@ -81,11 +80,7 @@ impl Lockfile {
         let toml_string = toml::to_string_pretty(&self)
             .map_err(|e| WaxError::LockfileError(format!("Failed to serialize lockfile: {}", e)))?;
 
-        let temp_path = temp_path_for(path);
-        fs::write(&temp_path, toml_string).await?;
-        fs::rename(&temp_path, path).await.inspect_err(|_| {
-            let _ = std::fs::remove_file(&temp_path);
-        })?;
+        write_atomic(path, toml_string).await?;
 
         debug!("Lockfile saved successfully");
         Ok(())
@@ -126,6 +121,32 @@ impl Lockfile {
         }
     }
 
+    /// Apply what changed between two snapshots of the installed packages (see
+    /// [`Lockfile::generate`]): new or changed entries are written, removed ones dropped,
+    /// and everything else in the lockfile is left as it was. Returns how many entries
+    /// changed.
+    pub fn apply_changes(&mut self, before: &Lockfile, after: &Lockfile) -> usize {
+        apply_diff(&mut self.packages, &before.packages, &after.packages)
+            + apply_diff(&mut self.casks, &before.casks, &after.casks)
+    }
+
+    /// Edit the lockfile at `path` through `update`, but only if one already exists:
+    /// wax never creates a lockfile as a side effect. The file is rewritten (atomically)
+    /// only when `update` returns true. Returns whether a lockfile was found.
+    pub async fn update_existing(
+        path: &Path,
+        update: impl FnOnce(&mut Lockfile) -> bool,
+    ) -> Result<bool> {
+        if !path.exists() {
+            return Ok(false);
+        }
+        let mut lockfile = Self::load(path).await?;
+        if update(&mut lockfile) {
+            lockfile.save(path).await?;
+        }
+        Ok(true)
+    }
+
     pub async fn remove_cask(&mut self, name: &str) {
         self.casks.remove(name);
     }
@@ -135,25 +156,31 @@ impl Lockfile {
     }
 }
 
+fn apply_diff<T: Clone + PartialEq>(
+    locked: &mut BTreeMap<String, T>,
+    before: &BTreeMap<String, T>,
+    after: &BTreeMap<String, T>,
+) -> usize {
+    let mut changed = 0;
+    for (name, entry) in after {
+        if before.get(name) != Some(entry) {
+            locked.insert(name.clone(), entry.clone());
+            changed += 1;
+        }
+    }
+    for name in before.keys().filter(|name| !after.contains_key(*name)) {
+        locked.remove(name);
+        changed += 1;
+    }
+    changed
+}
+
 impl Default for Lockfile {
     fn default() -> Self {
         Self::new()
     }
 }
 
-fn temp_path_for(path: &Path) -> PathBuf {
-    let pid = std::process::id();
-    let nanos = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_nanos())
-        .unwrap_or_default();
-    let file_name = path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("wax.lock");
-    path.with_file_name(format!(".{}.{}.{}.tmp", file_name, pid, nanos))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -185,6 +212,58 @@ mod tests {
         assert!(lockfile.packages.is_empty());
     }
 
+    fn package(version: &str) -> LockfilePackage {
+        LockfilePackage {
+            version: version.to_string(),
+            bottle: "arm64_sonoma".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn apply_changes_touches_only_what_changed_and_only_existing_lockfiles() {
+        let mut before = Lockfile::new();
+        before.packages.insert("jq".to_string(), package("1.7"));
+        before.packages.insert("wget".to_string(), package("1.24"));
+        let mut after = Lockfile::new();
+        after.packages.insert("jq".to_string(), package("1.7.1"));
+        after
+            .packages
+            .insert("oniguruma".to_string(), package("6.9"));
+        after.casks.insert(
+            "firefox".to_string(),
+            LockfileCask {
+                version: "130.0".to_string(),
+            },
+        );
+
+        // `tree` was locked by hand and is not installed; it is left alone.
+        let mut locked = Lockfile::new();
+        locked.packages.insert("tree".to_string(), package("2.1"));
+        locked.packages.insert("wget".to_string(), package("1.24"));
+        assert_eq!(locked.apply_changes(&before, &after), 4);
+        assert_eq!(locked.packages["jq"], package("1.7.1"));
+        assert_eq!(locked.packages["oniguruma"], package("6.9"));
+        assert!(locked.packages.contains_key("tree"));
+        assert!(!locked.packages.contains_key("wget"));
+        assert_eq!(locked.casks["firefox"].version, "130.0");
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("wax.lock");
+        assert!(!Lockfile::update_existing(&path, |_| true).await.unwrap());
+        assert!(!path.exists());
+
+        locked.save(&path).await.unwrap();
+        let updated =
+            Lockfile::update_existing(&path, |l| l.apply_changes(&after, &Lockfile::new()) > 0)
+                .await
+                .unwrap();
+        assert!(updated);
+        let reloaded = Lockfile::load(&path).await.unwrap();
+        assert!(!reloaded.packages.contains_key("jq"));
+        assert!(reloaded.packages.contains_key("tree"));
+        assert!(reloaded.casks.is_empty());
+    }
+
     #[tokio::test]
     async fn test_remove_nonexistent() {
         let mut lockfile = Lockfile::new();
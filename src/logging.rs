@@ -0,0 +1,143 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const LOG_FILE_NAME: &str = "wax.log";
+/// Rotate `wax.log` once it would grow past this many bytes.
+pub const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+/// Rotated files kept next to the live log (`wax.log.1` is the newest).
+pub const KEEP_ROTATED_LOGS: usize = 3;
+
+/// Append-only log file that rolls over by size: `wax.log` → `wax.log.1` → … →
+/// `wax.log.<keep>`, dropping the oldest.
+pub struct RollingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl RollingFile {
+    pub fn open(path: PathBuf, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_bytes,
+            keep,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            // A failed rotation should not lose the record; keep appending instead.
+            let _ = self.rotate();
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// `wax.log.<n>` for a live log at `path`.
+pub fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", n));
+    path.with_file_name(name)
+}
+
+/// The last `count` lines across the live log and its rotations, oldest first.
+pub fn tail_lines(path: &Path, count: usize, keep: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let files =
+        std::iter::once(path.to_path_buf()).chain((1..=keep).map(|n| rotated_path(path, n)));
+    for file in files {
+        if lines.len() >= count {
+            break;
+        }
+        let Ok(bytes) = fs::read(&file) else {
+            continue;
+        };
+        let content = String::from_utf8_lossy(&bytes);
+        let needed = count - lines.len();
+        let mut chunk: Vec<String> = content
+            .lines()
+            .rev()
+            .take(needed)
+            .map(str::to_string)
+            .collect();
+        chunk.reverse();
+        chunk.append(&mut lines);
+        lines = chunk;
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_file_rotates_and_keeps_limited_history() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join(LOG_FILE_NAME);
+        let mut log = RollingFile::open(path.clone(), 16, 2).unwrap();
+
+        for line in [
+            "aaaaaaaaaa\n",
+            "bbbbbbbbbb\n",
+            "cccccccccc\n",
+            "dddddddddd\n",
+        ] {
+            log.write_all(line.as_bytes()).unwrap();
+        }
+        log.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "dddddddddd\n");
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+            "cccccccccc\n"
+        );
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 2)).unwrap(),
+            "bbbbbbbbbb\n"
+        );
+        assert!(!rotated_path(&path, 3).exists());
+
+        assert_eq!(
+            tail_lines(&path, 3, 2),
+            vec!["bbbbbbbbbb", "cccccccccc", "dddddddddd"]
+        );
+        assert_eq!(tail_lines(&path, 1, 2), vec!["dddddddddd"]);
+    }
+}
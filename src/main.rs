@@ -1,43 +1,7 @@
-mod api;
-mod bottle;
-mod builder;
-mod cache;
-mod cask;
-mod catalog_match;
-mod commands;
-mod deps;
-mod digest;
-mod discovery;
-mod error;
-mod formula_parser;
-mod http_client;
-mod install;
-mod lockfile;
-mod signal;
-mod sudo;
-mod system_pm;
-mod tap;
-mod ui;
-mod version;
-
-// Windows package manager support (scoop, winget, chocolatey)
-#[cfg(target_os = "windows")]
-mod chocolatey;
-#[cfg(target_os = "windows")]
-mod ecosystem_install;
-#[cfg(target_os = "windows")]
-mod package_spec;
-#[cfg(target_os = "windows")]
-mod remote_search;
-#[cfg(target_os = "windows")]
-mod scoop;
-#[cfg(target_os = "windows")]
-mod windows_state;
-#[cfg(target_os = "windows")]
-mod winget_install;
+use waxpkg::{cache, commands, error, install, logging, signal, ui, version};
 
 use cache::Cache;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::Shell;
 use error::Result;
 use std::time::Instant;
@@ -55,11 +19,17 @@ fn command_prints_timing(command: &Commands) -> bool {
             | Commands::Reinstall { .. }
             | Commands::Upgrade { .. }
             | Commands::Outdated { .. }
-            | Commands::Sync
+            | Commands::Sync { .. }
     )
 }
 
-async fn run_self_update(nightly: bool, force: bool, clean: bool, no_clean: bool) -> Result<()> {
+async fn run_self_update(
+    nightly: bool,
+    force: bool,
+    clean: bool,
+    no_clean: bool,
+    yes: bool,
+) -> Result<()> {
     if clean && no_clean {
         return Err(error::WaxError::InvalidInput(
             "Cannot specify both --clean and --no-clean".to_string(),
@@ -76,6 +46,9 @@ async fn run_self_update(nightly: bool, force: bool, clean: bool, no_clean: bool
             Some(true)
         } else if no_clean {
             Some(false)
+        } else if yes {
+            // --yes answers the cleanup prompt.
+            Some(true)
         } else {
             None
         }
@@ -108,6 +81,21 @@ struct Cli {
         help = "Show command duration in result output"
     )]
     time_to_action: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Print a per-package breakdown of install phases (resolve, download, extract, ...)"
+    )]
+    timings: bool,
+
+    #[arg(
+        long,
+        alias = "no-cache",
+        global = true,
+        help = "Revalidate the package index before running, even if the cache is fresh"
+    )]
+    refresh: bool,
 }
 
 #[derive(Subcommand)]
@@ -139,6 +127,11 @@ enum Commands {
         clean: bool,
         #[arg(long, help = "After nightly self-update, keep Cargo git cache")]
         no_clean: bool,
+        #[arg(
+            long,
+            help = "List added, removed, and version-bumped formulae (implied by --verbose)"
+        )]
+        changes: bool,
     },
 
     #[command(about = "Update wax itself  [alias: self-up]")]
@@ -161,7 +154,13 @@ enum Commands {
     #[command(about = "Search formulae and casks  [alias: s, find]")]
     #[command(visible_alias = "s")]
     #[command(alias = "find")]
-    Search { query: String },
+    Search {
+        query: String,
+        #[arg(long, help = "Show every match instead of the top results per section")]
+        all: bool,
+        #[arg(long, help = "Only show matches that are installed")]
+        installed: bool,
+    },
 
     #[command(about = "Show formula details  [alias: show]")]
     #[command(visible_alias = "show")]
@@ -169,6 +168,23 @@ enum Commands {
         formula: String,
         #[arg(long)]
         cask: bool,
+        #[arg(
+            long,
+            help = "Show only what was recorded at install time (no index load; works offline)"
+        )]
+        installed: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["installed", "url"],
+            help = "Open the homepage in the default browser"
+        )]
+        open: bool,
+        #[arg(
+            long,
+            conflicts_with = "installed",
+            help = "Print the bottle (or source) URL for this platform"
+        )]
+        url: bool,
     },
 
     #[command(about = "List installed packages  [alias: ls]")]
@@ -180,6 +196,13 @@ enum Commands {
         user: bool,
         #[arg(long, conflicts_with = "user")]
         global: bool,
+        #[arg(
+            long,
+            help = "List every Cellar version per formula and mark the linked one"
+        )]
+        versions: bool,
+        #[arg(long, help = "Show how much disk space each formula uses")]
+        sizes: bool,
     },
 
     #[command(about = "Install one or more formulae or casks  [alias: i, add]")]
@@ -202,11 +225,62 @@ enum Commands {
         build_from_source: bool,
         #[arg(
             long,
+            alias = "HEAD",
             help = "Install the HEAD version (clones git repo, builds from source)"
         )]
         head: bool,
         #[arg(long = "no-script", help = "Skip automatic post-install scripts")]
         no_script: bool,
+        #[arg(
+            long = "with",
+            value_name = "OPTION",
+            help = "Enable a formula build option (e.g. --with x11 for --with-x11); builds from source"
+        )]
+        with: Vec<String>,
+        #[arg(
+            long = "without",
+            value_name = "OPTION",
+            help = "Disable a formula build option (e.g. --without docs); builds from source"
+        )]
+        without: Vec<String>,
+        #[arg(
+            long,
+            help = "Install even if Homebrew already has the formula in its Cellar; reinstall casks"
+        )]
+        force: bool,
+        #[arg(long, help = "Install only the named formulae, not their dependencies")]
+        ignore_dependencies: bool,
+        #[arg(
+            long,
+            conflicts_with = "ignore_dependencies",
+            help = "Install the dependencies of the named formulae, but not the formulae"
+        )]
+        only_dependencies: bool,
+        #[arg(
+            long,
+            help = "Build from source even when the formula declares no sha256 for its source"
+        )]
+        no_verify: bool,
+        #[arg(
+            long,
+            help = "Install bottles without rewriting Homebrew prefix placeholders (debugging)"
+        )]
+        no_relocate: bool,
+        #[arg(
+            long,
+            help = "Replace files and other packages' links that conflict with this package's"
+        )]
+        overwrite: bool,
+        #[arg(long, help = "Record the installed packages in an existing wax.lock")]
+        lock: bool,
+        #[arg(
+            long,
+            value_name = "TAG",
+            requires = "packages",
+            conflicts_with_all = ["cask", "build_from_source", "head", "with", "without"],
+            help = "Install bottles for another platform (e.g. x86_64_linux, arm64_sonoma)"
+        )]
+        platform: Option<String>,
     },
 
     #[command(about = "Install casks  [alias: c]")]
@@ -239,19 +313,46 @@ enum Commands {
         dry_run: bool,
         #[arg(long)]
         cask: bool,
+        #[arg(
+            long,
+            help = "For casks, also remove the files the cask's zap stanza lists (preferences, caches, support files)"
+        )]
+        zap: bool,
         #[arg(long, help = "Uninstall all installed formulae")]
         all: bool,
+        #[arg(
+            long,
+            alias = "keep-receipt",
+            help = "Only forget the install record; leave Cellar files and symlinks in place"
+        )]
+        state_only: bool,
+        #[arg(
+            long,
+            visible_alias = "recursive",
+            conflicts_with_all = ["cask", "state_only"],
+            help = "Also remove dependencies that nothing else needs any more"
+        )]
+        autoremove: bool,
     },
 
     #[command(about = "Reinstall a formula or cask  [alias: ri]")]
     #[command(visible_alias = "ri")]
     Reinstall {
-        #[arg(conflicts_with = "all", required_unless_present = "all")]
+        #[arg(
+            conflicts_with_all = ["all", "all_source"],
+            required_unless_present_any = ["all", "all_source"]
+        )]
         packages: Vec<String>,
         #[arg(long)]
         cask: bool,
         #[arg(long, help = "Reinstall all installed formulae and casks")]
         all: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["all", "cask"],
+            help = "Rebuild every formula that was built from source, from source, dependencies first"
+        )]
+        all_source: bool,
     },
 
     #[command(about = "Run post-installation steps for a package")]
@@ -293,6 +394,11 @@ enum Commands {
         user: bool,
         #[arg(long, conflicts_with = "user")]
         global: bool,
+        #[arg(
+            long,
+            help = "Upgrade source-built formulae from bottles instead of rebuilding them"
+        )]
+        force_bottle: bool,
     },
 
     #[command(about = "Manage OS-level packages via the native package manager")]
@@ -301,6 +407,9 @@ enum Commands {
         action: SystemAction,
     },
 
+    #[command(about = "Prefetch the package index and installed cask details")]
+    Warm,
+
     #[command(about = "List packages with available updates")]
     Outdated {
         #[arg(long, conflicts_with = "global")]
@@ -314,6 +423,22 @@ enum Commands {
     Link {
         #[arg(required = true)]
         packages: Vec<String>,
+        #[arg(
+            long,
+            help = "Replace files and other packages' links that conflict with this package's"
+        )]
+        overwrite: bool,
+        #[arg(
+            long,
+            help = "Remove a formula's links first and rebuild them from its keg"
+        )]
+        relink: bool,
+    },
+
+    #[command(about = "Recreate a formula's symlinks from its installed keg (link --relink)")]
+    Relink {
+        #[arg(help = "Installed formula name")]
+        formula: String,
     },
 
     #[command(about = "Remove symlinks for a package (keeps Cellar)")]
@@ -326,11 +451,71 @@ enum Commands {
     Cleanup {
         #[arg(long)]
         dry_run: bool,
+        #[arg(
+            long,
+            help = "Also prune the cache: tap caches for removed taps and stale cask details and catalogues"
+        )]
+        prune_cache: bool,
+        #[arg(
+            long,
+            value_name = "DAYS",
+            default_value_t = 30,
+            requires = "prune_cache",
+            help = "With --prune-cache, remove cached files older than this"
+        )]
+        older_than: u64,
+        #[arg(
+            long,
+            requires = "prune_cache",
+            help = "With --prune-cache, also remove the package index and all tap caches"
+        )]
+        all: bool,
+    },
+
+    #[command(about = "Show cache and Cellar disk usage, or clean the cache")]
+    Cache {
+        #[command(subcommand)]
+        action: Option<CacheAction>,
     },
 
     #[command(about = "Show installed packages not required by any other package")]
     Leaves,
 
+    #[command(about = "Print the install prefix (like `brew --prefix`)")]
+    Prefix {
+        #[arg(long, conflicts_with = "global", help = "Print the user prefix")]
+        user: bool,
+        #[arg(
+            long,
+            conflicts_with = "user",
+            help = "Print the global (Homebrew) prefix"
+        )]
+        global: bool,
+    },
+
+    #[command(about = "Print the Cellar path (like `brew --cellar`)")]
+    Cellar {
+        #[arg(long, conflicts_with = "global", help = "Print the user Cellar")]
+        user: bool,
+        #[arg(
+            long,
+            conflicts_with = "user",
+            help = "Print the global (Homebrew) Cellar"
+        )]
+        global: bool,
+    },
+
+    #[command(about = "Print the most recent lines of the wax log")]
+    Log {
+        #[arg(
+            short = 'n',
+            long,
+            default_value_t = 50,
+            help = "Number of lines to show"
+        )]
+        lines: usize,
+    },
+
     #[command(about = "Show formulae that depend on a given formula")]
     Uses {
         formula: String,
@@ -363,14 +548,26 @@ enum Commands {
     Lock,
 
     #[command(about = "Install packages from lockfile")]
-    Sync,
+    Sync {
+        #[arg(
+            long,
+            help = "Fail instead of warning when the lockfile cannot be applied exactly"
+        )]
+        frozen: bool,
+    },
+
+    #[command(about = "Compare installed packages with the lockfile")]
+    Bundle {
+        #[command(subcommand)]
+        action: BundleAction,
+    },
 
     #[command(about = "Manage custom taps  [alias: untap]")]
     Tap {
         #[arg(long, help = "Re-clone missing or broken taps")]
         repair: bool,
         #[command(subcommand)]
-        action: Option<TapAction>,
+        action: Option<commands::tap::TapAction>,
     },
 
     #[command(about = "Check system for potential problems  [alias: dr]")]
@@ -393,6 +590,18 @@ enum Commands {
         formula: String,
     },
 
+    #[command(about = "Print a formula's Ruby source")]
+    Cat {
+        #[arg(help = "Formula name (tap formulae as user/repo/name)")]
+        formula: String,
+    },
+
+    #[command(about = "Open a tap formula's Ruby file in $EDITOR")]
+    Edit {
+        #[arg(help = "Tap formula as user/repo/name")]
+        formula: String,
+    },
+
     #[command(about = "Install shell completions (auto-detects shell)")]
     Completions {
         #[arg(
@@ -413,6 +622,9 @@ enum Commands {
 
     #[command(about = "Check installed packages for issues (deprecated, disabled, outdated)")]
     Audit,
+
+    #[command(about = "Show version with build commit, profile, target and platform tag")]
+    Version,
 }
 
 #[derive(Subcommand)]
@@ -427,44 +639,22 @@ enum SystemAction {
 }
 
 #[derive(Subcommand)]
-enum TapAction {
-    #[command(about = "Add a custom tap")]
-    Add {
-        #[arg(help = "Tap specification: user/repo, Git URL, local directory, or .rb file path")]
-        tap: String,
-        #[arg(long, help = "Trust this tap for formula discovery and installs")]
-        trust: bool,
+enum CacheAction {
+    #[command(about = "Show the size of the index, tap caches and Cellars, and the index age")]
+    Info,
+    #[command(about = "Remove cached data wax can fetch again (keeps the index and current taps)")]
+    Clean {
+        #[arg(long)]
+        dry_run: bool,
     },
+}
+
+#[derive(Subcommand)]
+enum BundleAction {
     #[command(
-        about = "Remove a custom tap",
-        visible_alias = "rm",
-        alias = "uninstall",
-        alias = "delete"
+        about = "Exit non-zero and list the drift if anything in the lockfile is missing or differs"
     )]
-    Remove {
-        #[arg(help = "Tap specification: user/repo, Git URL, local directory, or .rb file path")]
-        tap: String,
-    },
-    #[command(about = "List installed taps", visible_alias = "ls")]
-    List,
-    #[command(about = "Update a tap", visible_alias = "up")]
-    Update {
-        #[arg(help = "Tap specification: user/repo, Git URL, local directory, or .rb file path")]
-        tap: String,
-    },
-    #[command(about = "Trust a tap for formula discovery and installs")]
-    Trust {
-        #[arg(help = "Tap specification: user/repo, Git URL, local directory, or .rb file path")]
-        tap: String,
-    },
-    #[command(about = "Remove trust from a tap")]
-    Untrust {
-        #[arg(help = "Tap specification: user/repo, Git URL, local directory, or .rb file path")]
-        tap: String,
-    },
-    /// Bare `wax tap user/repo` — treated as an add.
-    #[command(external_subcommand)]
-    External(Vec<String>),
+    Check,
 }
 
 fn init_logging(verbose: bool) -> Result<()> {
@@ -472,16 +662,23 @@ fn init_logging(verbose: bool) -> Result<()> {
 
     std::fs::create_dir_all(&log_dir)?;
 
-    let log_file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(log_dir.join("wax.log"))?;
+    let log_file = std::sync::Mutex::new(logging::RollingFile::open(
+        log_dir.join(logging::LOG_FILE_NAME),
+        logging::MAX_LOG_BYTES,
+        logging::KEEP_ROTATED_LOGS,
+    )?);
 
     let level = if verbose { Level::DEBUG } else { Level::INFO };
 
+    // With --verbose, INFO and above (fetched URLs, the platform tag) are also shown
+    // live on stderr; the log file keeps everything at the chosen level.
+    let live = std::io::stderr
+        .with_max_level(Level::INFO)
+        .with_filter(move |_| verbose);
+
     tracing_subscriber::fmt()
         .with_max_level(level)
-        .with_writer(log_file.with_max_level(Level::TRACE))
+        .with_writer(log_file.with_max_level(Level::TRACE).and(live))
         .with_ansi(false)
         .init();
 
@@ -493,7 +690,7 @@ fn install_scope(user: bool, global: bool) -> Result<Option<install::InstallMode
 }
 
 async fn handle_system_upgrade() -> Result<()> {
-    use crate::system_pm::SystemPm;
+    use waxpkg::system_pm::SystemPm;
     match SystemPm::detect().await {
         Some(pm) => {
             println!(
@@ -549,7 +746,7 @@ fn print_error_and_exit(err: error::WaxError) -> ! {
     std::process::exit(1);
 }
 
-async fn execute_command(command: Commands, cache: &Cache, yes: bool) -> Result<()> {
+async fn execute_command(command: Commands, cache: &Cache, yes: bool, verbose: bool) -> Result<()> {
     match command {
         Commands::Update {
             action,
@@ -558,6 +755,7 @@ async fn execute_command(command: Commands, cache: &Cache, yes: bool) -> Result<
             force,
             clean,
             no_clean,
+            changes,
         } => {
             if let Some(action) = action {
                 match action.as_str() {
@@ -575,11 +773,11 @@ async fn execute_command(command: Commands, cache: &Cache, yes: bool) -> Result<
             }
 
             if update_self {
-                run_self_update(nightly, force, clean, no_clean).await
+                run_self_update(nightly, force, clean, no_clean, yes).await
             } else {
                 #[cfg(target_os = "windows")]
-                crate::error::reject_homebrew_cli("update")?;
-                commands::update::update(cache).await
+                waxpkg::error::reject_homebrew_cli("update")?;
+                commands::update::update(cache, changes || verbose).await
             }
         }
         Commands::SelfUpdate {
@@ -587,18 +785,40 @@ async fn execute_command(command: Commands, cache: &Cache, yes: bool) -> Result<
             force,
             clean,
             no_clean,
-        } => run_self_update(nightly, force, clean, no_clean).await,
-        Commands::Search { query } => commands::search::search(cache, &query).await,
-        Commands::Info { formula, cask } => {
+        } => run_self_update(nightly, force, clean, no_clean, yes).await,
+        Commands::Search {
+            query,
+            all,
+            installed,
+        } => commands::search::search(cache, &query, all, installed).await,
+        Commands::Info {
+            formula,
+            cask,
+            installed,
+            open,
+            url,
+        } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("info")?;
-            commands::info::info(cache, &formula, cask).await
+            waxpkg::error::reject_homebrew_cli("info")?;
+            if installed {
+                commands::info::info_installed(&formula, cask).await
+            } else if open {
+                commands::info::info_open(cache, &formula, cask).await
+            } else if url {
+                commands::info::info_url(cache, &formula, cask).await
+            } else {
+                commands::info::info(cache, &formula, cask).await
+            }
         }
         Commands::List {
             query,
             user,
             global,
-        } => commands::list::list(cache, query, install_scope(user, global)?).await,
+            versions,
+            sizes,
+        } => {
+            commands::list::list(cache, query, install_scope(user, global)?, versions, sizes).await
+        }
         Commands::Install {
             packages,
             dry_run,
@@ -609,26 +829,67 @@ async fn execute_command(command: Commands, cache: &Cache, yes: bool) -> Result<
             build_from_source,
             head,
             no_script,
+            with,
+            without,
+            force,
+            ignore_dependencies,
+            only_dependencies,
+            no_verify,
+            no_relocate,
+            overwrite,
+            lock,
+            platform,
         } => {
+            let options = commands::install::build_options(&with, &without);
+            if packages.is_empty() && !options.is_empty() {
+                return Err(error::WaxError::InvalidInput(
+                    "--with/--without need at least one package name".to_string(),
+                ));
+            }
+            if packages.is_empty() && (ignore_dependencies || only_dependencies) {
+                return Err(error::WaxError::InvalidInput(format!(
+                    "{} needs at least one package name",
+                    if ignore_dependencies {
+                        "--ignore-dependencies"
+                    } else {
+                        "--only-dependencies"
+                    }
+                )));
+            }
             if packages.is_empty() && !cask {
                 #[cfg(target_os = "windows")]
-                crate::error::reject_homebrew_cli("install")?;
+                waxpkg::error::reject_homebrew_cli("install")?;
                 // No packages specified — sync from lockfile like `npm install`
-                commands::sync::sync(cache).await
+                commands::sync::sync(cache, false).await
             } else {
-                commands::install::install(
+                let install = commands::install::install(
                     cache,
                     &packages,
-                    dry_run,
-                    ask && !yes,
-                    cask,
-                    user,
-                    global,
-                    build_from_source,
-                    head,
-                    !no_script,
-                )
-                .await
+                    commands::install::InstallArgs {
+                        dry_run,
+                        ask: ask && !yes,
+                        cask,
+                        user,
+                        global,
+                        build_from_source,
+                        head,
+                        run_scripts: !no_script,
+                        force_reinstall: force,
+                        options: &options,
+                        ignore_dependencies,
+                        only_dependencies,
+                        no_verify,
+                        no_relocate,
+                        platform: platform.as_deref(),
+                        overwrite,
+                        ..Default::default()
+                    },
+                );
+                if lock && !dry_run {
+                    commands::lock::recording_changes(install).await
+                } else {
+                    install.await
+                }
             }
         }
         Commands::InstallCask {
@@ -640,18 +901,19 @@ async fn execute_command(command: Commands, cache: &Cache, yes: bool) -> Result<
             no_script,
         } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("install --cask")?;
+            waxpkg::error::reject_homebrew_cli("install --cask")?;
             commands::install::install(
                 cache,
                 &packages,
-                dry_run,
-                ask && !yes,
-                true,
-                user,
-                global,
-                false,
-                false,
-                !no_script,
+                commands::install::InstallArgs {
+                    dry_run,
+                    ask: ask && !yes,
+                    cask: true,
+                    user,
+                    global,
+                    run_scripts: !no_script,
+                    ..Default::default()
+                },
             )
             .await
         }
@@ -659,16 +921,25 @@ async fn execute_command(command: Commands, cache: &Cache, yes: bool) -> Result<
             formulae,
             dry_run,
             cask,
+            zap,
             all,
-        } => commands::uninstall::uninstall(cache, &formulae, dry_run, cask, yes, all).await,
+            state_only,
+            autoremove,
+        } => {
+            commands::uninstall::uninstall(
+                cache, &formulae, dry_run, cask, zap, yes, all, state_only, autoremove,
+            )
+            .await
+        }
         Commands::Reinstall {
             packages,
             cask,
             all,
+            all_source,
         } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("reinstall")?;
-            commands::reinstall::reinstall(cache, &packages, cask, all).await
+            waxpkg::error::reject_homebrew_cli("reinstall")?;
+            commands::reinstall::reinstall(cache, &packages, cask, all, all_source).await
         }
         Commands::Postinstall {
             formulae,
@@ -676,7 +947,7 @@ async fn execute_command(command: Commands, cache: &Cache, yes: bool) -> Result<
             global,
         } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("postinstall")?;
+            waxpkg::error::reject_homebrew_cli("postinstall")?;
             commands::install::postinstall(cache, &formulae, user, global).await
         }
         Commands::Upgrade {
@@ -690,23 +961,27 @@ async fn execute_command(command: Commands, cache: &Cache, yes: bool) -> Result<
             system,
             user,
             global,
+            force_bottle,
         } => {
             if upgrade_self {
-                run_self_update(nightly, false, clean, no_clean).await?;
+                run_self_update(nightly, false, clean, no_clean, yes).await?;
                 return Ok(());
             }
 
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("upgrade")?;
+            waxpkg::error::reject_homebrew_cli("upgrade")?;
 
             let explicit_packages_requested = !packages.is_empty();
 
             commands::upgrade::upgrade(
                 cache,
                 &packages,
-                dry_run,
-                ask && !yes,
-                install_scope(user, global)?,
+                commands::upgrade::UpgradeArgs {
+                    dry_run,
+                    ask: ask && !yes,
+                    scope: install_scope(user, global)?,
+                    force_bottle,
+                },
             )
             .await?;
             if system {
@@ -730,46 +1005,88 @@ async fn execute_command(command: Commands, cache: &Cache, yes: bool) -> Result<
         Commands::System { action } => match action {
             SystemAction::Upgrade => handle_system_upgrade().await,
             SystemAction::Install { packages } => {
-                use crate::system_pm::SystemPm;
+                use waxpkg::system_pm::SystemPm;
                 match SystemPm::detect().await {
                     Some(pm) => {
                         println!("installing via {}", pm.name());
                         pm.install(&packages).await
                     }
-                    None => Err(crate::error::WaxError::PlatformNotSupported(
+                    None => Err(waxpkg::error::WaxError::PlatformNotSupported(
                         "No supported system package manager found".to_string(),
                     )),
                 }
             }
         },
+        Commands::Warm => {
+            #[cfg(target_os = "windows")]
+            waxpkg::error::reject_homebrew_cli("warm")?;
+            commands::warm::warm(cache).await
+        }
         Commands::Outdated { user, global } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("outdated")?;
+            waxpkg::error::reject_homebrew_cli("outdated")?;
             commands::outdated::outdated(cache, install_scope(user, global)?).await
         }
-        Commands::Link { packages } => {
+        Commands::Link {
+            packages,
+            overwrite,
+            relink,
+        } => {
+            #[cfg(target_os = "windows")]
+            waxpkg::error::reject_homebrew_cli("link")?;
+            commands::link::link(&packages, overwrite, relink).await
+        }
+        Commands::Relink { formula } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("link")?;
-            commands::link::link(&packages).await
+            waxpkg::error::reject_homebrew_cli("relink")?;
+            commands::link::link(&[formula], false, true).await
         }
         Commands::Unlink { packages } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("unlink")?;
+            waxpkg::error::reject_homebrew_cli("unlink")?;
             commands::link::unlink(&packages).await
         }
-        Commands::Cleanup { dry_run } => {
+        Commands::Cleanup {
+            dry_run,
+            prune_cache,
+            older_than,
+            all,
+        } => {
+            #[cfg(target_os = "windows")]
+            waxpkg::error::reject_homebrew_cli("cleanup")?;
+            let prune = prune_cache.then_some(commands::cleanup::CachePrune {
+                older_than_days: older_than,
+                all,
+            });
+            commands::cleanup::cleanup(cache, dry_run, prune).await
+        }
+        Commands::Cache { action } => match action.unwrap_or(CacheAction::Info) {
+            CacheAction::Info => commands::cache::info(cache).await,
+            CacheAction::Clean { dry_run } => {
+                #[cfg(target_os = "windows")]
+                waxpkg::error::reject_homebrew_cli("cache clean")?;
+                commands::cache::clean(cache, dry_run).await
+            }
+        },
+        Commands::Log { lines } => commands::log::log(lines).await,
+        Commands::Prefix { user, global } => {
+            #[cfg(target_os = "windows")]
+            waxpkg::error::reject_homebrew_cli("prefix")?;
+            commands::prefix::prefix(install_scope(user, global)?)
+        }
+        Commands::Cellar { user, global } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("cleanup")?;
-            commands::cleanup::cleanup(dry_run).await
+            waxpkg::error::reject_homebrew_cli("cellar")?;
+            commands::prefix::cellar(install_scope(user, global)?)
         }
         Commands::Leaves => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("leaves")?;
+            waxpkg::error::reject_homebrew_cli("leaves")?;
             commands::leaves::leaves(cache).await
         }
         Commands::Uses { formula, installed } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("uses")?;
+            waxpkg::error::reject_homebrew_cli("uses")?;
             commands::uses::uses(cache, &formula, installed).await
         }
         Commands::Deps {
@@ -778,71 +1095,116 @@ async fn execute_command(command: Commands, cache: &Cache, yes: bool) -> Result<
             installed,
         } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("deps")?;
+            waxpkg::error::reject_homebrew_cli("deps")?;
             commands::show_deps::deps(cache, &formula, tree, installed).await
         }
         Commands::Pin { packages } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("pin")?;
+            waxpkg::error::reject_homebrew_cli("pin")?;
             commands::pin::pin(&packages).await
         }
         Commands::Unpin { packages } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("unpin")?;
+            waxpkg::error::reject_homebrew_cli("unpin")?;
             commands::pin::unpin(&packages).await
         }
         Commands::Lock => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("lock")?;
+            waxpkg::error::reject_homebrew_cli("lock")?;
             commands::lock::lock(cache).await
         }
-        Commands::Sync => {
+        Commands::Sync { frozen } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("sync")?;
-            commands::sync::sync(cache).await
+            waxpkg::error::reject_homebrew_cli("sync")?;
+            commands::sync::sync(cache, frozen).await
+        }
+        Commands::Bundle { action } => {
+            #[cfg(target_os = "windows")]
+            waxpkg::error::reject_homebrew_cli("bundle")?;
+            match action {
+                BundleAction::Check => commands::sync::check(cache).await,
+            }
         }
         Commands::Tap { action, repair } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("tap")?;
+            waxpkg::error::reject_homebrew_cli("tap")?;
             commands::tap::tap(action, repair, Some(cache)).await
         }
         Commands::Doctor { fix, full } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("doctor")?;
+            waxpkg::error::reject_homebrew_cli("doctor")?;
             commands::doctor::doctor(cache, fix, full).await
         }
         Commands::Source { formula } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("source")?;
+            waxpkg::error::reject_homebrew_cli("source")?;
             commands::source::source(cache, &formula).await
         }
-        Commands::Completions { shell, print } => commands::completions::completions(shell, print),
+        Commands::Cat { formula } => {
+            #[cfg(target_os = "windows")]
+            waxpkg::error::reject_homebrew_cli("cat")?;
+            commands::cat::cat(cache, &formula).await
+        }
+        Commands::Edit { formula } => {
+            #[cfg(target_os = "windows")]
+            waxpkg::error::reject_homebrew_cli("edit")?;
+            commands::edit::edit(&formula).await
+        }
+        Commands::Completions { shell, print } => {
+            commands::completions::completions(Cli::command(), shell, print, yes)
+        }
         Commands::Why { formula } => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("why")?;
+            waxpkg::error::reject_homebrew_cli("why")?;
             commands::info::info(cache, &formula, false).await
         }
         Commands::Audit => {
             #[cfg(target_os = "windows")]
-            crate::error::reject_homebrew_cli("audit")?;
+            waxpkg::error::reject_homebrew_cli("audit")?;
             commands::audit::audit(cache).await
         }
+        Commands::Version => {
+            println!("{}", version::verbose_version());
+            Ok(())
+        }
     }
 }
 
+/// `--version` combined with `--verbose` prints build details. clap answers `--version`
+/// on its own before the rest of the line is parsed, so this is checked up front.
+fn wants_verbose_version(args: &[std::ffi::OsString]) -> bool {
+    let has = |flags: &[&str]| args.iter().skip(1).any(|a| flags.iter().any(|f| a == f));
+    has(&["--version", "-V"]) && has(&["--verbose", "-v"])
+}
+
 async fn run() -> Result<()> {
     let action_timer = Instant::now();
-    let cli = Cli::parse();
+    // `args_os`: a non-UTF-8 argument (a path, say) is clap's to report, not a panic.
+    let args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    if wants_verbose_version(&args) {
+        println!("{}", version::verbose_version());
+        return Ok(());
+    }
+    let cli = Cli::parse_from(args);
 
     signal::install_handler();
     init_logging(cli.verbose)?;
 
     let command = cli.command;
     let command_prints_own_timing = command_prints_timing(&command);
-    let cache = Cache::new()?;
+    let mut cache = Cache::new()?;
+    if cli.refresh {
+        cache = cache.force_refresh();
+    }
     ui::set_timing_enabled(cli.time_to_action);
+    if cli.timings {
+        ui::enable_phase_timings();
+    }
 
-    execute_command(command, &cache, cli.yes).await?;
+    let result = execute_command(command, &cache, cli.yes, cli.verbose).await;
+    // Print even on failure: a slow phase is often why the run was interrupted.
+    ui::print_phase_timings();
+    result?;
 
     if cli.time_to_action && !command_prints_own_timing {
         println!("[{}ms]", action_timer.elapsed().as_millis());
@@ -1,7 +1,9 @@
 //! Unified search across Homebrew index (existing), Scoop Main bucket listing,
 //! Chocolatey web search, and cached winget-pkgs manifest discovery.
 
-use crate::cache::Cache;
+use crate::cache::{
+    Cache, SCOOP_INDEX_CACHE, SCOOP_REPO_CACHE, WINGET_INDEX_CACHE, WINGET_REPO_CACHE,
+};
 use crate::chocolatey;
 use crate::error::Result;
 use crate::package_spec::Ecosystem;
@@ -13,8 +15,6 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::process::Command;
 use tracing::debug;
 
-const SCOOP_INDEX_CACHE: &str = "scoop_main_index.json";
-const WINGET_INDEX_CACHE: &str = "winget_pkgs_index.json";
 const SCOOP_INDEX_MAX_AGE_SECS: i64 = 86_400;
 const WINGET_INDEX_MAX_AGE_SECS: i64 = 86_400;
 
@@ -95,7 +95,7 @@ fn scoop_name_from_tree_path(path: &str) -> Option<String> {
 }
 
 async fn refresh_scoop_index_from_git(cache_dir: &Path) -> Result<Vec<String>> {
-    let repo_dir = cache_dir.join("scoop-main.git");
+    let repo_dir = cache_dir.join(SCOOP_REPO_CACHE);
     let repo = repo_dir.to_string_lossy().to_string();
     if !repo_dir.join("HEAD").exists() {
         if let Some(parent) = repo_dir.parent() {
@@ -223,7 +223,7 @@ async fn run_git(args: &[&str], cwd: Option<&Path>) -> Result<String> {
 }
 
 async fn refresh_winget_index_from_git(cache_dir: &Path) -> Result<Vec<String>> {
-    let repo_dir = cache_dir.join("winget-pkgs.git");
+    let repo_dir = cache_dir.join(WINGET_REPO_CACHE);
     let repo = repo_dir.to_string_lossy().to_string();
     if !repo_dir.join("HEAD").exists() {
         if let Some(parent) = repo_dir.parent() {
@@ -235,7 +235,7 @@ pub fn resolve_manifest_json(raw: &str) -> Result<ResolvedScoopPackage> {
 pub async fn scoop_manifest_exists(bucket_base: &str, package: &str) -> bool {
     let base = bucket_base.trim_end_matches('/');
     let url = format!("{base}/{}.json", package);
-    let client = crate::http_client::default_client();
+    let client = crate::http_client::default_client(&url);
     match client.head(&url).send().await {
         Ok(r) => r.status().is_success(),
         Err(_) => false,
@@ -246,7 +246,7 @@ async fn fetch_manifest_text(bucket_base: &str, package: &str) -> Result<String>
     let base = bucket_base.trim_end_matches('/');
     let url = format!("{base}/{}.json", package);
     debug!("Fetching Scoop manifest {}", url);
-    let client = crate::http_client::default_client();
+    let client = crate::http_client::default_client(&url);
     let resp = client.get(&url).send().await?;
     if !resp.status().is_success() {
         return Err(WaxError::InstallError(format!(
@@ -405,7 +405,8 @@ async fn download_and_verify(
     .await?;
     pb.finish_and_clear();
 
-    crate::digest::verify_sha256_file(download_path, &resolved.sha256)?;
+    crate::digest::verify_digest_file(download_path, &resolved.sha256)
+        .map_err(|e| e.for_download(package, &resolved.download_url))?;
     Ok(())
 }
 
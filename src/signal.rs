@@ -1,11 +1,12 @@
 use indicatif::MultiProgress;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Mutex, OnceLock};
 
 use crate::error::{Result, WaxError};
 
 static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
-static CRITICAL_SECTION: AtomicBool = AtomicBool::new(false);
+/// Number of critical sections currently open; parallel installs can each hold one.
+static CRITICAL_SECTION: AtomicUsize = AtomicUsize::new(0);
 
 static CURRENT_OP: OnceLock<Mutex<String>> = OnceLock::new();
 static ACTIVE_MULTI: OnceLock<Mutex<Option<MultiProgress>>> = OnceLock::new();
@@ -113,15 +114,15 @@ pub fn check_cancelled() -> Result<()> {
 }
 
 pub fn enter_critical_section() {
-    CRITICAL_SECTION.store(true, Ordering::SeqCst);
+    CRITICAL_SECTION.fetch_add(1, Ordering::SeqCst);
 }
 
 pub fn leave_critical_section() {
-    CRITICAL_SECTION.store(false, Ordering::SeqCst);
+    let _ = CRITICAL_SECTION.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
 }
 
 pub fn is_in_critical_section() -> bool {
-    CRITICAL_SECTION.load(Ordering::SeqCst)
+    CRITICAL_SECTION.load(Ordering::SeqCst) > 0
 }
 
 pub struct CriticalSection;
@@ -133,6 +134,12 @@ impl CriticalSection {
     }
 }
 
+impl Default for CriticalSection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Drop for CriticalSection {
     fn drop(&mut self) {
         leave_critical_section();
@@ -147,6 +154,12 @@ impl ActiveMultiGuard {
     }
 }
 
+impl Default for ActiveMultiGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Drop for ActiveMultiGuard {
     fn drop(&mut self) {
         clear_active_multi();
@@ -256,6 +269,10 @@ mod tests {
         {
             let _guard = CriticalSection::new();
             assert!(is_in_critical_section());
+            {
+                let _nested = CriticalSection::new();
+            }
+            assert!(is_in_critical_section());
         }
 
         assert!(!is_in_critical_section());
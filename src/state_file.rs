@@ -0,0 +1,143 @@
+use crate::error::Result;
+use console::style;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+
+/// Hidden sibling of `path` that is unique per process and call.
+pub fn temp_path_for(path: &Path) -> PathBuf {
+    let pid = std::process::id();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("state");
+    path.with_file_name(format!(".{}.{}.{}.tmp", file_name, pid, nanos))
+}
+
+/// Replace `path` with `contents` so readers see either the old file or the new one,
+/// never a truncated mix: write a sibling temp file, flush it, then rename over `path`.
+pub async fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let temp_path = temp_path_for(path);
+    let written = async {
+        let mut file = tokio::fs::File::create(&temp_path).await?;
+        file.write_all(contents.as_ref()).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&temp_path, path).await
+    }
+    .await;
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    Ok(written?)
+}
+
+/// Pretty JSON for a state map with keys in sorted order, so rewriting the file
+/// without changes produces no diff (a `HashMap` serializes in arbitrary order).
+pub fn to_sorted_json<V: Serialize>(map: &HashMap<String, V>) -> Result<String> {
+    let sorted: BTreeMap<&String, &V> = map.iter().collect();
+    Ok(serde_json::to_string_pretty(&sorted)?)
+}
+
+/// `installed.json` → `installed.json.corrupt`.
+pub fn corrupt_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".corrupt");
+    path.with_file_name(name)
+}
+
+/// Parse a state or cache file. A file that no longer parses (typically truncated by a
+/// crash mid-write) is moved aside to `<name>.corrupt` and `None` is returned so the
+/// caller can rebuild or start empty instead of failing every command.
+pub fn parse_or_quarantine<T: DeserializeOwned>(path: &Path, json: &str) -> Option<T> {
+    match serde_json::from_str(json) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            quarantine(path, &e);
+            None
+        }
+    }
+}
+
+/// Move an unreadable file to `<name>.corrupt` and say so.
+pub fn quarantine(path: &Path, reason: &dyn std::fmt::Display) {
+    let backup = corrupt_path(path);
+    let moved = std::fs::rename(path, &backup).is_ok();
+    eprintln!(
+        "{} {} is corrupt ({}){}",
+        style("warning:").yellow(),
+        path.display(),
+        reason,
+        if moved {
+            format!("; moved to {}", backup.display())
+        } else {
+            String::new()
+        }
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn parse_or_quarantine_moves_truncated_file_aside() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("installed.json");
+        let truncated = r#"{"jq": {"name": "jq", "vers"#;
+        std::fs::write(&path, truncated).unwrap();
+
+        let parsed: Option<HashMap<String, serde_json::Value>> =
+            parse_or_quarantine(&path, truncated);
+        assert!(parsed.is_none());
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read_to_string(corrupt_path(&path)).unwrap(),
+            truncated
+        );
+
+        let good: Option<HashMap<String, u32>> = parse_or_quarantine(&path, r#"{"a": 1}"#);
+        assert_eq!(good.unwrap()["a"], 1);
+    }
+
+    #[test]
+    fn sorted_json_is_stable_across_insertion_orders() {
+        let forward: HashMap<String, u32> = (0..50).map(|i| (format!("pkg{i:02}"), i)).collect();
+        let reverse: HashMap<String, u32> =
+            (0..50).rev().map(|i| (format!("pkg{i:02}"), i)).collect();
+        let json = to_sorted_json(&forward).unwrap();
+        assert_eq!(json, to_sorted_json(&reverse).unwrap());
+        assert!(json.find("pkg00").unwrap() < json.find("pkg49").unwrap());
+    }
+
+    #[tokio::test]
+    async fn interrupted_write_leaves_previous_file_intact() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("taps.json");
+        write_atomic(&path, r#"{"old": 1}"#).await.unwrap();
+
+        // A writer that died mid-write only ever touched its temp file.
+        std::fs::write(temp_path_for(&path), r#"{"new": "#).unwrap();
+        let survived: Option<HashMap<String, u32>> =
+            parse_or_quarantine(&path, &std::fs::read_to_string(&path).unwrap());
+        assert_eq!(survived.unwrap()["old"], 1);
+
+        write_atomic(&path, r#"{"new": 2}"#).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), r#"{"new": 2}"#);
+
+        // A failed rename (here: the target is a directory) cleans up its temp file.
+        let dir_target = tmp.path().join("blocked");
+        std::fs::create_dir_all(dir_target.join("child")).unwrap();
+        assert!(write_atomic(&dir_target, "x").await.is_err());
+        let leftovers = std::fs::read_dir(tmp.path())
+            .unwrap()
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().starts_with(".blocked"))
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+}
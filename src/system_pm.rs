@@ -9,7 +9,6 @@ use crate::error::{Result, WaxError};
 use crate::formula_parser::FormulaParser;
 use crate::ui::find_in_path;
 use console::style;
-use sha2::{Digest, Sha256};
 use tokio::process::Command;
 use tracing::{debug, warn};
 
@@ -282,7 +281,7 @@ impl SystemPm {
             ext
         );
 
-        let client = crate::http_client::download();
+        let client = crate::http_client::download(url);
 
         let response = client
             .get(url)
@@ -305,10 +304,9 @@ impl SystemPm {
 
         // Verify checksum if provided.
         if let Some(expected) = sha256 {
-            let mut hasher = Sha256::new();
-            hasher.update(&bytes);
-            let computed = format!("{:x}", hasher.finalize());
-            if computed != expected {
+            let (algorithm, expected) = crate::digest::Algorithm::detect(expected);
+            let computed = algorithm.hex_digest(&bytes);
+            if !computed.eq_ignore_ascii_case(expected) {
                 return Err(WaxError::InstallError(format!(
                     "{} checksum mismatch: expected {}, got {}",
                     name, expected, computed
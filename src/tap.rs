@@ -1,6 +1,7 @@
 use crate::api::Formula;
 use crate::error::{Result, WaxError};
 use crate::formula_parser::FormulaParser;
+use crate::state_file::{quarantine, to_sorted_json, write_atomic};
 use crate::ui::dirs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -25,24 +26,49 @@ pub struct Tap {
     pub trusted: bool,
 }
 
+/// Organization assumed for a single-segment spec: `cask-fonts` → `homebrew/cask-fonts`.
+pub const DEFAULT_TAP_ORG: &str = "homebrew";
+
 fn default_trusted() -> bool {
     true
 }
 
+/// A tap repository keeps formulae under `Formula/` (casks under `Casks/`), or,
+/// for small taps, as `.rb` files at the root.
+fn looks_like_tap(path: &Path) -> bool {
+    if path.join("Formula").is_dir() || path.join("Casks").is_dir() {
+        return true;
+    }
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|e| e.path().extension().is_some_and(|ext| ext == "rb"))
+        })
+        .unwrap_or(false)
+}
+
 impl Tap {
     pub fn from_spec(spec: &str) -> Result<Self> {
-        let expanded = shellexpand::tilde(spec).to_string();
+        let expanded = crate::ui::dirs::expand_user_path(spec)
+            .to_string_lossy()
+            .into_owned();
         let path = Path::new(&expanded);
 
         if path.exists() {
             if path.is_file() {
-                if path.extension().and_then(|s| s.to_str()) == Some("rb") {
-                    return Self::new_local_file(path);
-                } else {
-                    return Err(WaxError::TapError(
-                        "Local file must have .rb extension".to_string(),
-                    ));
-                }
+                return match path.extension().and_then(|s| s.to_str()) {
+                    Some("rb") => Self::new_local_file(path),
+                    Some(ext) => Err(WaxError::TapError(format!(
+                        "Local tap file {} has a .{} extension; expected a .rb formula file",
+                        path.display(),
+                        ext
+                    ))),
+                    None => Err(WaxError::TapError(format!(
+                        "Local tap file {} has no extension; expected a .rb formula file",
+                        path.display()
+                    ))),
+                };
             } else if path.is_dir() {
                 return Self::new_local_dir(path);
             }
@@ -59,15 +85,50 @@ impl Tap {
             return Self::new_git(&expanded);
         }
 
+        if Self::looks_like_path(spec) {
+            return Err(WaxError::TapError(format!(
+                "Local tap path {} does not exist",
+                expanded
+            )));
+        }
+
         let parts: Vec<&str> = spec.split('/').collect();
-        if parts.len() == 2 && !spec.contains('.') && !spec.starts_with('/') {
-            return Self::new_github(parts[0], parts[1]);
+        match parts.as_slice() {
+            [user, repo] if !user.is_empty() && !repo.is_empty() && !spec.contains('.') => {
+                Self::new_github(user, repo)
+            }
+            [repo] if !repo.is_empty() && !spec.contains('.') => {
+                Self::new_github(DEFAULT_TAP_ORG, repo)
+            }
+            [user, repo] if user.is_empty() || repo.is_empty() => Err(WaxError::TapError(format!(
+                "Invalid tap specification: {}. Both parts of 'user/repo' must be non-empty",
+                spec
+            ))),
+            [user, repo, formula, ..] => Err(WaxError::TapError(format!(
+                "Invalid tap specification: {} has {} segments, but a tap is 'user/repo'. \
+                 To install a formula from a tap, run: wax install {}/{}/{}",
+                spec,
+                parts.len(),
+                user,
+                repo,
+                formula
+            ))),
+            _ => Err(WaxError::TapError(format!(
+                "Invalid tap specification: {}. Use 'user/repo', a Git URL, or a local path",
+                spec
+            ))),
         }
+    }
 
-        Err(WaxError::TapError(format!(
-            "Invalid tap specification: {}. Use 'user/repo', a Git URL, or a local path",
-            spec
-        )))
+    /// Specs that can only mean a filesystem path: absolute, relative (`./`, `../`),
+    /// home- or variable-relative, or naming a `.rb` file.
+    fn looks_like_path(spec: &str) -> bool {
+        spec.starts_with('/')
+            || spec.starts_with("./")
+            || spec.starts_with("../")
+            || spec.starts_with('~')
+            || spec.starts_with('$')
+            || spec.ends_with(".rb")
     }
 
     pub fn new_github(user: &str, repo: &str) -> Result<Self> {
@@ -162,6 +223,14 @@ impl Tap {
         }
     }
 
+    /// The `.rb` file defining `name` in this tap's checkout, if there is one.
+    pub fn formula_path(&self, name: &str) -> Option<PathBuf> {
+        match &self.kind {
+            TapKind::LocalFile { path } => (path.file_stem()? == name).then(|| path.clone()),
+            _ => formula_file_in(&self.formula_dir(), name),
+        }
+    }
+
     pub fn url(&self) -> Option<String> {
         match &self.kind {
             TapKind::GitHub { user, repo } => {
@@ -174,6 +243,69 @@ impl Tap {
     }
 }
 
+/// How a tap's formulae changed across an update.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TapChanges {
+    pub added: usize,
+    pub removed: usize,
+    pub updated: usize,
+    /// Formulae in the tap after the update.
+    pub total: usize,
+}
+
+impl TapChanges {
+    /// Compare name → version snapshots taken before and after an update.
+    pub fn between(before: &HashMap<String, String>, after: &HashMap<String, String>) -> Self {
+        let mut changes = TapChanges {
+            total: after.len(),
+            ..Default::default()
+        };
+        for (name, version) in after {
+            match before.get(name) {
+                None => changes.added += 1,
+                Some(old) if old != version => changes.updated += 1,
+                Some(_) => {}
+            }
+        }
+        changes.removed = before.keys().filter(|n| !after.contains_key(*n)).count();
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0 && self.updated == 0
+    }
+}
+
+/// A local homebrew-core checkout to use instead of the JSON API and raw GitHub.
+pub const CORE_TAP_ENV: &str = "WAX_CORE_TAP";
+
+/// The homebrew-core checkout named by `WAX_CORE_TAP`, if set.
+pub fn local_core_tap() -> Option<PathBuf> {
+    let raw = std::env::var(CORE_TAP_ENV).ok()?;
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return None;
+    }
+    Some(crate::ui::dirs::expand_user_path(raw))
+}
+
+/// `Formula/<letter>/<name>.rb` (the sharded layout) or `Formula/<name>.rb` in a
+/// homebrew-core checkout.
+pub fn core_formula_path(core: &Path, name: &str) -> Option<PathBuf> {
+    formula_file_in(&core.join("Formula"), name)
+}
+
+/// `<name>.rb` under `formula_dir`, sharded by first letter (`j/jq.rb`) or flat.
+fn formula_file_in(formula_dir: &Path, name: &str) -> Option<PathBuf> {
+    let first = name.chars().next()?.to_lowercase().to_string();
+    [
+        formula_dir.join(first).join(format!("{}.rb", name)),
+        formula_dir.join(format!("{}.rb", name)),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
 pub struct TapManager {
     taps: HashMap<String, Tap>,
     state_path: PathBuf,
@@ -199,11 +331,21 @@ impl TapManager {
             Ok(taps) => {
                 self.taps = taps;
             }
-            Err(_) => {
-                debug!("Migrating legacy taps.json format");
-                self.taps = Self::migrate_legacy_taps(&json)?;
-                self.save().await?;
-            }
+            Err(_) => match Self::migrate_legacy_taps(&json) {
+                Ok(taps) => {
+                    debug!("Migrated legacy taps.json format");
+                    self.taps = taps;
+                    self.save().await?;
+                }
+                Err(e) => {
+                    // Neither format parses: quarantine it and carry on with no taps.
+                    quarantine(&self.state_path, &e);
+                    eprintln!(
+                        "{} tap list reset; re-add taps with `wax tap add`",
+                        console::style("note:").yellow()
+                    );
+                }
+            },
         }
 
         Ok(())
@@ -267,9 +409,8 @@ impl TapManager {
             .ok_or_else(|| WaxError::CacheError("Cannot determine parent directory".into()))?;
         fs::create_dir_all(parent).await?;
 
-        let json = serde_json::to_string_pretty(&self.taps)?;
-        fs::write(&self.state_path, json).await?;
-        Ok(())
+        let json = to_sorted_json(&self.taps)?;
+        write_atomic(&self.state_path, json).await
     }
 
     #[instrument(skip(self))]
@@ -370,6 +511,14 @@ impl TapManager {
             )));
         }
 
+        if !looks_like_tap(&tap.path) {
+            let _ = fs::remove_dir_all(&tap.path).await;
+            return Err(WaxError::TapError(format!(
+                "{} does not look like a Homebrew tap (no Formula/, Casks/, or top-level .rb files); removed the clone",
+                url
+            )));
+        }
+
         Ok(())
     }
 
@@ -401,6 +550,10 @@ impl TapManager {
         Ok(())
     }
 
+    pub fn get_tap(&self, full_name: &str) -> Option<&Tap> {
+        self.taps.get(full_name)
+    }
+
     pub fn list_taps(&self) -> Vec<&Tap> {
         self.taps.values().collect()
     }
@@ -494,7 +647,7 @@ impl TapManager {
     }
 
     #[instrument(skip(self))]
-    pub async fn update_tap(&mut self, spec: &str) -> Result<()> {
+    pub async fn update_tap(&mut self, spec: &str) -> Result<TapChanges> {
         info!("Updating tap: {}", spec);
 
         let tap_to_update = Tap::from_spec(spec)?;
@@ -504,6 +657,7 @@ impl TapManager {
             .taps
             .get(full_name)
             .ok_or_else(|| WaxError::TapError(format!("Tap {} not found", full_name)))?;
+        let before = self.formula_versions(tap).await;
 
         match &tap.kind {
             TapKind::GitHub { .. } | TapKind::Git { .. } => {
@@ -547,7 +701,18 @@ impl TapManager {
             }
         }
 
-        Ok(())
+        let after = self.formula_versions(tap).await;
+        Ok(TapChanges::between(&before, &after))
+    }
+
+    /// Name → version for every formula the tap currently provides; unreadable taps are empty.
+    async fn formula_versions(&self, tap: &Tap) -> HashMap<String, String> {
+        self.load_formulae_from_tap(tap)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|f| (f.name.clone(), f.full_version()))
+            .collect()
     }
 
     #[instrument(skip(self))]
@@ -594,6 +759,51 @@ impl TapManager {
         }
     }
 
+    /// Every formula in a local homebrew-core checkout, named like core formulae from
+    /// the API (no tap prefix). Unparseable files are skipped, as for other taps.
+    pub async fn load_core_formulae(core: &Path) -> Result<Vec<Formula>> {
+        let formula_dir = core.join("Formula");
+        if !formula_dir.is_dir() {
+            return Err(WaxError::TapError(format!(
+                "{}={} is not a homebrew-core checkout (no Formula directory)",
+                CORE_TAP_ENV,
+                core.display()
+            )));
+        }
+
+        let mut files = Vec::new();
+        let mut dirs = vec![formula_dir];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().and_then(|s| s.to_str()) == Some("rb") {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+
+        let mut formulae = Vec::with_capacity(files.len());
+        for path in files {
+            match Self::parse_formula_file(&path, "homebrew/core").await {
+                Ok(mut formula) => {
+                    formula.full_name = formula.name.clone();
+                    formulae.push(formula);
+                }
+                Err(e) => debug!("{}", e),
+            }
+        }
+        info!(
+            "Loaded {} formulae from local core tap {}",
+            formulae.len(),
+            core.display()
+        );
+        Ok(formulae)
+    }
+
     async fn parse_formula_file(path: &Path, tap_full_name: &str) -> Result<Formula> {
         let name = path
             .file_stem()
@@ -625,6 +835,9 @@ impl TapManager {
                 keg_only: None,
                 keg_only_reason: None,
                 post_install_defined: false,
+                popularity: None,
+                aliases: Vec::new(),
+                oldnames: Vec::new(),
                 rb_path: Some(path.to_path_buf()),
             }),
             Err(e) => Err(crate::error::WaxError::ParseError(format!(
@@ -674,9 +887,99 @@ mod tests {
     }
 
     #[test]
-    fn from_spec_bare_word_returns_error() {
-        let result = Tap::from_spec("justaword");
-        assert!(result.is_err());
+    fn from_spec_rejects_extra_segments_with_install_hint() {
+        let err = Tap::from_spec("user/repo/extra").unwrap_err().to_string();
+        assert!(err.contains("has 3 segments"), "{err}");
+        assert!(err.contains("wax install user/repo/extra"), "{err}");
+    }
+
+    #[test]
+    fn from_spec_rejects_empty_segments() {
+        let err = Tap::from_spec("user/").unwrap_err().to_string();
+        assert!(err.contains("must be non-empty"), "{err}");
+    }
+
+    #[test]
+    fn from_spec_bare_word_expands_to_default_org() {
+        let tap = Tap::from_spec("cask-fonts").unwrap();
+        assert_eq!(tap.full_name, "homebrew/cask-fonts");
+        assert_eq!(
+            tap.url().unwrap(),
+            "https://github.com/homebrew/homebrew-cask-fonts.git"
+        );
+        assert!(Tap::from_spec("").is_err());
+    }
+
+    #[test]
+    fn from_spec_missing_local_path_says_so() {
+        let err = Tap::from_spec("./no-such-tap-dir/formula.rb")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("does not exist"), "{err}");
+    }
+
+    #[test]
+    fn from_spec_local_file_wrong_extension_names_expected_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let txt = dir.path().join("formula.txt");
+        std::fs::write(&txt, "").unwrap();
+        let err = Tap::from_spec(txt.to_str().unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(".txt extension"), "{err}");
+        assert!(err.contains("expected a .rb"), "{err}");
+
+        let bare = dir.path().join("Formula");
+        std::fs::write(&bare, "").unwrap();
+        let err = Tap::from_spec(bare.to_str().unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("no extension"), "{err}");
+    }
+
+    #[test]
+    fn from_spec_local_rb_file_and_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let rb = dir.path().join("foo.rb");
+        std::fs::write(&rb, "").unwrap();
+        let tap = Tap::from_spec(rb.to_str().unwrap()).unwrap();
+        assert!(matches!(tap.kind, TapKind::LocalFile { .. }));
+
+        let tap = Tap::from_spec(dir.path().to_str().unwrap()).unwrap();
+        assert!(matches!(tap.kind, TapKind::LocalDir { .. }));
+    }
+
+    #[test]
+    fn tap_layout_needs_formula_dir_or_root_ruby_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("README.md"), "").unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        assert!(!looks_like_tap(dir.path()));
+
+        std::fs::write(dir.path().join("foo.rb"), "").unwrap();
+        assert!(looks_like_tap(dir.path()));
+
+        let nested = tempfile::tempdir().unwrap();
+        std::fs::create_dir(nested.path().join("Formula")).unwrap();
+        assert!(looks_like_tap(nested.path()));
+    }
+
+    #[test]
+    fn formula_path_finds_flat_and_sharded_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let formula_dir = dir.path().join("Formula");
+        std::fs::create_dir_all(formula_dir.join("b")).unwrap();
+        std::fs::write(formula_dir.join("foo.rb"), "").unwrap();
+        std::fs::write(formula_dir.join("b").join("bar.rb"), "").unwrap();
+
+        let tap = Tap::new_local_dir(dir.path()).unwrap();
+        let formula_dir = tap.formula_dir();
+        assert_eq!(tap.formula_path("foo"), Some(formula_dir.join("foo.rb")));
+        assert_eq!(
+            tap.formula_path("bar"),
+            Some(formula_dir.join("b").join("bar.rb"))
+        );
+        assert_eq!(tap.formula_path("baz"), None);
     }
 
     // ── Tap::url ──────────────────────────────────────────────────────────────
@@ -697,9 +1000,76 @@ mod tests {
 
     // ── TapManager ────────────────────────────────────────────────────────────
 
+    #[test]
+    fn tap_changes_counts_added_removed_updated() {
+        let snapshot = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect()
+        };
+        let before = snapshot(&[("a", "1.0"), ("b", "2.0"), ("c", "3.0")]);
+        let after = snapshot(&[("a", "1.0"), ("b", "2.1"), ("d", "0.1")]);
+
+        let changes = TapChanges::between(&before, &after);
+        assert_eq!(
+            changes,
+            TapChanges {
+                added: 1,
+                removed: 1,
+                updated: 1,
+                total: 3,
+            }
+        );
+        assert!(TapChanges::between(&after, &after).is_empty());
+    }
+
     #[test]
     fn new_tap_manager_starts_empty() {
         let mgr = TapManager::new().unwrap();
         assert!(mgr.list_taps().is_empty());
     }
+
+    // ── local core tap ────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn local_core_checkout_loads_sharded_formulae_as_core() {
+        let tmp = tempfile::tempdir().unwrap();
+        let core = tmp.path();
+        let shard = core.join("Formula").join("j");
+        std::fs::create_dir_all(&shard).unwrap();
+        std::fs::write(
+            shard.join("jq.rb"),
+            r#"class Jq < Formula
+  desc "Lightweight JSON processor"
+  homepage "https://jqlang.github.io/jq/"
+  url "https://example.com/jq-1.7.1.tar.gz"
+  sha256 "478c9ca129fd2e3443fe27314b455e211e0d8c60bc8ff7df703873deeee580c2"
+  depends_on "oniguruma"
+
+  def install
+    system "./configure", *std_configure_args
+    system "make", "install"
+  end
+end
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(core_formula_path(core, "jq"), Some(shard.join("jq.rb")));
+        assert_eq!(core_formula_path(core, "missing"), None);
+
+        let formulae = TapManager::load_core_formulae(core).await.unwrap();
+        assert_eq!(formulae.len(), 1);
+        assert_eq!(formulae[0].name, "jq");
+        assert_eq!(formulae[0].full_name, "jq");
+        assert!(!formulae[0].is_from_tap());
+        assert_eq!(formulae[0].versions.stable, "1.7.1");
+        assert_eq!(formulae[0].rb_path, Some(shard.join("jq.rb")));
+
+        let not_core = TapManager::load_core_formulae(&shard).await.unwrap_err();
+        assert!(not_core
+            .to_string()
+            .contains("not a homebrew-core checkout"));
+    }
 }
@@ -0,0 +1,42 @@
+//! Fixture factories shared by the unit tests.
+
+use crate::api::Formula;
+use crate::install::{InstallMode, InstalledPackage};
+
+/// A bottled formula at version 1.0 with the given runtime dependencies.
+pub(crate) fn formula(name: &str, dependencies: &[&str]) -> Formula {
+    formula_with(name, serde_json::json!({ "dependencies": dependencies }))
+}
+
+/// A bottled formula at version 1.0, with `extra` API fields laid over it.
+pub(crate) fn formula_with(name: &str, extra: serde_json::Value) -> Formula {
+    let mut value = serde_json::json!({
+        "name": name,
+        "full_name": name,
+        "homepage": "",
+        "versions": { "stable": "1.0", "bottle": true },
+    });
+    value
+        .as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    serde_json::from_value(value).unwrap()
+}
+
+/// An install record for a bottle poured in user mode on x86_64 Linux.
+pub(crate) fn installed_package(name: &str, version: &str) -> InstalledPackage {
+    InstalledPackage {
+        name: name.to_string(),
+        version: version.to_string(),
+        platform: "x86_64_linux".to_string(),
+        install_date: 0,
+        install_mode: InstallMode::User,
+        from_source: false,
+        bottle_rebuild: 0,
+        bottle_sha256: None,
+        pinned: false,
+        options: Vec::new(),
+        installed_as_dependency: false,
+        unverified_source: false,
+    }
+}
@@ -3,9 +3,10 @@ use crate::sudo;
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
 use inquire::Confirm;
-use std::io::{self, IsTerminal, Write};
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::time::Duration;
 use tracing::debug;
 
@@ -27,6 +28,124 @@ pub fn elapsed_suffix(elapsed: Duration) -> String {
     }
 }
 
+/// Install phases in the order they happen; the `--timings` table uses this column order.
+pub const PHASES: &[&str] = &[
+    "resolve", "download", "verify", "extract", "copy", "relocate", "link",
+];
+
+/// `(package, phase, elapsed)`.
+type PhaseRecord = (String, &'static str, Duration);
+
+/// `None` unless `--timings` was passed.
+static PHASE_TIMINGS: Mutex<Option<Vec<PhaseRecord>>> = Mutex::new(None);
+
+pub fn enable_phase_timings() {
+    if let Ok(mut guard) = PHASE_TIMINGS.lock() {
+        guard.get_or_insert_with(Vec::new);
+    }
+}
+
+/// Record how long `phase` took for `package`. A no-op unless `--timings` is on.
+pub fn record_phase(package: &str, phase: &'static str, elapsed: Duration) {
+    if let Ok(mut guard) = PHASE_TIMINGS.lock() {
+        if let Some(records) = guard.as_mut() {
+            records.push((package.to_string(), phase, elapsed));
+        }
+    }
+}
+
+/// Print the `--timings` summary to stderr, if anything was recorded.
+pub fn print_phase_timings() {
+    let records = match PHASE_TIMINGS.lock() {
+        Ok(guard) => guard.clone().unwrap_or_default(),
+        Err(_) => return,
+    };
+    if records.is_empty() {
+        return;
+    }
+    eprintln!();
+    eprintln!("{}", style("timings").dim());
+    for line in format_phase_table(&records) {
+        eprintln!("{}", line);
+    }
+}
+
+/// One row per package (in first-seen order) with a column per recorded phase, plus a
+/// totals row. Repeated phases for the same package are summed.
+fn format_phase_table(records: &[PhaseRecord]) -> Vec<String> {
+    let phases: Vec<&str> = PHASES
+        .iter()
+        .copied()
+        .filter(|p| records.iter().any(|(_, phase, _)| phase == p))
+        .collect();
+    let mut packages: Vec<&str> = Vec::new();
+    for (package, _, _) in records {
+        if !packages.contains(&package.as_str()) {
+            packages.push(package);
+        }
+    }
+
+    let sum = |package: Option<&str>, phase: Option<&str>| -> Duration {
+        records
+            .iter()
+            .filter(|(pkg, ph, _)| {
+                package.is_none_or(|p| p == pkg) && phase.is_none_or(|p| p == *ph)
+            })
+            .map(|(_, _, d)| *d)
+            .sum()
+    };
+    let ms = |d: Duration| format!("{}ms", d.as_millis());
+
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut header = vec!["package".to_string()];
+    header.extend(phases.iter().map(|p| p.to_string()));
+    header.push("total".to_string());
+    rows.push(header);
+    for package in &packages {
+        let mut row = vec![package.to_string()];
+        for phase in &phases {
+            let recorded = records
+                .iter()
+                .any(|(pkg, ph, _)| pkg == package && ph == phase);
+            row.push(if recorded {
+                ms(sum(Some(package), Some(phase)))
+            } else {
+                "-".to_string()
+            });
+        }
+        row.push(ms(sum(Some(package), None)));
+        rows.push(row);
+    }
+    if packages.len() > 1 {
+        let mut row = vec!["total".to_string()];
+        row.extend(phases.iter().map(|phase| ms(sum(None, Some(phase)))));
+        row.push(ms(sum(None, None)));
+        rows.push(row);
+    }
+
+    let columns = rows[0].len();
+    let widths: Vec<usize> = (0..columns)
+        .map(|c| rows.iter().map(|r| r[c].chars().count()).max().unwrap_or(0))
+        .collect();
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(c, cell)| {
+                    if c == 0 {
+                        format!("{:<width$}", cell, width = widths[c])
+                    } else {
+                        format!("{:>width$}", cell, width = widths[c])
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
 pub const PROGRESS_BAR_CHARS: &str = "█▓▒░ ";
 pub const PROGRESS_BAR_TEMPLATE: &str =
     "{msg} {wide_bar:.cyan/blue} {bytes}/{total_bytes} {bytes_per_sec}  eta {eta}";
@@ -35,7 +154,16 @@ pub const PROGRESS_BAR_PREFIX_TEMPLATE: &str =
 pub const SPINNER_TICK_CHARS: &str = "⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏";
 
 pub fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
-    match copy_dir_all_inner(src, dst) {
+    copy_dir_all_with_progress(src, dst, None)
+}
+
+/// `copy_dir_all` that ticks `progress` once per file or symlink copied.
+pub fn copy_dir_all_with_progress(
+    src: &Path,
+    dst: &Path,
+    progress: Option<&ProgressBar>,
+) -> Result<()> {
+    match copy_dir_all_inner(src, dst, progress) {
         Ok(()) => Ok(()),
         Err(ref e) if sudo::is_permission_error(e) || sudo::is_file_exists_error(e) => {
             debug!(
@@ -50,7 +178,7 @@ pub fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
     }
 }
 
-fn copy_dir_all_inner(src: &Path, dst: &Path) -> Result<()> {
+fn copy_dir_all_inner(src: &Path, dst: &Path, progress: Option<&ProgressBar>) -> Result<()> {
     std::fs::create_dir_all(dst)?;
 
     for entry in std::fs::read_dir(src)? {
@@ -65,7 +193,8 @@ fn copy_dir_all_inner(src: &Path, dst: &Path) -> Result<()> {
                     std::fs::remove_file(&dst_path).or_else(|_| sudo::sudo_remove(&dst_path))?;
                 }
             }
-            copy_dir_all_inner(&src_path, &dst_path)?;
+            copy_dir_all_inner(&src_path, &dst_path, progress)?;
+            continue;
         } else if ty.is_symlink() {
             #[cfg(unix)]
             {
@@ -98,6 +227,9 @@ fn copy_dir_all_inner(src: &Path, dst: &Path) -> Result<()> {
             }
             copy_regular_file(&src_path, &dst_path)?;
         }
+        if let Some(pb) = progress {
+            pb.inc(1);
+        }
     }
     Ok(())
 }
@@ -107,6 +239,41 @@ fn copy_regular_file(src: &Path, dst: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Bytes used by the files under `path`. Symlinks are skipped rather than followed: a
+/// keg's links into other kegs belong to those kegs, and a link cycle would never end.
+pub fn dir_size(path: &Path) -> u64 {
+    let mut total = 0u64;
+    if let Ok(entries) = std::fs::read_dir(path) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let p = entry.path();
+            let Ok(meta) = std::fs::symlink_metadata(&p) else {
+                continue;
+            };
+            if meta.is_symlink() {
+                continue;
+            }
+            if meta.is_dir() {
+                total += dir_size(&p);
+            } else {
+                total += meta.len();
+            }
+        }
+    }
+    total
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1_073_741_824 {
+        format!("{:.1} GB", bytes as f64 / 1_073_741_824.0)
+    } else if bytes >= 1_048_576 {
+        format!("{:.1} MB", bytes as f64 / 1_048_576.0)
+    } else if bytes >= 1_024 {
+        format!("{:.1} KB", bytes as f64 / 1_024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
 pub fn find_in_path(program: &str) -> Option<PathBuf> {
     if program.contains(std::path::MAIN_SEPARATOR) {
         let path = PathBuf::from(program);
@@ -120,28 +287,31 @@ pub fn find_in_path(program: &str) -> Option<PathBuf> {
         .find(|path| path.is_file())
 }
 
-pub fn confirm_prompt(message: &str) -> Result<bool> {
-    if io::stdin().is_terminal() {
-        return Confirm::new(message)
-            .with_default(false)
-            .prompt_skippable()
-            .map(|answer| answer.unwrap_or(false))
-            .map_err(|e| WaxError::InstallError(format!("prompt failed: {}", e)));
-    }
+/// Ask a yes/no `question` unless the answer is already decided: `--yes` answers yes,
+/// and without a terminal to ask on, `default` is taken instead of waiting for input.
+/// Skipping the prompt with Esc declines.
+pub fn confirm_prompt(yes: bool, question: &str, default: bool) -> Result<bool> {
+    let interactive = io::stdin().is_terminal() && io::stdout().is_terminal();
+    confirm_prompt_with(yes, question, default, interactive)
+}
 
-    print!(
-        "{} {} {} ",
-        style("?").cyan().bold(),
-        message,
-        style("[y/N]").dim()
-    );
-    io::stdout().flush()?;
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    Ok(matches!(
-        input.trim().to_ascii_lowercase().as_str(),
-        "y" | "yes"
-    ))
+fn confirm_prompt_with(
+    yes: bool,
+    question: &str,
+    default: bool,
+    interactive: bool,
+) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+    if !interactive {
+        return Ok(default);
+    }
+    Confirm::new(question)
+        .with_default(default)
+        .prompt_skippable()
+        .map(|answer| answer.unwrap_or(false))
+        .map_err(|e| WaxError::InvalidInput(format!("prompt failed: {}", e)))
 }
 
 pub fn create_spinner(message: &str) -> ProgressBar {
@@ -177,6 +347,17 @@ pub mod dirs {
         ))
     }
 
+    /// A path the user typed or set in the environment, with a leading `~` and
+    /// `$VAR`/`${VAR}` expanded. Unset variables are left as written.
+    pub fn expand_user_path(raw: &str) -> PathBuf {
+        let expanded = shellexpand::full_with_context_no_errors(
+            raw,
+            || home_dir().ok().map(|h| h.to_string_lossy().into_owned()),
+            |var| std::env::var(var).ok(),
+        );
+        PathBuf::from(expanded.as_ref())
+    }
+
     /// Central wax data directory: ~/.wax
     pub fn wax_dir() -> Result<PathBuf> {
         Ok(home_dir()?.join(".wax"))
@@ -185,7 +366,7 @@ pub mod dirs {
     pub fn wax_cache_dir() -> Result<PathBuf> {
         if let Ok(dir) = std::env::var("WAX_CACHE_DIR") {
             if !dir.is_empty() {
-                return Ok(PathBuf::from(dir));
+                return Ok(expand_user_path(&dir));
             }
         }
         Ok(wax_dir()?.join("cache"))
@@ -206,6 +387,33 @@ mod tests {
 
     static ENV_LOCK: Mutex<()> = Mutex::new(());
 
+    #[test]
+    fn confirm_prompt_never_blocks_without_a_terminal() {
+        assert!(confirm_prompt(true, "Continue?", false).unwrap());
+        assert!(!confirm_prompt_with(false, "Continue?", false, false).unwrap());
+        assert!(confirm_prompt_with(false, "Continue?", true, false).unwrap());
+    }
+
+    #[test]
+    fn format_phase_table_sums_phases_per_package() {
+        let ms = Duration::from_millis;
+        let records = vec![
+            ("jq".to_string(), "download", ms(120)),
+            ("oniguruma".to_string(), "download", ms(80)),
+            ("jq".to_string(), "extract", ms(30)),
+            ("jq".to_string(), "extract", ms(5)),
+        ];
+        assert_eq!(
+            format_phase_table(&records),
+            vec![
+                "package    download  extract  total",
+                "jq            120ms     35ms  155ms",
+                "oniguruma      80ms        -   80ms",
+                "total         200ms     35ms  235ms",
+            ]
+        );
+    }
+
     #[test]
     fn test_create_spinner() {
         let message = "Loading...";
@@ -213,6 +421,19 @@ mod tests {
         assert_eq!(spinner.message(), message);
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn dir_size_does_not_follow_symlinks() {
+        let tmp = tempdir().unwrap();
+        let keg = tmp.path().join("keg");
+        std::fs::create_dir_all(keg.join("bin")).unwrap();
+        std::fs::write(keg.join("bin/tool"), vec![0u8; 100]).unwrap();
+        std::fs::write(tmp.path().join("big"), vec![0u8; 4096]).unwrap();
+        std::os::unix::fs::symlink(tmp.path().join("big"), keg.join("bin/big")).unwrap();
+        std::os::unix::fs::symlink(&keg, keg.join("loop")).unwrap();
+        assert_eq!(dir_size(&keg), 100);
+    }
+
     #[test]
     fn test_dirs_resolution() {
         let _guard = ENV_LOCK.lock().unwrap();
@@ -234,6 +455,17 @@ mod tests {
         let override_cache = dummy_home.join("override-cache");
         env::set_var("WAX_CACHE_DIR", &override_cache);
         assert_eq!(dirs::wax_cache_dir().unwrap(), override_cache);
+        env::set_var("WAX_CACHE_DIR", "~/foo");
+        assert_eq!(dirs::wax_cache_dir().unwrap(), dummy_home.join("foo"));
+        env::set_var("WAX_CACHE_DIR", "$HOME/stuff/${HOME}x");
+        assert_eq!(
+            dirs::wax_cache_dir().unwrap(),
+            dummy_home.join(format!("stuff/{}x", dummy_home.display()))
+        );
+        assert_eq!(
+            dirs::expand_user_path("$WAX_TEST_UNSET_VAR/x"),
+            std::path::PathBuf::from("$WAX_TEST_UNSET_VAR/x")
+        );
         env::remove_var("WAX_CACHE_DIR");
         assert_eq!(dirs::wax_logs_dir().unwrap(), dummy_home.join(".wax/logs"));
 
@@ -283,6 +515,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn copy_progress_counts_files_not_directories() {
+        let temp = tempdir().unwrap();
+        let src = temp.path().join("src");
+        fs::create_dir_all(src.join("bin")).unwrap();
+        fs::create_dir_all(src.join("share/doc")).unwrap();
+        fs::write(src.join("bin/tool"), "x").unwrap();
+        fs::write(src.join("share/doc/README"), "y").unwrap();
+        fs::write(src.join("INSTALL_RECEIPT.json"), "{}").unwrap();
+
+        let pb = ProgressBar::hidden();
+        copy_dir_all_with_progress(&src, &temp.path().join("dst"), Some(&pb)).unwrap();
+        assert_eq!(pb.position(), 3);
+    }
+
     #[cfg(unix)]
     #[test]
     fn test_copy_dir_all_with_symlink() {
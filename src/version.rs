@@ -1,6 +1,29 @@
 use std::cmp::Ordering;
 
 pub const WAX_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Short commit hash the binary was built from (`unknown` outside a git checkout).
+pub const GIT_COMMIT: &str = env!("WAX_GIT_COMMIT");
+/// Cargo profile of the build, `debug` or `release`.
+pub const BUILD_PROFILE: &str = env!("WAX_BUILD_PROFILE");
+/// Target triple the binary was compiled for.
+pub const BUILD_TARGET: &str = env!("WAX_BUILD_TARGET");
+
+/// `wax --version --verbose`: the version plus the build and platform details a bug
+/// report needs.
+pub fn verbose_version() -> String {
+    format!(
+        "wax {}
+commit:   {}
+profile:  {}
+target:   {}
+platform: {}",
+        WAX_VERSION,
+        GIT_COMMIT,
+        BUILD_PROFILE,
+        BUILD_TARGET,
+        crate::bottle::detect_platform()
+    )
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BrewVersion {
@@ -65,10 +88,30 @@ impl PartialOrd for BrewVersion {
     }
 }
 
+/// Versions recorded for `install --head` builds, e.g. `HEAD-1a2b3c4`.
+pub fn is_head_version(version: &str) -> bool {
+    version == "HEAD" || version.starts_with("HEAD-")
+}
+
+/// How an installed version relates to the latest version in the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradeStatus {
+    Outdated,
+    UpToDate,
+    /// Installed sorts newer than the index (local build, lagging index).
+    Newer,
+}
+
+pub fn upgrade_status(installed: &str, latest: &str) -> UpgradeStatus {
+    match BrewVersion::parse(installed).cmp(&BrewVersion::parse(latest)) {
+        Ordering::Less => UpgradeStatus::Outdated,
+        Ordering::Equal => UpgradeStatus::UpToDate,
+        Ordering::Greater => UpgradeStatus::Newer,
+    }
+}
+
 pub fn is_same_or_newer(installed: &str, latest: &str) -> bool {
-    let installed_v = BrewVersion::parse(installed);
-    let latest_v = BrewVersion::parse(latest);
-    installed_v >= latest_v
+    upgrade_status(installed, latest) != UpgradeStatus::Outdated
 }
 
 pub fn sort_versions(versions: &mut [String]) {
@@ -167,6 +210,23 @@ mod tests {
         assert!(!is_same_or_newer("2.51.0", "2.52.0"));
     }
 
+    #[test]
+    fn test_upgrade_status() {
+        assert_eq!(upgrade_status("1.2.3", "1.2.4"), UpgradeStatus::Outdated);
+        assert_eq!(upgrade_status("1.2.3", "1.2.3_1"), UpgradeStatus::Outdated);
+        assert_eq!(upgrade_status("1.2.3", "1.2.3"), UpgradeStatus::UpToDate);
+        assert_eq!(upgrade_status("1.2.3_1", "1.2.3"), UpgradeStatus::Newer);
+        assert_eq!(upgrade_status("1.10.0", "1.9.9"), UpgradeStatus::Newer);
+    }
+
+    #[test]
+    fn test_is_head_version() {
+        assert!(is_head_version("HEAD-1a2b3c4"));
+        assert!(is_head_version("HEAD"));
+        assert!(!is_head_version("1.2.3"));
+        assert!(!is_head_version("HEADLESS-1.0"));
+    }
+
     #[test]
     fn test_sort_versions() {
         let mut versions = vec![
@@ -42,8 +42,8 @@ fn package_id_to_content_path(id: &str) -> Result<String> {
     Ok(format!("manifests/{}/{}", first, parts.join("/")))
 }
 
-fn github_client() -> &'static reqwest::Client {
-    crate::http_client::default_client()
+fn github_client(url: &str) -> &'static reqwest::Client {
+    crate::http_client::default_client(url)
 }
 
 async fn gh_get_json(url: &str) -> Result<Vec<GhContentEntry>> {
@@ -53,7 +53,7 @@ async fn gh_get_json(url: &str) -> Result<Vec<GhContentEntry>> {
 }
 
 async fn gh_list_dir_url(url: &str) -> Result<Option<Vec<GhContentEntry>>> {
-    let client = github_client();
+    let client = github_client(url);
     let resp = client.get(url).send().await?;
     if resp.status() == reqwest::StatusCode::NOT_FOUND {
         return Ok(None);
@@ -468,7 +468,7 @@ pub async fn install_winget_package(package_id: &str) -> Result<()> {
     let yaml_path = &installer_yaml.path;
     let raw_url = format!("{WINGET_PKGS_RAW}/{yaml_path}");
     debug!("Fetching winget installer yaml {}", raw_url);
-    let yaml_text = github_client()
+    let yaml_text = github_client(&raw_url)
         .get(&raw_url)
         .send()
         .await?
@@ -508,7 +508,8 @@ pub async fn install_winget_package(package_id: &str) -> Result<()> {
         .await?;
     pb.finish_and_clear();
 
-    crate::digest::verify_sha256_file(&archive_path, &sha_expected)?;
+    crate::digest::verify_digest_file(&archive_path, &sha_expected)
+        .map_err(|e| e.for_download(package_id, &inst.installer_url))?;
 
     if inst_type.eq_ignore_ascii_case("portable") {
         return install_portable_winget_exe(&package_id, &latest, &doc, inst, &archive_path).await;
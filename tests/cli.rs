@@ -4,6 +4,9 @@
 //! command dispatch path.  Network-dependent tests are gated behind the
 //! `INTEGRATION` env var so they don't run in CI without connectivity.
 
+mod common;
+
+use serde_json::json;
 use std::path::Path;
 use std::process::Command;
 
@@ -89,6 +92,32 @@ fn version_output_contains_version_string() {
     );
 }
 
+#[test]
+fn verbose_version_reports_build_metadata() {
+    for args in [&["--version", "--verbose"][..], &["version"][..]] {
+        let out = wax().args(args).output().unwrap();
+        assert!(out.status.success(), "{args:?}: {:?}", out.status.code());
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        for field in ["commit:", "profile:", "target:", "platform:"] {
+            assert!(stdout.contains(field), "{args:?} missing {field}: {stdout}");
+        }
+        assert!(stdout.starts_with(&format!("wax {}", env!("CARGO_PKG_VERSION"))));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_argument_is_reported_not_a_panic() {
+    use std::os::unix::ffi::OsStrExt;
+    let out = wax()
+        .arg("info")
+        .arg(std::ffi::OsStr::from_bytes(b"caf\xe9"))
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("invalid UTF-8"));
+}
+
 #[test]
 fn help_flag_exits_zero() {
     let out = wax().arg("--help").output().unwrap();
@@ -153,6 +182,14 @@ fn install_help_mentions_no_script_flag() {
     assert!(stdout.contains("--no-script"), "{stdout}");
 }
 
+#[test]
+fn search_help_mentions_all_flag() {
+    let out = wax().args(["search", "--help"]).output().unwrap();
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("--all"), "{stdout}");
+}
+
 #[test]
 fn update_help_mentions_self_nightly_shorts() {
     let out = wax().args(["update", "--help"]).output().unwrap();
@@ -364,6 +401,182 @@ fn list_plain_shows_test_cellar_formulae() {
     );
 }
 
+#[cfg(not(windows))]
+#[test]
+fn cache_info_reports_sizes_and_clean_keeps_the_index() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cache = tmp.path().join("cache");
+    std::fs::create_dir_all(cache.join("taps")).unwrap();
+    std::fs::create_dir_all(cache.join("cask-details")).unwrap();
+    std::fs::write(cache.join("formulae.json"), vec![b' '; 2048]).unwrap();
+    std::fs::write(cache.join("cask-details/firefox.json"), "{}").unwrap();
+    std::fs::write(cache.join("taps/gone-tap.json"), "[]").unwrap();
+    std::fs::write(cache.join("winget_pkgs_index.json"), "{}").unwrap();
+    std::fs::write(cache.join("notes.txt"), "not wax's").unwrap();
+    let keg = tmp.path().join(".local/wax/Cellar/wax-cachetest/1.0/bin");
+    std::fs::create_dir_all(&keg).unwrap();
+    std::fs::write(keg.join("tool"), vec![0u8; 1024]).unwrap();
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["cache", "info"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("index"), "{stdout}");
+    assert!(stdout.contains("catalogues"), "{stdout}");
+    assert!(stdout.contains("2.0 KB"), "{stdout}");
+    assert!(stdout.contains("1 formula, 1.0 KB"), "{stdout}");
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["cache", "clean"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(cache.join("formulae.json").exists());
+    assert!(!cache.join("cask-details/firefox.json").exists());
+    assert!(!cache.join("taps/gone-tap.json").exists());
+    assert!(!cache.join("winget_pkgs_index.json").exists());
+    assert!(cache.join("notes.txt").exists());
+}
+
+#[cfg(not(windows))]
+#[test]
+fn list_sizes_and_info_report_keg_disk_usage() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cellar = tmp.path().join(".local/wax/Cellar");
+    std::fs::create_dir_all(cellar.join("wax-a-listtest/1.0.0/bin")).unwrap();
+    std::fs::write(
+        cellar.join("wax-a-listtest/1.0.0/bin/tool"),
+        vec![0u8; 2048],
+    )
+    .unwrap();
+    // An older keg left behind is not counted towards the listed one.
+    std::fs::create_dir_all(cellar.join("wax-a-listtest/0.9.0")).unwrap();
+    std::fs::write(cellar.join("wax-a-listtest/0.9.0/big"), vec![0u8; 8192]).unwrap();
+    let cache = tmp.path().join("cache");
+    std::fs::create_dir_all(&cache).unwrap();
+
+    let out = wax_with_home(tmp.path())
+        .env("CI", "1")
+        .env("WAX_CACHE_DIR", &cache)
+        .env("WAX_TEST_CELLAR", &cellar)
+        .args(["list", "--sizes"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(
+        stdout.contains("wax-a-listtest 0.9.0, 1.0.0 2.0 KB"),
+        "{stdout}"
+    );
+
+    common::write_installed(
+        tmp.path(),
+        &[common::installed_record("wax-a-listtest", "1.0.0")],
+    );
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["info", "--installed", "wax-a-listtest"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("size: 2.0 KB"), "{stdout}");
+}
+
+#[cfg(not(windows))]
+#[test]
+fn list_versions_prints_each_cellar_version() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cellar = tmp.path().join("Cellar");
+    std::fs::create_dir_all(cellar.join("wax-a-listtest/1.0.0")).unwrap();
+    std::fs::create_dir_all(cellar.join("wax-a-listtest/1.2.0")).unwrap();
+    std::fs::create_dir_all(tmp.path().join("opt")).unwrap();
+    std::os::unix::fs::symlink(
+        cellar.join("wax-a-listtest/1.2.0"),
+        tmp.path().join("opt/wax-a-listtest"),
+    )
+    .unwrap();
+    let cache = tmp.path().join("cache");
+    std::fs::create_dir_all(&cache).unwrap();
+
+    let out = wax_with_home(tmp.path())
+        .env("CI", "1")
+        .env("WAX_CACHE_DIR", &cache)
+        .env("WAX_TEST_CELLAR", &cellar)
+        .args(["list", "--versions"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("  1.0.0\n"), "{stdout}");
+    assert!(stdout.contains("  1.2.0 (linked)"), "{stdout}");
+}
+
+#[cfg(not(windows))]
+#[test]
+fn list_merges_install_state_with_cellar_and_flags_drift() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cellar = tmp.path().join(".local/wax/Cellar");
+    std::fs::create_dir_all(cellar.join("wax-kept/1.0.0")).unwrap();
+    std::fs::create_dir_all(cellar.join("wax-stray/0.3.0")).unwrap();
+    common::write_installed(
+        tmp.path(),
+        &[
+            common::installed_record("wax-kept", "1.0.0"),
+            common::installed_record("wax-gone", "2.0.0"),
+        ],
+    );
+    let cache = tmp.path().join("cache");
+    std::fs::create_dir_all(&cache).unwrap();
+
+    let out = wax_with_home(tmp.path())
+        .env("CI", "1")
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["list", "--user"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("wax-kept 1.0.0 [user]\n"), "{stdout}");
+    assert!(
+        stdout.contains("wax-gone 2.0.0 [user] (missing)"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("wax-stray 0.3.0 [user] (untracked)"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("3 packages installed"), "{stdout}");
+}
+
 #[cfg(not(windows))]
 #[test]
 fn list_plain_filter_excludes_non_matching() {
@@ -528,91 +741,305 @@ fn tap_list_exits_zero() {
     );
 }
 
-#[cfg(windows)]
+#[cfg(not(windows))]
 #[test]
-fn tap_list_rejected_on_windows() {
+fn tap_add_warns_when_tap_has_no_formulae() {
     let tmp = tempfile::tempdir().unwrap();
+    let empty_tap = tmp.path().join("not-a-tap");
+    std::fs::create_dir_all(&empty_tap).unwrap();
     let out = wax_with_home(tmp.path())
         .env("WAX_CACHE_DIR", tmp.path().join("cache"))
-        .args(["tap", "list"])
+        .args(["tap", "add", empty_tap.to_str().unwrap()])
         .output()
         .unwrap();
-    assert!(!out.status.success());
     let stderr = String::from_utf8_lossy(&out.stderr);
-    assert!(stderr.contains("not available on Windows"), "{stderr}");
+    assert!(out.status.success(), "{stderr}");
+    assert!(stderr.contains("has no formulae"), "{stderr}");
 }
 
-// ── invalid input should not panic ───────────────────────────────────────────
-
+#[cfg(not(windows))]
 #[test]
-fn install_no_args_does_not_panic() {
-    let out = wax().arg("install").output().unwrap();
-    // Should not panic or abort.
-    let stderr = String::from_utf8_lossy(&out.stderr);
-    // Must not produce a Rust panic message.
+fn edit_opens_tap_formula_and_refuses_core_formulae() {
+    let tmp = tempfile::tempdir().unwrap();
+    let tap = tmp.path().join("mytap");
+    std::fs::create_dir_all(tap.join("Formula")).unwrap();
+    std::fs::write(tap.join("Formula/foo.rb"), "class Foo < Formula\nend\n").unwrap();
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["tap", "add", tap.to_str().unwrap()])
+        .output()
+        .unwrap();
     assert!(
-        !stderr.contains("thread 'main' panicked"),
-        "wax panicked: {stderr}"
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
     );
-}
 
-#[test]
-fn search_no_args_does_not_panic() {
-    let out = wax().arg("search").output().unwrap();
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .env_remove("VISUAL")
+        .env("EDITOR", "cat")
+        .args(["edit", "local/mytap/foo"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        "class Foo < Formula\nend\n"
+    );
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .env_remove("WAX_CORE_TAP")
+        .env("EDITOR", "cat")
+        .args(["edit", "jq"])
+        .output()
+        .unwrap();
     let stderr = String::from_utf8_lossy(&out.stderr);
-    assert!(!stderr.contains("thread 'main' panicked"), "{stderr}");
+    assert!(!out.status.success());
+    assert!(stderr.contains("formula not found: jq"), "{stderr}");
+    assert!(stderr.contains("WAX_CORE_TAP"), "{stderr}");
+
+    // A bare name found in two taps has to be qualified.
+    let other = tmp.path().join("othertap");
+    std::fs::create_dir_all(other.join("Formula")).unwrap();
+    std::fs::write(other.join("Formula/foo.rb"), "class Foo < Formula\nend\n").unwrap();
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["tap", "add", other.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .env_remove("WAX_CORE_TAP")
+        .env("EDITOR", "cat")
+        .args(["edit", "foo"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(!out.status.success());
+    assert!(stderr.contains("local/mytap/foo"), "{stderr}");
+    assert!(stderr.contains("local/othertap/foo"), "{stderr}");
+    assert!(!stderr.contains("served from the API"), "{stderr}");
 }
 
+#[cfg(not(windows))]
 #[test]
-fn unknown_subcommand_exits_nonzero() {
-    let out = wax()
-        .arg("definitely-not-a-real-subcommand")
+fn cleanup_prune_cache_dry_run_lists_orphaned_tap_cache() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cache = tmp.path().join("cache");
+    std::fs::create_dir_all(cache.join("taps")).unwrap();
+    std::fs::write(cache.join("formulae.json"), "[]").unwrap();
+    std::fs::write(cache.join("taps/user-gone.json"), "[]").unwrap();
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["cleanup", "--prune-cache", "--dry-run"])
         .output()
         .unwrap();
-    assert!(!out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("user-gone.json"), "{stdout}");
+    assert!(!stdout.contains("formulae.json"), "{stdout}");
+    assert!(cache.join("taps/user-gone.json").exists());
 }
 
 #[cfg(not(windows))]
 #[test]
-fn reinstall_missing_package_exits_nonzero_without_installing() {
+fn corrupt_state_files_are_quarantined_not_fatal() {
     let tmp = tempfile::tempdir().unwrap();
+    let wax_dir = tmp.path().join(".wax");
+    std::fs::create_dir_all(&wax_dir).unwrap();
+    std::fs::write(wax_dir.join("installed.json"), r#"{"jq": {"name": "j"#).unwrap();
+    std::fs::write(wax_dir.join("taps.json"), "{").unwrap();
+
     let out = wax_with_home(tmp.path())
-        .env("CI", "1")
-        .args(["reinstall", "definitely-no-such-package"])
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["tap", "list"])
         .output()
         .unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "{stderr}");
+    assert!(stderr.contains("is corrupt"), "{stderr}");
+    assert!(wax_dir.join("taps.json.corrupt").exists());
 
-    assert!(!out.status.success());
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["list", "--versions"])
+        .output()
+        .unwrap();
     let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "{stderr}");
+    assert!(wax_dir.join("installed.json.corrupt").exists());
+}
+
+#[cfg(not(windows))]
+#[test]
+fn info_installed_reads_local_state_only() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut jq = common::installed_record("jq", "1.7.1");
+    jq["from_source"] = true.into();
+    let mut oniguruma = common::installed_record("oniguruma", "6.9.9");
+    oniguruma["installed_as_dependency"] = true.into();
+    common::write_installed(tmp.path(), &[jq, oniguruma]);
+
+    // No index in the cache dir: this must not try to fetch one.
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["info", "--installed", "jq"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
     assert!(
-        stderr.contains("definitely-no-such-package is not installed"),
-        "{stderr}"
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
     );
+    assert!(stdout.contains("1.7.1"), "{stdout}");
+    assert!(stdout.contains("2023-11-14"), "{stdout}");
+    assert!(stdout.contains("from source"), "{stdout}");
+    assert!(stdout.contains("reason: on request"), "{stdout}");
+    assert!(!tmp.path().join("cache/formulae.json").exists());
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["info", "--installed", "oniguruma"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("reason: dependency"), "{stdout}");
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["info", "--installed", "not-installed"])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
 }
 
 #[cfg(windows)]
 #[test]
-fn reinstall_rejected_on_windows() {
+fn tap_list_rejected_on_windows() {
     let tmp = tempfile::tempdir().unwrap();
     let out = wax_with_home(tmp.path())
-        .env("CI", "1")
-        .args(["reinstall", "definitely-no-such-package"])
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["tap", "list"])
         .output()
         .unwrap();
-
     assert!(!out.status.success());
     let stderr = String::from_utf8_lossy(&out.stderr);
     assert!(stderr.contains("not available on Windows"), "{stderr}");
 }
 
-// ── network integration tests (skipped unless INTEGRATION=1) ─────────────────
-
-fn integration_enabled() -> bool {
-    std::env::var("INTEGRATION").unwrap_or_default() == "1"
-}
+// ── invalid input should not panic ───────────────────────────────────────────
 
 #[test]
-fn search_tree_finds_results() {
+fn install_no_args_does_not_panic() {
+    let out = wax().arg("install").output().unwrap();
+    // Should not panic or abort.
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    // Must not produce a Rust panic message.
+    assert!(
+        !stderr.contains("thread 'main' panicked"),
+        "wax panicked: {stderr}"
+    );
+}
+
+#[test]
+fn install_ignore_dependencies_requires_package() {
+    let tmp = tempfile::tempdir().unwrap();
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["install", "--ignore-dependencies"])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("--ignore-dependencies needs"), "{stderr}");
+}
+
+#[test]
+fn install_only_and_ignore_dependencies_conflict() {
+    let out = wax()
+        .args([
+            "install",
+            "--only-dependencies",
+            "--ignore-dependencies",
+            "foo",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("cannot be used with"), "{stderr}");
+}
+
+#[test]
+fn search_no_args_does_not_panic() {
+    let out = wax().arg("search").output().unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(!stderr.contains("thread 'main' panicked"), "{stderr}");
+}
+
+#[test]
+fn unknown_subcommand_exits_nonzero() {
+    let out = wax()
+        .arg("definitely-not-a-real-subcommand")
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+}
+
+#[cfg(not(windows))]
+#[test]
+fn reinstall_missing_package_exits_nonzero_without_installing() {
+    let tmp = tempfile::tempdir().unwrap();
+    let out = wax_with_home(tmp.path())
+        .env("CI", "1")
+        .args(["reinstall", "definitely-no-such-package"])
+        .output()
+        .unwrap();
+
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("definitely-no-such-package is not installed"),
+        "{stderr}"
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn reinstall_rejected_on_windows() {
+    let tmp = tempfile::tempdir().unwrap();
+    let out = wax_with_home(tmp.path())
+        .env("CI", "1")
+        .args(["reinstall", "definitely-no-such-package"])
+        .output()
+        .unwrap();
+
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("not available on Windows"), "{stderr}");
+}
+
+// ── network integration tests (skipped unless INTEGRATION=1) ─────────────────
+
+fn integration_enabled() -> bool {
+    std::env::var("INTEGRATION").unwrap_or_default() == "1"
+}
+
+#[test]
+fn search_tree_finds_results() {
     if !integration_enabled() {
         return;
     }
@@ -653,3 +1080,586 @@ fn update_fetches_index() {
     assert!(cache_dir.path().join("formulae.json").exists());
     assert!(cache_dir.path().join("casks.json").exists());
 }
+
+#[test]
+fn log_prints_tail_of_log_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let logs = tmp.path().join(".wax/logs");
+    std::fs::create_dir_all(&logs).unwrap();
+    std::fs::write(logs.join("wax.log.1"), "older-1\nolder-2\n").unwrap();
+    std::fs::write(logs.join("wax.log"), "recent-1\nrecent-2\n").unwrap();
+
+    let out = wax_with_home(tmp.path())
+        .env("CI", "1")
+        .args(["log", "-n", "3"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        stdout.starts_with("older-2\nrecent-1\nrecent-2\n"),
+        "{stdout}"
+    );
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn prefix_and_cellar_print_bare_paths() {
+    let tmp = tempfile::tempdir().unwrap();
+    let user_prefix = tmp.path().join(".local").join("wax");
+
+    for (cmd, expected) in [
+        ("prefix", user_prefix.clone()),
+        ("cellar", user_prefix.join("Cellar")),
+    ] {
+        let out = wax_with_home(tmp.path())
+            .env("CI", "1")
+            .args([cmd, "--user"])
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "{}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&out.stdout),
+            format!("{}\n", expected.display())
+        );
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn uninstall_dry_run_reports_symlinks_and_size() {
+    let tmp = tempfile::tempdir().unwrap();
+    common::write_installed(tmp.path(), &[common::installed_record("foo", "1.0")]);
+    let prefix = tmp.path().join(".local").join("wax");
+    let keg_bin = prefix.join("Cellar/foo/1.0/bin");
+    std::fs::create_dir_all(&keg_bin).unwrap();
+    std::fs::write(keg_bin.join("foo"), vec![b'x'; 100]).unwrap();
+    std::fs::create_dir_all(prefix.join("bin")).unwrap();
+    std::os::unix::fs::symlink(keg_bin.join("foo"), prefix.join("bin/foo")).unwrap();
+    let cache = tmp.path().join("cache");
+    common::write_index(&cache, json!([]), json!([]));
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["uninstall", "foo", "--dry-run"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("(1 symlink, 100 B)"), "{stdout}");
+    assert!(prefix.join("bin/foo").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn uninstall_dry_run_of_an_unrecorded_keg_saves_no_state() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(tmp.path().join(".local/wax/Cellar/foo/1.0/bin")).unwrap();
+    let cache = tmp.path().join("cache");
+    common::write_index(&cache, json!([]), json!([]));
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["uninstall", "foo", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stdout).contains("would remove foo@1.0"));
+    assert!(!tmp.path().join(".wax/installed.json").exists());
+    assert!(!tmp.path().join(".wax/installed_casks.json").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn uninstall_of_a_dependency_needs_yes_when_not_interactive() {
+    let tmp = tempfile::tempdir().unwrap();
+    common::write_installed(
+        tmp.path(),
+        &[
+            common::installed_record("foo", "1.0"),
+            common::installed_record("bar", "1.0"),
+        ],
+    );
+    let keg = tmp.path().join(".local/wax/Cellar/foo/1.0");
+    std::fs::create_dir_all(keg.join("bin")).unwrap();
+    std::fs::create_dir_all(tmp.path().join(".local/wax/Cellar/bar/1.0")).unwrap();
+    let cache = tmp.path().join("cache");
+    common::write_index(
+        &cache,
+        json!([common::formula_json("bar", &["foo"])]),
+        json!([]),
+    );
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["uninstall", "foo"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("uninstall cancelled"), "{stdout}");
+    assert!(keg.exists());
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["uninstall", "--yes", "foo"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(!keg.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn relink_restores_missing_and_dangling_links() {
+    let tmp = tempfile::tempdir().unwrap();
+    common::write_installed(tmp.path(), &[common::installed_record("foo", "1.0")]);
+    let prefix = tmp.path().join(".local/wax");
+    let keg_bin = prefix.join("Cellar/foo/1.0/bin");
+    std::fs::create_dir_all(&keg_bin).unwrap();
+    std::fs::write(keg_bin.join("foo"), "").unwrap();
+    std::fs::write(keg_bin.join("foo-helper"), "").unwrap();
+    std::fs::create_dir_all(prefix.join("bin")).unwrap();
+    std::os::unix::fs::symlink(
+        prefix.join("Cellar/foo/0.9/bin/foo"),
+        prefix.join("bin/foo"),
+    )
+    .unwrap();
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["relink", "foo"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    for tool in ["foo", "foo-helper"] {
+        assert!(prefix.join("bin").join(tool).exists(), "{tool} not linked");
+    }
+
+    std::fs::remove_file(prefix.join("bin/foo-helper")).unwrap();
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["link", "--relink", "foo"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(prefix.join("bin/foo-helper").exists());
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .args(["relink", "bar"])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+}
+
+#[test]
+fn uninstall_finds_installed_cask_without_cask_flag() {
+    let tmp = tempfile::tempdir().unwrap();
+    let wax_dir = tmp.path().join(".wax");
+    std::fs::create_dir_all(&wax_dir).unwrap();
+    std::fs::write(
+        wax_dir.join("installed_casks.json"),
+        r#"{"firefox": {"name": "firefox", "version": "128.0", "install_date": 1700000000,
+            "artifact_type": "binary"}}"#,
+    )
+    .unwrap();
+    let cache = tmp.path().join("cache");
+    common::write_index(&cache, json!([]), json!([]));
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["uninstall", "firefox", "--dry-run"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("- firefox (cask)"), "{stdout}");
+}
+
+#[test]
+fn uninstall_several_casks_in_one_pass_with_zap() {
+    let tmp = tempfile::tempdir().unwrap();
+    let wax_dir = tmp.path().join(".wax");
+    std::fs::create_dir_all(&wax_dir).unwrap();
+    std::fs::write(
+        wax_dir.join("installed_casks.json"),
+        r#"{"firefox": {"name": "firefox", "version": "128.0", "install_date": 1700000000,
+            "artifact_type": "binary"},
+           "slack": {"name": "slack", "version": "4.0", "install_date": 1700000000,
+            "artifact_type": "binary"}}"#,
+    )
+    .unwrap();
+    let prefs = tmp
+        .path()
+        .join("Library/Preferences/org.mozilla.firefox.plist");
+    std::fs::create_dir_all(prefs.parent().unwrap()).unwrap();
+    std::fs::write(&prefs, "").unwrap();
+
+    let cache = tmp.path().join("cache");
+    common::write_index(&cache, json!([]), json!([]));
+    std::fs::create_dir_all(cache.join("cask-details")).unwrap();
+    std::fs::write(
+        cache.join("cask-details/firefox.json"),
+        r#"{"token": "firefox", "name": ["Firefox"], "homepage": "", "version": "128.0",
+            "url": "https://example.com/firefox.dmg",
+            "artifacts": [{"zap": [{"trash": "~/Library/Preferences/org.mozilla.*"}]}]}"#,
+    )
+    .unwrap();
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args([
+            "uninstall",
+            "--cask",
+            "--zap",
+            "--dry-run",
+            "firefox",
+            "nope",
+            "slack",
+        ])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    // The missing token fails the command, but only after the others were handled.
+    assert!(!out.status.success(), "{stderr}");
+    assert!(stdout.contains("[1/3] - firefox (cask)"), "{stdout}");
+    assert!(
+        stdout.contains("would zap") && stdout.contains("org.mozilla.firefox.plist"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("[3/3] - slack (cask)"), "{stdout}");
+    assert!(
+        stderr.contains("1 cask failed to uninstall: nope"),
+        "{stderr}"
+    );
+    assert!(prefs.exists());
+}
+
+#[test]
+fn verbose_traces_fetched_urls_on_stderr() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cache = tmp.path().join("cache");
+    common::write_index(
+        &cache,
+        json!([]),
+        json!([{"token": "firefox", "full_token": "firefox", "name": ["Firefox"],
+            "homepage": "", "version": "128.0"}]),
+    );
+    let url = "https://formulae.brew.sh/api/cask/firefox.json";
+
+    let run = |args: &[&str]| {
+        let out = wax_with_home(tmp.path())
+            .env("WAX_CACHE_DIR", &cache)
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&out.stderr).into_owned()
+    };
+    let verbose = run(&["-v", "info", "--cask", "firefox"]);
+    assert!(
+        verbose.contains(&format!("Fetching details for cask firefox: {url}")),
+        "{verbose}"
+    );
+    let quiet = run(&["info", "--cask", "firefox"]);
+    assert!(!quiet.contains("Fetching details"), "{quiet}");
+}
+
+#[test]
+fn info_unknown_package_fails_with_styled_not_found() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cache = tmp.path().join("cache");
+    common::write_index(&cache, json!([]), json!([]));
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["info", "nonexistent"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(1), "{stderr}");
+    assert_eq!(stderr.trim(), "error: formula not found: nonexistent");
+}
+
+#[test]
+fn info_reads_formulae_from_local_core_tap() {
+    let tmp = tempfile::tempdir().unwrap();
+    let core = tmp.path().join("homebrew-core");
+    std::fs::create_dir_all(core.join("Formula/h")).unwrap();
+    std::fs::write(
+        core.join("Formula/h/hello.rb"),
+        r#"class Hello < Formula
+  desc "Program providing model for GNU coding standards"
+  homepage "https://www.gnu.org/software/hello/"
+  url "https://ftp.gnu.org/gnu/hello/hello-2.12.1.tar.gz"
+  sha256 "8d99142afd92576f30b0cd7cb42a8dc6809998bc5d607d88761f512e26c7db20"
+
+  def install
+    system "./configure", *std_configure_args
+    system "make", "install"
+  end
+end
+"#,
+    )
+    .unwrap();
+    // No index in the cache: everything must come from the checkout.
+    let cache = tmp.path().join("cache");
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .env("WAX_CORE_TAP", &core)
+        .args(["info", "hello"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("hello · 2.12.1"), "{stdout}");
+    assert!(!cache.join("formulae.json").exists());
+}
+
+#[test]
+fn cat_prints_the_formula_ruby_source() {
+    let tmp = tempfile::tempdir().unwrap();
+    let core = tmp.path().join("homebrew-core");
+    std::fs::create_dir_all(core.join("Formula/h")).unwrap();
+    let ruby = r#"class Hello < Formula
+  desc "Program providing model for GNU coding standards"
+  homepage "https://www.gnu.org/software/hello/"
+  url "https://ftp.gnu.org/gnu/hello/hello-2.12.1.tar.gz"
+  sha256 "8d99142afd92576f30b0cd7cb42a8dc6809998bc5d607d88761f512e26c7db20"
+
+  def install
+    system "make", "install"
+  end
+end
+"#;
+    std::fs::write(core.join("Formula/h/hello.rb"), ruby).unwrap();
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .env("WAX_CORE_TAP", &core)
+        .args(["cat", "hello"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&out.stdout), ruby);
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", tmp.path().join("cache"))
+        .env("WAX_CORE_TAP", &core)
+        .args(["cat", "nope"])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+}
+
+#[test]
+fn api_url_override_routes_cask_details_to_mirror() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cache = tmp.path().join("cache");
+    common::write_index(
+        &cache,
+        json!([]),
+        json!([{"token": "firefox", "full_token": "firefox", "name": ["Firefox"],
+            "homepage": "", "version": "128.0"}]),
+    );
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .env("WAX_API_URL", "http://127.0.0.1:9/brew-api/")
+        .args(["-v", "info", "--cask", "firefox"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("http://127.0.0.1:9/brew-api/cask/firefox.json"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("formulae.brew.sh"), "{stderr}");
+}
+
+#[test]
+fn search_installed_only_lists_installed_matches() {
+    let tmp = tempfile::tempdir().unwrap();
+    common::write_installed(tmp.path(), &[common::installed_record("libfoo", "1.0")]);
+    let cache = tmp.path().join("cache");
+    common::write_index(
+        &cache,
+        json!([
+            common::formula_json("libfoo", &[]),
+            common::formula_json("foobar", &[])
+        ]),
+        json!([]),
+    );
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["search", "foo", "--installed"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("libfoo"), "{stdout}");
+    assert!(!stdout.contains("foobar"), "{stdout}");
+}
+
+#[test]
+fn bundle_check_fails_and_lists_drift() {
+    let tmp = tempfile::tempdir().unwrap();
+    let wax_dir = tmp.path().join(".wax");
+    std::fs::create_dir_all(&wax_dir).unwrap();
+    std::fs::write(
+        wax_dir.join("wax.lock"),
+        "[packages.wax-test-missing]\nversion = \"1.0\"\nbottle = \"x86_64_linux\"\n",
+    )
+    .unwrap();
+    let cache = tmp.path().join("cache");
+    common::write_index(&cache, json!([]), json!([]));
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["bundle", "check"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(!out.status.success());
+    assert!(
+        stdout.contains("wax-test-missing: missing (locked 1.0)"),
+        "{stdout}"
+    );
+    assert!(String::from_utf8_lossy(&out.stderr).contains("wax sync"));
+}
+
+#[cfg(unix)]
+#[test]
+fn uninstall_autoremove_removes_orphaned_dependencies_only() {
+    let tmp = tempfile::tempdir().unwrap();
+    let record = |name: &str, as_dependency: bool| {
+        let mut record = common::installed_record(name, "1.0");
+        record["installed_as_dependency"] = as_dependency.into();
+        record
+    };
+    common::write_installed(
+        tmp.path(),
+        &[
+            record("app", false),
+            record("liba", true),
+            record("libshared", true),
+            record("tool", false),
+        ],
+    );
+    let cellar = tmp.path().join(".local/wax/Cellar");
+    for name in ["app", "liba", "libshared", "tool"] {
+        std::fs::create_dir_all(cellar.join(name).join("1.0/bin")).unwrap();
+    }
+    let cache = tmp.path().join("cache");
+    common::write_index(
+        &cache,
+        json!([
+            common::formula_json("app", &["liba", "libshared"]),
+            common::formula_json("liba", &[]),
+            common::formula_json("libshared", &[]),
+            common::formula_json("tool", &["libshared"])
+        ]),
+        json!([]),
+    );
+
+    let out = wax_with_home(tmp.path())
+        .env("WAX_CACHE_DIR", &cache)
+        .args(["uninstall", "--autoremove", "--yes", "app"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("no longer needed"), "{stdout}");
+    assert!(!cellar.join("app").exists());
+    assert!(!cellar.join("liba").exists());
+    assert!(cellar.join("libshared/1.0").exists());
+    assert!(cellar.join("tool/1.0").exists());
+}
+
+#[cfg(not(windows))]
+#[test]
+fn old_index_is_noted_when_the_api_is_unreachable_but_bad_responses_fail() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cache = tmp.path().join("cache");
+    common::write_index(&cache, json!([]), json!([]));
+    std::fs::write(
+        cache.join("metadata.json"),
+        r#"{"last_updated": 1700000000, "formula_count": 0, "cask_count": 0}"#,
+    )
+    .unwrap();
+    let search = |api: &str| {
+        wax_with_home(tmp.path())
+            .env("WAX_CACHE_DIR", &cache)
+            .env("WAX_API_URL", api)
+            .env_remove("WAX_CORE_TAP")
+            .env_remove("WAX_INDEX_MAX_AGE_DAYS")
+            .args(["search", "hello"])
+            .output()
+            .unwrap()
+    };
+
+    // Nothing listens on the discard port: offline, so the cached index is used.
+    let out = search("http://127.0.0.1:9");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "{stderr}");
+    assert!(stderr.contains("days old"), "{stderr}");
+
+    // A plain-HTTP remote mirror is refused outright, which is not being offline.
+    let out = search("http://mirror.invalid/api");
+    assert!(!out.status.success());
+}
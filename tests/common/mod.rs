@@ -0,0 +1,54 @@
+//! Fixture factories shared by the integration tests.
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// An API index entry for a bottled formula at version 1.0.
+pub fn formula_json(name: &str, dependencies: &[&str]) -> Value {
+    json!({
+        "name": name,
+        "full_name": name,
+        "homepage": "",
+        "versions": { "stable": "1.0", "bottle": true },
+        "dependencies": dependencies,
+    })
+}
+
+pub fn formula(name: &str, dependencies: &[&str]) -> waxpkg::Formula {
+    serde_json::from_value(formula_json(name, dependencies)).unwrap()
+}
+
+/// An `installed.json` record for a bottle poured in user mode.
+pub fn installed_record(name: &str, version: &str) -> Value {
+    json!({
+        "name": name,
+        "version": version,
+        "platform": "x86_64_linux",
+        "install_date": 1700000000,
+        "install_mode": "user",
+        "from_source": false,
+    })
+}
+
+/// Writes `records` to `<home>/.wax/installed.json`, keyed by name.
+pub fn write_installed(home: &Path, records: &[Value]) {
+    let state: serde_json::Map<String, Value> = records
+        .iter()
+        .map(|record| (record["name"].as_str().unwrap().to_string(), record.clone()))
+        .collect();
+    let wax_dir = home.join(".wax");
+    std::fs::create_dir_all(&wax_dir).unwrap();
+    std::fs::write(
+        wax_dir.join("installed.json"),
+        serde_json::to_string(&state).unwrap(),
+    )
+    .unwrap();
+}
+
+/// Seeds `cache` with formula and cask indexes so commands skip the network.
+pub fn write_index(cache: &Path, formulae: Value, casks: Value) {
+    std::fs::create_dir_all(cache).unwrap();
+    std::fs::write(cache.join("formulae.json"), formulae.to_string()).unwrap();
+    std::fs::write(cache.join("casks.json"), casks.to_string()).unwrap();
+}
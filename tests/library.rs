@@ -0,0 +1,24 @@
+mod common;
+
+use common::formula;
+use std::collections::{HashMap, HashSet};
+use waxpkg::{resolve_dependencies, Formula};
+
+#[test]
+fn resolves_install_order_through_the_public_api() {
+    let formulae = [
+        formula("wget", &["openssl@3", "libidn2"]),
+        formula("openssl@3", &["ca-certificates"]),
+        formula("libidn2", &[]),
+        formula("ca-certificates", &[]),
+    ];
+    let index: HashMap<&str, &Formula> = formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+
+    let installed = HashSet::from(["libidn2".to_string()]);
+    let order = resolve_dependencies(&formulae[0], &index, &installed).unwrap();
+
+    let position = |name: &str| order.iter().position(|n| n == name).unwrap();
+    assert!(!order.contains(&"libidn2".to_string()));
+    assert!(position("ca-certificates") < position("openssl@3"));
+    assert!(position("openssl@3") < position("wget"));
+}
@@ -0,0 +1,502 @@
+//! End-to-end tests against a local mock of the Homebrew API.
+//!
+//! The binary is pointed at a `wiremock` server through `WAX_API_URL`, and bottle
+//! URLs in the canned index point at the same server, so index refreshes and bottle
+//! installs run for real without touching the network.
+
+mod common;
+
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::process::{Command, Output};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn wax(home: &Path, server: &MockServer) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_wax"));
+    cmd.env("HOME", home)
+        .env("WAX_CACHE_DIR", home.join("cache"))
+        .env("WAX_API_URL", server.uri())
+        .env_remove("WAX_CORE_TAP");
+    #[cfg(windows)]
+    cmd.env("USERPROFILE", home);
+    cmd
+}
+
+/// Run the binary off the async runtime so the mock server keeps answering.
+async fn run(mut cmd: Command) -> Output {
+    tokio::task::spawn_blocking(move || cmd.output().unwrap())
+        .await
+        .unwrap()
+}
+
+fn assert_success(out: &Output) {
+    assert!(
+        out.status.success(),
+        "stdout: {}\nstderr: {}",
+        String::from_utf8_lossy(&out.stdout),
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+
+/// A bottle laid out the way Homebrew packs them: `<name>/<version>/bin/<name>`.
+fn bottle(name: &str, version: &str) -> Vec<u8> {
+    let mut archive = tar::Builder::new(flate2::write::GzEncoder::new(
+        Vec::new(),
+        flate2::Compression::default(),
+    ));
+    let script = format!("#!/bin/sh\necho {name} {version}\n");
+    let mut header = tar::Header::new_gnu();
+    header.set_size(script.len() as u64);
+    header.set_mode(0o755);
+    header.set_cksum();
+    archive
+        .append_data(
+            &mut header,
+            format!("{name}/{version}/bin/{name}"),
+            script.as_bytes(),
+        )
+        .unwrap();
+    archive.into_inner().unwrap().finish().unwrap()
+}
+
+fn formula_json(server: &MockServer, name: &str, version: &str, sha256: &str) -> serde_json::Value {
+    let mut formula = common::formula_json(name, &[]);
+    formula["desc"] = format!("{name} from the mock API").into();
+    formula["homepage"] = "https://example.invalid".into();
+    formula["versions"]["stable"] = version.into();
+    formula["bottle"] = serde_json::json!({ "stable": { "rebuild": 0, "files": {
+        "all": {
+            "url": format!("{}/bottles/{name}-{version}.tar.gz", server.uri()),
+            "sha256": sha256,
+        }
+    }}});
+    formula
+}
+
+async fn serve_index(server: &MockServer, formulae: serde_json::Value) {
+    Mock::given(method("GET"))
+        .and(path("/formula.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(formulae))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/cask.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn update_fetches_index_from_mock_api() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    serve_index(
+        &server,
+        serde_json::json!([formula_json(&server, "hello", "2.12.1", &"0".repeat(64))]),
+    )
+    .await;
+
+    let mut update = wax(home.path(), &server);
+    update.arg("update");
+    assert_success(&run(update).await);
+    assert!(home.path().join("cache/formulae.json").exists());
+
+    let mut search = wax(home.path(), &server);
+    search.args(["search", "hello"]);
+    let out = run(search).await;
+    assert_success(&out);
+    assert!(
+        String::from_utf8_lossy(&out.stdout).contains("hello"),
+        "{}",
+        String::from_utf8_lossy(&out.stdout)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn first_command_initializes_cache_from_mock_api() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    serve_index(
+        &server,
+        serde_json::json!([formula_json(&server, "hello", "2.12.1", &"0".repeat(64))]),
+    )
+    .await;
+
+    let mut info = wax(home.path(), &server);
+    info.args(["info", "hello"]);
+    let out = run(info).await;
+    assert_success(&out);
+    assert!(String::from_utf8_lossy(&out.stdout).contains("2.12.1"));
+    assert!(home.path().join("cache/casks.json").exists());
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test(flavor = "multi_thread")]
+async fn info_prints_bottle_url_and_falls_back_when_no_browser() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    serve_index(
+        &server,
+        serde_json::json!([formula_json(&server, "hello", "2.12.1", &"0".repeat(64))]),
+    )
+    .await;
+
+    let mut url = wax(home.path(), &server);
+    url.args(["info", "hello", "--url"]);
+    let out = run(url).await;
+    assert_success(&out);
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout).trim(),
+        format!("{}/bottles/hello-2.12.1.tar.gz", server.uri())
+    );
+
+    let mut open = wax(home.path(), &server);
+    open.args(["info", "hello", "--open"])
+        .env_remove("DISPLAY")
+        .env_remove("WAYLAND_DISPLAY");
+    let out = run(open).await;
+    assert_success(&out);
+    assert!(String::from_utf8_lossy(&out.stdout).contains("https://example.invalid"));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("no browser available"));
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn install_bottle_from_mock_api_into_user_prefix() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let tarball = bottle("hello", "2.12.1");
+    let sha256 = format!("{:x}", Sha256::digest(&tarball));
+    serve_index(
+        &server,
+        serde_json::json!([formula_json(&server, "hello", "2.12.1", &sha256)]),
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/bottles/hello-2.12.1.tar.gz"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(tarball))
+        .mount(&server)
+        .await;
+
+    let mut install = wax(home.path(), &server);
+    install.args(["install", "--user", "hello"]);
+    assert_success(&run(install).await);
+
+    let prefix = home.path().join(".local/wax");
+    let keg_bin = prefix.join("Cellar/hello/2.12.1/bin/hello");
+    assert!(keg_bin.is_file(), "{} missing", keg_bin.display());
+    assert_eq!(
+        std::fs::canonicalize(prefix.join("bin/hello")).unwrap(),
+        std::fs::canonicalize(&keg_bin).unwrap()
+    );
+    let installed = std::fs::read_to_string(home.path().join(".wax/installed.json")).unwrap();
+    assert!(installed.contains("\"hello\""), "{installed}");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test(flavor = "multi_thread")]
+async fn install_platform_override_pours_the_foreign_bottle() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let tarball = bottle("hello", "2.12.1");
+    let sha256 = format!("{:x}", Sha256::digest(&tarball));
+    let mut hello = formula_json(&server, "hello", "2.12.1", &sha256);
+    let files = &mut hello["bottle"]["stable"]["files"];
+    *files = serde_json::json!({ "arm64_sonoma": files["all"].take() });
+    serve_index(&server, serde_json::json!([hello])).await;
+    Mock::given(method("GET"))
+        .and(path("/bottles/hello-2.12.1.tar.gz"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(tarball))
+        .mount(&server)
+        .await;
+
+    let mut bogus = wax(home.path(), &server);
+    bogus.args(["install", "--user", "--platform", "arm64_atari", "hello"]);
+    let out = run(bogus).await;
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("unknown platform 'arm64_atari'"));
+
+    let mut install = wax(home.path(), &server);
+    install.args(["install", "--user", "--platform", "arm64_sonoma", "hello"]);
+    let out = run(install).await;
+    assert_success(&out);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("may not run on this machine"));
+
+    let keg_bin = home.path().join(".local/wax/Cellar/hello/2.12.1/bin/hello");
+    assert!(keg_bin.is_file(), "{} missing", keg_bin.display());
+    let installed: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(home.path().join(".wax/installed.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(installed["hello"]["platform"], "arm64_sonoma");
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn install_with_lock_updates_only_an_existing_lockfile() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let tarball = bottle("hello", "2.12.1");
+    let sha256 = format!("{:x}", Sha256::digest(&tarball));
+    serve_index(
+        &server,
+        serde_json::json!([formula_json(&server, "hello", "2.12.1", &sha256)]),
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/bottles/hello-2.12.1.tar.gz"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(tarball))
+        .mount(&server)
+        .await;
+
+    let lockfile = home.path().join(".wax/wax.lock");
+    let mut install = wax(home.path(), &server);
+    install.args(["install", "--user", "--lock", "hello"]);
+    let out = run(install).await;
+    assert_success(&out);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("no wax.lock to update"));
+    assert!(!lockfile.exists());
+
+    std::fs::write(
+        &lockfile,
+        "[packages.tree]\nversion = \"2.1.1\"\nbottle = \"all\"\n",
+    )
+    .unwrap();
+    let mut uninstall = wax(home.path(), &server);
+    uninstall.args(["uninstall", "hello"]);
+    assert_success(&run(uninstall).await);
+    let mut install = wax(home.path(), &server);
+    install.args(["install", "--user", "--lock", "hello"]);
+    assert_success(&run(install).await);
+
+    let locked = std::fs::read_to_string(&lockfile).unwrap();
+    assert!(locked.contains("[packages.hello]"), "{locked}");
+    assert!(locked.contains("2.12.1"), "{locked}");
+    assert!(locked.contains("[packages.tree]"), "{locked}");
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn install_pours_dependencies_in_parallel_and_links_them_all() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let names = ["app", "liba", "libb"];
+    let mut index = Vec::new();
+    for name in names {
+        let tarball = bottle(name, "1.0");
+        let sha256 = format!("{:x}", Sha256::digest(&tarball));
+        let mut formula = formula_json(&server, name, "1.0", &sha256);
+        if name == "app" {
+            formula["dependencies"] = serde_json::json!(["liba", "libb"]);
+        }
+        index.push(formula);
+        Mock::given(method("GET"))
+            .and(path(format!("/bottles/{name}-1.0.tar.gz")))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(tarball))
+            .mount(&server)
+            .await;
+    }
+    serve_index(&server, serde_json::Value::Array(index)).await;
+
+    let mut install = wax(home.path(), &server);
+    install.args(["install", "--user", "app"]);
+    assert_success(&run(install).await);
+
+    let prefix = home.path().join(".local/wax");
+    let installed = std::fs::read_to_string(home.path().join(".wax/installed.json")).unwrap();
+    for name in names {
+        assert!(prefix.join("bin").join(name).exists(), "{name} not linked");
+        assert!(installed.contains(&format!("\"{name}\"")), "{installed}");
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn install_downloads_a_bottle_shared_by_two_formulae_once() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let tarball = bottle("libfoo", "1.0");
+    let sha256 = format!("{:x}", Sha256::digest(&tarball));
+    let libfoo = formula_json(&server, "libfoo", "1.0", &sha256);
+    let mut compat = libfoo.clone();
+    compat["name"] = serde_json::json!("foo-compat");
+    compat["full_name"] = serde_json::json!("foo-compat");
+    serve_index(&server, serde_json::json!([libfoo, compat])).await;
+    Mock::given(method("GET"))
+        .and(path("/bottles/libfoo-1.0.tar.gz"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(tarball))
+        .mount(&server)
+        .await;
+
+    let mut install = wax(home.path(), &server);
+    install.args(["install", "--user", "libfoo", "foo-compat"]);
+    assert_success(&run(install).await);
+
+    let cellar = home.path().join(".local/wax/Cellar");
+    for name in ["libfoo", "foo-compat"] {
+        let keg_bin = cellar.join(name).join("1.0/bin/libfoo");
+        assert!(keg_bin.is_file(), "{} missing", keg_bin.display());
+    }
+    let installed = std::fs::read_to_string(home.path().join(".wax/installed.json")).unwrap();
+    assert!(installed.contains("\"foo-compat\""), "{installed}");
+
+    // Size probes aside, the bottle body is fetched exactly once.
+    let fetches = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|r| {
+            r.method.as_str() == "GET"
+                && r.url.path() == "/bottles/libfoo-1.0.tar.gz"
+                && !r.headers.contains_key("range")
+        })
+        .count();
+    assert_eq!(fetches, 1);
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn install_rejects_bottle_with_wrong_checksum() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    serve_index(
+        &server,
+        serde_json::json!([formula_json(&server, "hello", "2.12.1", &"0".repeat(64))]),
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/bottles/hello-2.12.1.tar.gz"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(bottle("hello", "2.12.1")))
+        .mount(&server)
+        .await;
+
+    let mut install = wax(home.path(), &server);
+    install.args(["install", "--user", "hello"]);
+    let out = run(install).await;
+    assert!(!out.status.success());
+    assert!(!home
+        .path()
+        .join(".local/wax/Cellar/hello/2.12.1/bin/hello")
+        .exists());
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade_force_bottle_pours_a_bottle_over_a_source_build() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let tarball = bottle("hello", "2.12.1");
+    let sha256 = format!("{:x}", Sha256::digest(&tarball));
+    serve_index(
+        &server,
+        serde_json::json!([formula_json(&server, "hello", "2.12.1", &sha256)]),
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/bottles/hello-2.12.1.tar.gz"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(tarball))
+        .mount(&server)
+        .await;
+
+    // hello 2.10 was built from source; without --force-bottle it would be rebuilt.
+    let prefix = home.path().join(".local/wax");
+    let old_bin = prefix.join("Cellar/hello/2.10/bin");
+    std::fs::create_dir_all(&old_bin).unwrap();
+    std::fs::write(old_bin.join("hello"), "#!/bin/sh\n").unwrap();
+    let mut hello = common::installed_record("hello", "2.10");
+    hello["from_source"] = true.into();
+    common::write_installed(home.path(), &[hello]);
+
+    let mut upgrade = wax(home.path(), &server);
+    upgrade.args(["upgrade", "--user", "--force-bottle", "hello"]);
+    assert_success(&run(upgrade).await);
+
+    let keg_bin = prefix.join("Cellar/hello/2.12.1/bin/hello");
+    assert!(keg_bin.is_file(), "{} missing", keg_bin.display());
+    let installed: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(home.path().join(".wax/installed.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(installed["hello"]["version"], "2.12.1");
+    assert_eq!(installed["hello"]["from_source"], false);
+}
+
+/// A source archive whose Makefile installs `bin/<name>` into `$(PREFIX)`.
+fn source_tarball(name: &str, version: &str) -> Vec<u8> {
+    let mut archive = tar::Builder::new(flate2::write::GzEncoder::new(
+        Vec::new(),
+        flate2::Compression::default(),
+    ));
+    let script = format!("#!/bin/sh\necho {name} {version}\n");
+    let makefile =
+        format!("all:\n\ninstall:\n\tmkdir -p $(PREFIX)/bin\n\tcp {name} $(PREFIX)/bin/{name}\n");
+    for (file, body, mode) in [(name, &script, 0o755), ("Makefile", &makefile, 0o644)] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(body.len() as u64);
+        header.set_mode(mode);
+        header.set_cksum();
+        archive
+            .append_data(
+                &mut header,
+                format!("{name}-{version}/{file}"),
+                body.as_bytes(),
+            )
+            .unwrap();
+    }
+    archive.into_inner().unwrap().finish().unwrap()
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade_rebuilds_a_source_build_from_source() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    serve_index(&server, serde_json::json!([])).await;
+    let source = source_tarball("hello", "2.12.1");
+    let source_sha256 = format!("{:x}", Sha256::digest(&source));
+    Mock::given(method("GET"))
+        .and(path("/src/hello-2.12.1.tar.gz"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(source))
+        .mount(&server)
+        .await;
+
+    // The formula comes from a local core checkout, so the upgrade fetches and
+    // builds its source with the recorded build settings.
+    let core = home.path().join("homebrew-core");
+    std::fs::create_dir_all(core.join("Formula/h")).unwrap();
+    std::fs::write(
+        core.join("Formula/h/hello.rb"),
+        format!(
+            "class Hello < Formula\n  desc \"hello\"\n  homepage \"https://example.invalid\"\n  \
+             url \"{}/src/hello-2.12.1.tar.gz\"\n  sha256 \"{source_sha256}\"\n\n  \
+             def install\n    system \"make\", \"install\"\n  end\nend\n",
+            server.uri()
+        ),
+    )
+    .unwrap();
+
+    let prefix = home.path().join(".local/wax");
+    let old_bin = prefix.join("Cellar/hello/2.10/bin");
+    std::fs::create_dir_all(&old_bin).unwrap();
+    std::fs::write(old_bin.join("hello"), "#!/bin/sh\n").unwrap();
+    let mut hello = common::installed_record("hello", "2.10");
+    hello["from_source"] = true.into();
+    common::write_installed(home.path(), &[hello]);
+
+    let mut upgrade = wax(home.path(), &server);
+    upgrade
+        .env("WAX_CORE_TAP", &core)
+        .args(["upgrade", "--user", "hello"]);
+    assert_success(&run(upgrade).await);
+
+    let keg_bin = prefix.join("Cellar/hello/2.12.1/bin/hello");
+    assert!(keg_bin.is_file(), "{} missing", keg_bin.display());
+    let installed: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(home.path().join(".wax/installed.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(installed["hello"]["version"], "2.12.1");
+    assert_eq!(installed["hello"]["from_source"], true);
+}